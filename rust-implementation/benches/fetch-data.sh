@@ -0,0 +1,9 @@
+#!/bin/sh
+# Downloads the nativejson-benchmark corpora the parse bench reads.
+set -eu
+cd "$(dirname "$0")"
+mkdir -p data
+base=https://raw.githubusercontent.com/miloyip/nativejson-benchmark/master/data
+for name in citm_catalog twitter canada; do
+    curl --fail --location --silent --show-error -o "data/$name.json" "$base/$name.json"
+done
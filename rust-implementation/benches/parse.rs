@@ -0,0 +1,121 @@
+//! Throughput of DOM parsing, validation and NDJSON streaming, next to
+//! serde_json on the same input.
+//!
+//! The standard corpora (`citm_catalog.json`, `twitter.json` and
+//! `canada.json` from nativejson-benchmark) are read from `benches/data/`;
+//! run `benches/fetch-data.sh` once to download them.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use streaming_json_parser::{parse_json_bytes, parse_json_stream, EventReader};
+
+/// A small deterministic generator, so the NDJSON input is the same on
+/// every run.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn float(&mut self, range: f64) -> f64 {
+        (self.next() as f64 / (1u64 << 31) as f64 - 0.5) * range
+    }
+}
+
+/// One small record per line.
+fn synthetic_ndjson() -> String {
+    let mut rng = Lcg(4);
+    let mut out = String::new();
+    for record in 0..20_000 {
+        writeln!(
+            out,
+            r#"{{"id":{record},"level":"{}","latency_ms":{:.3},"path":"/api/v1/items/{}","ok":{}}}"#,
+            ["info", "warn", "error"][record % 3],
+            rng.float(500.0).abs(),
+            rng.below(10_000),
+            record % 11 != 0,
+        )
+        .unwrap();
+    }
+    out
+}
+
+fn corpus(name: &str) -> (String, Vec<u8>) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/data").join(format!("{name}.json"));
+    match fs::read(&path) {
+        Ok(bytes) => (name.to_string(), bytes),
+        Err(e) => panic!("cannot read {}: {e}; run benches/fetch-data.sh first", path.display()),
+    }
+}
+
+fn documents() -> Vec<(String, Vec<u8>)> {
+    let mut documents = vec![
+        corpus("citm_catalog"),
+        corpus("twitter"),
+        corpus("canada"),
+    ];
+    let shared = Path::new(env!("CARGO_MANIFEST_DIR")).join("../common/performance-files");
+    for name in ["performance_array", "performance_large"] {
+        if let Ok(bytes) = fs::read(shared.join(format!("{name}.json"))) {
+            documents.push((name.to_string(), bytes));
+        }
+    }
+    documents
+}
+
+fn parse_dom(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dom");
+    for (name, input) in documents() {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("streaming_json_parser", &name), &input, |b, input| {
+            b.iter(|| parse_json_bytes(black_box(input)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("serde_json", &name), &input, |b, input| {
+            b.iter(|| serde_json::from_slice::<serde_json::Value>(black_box(input)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn validate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validate");
+    for (name, input) in documents() {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("streaming_json_parser", &name), &input, |b, input| {
+            b.iter(|| EventReader::new(black_box(input.as_slice())).skip_value().unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("serde_json", &name), &input, |b, input| {
+            b.iter(|| serde_json::from_slice::<serde::de::IgnoredAny>(black_box(input)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn stream_ndjson(c: &mut Criterion) {
+    let input = synthetic_ndjson().into_bytes();
+    let mut group = c.benchmark_group("ndjson");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("streaming_json_parser", |b| {
+        b.iter(|| parse_json_stream(black_box(input.as_slice())).map(Result::unwrap).count())
+    });
+    group.bench_function("serde_json", |b| {
+        b.iter(|| {
+            serde_json::Deserializer::from_reader(black_box(input.as_slice()))
+                .into_iter::<serde_json::Value>()
+                .map(Result::unwrap)
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_dom, validate, stream_ndjson);
+criterion_main!(benches);
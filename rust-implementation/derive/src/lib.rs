@@ -0,0 +1,348 @@
+//! `#[derive(FromJson, ToJson)]` for the traits of `streaming-json-parser`.
+//! Use them through that crate's `derive` feature rather than directly.
+//!
+//! Structs become objects keyed by field name, newtype structs their one
+//! field, tuple structs arrays and unit structs `null`. Unit enum variants
+//! become their name as a string, and other variants an object with the
+//! name as its one key, as in serde_json.
+//!
+//! Names can be changed with `#[json(rename = "...")]` on a field or variant,
+//! or for all of them with `#[json(rename_all = "...")]` on the type, using
+//! one of `lowercase`, `UPPERCASE`, `PascalCase`, `camelCase`, `snake_case`,
+//! `SCREAMING_SNAKE_CASE`, `kebab-case` or `SCREAMING-KEBAB-CASE`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Fields, Generics, Ident, Index,
+    LitStr,
+};
+
+#[proc_macro_derive(ToJson, attributes(json))]
+pub fn derive_to_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_json(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn derive_from_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_json(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(lit: &LitStr) -> syn::Result<Self> {
+        Ok(match lit.value().as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => return Err(syn::Error::new(lit.span(), "unknown rename_all rule")),
+        })
+    }
+
+    /// Renames a field, written in snake_case.
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => pascal_case(field),
+            RenameRule::Camel => lower_first(&pascal_case(field)),
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+
+    /// Renames a variant, written in PascalCase.
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Pascal => variant.to_string(),
+            RenameRule::Camel => lower_first(variant),
+            RenameRule::Snake => snake_case(variant),
+            RenameRule::ScreamingSnake => snake_case(variant).to_ascii_uppercase(),
+            RenameRule::Kebab => snake_case(variant).replace('_', "-"),
+            RenameRule::ScreamingKebab => snake_case(variant).replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+}
+
+fn pascal_case(snake: &str) -> String {
+    snake
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or(String::new(), |first| first.to_ascii_uppercase().to_string() + chars.as_str())
+        })
+        .collect()
+}
+
+fn lower_first(s: &str) -> String {
+    let mut chars = s.chars();
+    chars.next().map_or(String::new(), |first| first.to_ascii_lowercase().to_string() + chars.as_str())
+}
+
+fn snake_case(pascal: &str) -> String {
+    let mut out = String::new();
+    for (i, ch) in pascal.char_indices() {
+        if ch.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(ch.to_ascii_lowercase());
+    }
+    out
+}
+
+#[derive(Default)]
+struct Attrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+}
+
+/// Reads the `#[json(...)]` attributes of a type (`rename_all`) or of one
+/// of its fields or variants (`rename`).
+fn json_attrs(attrs: &[Attribute], on_type: bool) -> syn::Result<Attrs> {
+    let mut out = Attrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("json")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") && !on_type {
+                out.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else if meta.path.is_ident("rename_all") && on_type {
+                out.rename_all = Some(RenameRule::parse(&meta.value()?.parse()?)?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported json attribute"))
+            }
+        })?;
+    }
+    Ok(out)
+}
+
+/// The JSON names of named fields, paired with their identifiers.
+fn field_names(fields: &Fields, rule: Option<RenameRule>) -> syn::Result<Vec<(Ident, String)>> {
+    fields
+        .iter()
+        .map(|field| {
+            let ident = field.ident.clone().expect("named field");
+            let name = match json_attrs(&field.attrs, false)?.rename {
+                Some(name) => name,
+                None => {
+                    let name = ident.unraw().to_string();
+                    rule.map_or(name.clone(), |rule| rule.apply_to_field(&name))
+                }
+            };
+            Ok((ident, name))
+        })
+        .collect()
+}
+
+fn reject_field_attrs(fields: &Fields) -> syn::Result<()> {
+    for field in fields {
+        if let Some(attr) = field.attrs.iter().find(|attr| attr.path().is_ident("json")) {
+            return Err(syn::Error::new_spanned(attr, "only named fields can be renamed"));
+        }
+    }
+    Ok(())
+}
+
+/// `generics` with `bound` required of every type parameter.
+fn bounded(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<Ident> = generics.type_params().map(|param| param.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote!(#param: #bound));
+    }
+    generics
+}
+
+fn expand_to_json(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let krate = quote!(::streaming_json_parser);
+    let rule = json_attrs(&input.attrs, true)?.rename_all;
+
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(_) => {
+                let (idents, names): (Vec<_>, Vec<_>) = field_names(&data.fields, rule)?.into_iter().unzip();
+                quote! {
+                    let mut map = #krate::Map::default();
+                    #(map.insert(#krate::Key::from(#names), #krate::ToJson::to_json(&self.#idents));)*
+                    #krate::JsonValue::Object(map)
+                }
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                reject_field_attrs(&data.fields)?;
+                quote!(#krate::ToJson::to_json(&self.0))
+            }
+            Fields::Unnamed(fields) => {
+                reject_field_attrs(&data.fields)?;
+                let indices = (0..fields.unnamed.len()).map(Index::from);
+                quote!(#krate::JsonValue::Array(vec![#(#krate::ToJson::to_json(&self.#indices)),*]))
+            }
+            Fields::Unit => quote!(#krate::JsonValue::Null),
+        },
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let name = variant_name(variant, rule)?;
+                arms.push(match &variant.fields {
+                    Fields::Unit => quote!(Self::#ident => #krate::JsonValue::from(#name)),
+                    Fields::Unnamed(fields) => {
+                        reject_field_attrs(&variant.fields)?;
+                        let bindings: Vec<Ident> =
+                            (0..fields.unnamed.len()).map(|i| format_ident!("field{}", i)).collect();
+                        let content = if bindings.len() == 1 {
+                            quote!(#krate::ToJson::to_json(field0))
+                        } else {
+                            quote!(#krate::JsonValue::Array(vec![#(#krate::ToJson::to_json(#bindings)),*]))
+                        };
+                        quote! {
+                            Self::#ident(#(#bindings),*) => {
+                                #krate::convert::__private::variant_object(#name, #content)
+                            }
+                        }
+                    }
+                    Fields::Named(_) => {
+                        let (idents, names): (Vec<_>, Vec<_>) =
+                            field_names(&variant.fields, None)?.into_iter().unzip();
+                        quote! {
+                            Self::#ident { #(#idents),* } => {
+                                let mut map = #krate::Map::default();
+                                #(map.insert(#krate::Key::from(#names), #krate::ToJson::to_json(#idents));)*
+                                #krate::convert::__private::variant_object(#name, #krate::JsonValue::Object(map))
+                            }
+                        }
+                    }
+                });
+            }
+            quote! {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+        Data::Union(_) => return Err(syn::Error::new(Span::call_site(), "ToJson can't be derived for unions")),
+    };
+
+    let ident = &input.ident;
+    let generics = bounded(&input.generics, quote!(#krate::ToJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::ToJson for #ident #ty_generics #where_clause {
+            fn to_json(&self) -> #krate::JsonValue {
+                #body
+            }
+        }
+    })
+}
+
+fn variant_name(variant: &syn::Variant, rule: Option<RenameRule>) -> syn::Result<String> {
+    Ok(match json_attrs(&variant.attrs, false)?.rename {
+        Some(name) => name,
+        None => {
+            let name = variant.ident.unraw().to_string();
+            rule.map_or(name.clone(), |rule| rule.apply_to_variant(&name))
+        }
+    })
+}
+
+fn expand_from_json(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let krate = quote!(::streaming_json_parser);
+    let private = quote!(#krate::convert::__private);
+    let rule = json_attrs(&input.attrs, true)?.rename_all;
+
+    // Builds `path` (a struct or variant) from `value`.
+    let build = |path: TokenStream2, fields: &Fields, value: TokenStream2, rule| -> syn::Result<TokenStream2> {
+        Ok(match fields {
+            Fields::Named(_) => {
+                let (idents, names): (Vec<_>, Vec<_>) = field_names(fields, rule)?.into_iter().unzip();
+                quote! {{
+                    let object = #private::object(#value, "object")?;
+                    Ok(#path { #(#idents: #private::field(object, #names)?),* })
+                }}
+            }
+            Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+                reject_field_attrs(fields)?;
+                quote!(#krate::FromJson::from_json(#value).map(#path))
+            }
+            Fields::Unnamed(unnamed) => {
+                reject_field_attrs(fields)?;
+                let len = unnamed.unnamed.len();
+                let expected = format!("array of {}", len);
+                let indices = 0..len;
+                quote! {{
+                    let items = #private::tuple(#value, #len, #expected)?;
+                    Ok(#path(#(#private::element(items, #indices)?),*))
+                }}
+            }
+            Fields::Unit => quote!(#private::null(#value).map(|()| #path)),
+        })
+    };
+
+    let body = match &input.data {
+        Data::Struct(data) => build(quote!(Self), &data.fields, quote!(value), rule)?,
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            let mut names = Vec::new();
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let name = variant_name(variant, rule)?;
+                arms.push(match &variant.fields {
+                    Fields::Unit => quote! {
+                        #name => #private::unit_variant(name, content).map(|()| Self::#ident)
+                    },
+                    fields => {
+                        let build = build(quote!(Self::#ident), fields, quote!(content), None)?;
+                        quote! {
+                            #name => {
+                                let content = #private::content(name, content)?;
+                                #private::in_variant(name, (|| -> #krate::ParseResult<Self> { #build })())
+                            }
+                        }
+                    }
+                });
+                names.push(name);
+            }
+            quote! {
+                let (name, content) = #private::variant(value, "string or object with one member")?;
+                match name {
+                    #(#arms,)*
+                    _ => Err(#private::unknown_variant(name, &[#(#names),*])),
+                }
+            }
+        }
+        Data::Union(_) => return Err(syn::Error::new(Span::call_site(), "FromJson can't be derived for unions")),
+    };
+
+    let ident = &input.ident;
+    let generics = bounded(&input.generics, quote!(#krate::FromJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::FromJson for #ident #ty_generics #where_clause {
+            fn from_json(value: &#krate::JsonValue) -> #krate::ParseResult<Self> {
+                #body
+            }
+        }
+    })
+}
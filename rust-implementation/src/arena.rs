@@ -0,0 +1,172 @@
+use bumpalo::collections::Vec as BumpVec;
+use bumpalo::Bump;
+use crate::borrowed::BorrowedParser;
+use crate::types::{Context, JsonValue, Map, ParseResult};
+
+/// A JSON value whose nodes and strings all live in a [`Bump`] arena.
+///
+/// Nothing inside is ever dropped individually: the whole tree is freed at
+/// once with the arena. Numbers are held as `f64`; objects keep their members
+/// in document order, duplicates included.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArenaValue<'a> {
+    String(&'a str),
+    Number(f64),
+    Boolean(bool),
+    Null,
+    Object(&'a [(&'a str, ArenaValue<'a>)]),
+    Array(&'a [ArenaValue<'a>]),
+}
+
+impl<'a> ArenaValue<'a> {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ArenaValue::String(_) => "string",
+            ArenaValue::Number(_) => "number",
+            ArenaValue::Boolean(_) => "boolean",
+            ArenaValue::Null => "null",
+            ArenaValue::Object(_) => "object",
+            ArenaValue::Array(_) => "array",
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, ArenaValue::Null)
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            ArenaValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            ArenaValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ArenaValue::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&'a [(&'a str, ArenaValue<'a>)]> {
+        match self {
+            ArenaValue::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&'a [ArenaValue<'a>]> {
+        match self {
+            ArenaValue::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// Looks up a member of an object; with duplicate keys the last one wins,
+    /// as when parsing into a [`JsonValue`].
+    pub fn get(&self, key: &str) -> Option<&'a ArenaValue<'a>> {
+        self.as_object()?
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Copies the tree out of the arena.
+    pub fn to_json_value(&self) -> JsonValue {
+        match *self {
+            ArenaValue::String(s) => JsonValue::String(s.to_string()),
+            ArenaValue::Number(n) => JsonValue::Number(n.into()),
+            ArenaValue::Boolean(b) => JsonValue::Boolean(b),
+            ArenaValue::Null => JsonValue::Null,
+            ArenaValue::Object(members) => {
+                let mut object = Map::with_capacity(members.len());
+                for (key, value) in members {
+                    object.insert((*key).into(), value.to_json_value());
+                }
+                JsonValue::Object(object)
+            }
+            ArenaValue::Array(elements) => {
+                JsonValue::Array(elements.iter().map(ArenaValue::to_json_value).collect())
+            }
+        }
+    }
+}
+
+/// Parses `input` with every node allocated in `bump`. Containers may nest
+/// [`DEFAULT_MAX_DEPTH`](crate::DEFAULT_MAX_DEPTH) deep.
+pub fn parse_json_in<'a>(input: &str, bump: &'a Bump) -> ParseResult<&'a ArenaValue<'a>> {
+    let mut parser = ArenaParser {
+        inner: BorrowedParser::new(input),
+        bump,
+    };
+    let value = parser.parse_value()?;
+    parser.inner.finish()?;
+    Ok(bump.alloc(value))
+}
+
+struct ArenaParser<'i, 'a> {
+    inner: BorrowedParser<'i>,
+    bump: &'a Bump,
+}
+
+impl<'a> ArenaParser<'_, 'a> {
+    fn parse_value(&mut self) -> ParseResult<ArenaValue<'a>> {
+        let parser = &mut self.inner;
+        parser.skip_whitespace();
+        match parser.peek() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => {
+                let s = parser.parse_string()?;
+                Ok(ArenaValue::String(self.bump.alloc_str(&s)))
+            }
+            Some(b'-' | b'0'..=b'9') => Ok(ArenaValue::Number(parser.parse_number()?.as_f64())),
+            Some(b't') => parser.parse_literal("true", ArenaValue::Boolean(true)),
+            Some(b'f') => parser.parse_literal("false", ArenaValue::Boolean(false)),
+            Some(b'n') => parser.parse_literal("null", ArenaValue::Null),
+            Some(_) => Err(parser.invalid_character()),
+            None => Err(parser.end_of_input(Context::Value)),
+        }
+    }
+
+    fn parse_object(&mut self) -> ParseResult<ArenaValue<'a>> {
+        let mut members = BumpVec::new_in(self.bump);
+        self.inner.enter()?;
+        if !self.inner.open_container(b'}') {
+            loop {
+                let key = self.inner.parse_key()?;
+                let key = &*self.bump.alloc_str(&key);
+                let value = self.parse_value()?;
+                members.push((key, value));
+                if self.inner.parse_separator(b'}', "',' or '}'")? {
+                    break;
+                }
+            }
+        }
+        self.inner.leave();
+        Ok(ArenaValue::Object(members.into_bump_slice()))
+    }
+
+    fn parse_array(&mut self) -> ParseResult<ArenaValue<'a>> {
+        let mut elements = BumpVec::new_in(self.bump);
+        self.inner.enter()?;
+        if !self.inner.open_container(b']') {
+            loop {
+                elements.push(self.parse_value()?);
+                if self.inner.parse_separator(b']', "',' or ']'")? {
+                    break;
+                }
+            }
+        }
+        self.inner.leave();
+        Ok(ArenaValue::Array(elements.into_bump_slice()))
+    }
+}
@@ -0,0 +1,66 @@
+use tokio::io::{AsyncRead, AsyncReadExt};
+use crate::chunk::ChunkParser;
+use crate::options::{ParserOptions, MIN_BUFFER_SIZE};
+use crate::path::Path;
+use crate::types::{JsonValue, ParseResult, RawJson};
+use crate::warning::Warning;
+
+/// Reads a stream of documents from a tokio [`AsyncRead`], such as a
+/// request body or socket, without blocking the runtime while it waits for
+/// input. The bytes go through the same lexer, grammar and value builder
+/// as the other parsers, by way of a [`ChunkParser`], so all options apply.
+pub struct AsyncStreamingJsonParser<R> {
+    reader: R,
+    chunks: ChunkParser,
+    buffer: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncStreamingJsonParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParserOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParserOptions) -> Self {
+        let buffer = vec![0; options.buffer_size.max(MIN_BUFFER_SIZE)];
+        Self {
+            reader,
+            chunks: ChunkParser::with_options(options),
+            buffer,
+        }
+    }
+
+    /// The warnings found since the last call; see
+    /// [`ParserOptions::collect_warnings`].
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.chunks.take_warnings()
+    }
+
+    /// The values kept as source text since the last call; see
+    /// [`StreamingJsonParser::take_raw_values`](crate::StreamingJsonParser::take_raw_values).
+    pub fn take_raw_values(&mut self) -> Vec<(Path, RawJson)> {
+        self.chunks.take_raw_values()
+    }
+
+    /// See [`StreamingJsonParser::error_path`](crate::StreamingJsonParser::error_path).
+    pub fn error_path(&self) -> Option<&Path> {
+        self.chunks.error_path()
+    }
+
+    /// The next document, or `None` at the end of the input. After an
+    /// error, reading stops.
+    pub async fn next_value(&mut self) -> Option<ParseResult<JsonValue>> {
+        loop {
+            if let Some(result) = self.chunks.pop() {
+                return Some(result);
+            }
+            if self.chunks.is_closed() {
+                return None;
+            }
+            match self.reader.read(&mut self.buffer).await {
+                Ok(0) => self.chunks.close(),
+                Ok(read) => self.chunks.push_chunk(&self.buffer[..read]),
+                Err(e) => self.chunks.fail(e.into()),
+            }
+        }
+    }
+}
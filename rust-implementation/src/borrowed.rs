@@ -0,0 +1,517 @@
+use std::borrow::Cow;
+#[cfg(not(feature = "preserve_order"))]
+use std::collections::HashMap;
+use crate::lexer::{combine_surrogates, is_high_surrogate, must_escape, simple_escape, NumberGrammar};
+use crate::number::JsonNumber;
+use crate::options::DEFAULT_MAX_DEPTH;
+use crate::types::{Context, JsonValue, ParseError, ParseResult, Position, Span};
+
+/// A parsed JSON value that borrows from its input wherever it can: strings
+/// and keys without escape sequences are slices of the source text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValueRef<'a> {
+    String(Cow<'a, str>),
+    Number(JsonNumber),
+    Boolean(bool),
+    Null,
+    Object(MapRef<'a>),
+    Array(Vec<JsonValueRef<'a>>),
+}
+
+/// The map behind `JsonValueRef::Object`, ordered like [`Map`](crate::Map).
+#[cfg(not(feature = "preserve_order"))]
+pub type MapRef<'a> = HashMap<Cow<'a, str>, JsonValueRef<'a>>;
+#[cfg(feature = "preserve_order")]
+pub type MapRef<'a> = indexmap::IndexMap<Cow<'a, str>, JsonValueRef<'a>>;
+
+impl<'a> JsonValueRef<'a> {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            JsonValueRef::String(_) => "string",
+            JsonValueRef::Number(_) => "number",
+            JsonValueRef::Boolean(_) => "boolean",
+            JsonValueRef::Null => "null",
+            JsonValueRef::Object(_) => "object",
+            JsonValueRef::Array(_) => "array",
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValueRef::Null)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValueRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&JsonNumber> {
+        match self {
+            JsonValueRef::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(JsonNumber::as_f64)
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(JsonNumber::as_i64)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValueRef::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&MapRef<'a>> {
+        match self {
+            JsonValueRef::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValueRef<'a>>> {
+        match self {
+            JsonValueRef::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// Looks up a member of an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValueRef<'a>> {
+        self.as_object().and_then(|obj| obj.get(key))
+    }
+
+    /// Copies every borrowed string, detaching the value from its input.
+    pub fn into_owned(self) -> JsonValue {
+        match self {
+            JsonValueRef::String(s) => JsonValue::String(s.into_owned()),
+            JsonValueRef::Number(n) => JsonValue::Number(n),
+            JsonValueRef::Boolean(b) => JsonValue::Boolean(b),
+            JsonValueRef::Null => JsonValue::Null,
+            JsonValueRef::Object(obj) => JsonValue::Object(
+                obj.into_iter()
+                    .map(|(key, value)| (key.into(), value.into_owned()))
+                    .collect(),
+            ),
+            JsonValueRef::Array(arr) => {
+                JsonValue::Array(arr.into_iter().map(JsonValueRef::into_owned).collect())
+            }
+        }
+    }
+}
+
+impl<'a> From<JsonValueRef<'a>> for JsonValue {
+    fn from(value: JsonValueRef<'a>) -> Self {
+        value.into_owned()
+    }
+}
+
+/// Parses a complete document without copying strings that contain no
+/// escape sequences. Like the streaming parser, it fails with
+/// `DepthLimitExceeded` past [`DEFAULT_MAX_DEPTH`] nested containers.
+pub fn parse_json_str_borrowed(input: &str) -> ParseResult<JsonValueRef<'_>> {
+    let mut parser = BorrowedParser::new(input);
+    let value = parser.parse_value()?;
+    parser.finish()?;
+    Ok(value)
+}
+
+/// A recursive-descent parser over an in-memory string. Containers nest
+/// at most [`DEFAULT_MAX_DEPTH`] deep, as in the streaming parser, which
+/// keeps the recursion off the end of the stack.
+pub(crate) struct BorrowedParser<'a> {
+    input: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> BorrowedParser<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        // Skip a byte order mark, counted as one character in positions.
+        let pos = if input.starts_with('\u{FEFF}') { '\u{FEFF}'.len_utf8() } else { 0 };
+        Self { input, pos, depth: 0 }
+    }
+
+    /// A parser that starts at byte `pos` of `input`, such as the start of a
+    /// value found earlier.
+    pub(crate) fn at(input: &'a str, pos: usize) -> Self {
+        Self { input, pos, depth: 0 }
+    }
+
+    pub(crate) fn input(&self) -> &'a str {
+        self.input
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /// Fails unless only whitespace remains.
+    pub(crate) fn finish(&mut self) -> ParseResult<()> {
+        self.skip_whitespace();
+        match self.next_char() {
+            None => Ok(()),
+            Some(_) => Err(self.unexpected("end of input")),
+        }
+    }
+
+    fn position_at(&self, pos: usize) -> Position {
+        Position::in_text(self.input, pos)
+    }
+
+    pub(crate) fn position(&self) -> Position {
+        self.position_at(self.pos)
+    }
+
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn next_char(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    /// The error for the next token when `expected` should be there.
+    pub(crate) fn unexpected(&self, expected: &str) -> ParseError {
+        match self.next_char() {
+            Some(ch) => ParseError::UnexpectedToken {
+                expected: expected.to_string(),
+                found: format!("{:?}", ch),
+                span: self.span_to(self.token_end()),
+                hint: None,
+            },
+            None => self.end_of_input(Context::Value),
+        }
+    }
+
+    /// The byte offset just past the token at the current position, as the
+    /// lexer would read it: a whole string, number or literal, or else one
+    /// character.
+    fn token_end(&self) -> usize {
+        let rest = &self.input[self.pos..];
+        match rest.bytes().next() {
+            Some(b'"') => {
+                let mut string = BorrowedParser::at(self.input, self.pos);
+                string.skip_string().map_or(self.input.len(), |()| string.pos)
+            }
+            Some(b'-' | b'0'..=b'9' | b'a'..=b'z') => {
+                let word = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.');
+                self.pos + rest.find(|c| !word(c)).unwrap_or(rest.len())
+            }
+            _ => self.pos + self.next_char().map_or(0, char::len_utf8),
+        }
+    }
+
+    fn span_to(&self, end: usize) -> Span {
+        Span { start: self.position(), end: self.position_at(end) }
+    }
+
+    pub(crate) fn end_of_input(&self, while_parsing: Context) -> ParseError {
+        ParseError::UnexpectedEndOfInput { while_parsing, position: self.position() }
+    }
+
+    /// Steps over the next character, whatever it is.
+    pub(crate) fn skip_char(&mut self) {
+        self.pos += self.next_char().map_or(0, char::len_utf8);
+    }
+
+    pub(crate) fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    pub(crate) fn invalid_character(&self) -> ParseError {
+        match self.next_char() {
+            Some(ch) => ParseError::InvalidCharacter {
+                char: ch,
+                position: self.position(),
+            },
+            None => self.end_of_input(Context::Value),
+        }
+    }
+
+    pub(crate) fn expect(&mut self, byte: u8, expected: &str) -> ParseResult<()> {
+        self.skip_whitespace();
+        match self.next_char() {
+            Some(found) if found == byte as char => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected(expected)),
+            None => Err(self.end_of_input(match byte {
+                b':' | b'}' => Context::Object,
+                b']' => Context::Array,
+                _ => Context::Value,
+            })),
+        }
+    }
+
+    pub(crate) fn parse_value(&mut self) -> ParseResult<JsonValueRef<'a>> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => self.parse_string().map(JsonValueRef::String),
+            Some(b'-' | b'0'..=b'9') => self.parse_number().map(JsonValueRef::Number),
+            Some(b't') => self.parse_literal("true", JsonValueRef::Boolean(true)),
+            Some(b'f') => self.parse_literal("false", JsonValueRef::Boolean(false)),
+            Some(b'n') => self.parse_literal("null", JsonValueRef::Null),
+            Some(_) => Err(self.invalid_character()),
+            None => Err(self.end_of_input(Context::Value)),
+        }
+    }
+
+    fn parse_object(&mut self) -> ParseResult<JsonValueRef<'a>> {
+        let mut object = MapRef::new();
+        self.enter()?;
+        if !self.open_container(b'}') {
+            loop {
+                let key = self.parse_key()?;
+                let value = self.parse_value()?;
+                object.insert(key, value);
+                if self.parse_separator(b'}', "',' or '}'")? {
+                    break;
+                }
+            }
+        }
+        self.leave();
+        Ok(JsonValueRef::Object(object))
+    }
+
+    fn parse_array(&mut self) -> ParseResult<JsonValueRef<'a>> {
+        let mut array = Vec::new();
+        self.enter()?;
+        if !self.open_container(b']') {
+            loop {
+                array.push(self.parse_value()?);
+                if self.parse_separator(b']', "',' or ']'")? {
+                    break;
+                }
+            }
+        }
+        self.leave();
+        Ok(JsonValueRef::Array(array))
+    }
+
+    /// Counts a container about to be opened, at its bracket, failing if it
+    /// would nest deeper than the limit.
+    pub(crate) fn enter(&mut self) -> ParseResult<()> {
+        if self.depth >= DEFAULT_MAX_DEPTH {
+            return Err(ParseError::DepthLimitExceeded {
+                limit: DEFAULT_MAX_DEPTH,
+                position: self.position(),
+            });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Counts a container as closed again.
+    pub(crate) fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Consumes the opening bracket; returns true if the container is empty
+    /// and its closing bracket was consumed too.
+    pub(crate) fn open_container(&mut self, close: u8) -> bool {
+        self.pos += 1;
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    /// Reads an object key and the colon after it.
+    pub(crate) fn parse_key(&mut self) -> ParseResult<Cow<'a, str>> {
+        self.skip_whitespace();
+        if self.peek() != Some(b'"') {
+            return Err(self.invalid_character());
+        }
+        let key = self.parse_string()?;
+        self.expect(b':', "':'")?;
+        Ok(key)
+    }
+
+    /// Reads the `,` or closing bracket after a container element; returns
+    /// true once the container is closed.
+    pub(crate) fn parse_separator(&mut self, close: u8, expected: &str) -> ParseResult<bool> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b',') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.peek() == Some(close) {
+                    return Err(ParseError::TrailingComma(self.position()));
+                }
+                Ok(false)
+            }
+            _ => self.expect(close, expected).map(|()| true),
+        }
+    }
+
+    pub(crate) fn parse_string(&mut self) -> ParseResult<Cow<'a, str>> {
+        let start_pos = self.pos;
+        self.pos += 1;
+        let start = self.pos;
+
+        // Borrow until the first escape; only then start copying.
+        loop {
+            match self.peek() {
+                Some(b'"') => {
+                    let s = &self.input[start..self.pos];
+                    self.pos += 1;
+                    return Ok(Cow::Borrowed(s));
+                }
+                Some(b'\\') => break,
+                // RFC 8259 requires control characters to be escaped.
+                Some(byte) if must_escape(byte as char, false, false) => return Err(self.invalid_character()),
+                Some(_) => self.pos += 1,
+                None => return Err(ParseError::UnterminatedString(self.position_at(start_pos))),
+            }
+        }
+
+        let mut result = self.input[start..self.pos].to_string();
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'"' => {
+                    self.pos += 1;
+                    return Ok(Cow::Owned(result));
+                }
+                b'\\' => {
+                    self.pos += 1;
+                    result.push(self.parse_escape()?);
+                }
+                byte if must_escape(byte as char, false, false) => return Err(self.invalid_character()),
+                _ => {
+                    let run = self.input[self.pos..]
+                        .find(|ch| ch == '"' || ch == '\\' || must_escape(ch, false, false))
+                        .map_or(self.input.len(), |offset| self.pos + offset);
+                    result.push_str(&self.input[self.pos..run]);
+                    self.pos = run;
+                }
+            }
+        }
+
+        Err(ParseError::UnterminatedString(self.position_at(start_pos)))
+    }
+
+    /// Moves past the next value without building it. Brackets are only
+    /// counted, and strings only need to end, so the value is checked less
+    /// strictly than by `parse_value`.
+    pub(crate) fn skip_value(&mut self) -> ParseResult<()> {
+        let mut depth = 0usize;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'{' | b'[') => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                Some(b'}' | b']') if depth > 0 => {
+                    depth -= 1;
+                    self.pos += 1;
+                }
+                Some(b',' | b':') if depth > 0 => {
+                    self.pos += 1;
+                    continue;
+                }
+                Some(b'"') => self.skip_string()?,
+                Some(b'-' | b'0'..=b'9') => {
+                    self.parse_number()?;
+                }
+                Some(b't') => self.parse_literal("true", ())?,
+                Some(b'f') => self.parse_literal("false", ())?,
+                Some(b'n') => self.parse_literal("null", ())?,
+                Some(_) => return Err(self.invalid_character()),
+                None => return Err(self.end_of_input(Context::Value)),
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_string(&mut self) -> ParseResult<()> {
+        let start_pos = self.pos;
+        self.pos += 1;
+        let bytes = self.input.as_bytes();
+        let special = |rest: &[u8]| rest.iter().position(|&b| b == b'"' || b == b'\\');
+        while let Some(offset) = bytes.get(self.pos..).and_then(special) {
+            self.pos += offset + 1;
+            if bytes[self.pos - 1] == b'"' {
+                return Ok(());
+            }
+            // Step over the escaped character, which may be a quote.
+            self.pos += 1;
+        }
+        self.pos = self.input.len();
+        Err(ParseError::UnterminatedString(self.position_at(start_pos)))
+    }
+
+    fn parse_escape(&mut self) -> ParseResult<char> {
+        let escape = self.peek().ok_or(ParseError::InvalidEscape(self.position()))?;
+        self.pos += 1;
+        let ch = match escape {
+            b'u' => {
+                let start = self.pos - 2;
+                let code_point = self.parse_hex4()?;
+                let end = self.pos;
+                let unpaired =
+                    |parser: &Self| ParseError::UnpairedSurrogate(Span { start: parser.position_at(start), end: parser.position_at(end) });
+                if is_high_surrogate(code_point) && self.input[self.pos..].starts_with("\\u") {
+                    self.pos += 2;
+                    let low = self.parse_hex4()?;
+                    combine_surrogates(code_point, low).ok_or_else(|| unpaired(self))?
+                } else {
+                    char::from_u32(code_point).ok_or_else(|| unpaired(self))?
+                }
+            }
+            _ => simple_escape(escape as char).ok_or(ParseError::InvalidEscape(self.position()))?,
+        };
+        Ok(ch)
+    }
+
+    fn parse_hex4(&mut self) -> ParseResult<u32> {
+        let hex = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or(ParseError::InvalidEscape(self.position()))?;
+        self.pos += 4;
+        Ok(u32::from_str_radix(hex, 16).expect("four hex digits"))
+    }
+
+    pub(crate) fn parse_number(&mut self) -> ParseResult<JsonNumber> {
+        let start = self.pos;
+        let invalid = |parser: &Self| ParseError::InvalidNumber(parser.position_at(start));
+
+        let mut grammar = NumberGrammar::Start;
+        while self.peek().is_some_and(|byte| grammar.accept(byte as char)) {
+            self.pos += 1;
+        }
+        if !grammar.is_complete() {
+            return Err(invalid(self));
+        }
+
+        JsonNumber::parse_lexeme(&self.input[start..self.pos]).ok_or_else(|| invalid(self))
+    }
+
+    pub(crate) fn parse_literal<T>(&mut self, literal: &str, value: T) -> ParseResult<T> {
+        if self.input[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.invalid_character())
+        }
+    }
+}
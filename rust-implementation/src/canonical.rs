@@ -0,0 +1,293 @@
+use std::cmp::Ordering;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use crate::number::{write_ecmascript, JsonNumber};
+use crate::ser::{write_str, SerializeError, STRING_WRITE};
+use crate::types::{JsonValue, Key};
+
+/// Wraps a [`JsonValue`] with `Eq`, `Ord` and `Hash` so documents can be used
+/// as `HashSet`/`BTreeMap` keys.
+///
+/// Objects compare by their members in sorted key order. Numbers compare by
+/// their exact decimal value where the parser kept their source text, as it
+/// does for big numbers with `bignum` or with
+/// [`preserve_number_text`](crate::ParserOptions::preserve_number_text), so
+/// digits beyond `f64` precision count; `-0` equals `0`. Values of different
+/// types order as null < boolean < number < string < array < object.
+#[derive(Debug, Clone)]
+pub struct CanonicalValue(pub JsonValue);
+
+impl CanonicalValue {
+    pub fn into_inner(self) -> JsonValue {
+        self.0
+    }
+}
+
+impl From<JsonValue> for CanonicalValue {
+    fn from(value: JsonValue) -> Self {
+        CanonicalValue(value)
+    }
+}
+
+impl Deref for CanonicalValue {
+    type Target = JsonValue;
+
+    fn deref(&self) -> &JsonValue {
+        &self.0
+    }
+}
+
+impl PartialEq for CanonicalValue {
+    fn eq(&self, other: &Self) -> bool {
+        compare(&self.0, &other.0) == Ordering::Equal
+    }
+}
+
+impl Eq for CanonicalValue {}
+
+impl PartialOrd for CanonicalValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CanonicalValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare(&self.0, &other.0)
+    }
+}
+
+impl Hash for CanonicalValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_value(&self.0, state);
+    }
+}
+
+fn type_rank(value: &JsonValue) -> u8 {
+    match value {
+        JsonValue::Null => 0,
+        JsonValue::Boolean(_) => 1,
+        JsonValue::Number(_) => 2,
+        JsonValue::String(_) => 3,
+        JsonValue::Array(_) => 4,
+        JsonValue::Object(_) => 5,
+    }
+}
+
+/// A number as the exact decimal it stands for, to compare and hash by:
+/// read from its source text when that was kept, and otherwise from the
+/// shortest text that reads back as its `f64`, so `0.1` is the same either
+/// way.
+#[derive(PartialEq, Eq, Hash)]
+enum Exact {
+    /// `±0.digits × 10^exponent`, without leading or trailing zero digits;
+    /// zero has no digits and is never negative.
+    Finite { negative: bool, digits: String, exponent: i64 },
+    /// `NaN` or an infinity, by the bits of its `f64`.
+    NonFinite(u64),
+}
+
+impl Exact {
+    fn of(number: &JsonNumber) -> Exact {
+        let text = number.as_raw_str().and_then(Exact::parse);
+        let value = number.as_f64();
+        match text {
+            Some(exact) => exact,
+            None if value.is_finite() => {
+                Exact::parse(&format!("{:e}", value)).expect("`{:e}` writes a decimal")
+            }
+            None => Exact::NonFinite(value.to_bits()),
+        }
+    }
+
+    /// Reads decimal number text, JSON or JSON5; `None` for anything else,
+    /// such as JSON5's `0xFF` or `Infinity`.
+    fn parse(text: &str) -> Option<Exact> {
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (unsigned, 0),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+        if whole.len() + fraction.len() == 0 || !all_digits(whole) || !all_digits(fraction) {
+            return None;
+        }
+        let digits = format!("{}{}", whole, fraction);
+        let leading = digits.bytes().take_while(|&byte| byte == b'0').count();
+        let significant = digits[leading..].trim_end_matches('0');
+        if significant.is_empty() {
+            return Some(Exact::Finite { negative: false, digits: String::new(), exponent: 0 });
+        }
+        let shift = i64::try_from(whole.len()).ok()? - i64::try_from(leading).ok()?;
+        Some(Exact::Finite {
+            negative,
+            digits: significant.to_string(),
+            exponent: exponent.checked_add(shift)?,
+        })
+    }
+
+    /// -1 below every finite number, 1 above, 0 for finite numbers.
+    fn rank(&self) -> i8 {
+        match self {
+            Exact::Finite { .. } => 0,
+            Exact::NonFinite(bits) if f64::from_bits(*bits).is_sign_negative() => -1,
+            Exact::NonFinite(_) => 1,
+        }
+    }
+}
+
+impl Ord for Exact {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (
+                Exact::Finite { negative, digits, exponent },
+                Exact::Finite { negative: other_negative, digits: other_digits, exponent: other_exponent },
+            ) => {
+                let sign = |negative: bool, digits: &str| match (digits.is_empty(), negative) {
+                    (true, _) => 0,
+                    (false, true) => -1,
+                    (false, false) => 1,
+                };
+                let magnitude = exponent.cmp(other_exponent).then_with(|| digits.cmp(other_digits));
+                sign(*negative, digits)
+                    .cmp(&sign(*other_negative, other_digits))
+                    .then(if *negative { magnitude.reverse() } else { magnitude })
+            }
+            (Exact::NonFinite(x), Exact::NonFinite(y)) => f64::from_bits(*x).total_cmp(&f64::from_bits(*y)),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for Exact {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn sorted_members(value: &JsonValue) -> Vec<(&Key, &JsonValue)> {
+    let mut members: Vec<_> = match value {
+        JsonValue::Object(obj) => obj.iter().collect(),
+        _ => Vec::new(),
+    };
+    members.sort_by(|a, b| a.0.cmp(b.0));
+    members
+}
+
+fn compare(a: &JsonValue, b: &JsonValue) -> Ordering {
+    match (a, b) {
+        (JsonValue::Null, JsonValue::Null) => Ordering::Equal,
+        (JsonValue::Boolean(x), JsonValue::Boolean(y)) => x.cmp(y),
+        (JsonValue::Number(x), JsonValue::Number(y)) => Exact::of(x).cmp(&Exact::of(y)),
+        (JsonValue::String(x), JsonValue::String(y)) => x.cmp(y),
+        (JsonValue::Array(x), JsonValue::Array(y)) => {
+            for (left, right) in x.iter().zip(y) {
+                match compare(left, right) {
+                    Ordering::Equal => {}
+                    ordering => return ordering,
+                }
+            }
+            x.len().cmp(&y.len())
+        }
+        (JsonValue::Object(_), JsonValue::Object(_)) => {
+            let x = sorted_members(a);
+            let y = sorted_members(b);
+            for ((left_key, left), (right_key, right)) in x.iter().zip(&y) {
+                match left_key.cmp(right_key).then_with(|| compare(left, right)) {
+                    Ordering::Equal => {}
+                    ordering => return ordering,
+                }
+            }
+            x.len().cmp(&y.len())
+        }
+        _ => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+fn hash_value<H: Hasher>(value: &JsonValue, state: &mut H) {
+    type_rank(value).hash(state);
+    match value {
+        JsonValue::Null => {}
+        JsonValue::Boolean(b) => b.hash(state),
+        JsonValue::Number(n) => Exact::of(n).hash(state),
+        JsonValue::String(s) => s.hash(state),
+        JsonValue::Array(arr) => {
+            arr.len().hash(state);
+            for element in arr {
+                hash_value(element, state);
+            }
+        }
+        JsonValue::Object(_) => {
+            let members = sorted_members(value);
+            members.len().hash(state);
+            for (key, member) in members {
+                key.hash(state);
+                hash_value(member, state);
+            }
+        }
+    }
+}
+
+/// Serializes `value` in the RFC 8785 JSON Canonicalization Scheme: no
+/// whitespace, keys sorted by UTF-16 code units, ECMAScript number formatting
+/// and minimal string escapes. Equal documents always produce identical bytes,
+/// so the output can be hashed or signed.
+///
+/// Fails on `NaN` and infinities, which JCS cannot represent.
+pub fn to_canonical_string(value: &JsonValue) -> Result<String, SerializeError> {
+    let mut out = String::new();
+    write_canonical(&mut out, value)?;
+    Ok(out)
+}
+
+fn write_canonical(out: &mut String, value: &JsonValue) -> Result<(), SerializeError> {
+    match value {
+        JsonValue::Number(n) if !n.as_f64().is_finite() => {
+            return Err(SerializeError::NonFiniteNumber(n.to_string()));
+        }
+        JsonValue::Number(n) => write_ecmascript(out, n.as_f64()).expect(STRING_WRITE),
+        JsonValue::String(s) => write_str(out, s).expect(STRING_WRITE),
+        JsonValue::Object(obj) => {
+            let mut members: Vec<_> = obj.iter().collect();
+            members.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+            out.push('{');
+            for (i, (key, member)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_str(out, key).expect(STRING_WRITE);
+                out.push(':');
+                write_canonical(out, member)?;
+            }
+            out.push('}');
+        }
+        JsonValue::Array(arr) => {
+            out.push('[');
+            for (i, element) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, element)?;
+            }
+            out.push(']');
+        }
+        scalar => write!(out, "{}", scalar).expect(STRING_WRITE),
+    }
+    Ok(())
+}
+
+impl JsonValue {
+    /// SHA-256 of the RFC 8785 canonical form, so documents that differ only
+    /// in key order, whitespace or number spelling hash the same.
+    #[cfg(feature = "digest")]
+    pub fn digest_sha256(&self) -> Result<[u8; 32], SerializeError> {
+        use sha2::{Digest, Sha256};
+        Ok(Sha256::digest(to_canonical_string(self)?.as_bytes()).into())
+    }
+}
+
@@ -0,0 +1,132 @@
+use std::collections::{vec_deque, VecDeque};
+use crate::options::ParserOptions;
+use crate::parser::Builder;
+use crate::path::Path;
+use crate::push::PushParser;
+use crate::types::{JsonValue, ParseError, ParseResult, RawJson};
+use crate::warning::Warning;
+
+/// Builds whole values from input handed over in chunks as it arrives, such
+/// as the `Bytes` frames of an HTTP body or server-sent events. Each value
+/// is returned as soon as its last byte is in; only the unfinished document
+/// and a token cut off at the end of a chunk are kept.
+///
+/// Any number of documents may follow one another. After an error, nothing
+/// more is read. Values are built as
+/// [`StreamingJsonParser`](crate::StreamingJsonParser) builds them, with
+/// the same options.
+pub struct ChunkParser {
+    // `None` once the input has ended or failed.
+    push: Option<PushParser>,
+    builder: Builder,
+    ready: VecDeque<ParseResult<JsonValue>>,
+}
+
+impl ChunkParser {
+    pub fn new() -> Self {
+        Self::with_options(ParserOptions::default())
+    }
+
+    pub fn with_options(options: ParserOptions) -> Self {
+        Self {
+            push: Some(PushParser::with_options(options)),
+            builder: Builder::new(),
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Adds `chunk` to the input and returns every value it completes,
+    /// followed by the error if the input turned out to be invalid.
+    pub fn feed(&mut self, chunk: impl AsRef<[u8]>) -> vec_deque::Drain<'_, ParseResult<JsonValue>> {
+        self.push_chunk(chunk.as_ref());
+        self.ready.drain(..)
+    }
+
+    /// Marks the end of the input, returning the last values, or an error if
+    /// it ends partway through a document.
+    pub fn finish(mut self) -> vec_deque::IntoIter<ParseResult<JsonValue>> {
+        self.close();
+        self.ready.into_iter()
+    }
+
+    /// Whether the input so far ends partway through a document.
+    pub fn needs_more_data(&self) -> bool {
+        self.push.as_ref().is_some_and(|push| !push.at_rest())
+    }
+
+    /// The warnings found since the last call; see
+    /// [`ParserOptions::collect_warnings`].
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.push.as_mut().map(PushParser::take_warnings).unwrap_or_default()
+    }
+
+    /// The values kept as source text since the last call; see
+    /// [`StreamingJsonParser::take_raw_values`](crate::StreamingJsonParser::take_raw_values).
+    pub fn take_raw_values(&mut self) -> Vec<(Path, RawJson)> {
+        self.builder.take_raw_values()
+    }
+
+    /// See [`StreamingJsonParser::error_path`](crate::StreamingJsonParser::error_path).
+    pub fn error_path(&self) -> Option<&Path> {
+        self.builder.error_path()
+    }
+
+    pub(crate) fn push_chunk(&mut self, chunk: &[u8]) {
+        if let Some(push) = &mut self.push {
+            push.push_bytes(chunk);
+            self.build();
+        }
+    }
+
+    pub(crate) fn close(&mut self) {
+        if let Some(push) = &mut self.push {
+            push.close();
+            self.build();
+            self.push = None;
+        }
+    }
+
+    /// Ends the input with `error`, such as a failed read.
+    pub(crate) fn fail(&mut self, error: ParseError) {
+        self.ready.push_back(Err(error));
+        self.push = None;
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn is_closed(&self) -> bool {
+        self.push.is_none()
+    }
+
+    /// The oldest value completed and not yet taken.
+    #[cfg(feature = "async")]
+    pub(crate) fn pop(&mut self) -> Option<ParseResult<JsonValue>> {
+        self.ready.pop_front()
+    }
+
+    /// Builds values from the events of the input so far, queueing each
+    /// once complete.
+    fn build(&mut self) {
+        let Some(push) = &mut self.push else { return };
+        loop {
+            self.builder.before_event(push.reader());
+            match push.next_event() {
+                Ok(Some(event)) => {
+                    if let Some(value) = self.builder.push(event, push.reader()) {
+                        self.ready.push_back(Ok(value));
+                    }
+                }
+                Ok(None) => return,
+                Err(error) => {
+                    self.builder.fail(push.reader());
+                    return self.fail(error);
+                }
+            }
+        }
+    }
+}
+
+impl Default for ChunkParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
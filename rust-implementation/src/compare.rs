@@ -0,0 +1,77 @@
+use crate::types::JsonValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CompareOptions {
+    epsilon: f64,
+    unordered_arrays: bool,
+}
+
+impl CompareOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Numbers may differ by `epsilon`, either absolutely or relative to the
+    /// larger of the two magnitudes.
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Compare arrays as multisets, ignoring element order.
+    pub fn unordered_arrays(mut self, unordered: bool) -> Self {
+        self.unordered_arrays = unordered;
+        self
+    }
+}
+
+fn numbers_close(a: f64, b: f64, epsilon: f64) -> bool {
+    if a == b {
+        return true;
+    }
+    let difference = (a - b).abs();
+    difference <= epsilon || difference <= epsilon * a.abs().max(b.abs())
+}
+
+fn same_multiset(a: &[JsonValue], b: &[JsonValue], options: CompareOptions) -> bool {
+    let mut unmatched: Vec<&JsonValue> = b.iter().collect();
+    for x in a {
+        match unmatched.iter().position(|y| x.equivalent(y, options)) {
+            Some(index) => {
+                unmatched.swap_remove(index);
+            }
+            None => return false,
+        }
+    }
+    true
+}
+
+impl JsonValue {
+    /// Structural equality where numbers may differ by `epsilon`.
+    pub fn approx_eq(&self, other: &JsonValue, epsilon: f64) -> bool {
+        self.equivalent(other, CompareOptions::new().epsilon(epsilon))
+    }
+
+    pub fn equivalent(&self, other: &JsonValue, options: CompareOptions) -> bool {
+        match (self, other) {
+            (JsonValue::Number(a), JsonValue::Number(b)) => {
+                numbers_close(a.as_f64(), b.as_f64(), options.epsilon)
+            }
+            (JsonValue::Array(a), JsonValue::Array(b)) => {
+                a.len() == b.len()
+                    && if options.unordered_arrays {
+                        same_multiset(a, b, options)
+                    } else {
+                        a.iter().zip(b).all(|(x, y)| x.equivalent(y, options))
+                    }
+            }
+            (JsonValue::Object(a), JsonValue::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter().all(|(key, x)| {
+                        b.get(key).is_some_and(|y| x.equivalent(y, options))
+                    })
+            }
+            _ => self == other,
+        }
+    }
+}
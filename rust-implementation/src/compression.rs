@@ -0,0 +1,107 @@
+use std::io::{self, Chain, Cursor, ErrorKind, Read};
+use std::mem;
+#[cfg(feature = "gzip")]
+use flate2::read::MultiGzDecoder;
+
+#[cfg(feature = "gzip")]
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+#[cfg(feature = "zstd")]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+// Enough of the input to tell every format apart.
+const HEAD_LEN: usize = 4;
+
+/// The bytes read to detect the format, followed by the rest of the input.
+type Rest<R> = Chain<Cursor<Vec<u8>>, R>;
+
+/// Reads input that may be compressed, telling from its first bytes and
+/// decompressing on the fly, so `.json.gz` and `.jsonl.zst` archives parse
+/// without `zcat` or `zstdcat`. Only the formats of the enabled features,
+/// `gzip` and `zstd`, are recognized; anything else is read as it is.
+pub struct Decompressor<R: Read> {
+    state: State<R>,
+}
+
+// Plain input is read straight through, and each decoder buffers its own
+// input, so nothing is buffered twice.
+enum State<R: Read> {
+    // The first bytes read so far, too few to tell the format from.
+    Unknown(R, Vec<u8>),
+    Plain(Rest<R>),
+    // Concatenated members or frames, as `cat a.gz b.gz` makes, are all read.
+    #[cfg(feature = "gzip")]
+    Gzip(MultiGzDecoder<Rest<R>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::read::Decoder<'static, io::BufReader<Rest<R>>>),
+    // A decoder failed to start, and took the reader with it.
+    Broken,
+}
+
+enum Format {
+    Plain,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+fn format(head: &[u8]) -> Format {
+    #[cfg(feature = "gzip")]
+    if head.starts_with(&GZIP_MAGIC) {
+        return Format::Gzip;
+    }
+    #[cfg(feature = "zstd")]
+    if head.starts_with(&ZSTD_MAGIC) {
+        return Format::Zstd;
+    }
+    Format::Plain
+}
+
+impl<R: Read> Decompressor<R> {
+    pub fn new(reader: R) -> Self {
+        Self { state: State::Unknown(reader, Vec::with_capacity(HEAD_LEN)) }
+    }
+
+    /// Reads until the format is known: the first few bytes, or all of a
+    /// shorter input. A read that fails, say with `WouldBlock`, keeps what
+    /// was read, so trying again carries on.
+    fn detect(&mut self) -> io::Result<()> {
+        let State::Unknown(reader, head) = &mut self.state else { return Ok(()) };
+        while head.len() < HEAD_LEN {
+            let mut buf = [0; HEAD_LEN];
+            match reader.read(&mut buf[..HEAD_LEN - head.len()]) {
+                Ok(0) => break,
+                Ok(n) => head.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let format = format(head);
+        let State::Unknown(reader, head) = mem::replace(&mut self.state, State::Broken) else {
+            unreachable!("checked above")
+        };
+        let rest = Cursor::new(head).chain(reader);
+        self.state = match format {
+            Format::Plain => State::Plain(rest),
+            #[cfg(feature = "gzip")]
+            Format::Gzip => State::Gzip(MultiGzDecoder::new(rest)),
+            #[cfg(feature = "zstd")]
+            Format::Zstd => State::Zstd(zstd::stream::read::Decoder::new(rest)?),
+        };
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decompressor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.detect()?;
+        match &mut self.state {
+            State::Plain(reader) => reader.read(buf),
+            #[cfg(feature = "gzip")]
+            State::Gzip(reader) => reader.read(buf),
+            #[cfg(feature = "zstd")]
+            State::Zstd(reader) => reader.read(buf),
+            State::Broken => Err(io::Error::other("the decompressor failed to start")),
+            State::Unknown(..) => unreachable!("detected above"),
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+use crate::path::{type_mismatch, Path};
+use crate::types::{JsonValue, Key, Map, ParseError, ParseResult};
+
+/// Conversion into a [`JsonValue`], without serde. Derive it with
+/// `#[derive(ToJson)]` (the `derive` feature).
+pub trait ToJson {
+    fn to_json(&self) -> JsonValue;
+}
+
+/// Conversion from a [`JsonValue`], without serde. Derive it with
+/// `#[derive(FromJson)]` (the `derive` feature).
+///
+/// A value of the wrong type fails with [`ParseError::TypeMismatch`], whose
+/// path points into `value` for errors inside containers.
+pub trait FromJson: Sized {
+    fn from_json(value: &JsonValue) -> ParseResult<Self>;
+}
+
+fn mismatch(expected: &str, found: &JsonValue) -> ParseError {
+    type_mismatch(expected, found, &Path::root()).into()
+}
+
+impl ToJson for JsonValue {
+    fn to_json(&self) -> JsonValue {
+        self.clone()
+    }
+}
+
+impl FromJson for JsonValue {
+    fn from_json(value: &JsonValue) -> ParseResult<Self> {
+        Ok(value.clone())
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Boolean(*self)
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(value: &JsonValue) -> ParseResult<Self> {
+        value.as_bool().ok_or_else(|| mismatch("boolean", value))
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::from(self)
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.clone())
+    }
+}
+
+impl FromJson for String {
+    fn from_json(value: &JsonValue) -> ParseResult<Self> {
+        value.as_str().map(str::to_string).ok_or_else(|| mismatch("string", value))
+    }
+}
+
+impl ToJson for char {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_string())
+    }
+}
+
+impl FromJson for char {
+    fn from_json(value: &JsonValue) -> ParseResult<Self> {
+        let mut chars = value.as_str().map(str::chars).ok_or_else(|| mismatch("character", value))?;
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(mismatch("character", value)),
+        }
+    }
+}
+
+// Integers out of the target's range are mismatches too.
+macro_rules! integer_impls {
+    ($via:ident, $as:ident: $($t:ty),*) => {$(
+        impl ToJson for $t {
+            fn to_json(&self) -> JsonValue {
+                JsonValue::from(*self as $via)
+            }
+        }
+
+        impl FromJson for $t {
+            fn from_json(value: &JsonValue) -> ParseResult<Self> {
+                let JsonValue::Number(n) = value else {
+                    return Err(mismatch(stringify!($t), value));
+                };
+                n.$as().and_then(|n| <$t>::try_from(n).ok()).ok_or_else(|| mismatch(stringify!($t), value))
+            }
+        }
+    )*};
+}
+
+integer_impls!(i64, as_i64: i8, i16, i32, i64, isize);
+integer_impls!(u64, as_u64: u8, u16, u32, u64, usize);
+
+/// `NaN` and the infinities have no JSON form and become null.
+impl ToJson for f64 {
+    fn to_json(&self) -> JsonValue {
+        if self.is_finite() {
+            JsonValue::from(*self)
+        } else {
+            JsonValue::Null
+        }
+    }
+}
+
+impl FromJson for f64 {
+    fn from_json(value: &JsonValue) -> ParseResult<Self> {
+        value.as_f64().ok_or_else(|| mismatch("number", value))
+    }
+}
+
+impl ToJson for f32 {
+    fn to_json(&self) -> JsonValue {
+        f64::from(*self).to_json()
+    }
+}
+
+impl FromJson for f32 {
+    fn from_json(value: &JsonValue) -> ParseResult<Self> {
+        f64::from_json(value).map(|n| n as f32)
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> JsonValue {
+        (**self).to_json()
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Box<T> {
+    fn to_json(&self) -> JsonValue {
+        (**self).to_json()
+    }
+}
+
+impl<T: FromJson> FromJson for Box<T> {
+    fn from_json(value: &JsonValue) -> ParseResult<Self> {
+        T::from_json(value).map(Box::new)
+    }
+}
+
+/// `None` is `null`.
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_ref().map_or(JsonValue::Null, ToJson::to_json)
+    }
+}
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &JsonValue) -> ParseResult<Self> {
+        match value {
+            JsonValue::Null => Ok(None),
+            value => T::from_json(value).map(Some),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_slice().to_json()
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &JsonValue) -> ParseResult<Self> {
+        let items = value.as_array().ok_or_else(|| mismatch("array", value))?;
+        items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| T::from_json(item).map_err(|error| error.within(index)))
+            .collect()
+    }
+}
+
+fn to_object<'a, V: ToJson + 'a>(entries: impl Iterator<Item = (&'a String, &'a V)>) -> JsonValue {
+    let mut map = Map::default();
+    for (key, value) in entries {
+        map.insert(Key::from(key.as_str()), value.to_json());
+    }
+    JsonValue::Object(map)
+}
+
+fn from_object<V: FromJson, C: FromIterator<(String, V)>>(value: &JsonValue) -> ParseResult<C> {
+    let map = value.as_object().ok_or_else(|| mismatch("object", value))?;
+    map.iter()
+        .map(|(key, value)| {
+            let value = V::from_json(value).map_err(|error| error.within(&**key))?;
+            Ok((key.to_string(), value))
+        })
+        .collect()
+}
+
+impl<V: ToJson, S> ToJson for HashMap<String, V, S> {
+    fn to_json(&self) -> JsonValue {
+        to_object(self.iter())
+    }
+}
+
+impl<V: FromJson, S: BuildHasher + Default> FromJson for HashMap<String, V, S> {
+    fn from_json(value: &JsonValue) -> ParseResult<Self> {
+        from_object(value)
+    }
+}
+
+impl<V: ToJson> ToJson for BTreeMap<String, V> {
+    fn to_json(&self) -> JsonValue {
+        to_object(self.iter())
+    }
+}
+
+impl<V: FromJson> FromJson for BTreeMap<String, V> {
+    fn from_json(value: &JsonValue) -> ParseResult<Self> {
+        from_object(value)
+    }
+}
+
+// `TryFrom` takes the value apart instead of copying out of it, and fails
+// with the same errors as `FromJson`.
+
+impl TryFrom<JsonValue> for String {
+    type Error = ParseError;
+
+    fn try_from(value: JsonValue) -> ParseResult<Self> {
+        match value {
+            JsonValue::String(s) => Ok(s),
+            other => Err(mismatch("string", &other)),
+        }
+    }
+}
+
+macro_rules! try_from_impls {
+    ($($t:ty),*) => {$(
+        impl TryFrom<JsonValue> for $t {
+            type Error = ParseError;
+
+            fn try_from(value: JsonValue) -> ParseResult<Self> {
+                <$t>::from_json(&value)
+            }
+        }
+    )*};
+}
+
+try_from_impls!(i64, u64, f64, bool);
+
+impl<T: TryFrom<JsonValue, Error = ParseError>> TryFrom<JsonValue> for Vec<T> {
+    type Error = ParseError;
+
+    fn try_from(value: JsonValue) -> ParseResult<Self> {
+        let JsonValue::Array(items) = value else {
+            return Err(mismatch("array", &value));
+        };
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| T::try_from(item).map_err(|error| error.within(index)))
+            .collect()
+    }
+}
+
+impl<T, S> TryFrom<JsonValue> for HashMap<String, T, S>
+where
+    T: TryFrom<JsonValue, Error = ParseError>,
+    S: BuildHasher + Default,
+{
+    type Error = ParseError;
+
+    fn try_from(value: JsonValue) -> ParseResult<Self> {
+        let JsonValue::Object(map) = value else {
+            return Err(mismatch("object", &value));
+        };
+        map.into_iter()
+            .map(|(key, value)| {
+                let value = T::try_from(value).map_err(|error| error.within(&*key))?;
+                Ok((key.to_string(), value))
+            })
+            .collect()
+    }
+}
+
+/// Support for the code the derive macros generate. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    use super::*;
+
+    pub fn null(value: &JsonValue) -> ParseResult<()> {
+        match value {
+            JsonValue::Null => Ok(()),
+            other => Err(mismatch("null", other)),
+        }
+    }
+
+    pub fn object<'a>(value: &'a JsonValue, expected: &str) -> ParseResult<&'a Map> {
+        value.as_object().ok_or_else(|| mismatch(expected, value))
+    }
+
+    /// The elements of an array that must hold exactly `len` of them.
+    pub fn tuple<'a>(value: &'a JsonValue, len: usize, expected: &str) -> ParseResult<&'a [JsonValue]> {
+        match value.as_array() {
+            Some(items) if items.len() == len => Ok(items),
+            _ => Err(mismatch(expected, value)),
+        }
+    }
+
+    pub fn element<T: FromJson>(items: &[JsonValue], index: usize) -> ParseResult<T> {
+        T::from_json(&items[index]).map_err(|error| error.within(index))
+    }
+
+    /// A missing field reads as `null`, so `Option` fields may be left out.
+    pub fn field<T: FromJson>(object: &Map, key: &str) -> ParseResult<T> {
+        match object.get(key) {
+            Some(value) => T::from_json(value).map_err(|error| error.within(key)),
+            None => T::from_json(&JsonValue::Null)
+                .map_err(|_| ParseError::custom(format_args!("missing field `{}`", key))),
+        }
+    }
+
+    /// The name of an enum variant and its content: a string for a unit
+    /// variant, an object with one member for any other.
+    pub fn variant<'a>(value: &'a JsonValue, expected: &str) -> ParseResult<(&'a str, Option<&'a JsonValue>)> {
+        match value {
+            JsonValue::String(name) => Ok((name, None)),
+            JsonValue::Object(map) if map.len() == 1 => {
+                let (name, content) = map.iter().next().expect("one member");
+                Ok((name, Some(content)))
+            }
+            other => Err(mismatch(expected, other)),
+        }
+    }
+
+    pub fn unit_variant(name: &str, content: Option<&JsonValue>) -> ParseResult<()> {
+        content.map_or(Ok(()), |content| null(content).map_err(|error| error.within(name)))
+    }
+
+    pub fn content<'a>(name: &str, content: Option<&'a JsonValue>) -> ParseResult<&'a JsonValue> {
+        content.ok_or_else(|| ParseError::custom(format_args!("variant `{}` is missing its content", name)))
+    }
+
+    pub fn in_variant<T>(name: &str, result: ParseResult<T>) -> ParseResult<T> {
+        result.map_err(|error| error.within(name))
+    }
+
+    pub fn unknown_variant(name: &str, expected: &[&str]) -> ParseError {
+        ParseError::custom(format_args!("unknown variant `{}`, expected one of {:?}", name, expected))
+    }
+
+    pub fn variant_object(name: &str, content: JsonValue) -> JsonValue {
+        let mut map = Map::default();
+        map.insert(Key::from(name), content);
+        JsonValue::Object(map)
+    }
+}
@@ -0,0 +1,96 @@
+use std::ops::Range;
+use crate::path::Path;
+use crate::types::{JsonValue, Map, ParseError};
+
+/// A [`ParseError`] as plain data, for build tools and language servers that
+/// read errors as JSON through [`to_json`](Diagnostic::to_json).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Names the kind of error, such as `invalid_character`; see [`ParseError::code`].
+    pub code: &'static str,
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// Byte offsets of the input the error is about; see [`ParseError::span`].
+    pub span: Option<Range<usize>>,
+    /// As in `$.servers[3].tls.cert`; see [`ParseError::path`] and
+    /// [`StreamingJsonParser::error_path`](crate::StreamingJsonParser::error_path).
+    pub path: Option<String>,
+}
+
+impl Diagnostic {
+    /// An object with `code`, `message`, `line`, `column`, `offset`, `end`
+    /// and `path`, each `null` if unknown.
+    pub fn to_json(&self) -> JsonValue {
+        let mut object = Map::new();
+        let mut insert = |key: &str, value: JsonValue| {
+            object.insert(key.into(), value);
+        };
+        insert("code", self.code.into());
+        insert("message", self.message.as_str().into());
+        insert("line", self.line.map(|line| line as u64).into());
+        insert("column", self.column.map(|column| column as u64).into());
+        insert("offset", self.span.as_ref().map(|span| span.start as u64).into());
+        insert("end", self.span.as_ref().map(|span| span.end as u64).into());
+        insert("path", self.path.as_deref().into());
+        JsonValue::Object(object)
+    }
+}
+
+impl ParseError {
+    /// A stable name for the kind of error, the variant's in snake case,
+    /// such as `unexpected_token`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedEndOfInput { .. } => "unexpected_end_of_input",
+            ParseError::InvalidCharacter { .. } => "invalid_character",
+            ParseError::InvalidNumber(_) => "invalid_number",
+            ParseError::UnterminatedString(_) => "unterminated_string",
+            ParseError::InvalidEscape(_) => "invalid_escape",
+            ParseError::UnpairedSurrogate(_) => "unpaired_surrogate",
+            ParseError::UnterminatedComment(_) => "unterminated_comment",
+            ParseError::UnexpectedToken { .. } => "unexpected_token",
+            ParseError::TrailingComma(_) => "trailing_comma",
+            ParseError::InvalidStructure(_) => "invalid_structure",
+            ParseError::DepthLimitExceeded { .. } => "depth_limit_exceeded",
+            ParseError::LimitExceeded { .. } => "limit_exceeded",
+            ParseError::Io(_) => "io",
+            ParseError::PathNotFound { .. } => "path_not_found",
+            ParseError::InvalidPointer(_) => "invalid_pointer",
+            ParseError::TypeMismatch { .. } => "type_mismatch",
+            ParseError::InvalidFraming { .. } => "invalid_framing",
+            ParseError::InvalidSchema { .. } => "invalid_schema",
+            ParseError::InvalidQuery { .. } => "invalid_query",
+            ParseError::UnsupportedOption(_) => "unsupported_option",
+            ParseError::Custom { .. } => "custom",
+        }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let position = self.position();
+        Diagnostic {
+            code: self.code(),
+            message: self.to_string(),
+            line: position.map(|position| position.line),
+            column: position.map(|position| position.column),
+            span: self.span(),
+            path: self.path().map(str::to_string),
+        }
+    }
+
+    /// The diagnostic with `path` for an error that has none of its own,
+    /// such as the [`error_path`](crate::StreamingJsonParser::error_path)
+    /// of the parser that found it.
+    pub fn diagnostic_at(&self, path: Option<&Path>) -> Diagnostic {
+        let mut diagnostic = self.diagnostic();
+        if diagnostic.path.is_none() {
+            diagnostic.path = path.map(Path::to_string);
+        }
+        diagnostic
+    }
+
+    /// Shorthand for `diagnostic().to_json()`.
+    pub fn to_json(&self) -> JsonValue {
+        self.diagnostic().to_json()
+    }
+}
@@ -0,0 +1,189 @@
+use std::io::{self, Write};
+use crate::number::JsonNumber;
+use crate::ser::{write_value, CompactFormatter, EscapePolicy, Formatter, IoAdapter};
+use crate::types::JsonValue;
+
+struct Frame {
+    object: bool,
+    len: usize,
+    // Inside an object, whether a key has been written and awaits its value.
+    has_key: bool,
+}
+
+/// Writes one JSON document piece by piece, so output of any size can be
+/// produced without building a `JsonValue` first.
+///
+/// Calls out of order (a value where a key is required, an `end_array` that
+/// closes an object, a second root value) fail with
+/// `io::ErrorKind::InvalidInput` and write nothing.
+pub struct JsonEncoder<W: Write, F: Formatter = CompactFormatter> {
+    out: IoAdapter<W>,
+    formatter: F,
+    stack: Vec<Frame>,
+    complete: bool,
+}
+
+impl<W: Write> JsonEncoder<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_formatter(writer, CompactFormatter)
+    }
+}
+
+impl<W: Write, F: Formatter> JsonEncoder<W, F> {
+    pub fn with_formatter(writer: W, formatter: F) -> Self {
+        Self {
+            out: IoAdapter::new(writer),
+            formatter,
+            stack: Vec::new(),
+            complete: false,
+        }
+    }
+
+    pub fn begin_object(&mut self) -> io::Result<()> {
+        self.begin_value()?;
+        let result = self.formatter.begin_object(&mut self.out);
+        self.out.check(result)?;
+        self.stack.push(Frame {
+            object: true,
+            len: 0,
+            has_key: false,
+        });
+        Ok(())
+    }
+
+    pub fn end_object(&mut self) -> io::Result<()> {
+        match self.stack.last() {
+            Some(frame) if frame.object && !frame.has_key => {}
+            Some(frame) if frame.object => {
+                return Err(misuse("end_object() before the last key's value"))
+            }
+            _ => return Err(misuse("end_object() without a matching begin_object()")),
+        }
+        self.stack.pop();
+        let result = self.formatter.end_object(&mut self.out);
+        self.out.check(result)?;
+        self.end_value()
+    }
+
+    pub fn begin_array(&mut self) -> io::Result<()> {
+        self.begin_value()?;
+        let result = self.formatter.begin_array(&mut self.out);
+        self.out.check(result)?;
+        self.stack.push(Frame {
+            object: false,
+            len: 0,
+            has_key: false,
+        });
+        Ok(())
+    }
+
+    pub fn end_array(&mut self) -> io::Result<()> {
+        if !matches!(self.stack.last(), Some(frame) if !frame.object) {
+            return Err(misuse("end_array() without a matching begin_array()"));
+        }
+        self.stack.pop();
+        let result = self.formatter.end_array(&mut self.out);
+        self.out.check(result)?;
+        self.end_value()
+    }
+
+    pub fn key(&mut self, key: &str) -> io::Result<()> {
+        let frame = match self.stack.last_mut() {
+            Some(frame) if frame.object && !frame.has_key => frame,
+            _ => return Err(misuse("key() outside an object or before the previous key's value")),
+        };
+        frame.has_key = true;
+        let first = frame.len == 0;
+
+        let formatter = &mut self.formatter;
+        let out = &mut self.out;
+        let result = formatter
+            .begin_object_key(out, first)
+            .and_then(|()| formatter.write_key(out, key, EscapePolicy::default()))
+            .and_then(|()| formatter.begin_object_value(out));
+        self.out.check(result)
+    }
+
+    pub fn string(&mut self, value: &str) -> io::Result<()> {
+        self.begin_value()?;
+        let result = self
+            .formatter
+            .write_string(&mut self.out, value, EscapePolicy::default());
+        self.out.check(result)?;
+        self.end_value()
+    }
+
+    pub fn number(&mut self, value: impl Into<JsonNumber>) -> io::Result<()> {
+        self.begin_value()?;
+        let result = self.formatter.write_number(&mut self.out, &value.into());
+        self.out.check(result)?;
+        self.end_value()
+    }
+
+    pub fn boolean(&mut self, value: bool) -> io::Result<()> {
+        self.begin_value()?;
+        let result = self.formatter.write_bool(&mut self.out, value);
+        self.out.check(result)?;
+        self.end_value()
+    }
+
+    pub fn null(&mut self) -> io::Result<()> {
+        self.begin_value()?;
+        let result = self.formatter.write_null(&mut self.out);
+        self.out.check(result)?;
+        self.end_value()
+    }
+
+    /// Writes a complete value in one call.
+    pub fn value(&mut self, value: &JsonValue) -> io::Result<()> {
+        self.begin_value()?;
+        let result = write_value(&mut self.out, value, &mut self.formatter);
+        self.out.check(result)?;
+        self.end_value()
+    }
+
+    /// Checks that the document is complete and hands back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.complete {
+            return Err(misuse("finish() before the root value was closed"));
+        }
+        self.out.inner.flush()?;
+        Ok(self.out.inner)
+    }
+
+    fn begin_value(&mut self) -> io::Result<()> {
+        let result = match self.stack.last() {
+            None if self.complete => return Err(misuse("a document has only one root value")),
+            None => Ok(()),
+            Some(frame) if frame.object && !frame.has_key => {
+                return Err(misuse("an object member needs key() before its value"))
+            }
+            Some(frame) if frame.object => Ok(()),
+            Some(frame) => self.formatter.begin_array_value(&mut self.out, frame.len == 0),
+        };
+        self.out.check(result)
+    }
+
+    fn end_value(&mut self) -> io::Result<()> {
+        let result = match self.stack.last_mut() {
+            None => {
+                self.complete = true;
+                Ok(())
+            }
+            Some(frame) if frame.object => {
+                frame.has_key = false;
+                frame.len += 1;
+                self.formatter.end_object_value(&mut self.out)
+            }
+            Some(frame) => {
+                frame.len += 1;
+                self.formatter.end_array_value(&mut self.out)
+            }
+        };
+        self.out.check(result)
+    }
+}
+
+fn misuse(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
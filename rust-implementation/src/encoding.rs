@@ -0,0 +1,200 @@
+use std::io::{self, Read};
+use crate::lexer::{combine_surrogates, is_high_surrogate};
+
+/// The Unicode encodings a JSON text can arrive in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+/// Guesses the encoding of a JSON text from its first (up to four) bytes.
+///
+/// A byte order mark decides if there is one. Otherwise this uses the pattern
+/// of zero bytes from RFC 4627: the first two characters of a JSON text are
+/// always ASCII, so in UTF-16 and UTF-32 they leave tell-tale zeros.
+pub fn detect_encoding(prefix: &[u8]) -> Encoding {
+    match prefix {
+        [0xFF, 0xFE, 0, 0, ..] => Encoding::Utf32Le,
+        [0, 0, 0xFE, 0xFF, ..] => Encoding::Utf32Be,
+        [0xFF, 0xFE, ..] => Encoding::Utf16Le,
+        [0xFE, 0xFF, ..] => Encoding::Utf16Be,
+        [0, 0, 0, _, ..] => Encoding::Utf32Be,
+        [_, 0, 0, 0, ..] => Encoding::Utf32Le,
+        [0, _, ..] => Encoding::Utf16Be,
+        [_, 0, ..] => Encoding::Utf16Le,
+        _ => Encoding::Utf8,
+    }
+}
+
+/// The encoding `prefix` settles on whatever bytes follow it, or `None`
+/// while the byte order mark or the pattern of zeros is still ambiguous.
+fn settled_encoding(prefix: &[u8]) -> Option<Encoding> {
+    let encoding = detect_encoding(prefix);
+    if prefix.len() >= 4 {
+        return Some(encoding);
+    }
+    // `detect_encoding` only tells apart zero, 0xFE, 0xFF and other bytes,
+    // so one of each stands for every byte that could come next.
+    let mut longer = prefix.to_vec();
+    longer.push(0);
+    for next in [0, 0xFE, 0xFF, b' '] {
+        *longer.last_mut().expect("just pushed") = next;
+        if settled_encoding(&longer) != Some(encoding) {
+            return None;
+        }
+    }
+    Some(encoding)
+}
+
+/// Transcodes UTF-16 and UTF-32 input to UTF-8 as it is read, detecting the
+/// encoding on the first read. UTF-8 input passes straight through.
+pub(crate) struct Decoder<R> {
+    inner: R,
+    encoding: Option<Encoding>,
+    // Decode malformed code units as U+FFFD instead of failing.
+    lossy: bool,
+    // Raw bytes read but not yet decoded: the sniffed prefix, or the start of
+    // a code unit or surrogate pair split across reads.
+    raw: Vec<u8>,
+    decoded: Vec<u8>,
+    decoded_pos: usize,
+}
+
+impl<R: Read> Decoder<R> {
+    pub(crate) fn new(inner: R, lossy: bool) -> Self {
+        Self {
+            inner,
+            encoding: None,
+            lossy,
+            raw: Vec::new(),
+            decoded: Vec::new(),
+            decoded_pos: 0,
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Whether bytes have been read from `inner` but not yet handed out.
+    pub(crate) fn has_pending(&self) -> bool {
+        !self.raw.is_empty() || self.decoded_pos < self.decoded.len()
+    }
+
+    /// Sniffs the first bytes, reading only until they settle the encoding:
+    /// `[1` is UTF-8 whatever follows, while `1` alone could still be the
+    /// start of UTF-16. They are kept in `raw` as they arrive, so a read that
+    /// fails partway, such as a non-blocking one, can be retried.
+    fn detect(&mut self) -> io::Result<Encoding> {
+        let mut prefix = [0; 4];
+        while settled_encoding(&self.raw).is_none() {
+            let want = prefix.len() - self.raw.len();
+            match self.inner.read(&mut prefix[..want]) {
+                Ok(0) => break,
+                Ok(n) => self.raw.extend_from_slice(&prefix[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(detect_encoding(&self.raw))
+    }
+
+    /// Decodes every complete character in `raw`, leaving any trailing
+    /// partial one for the next read.
+    fn decode_raw(&mut self, encoding: Encoding, at_eof: bool) -> io::Result<()> {
+        let unit_len = match encoding {
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            _ => 4,
+        };
+        let unit = |bytes: &[u8]| -> u32 {
+            match encoding {
+                Encoding::Utf16Le => u32::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+                Encoding::Utf16Be => u32::from(u16::from_be_bytes([bytes[0], bytes[1]])),
+                Encoding::Utf32Le => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                _ => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            }
+        };
+
+        let mut pos = 0;
+        while self.raw.len() - pos >= unit_len {
+            let mut code_point = unit(&self.raw[pos..]);
+            let mut consumed = unit_len;
+            if unit_len == 2 && is_high_surrogate(code_point) {
+                if self.raw.len() - pos < 4 {
+                    break;
+                }
+                if let Some(ch) = combine_surrogates(code_point, unit(&self.raw[pos + 2..])) {
+                    code_point = ch as u32;
+                    consumed = 4;
+                }
+            }
+            let ch = match char::from_u32(code_point) {
+                Some(ch) => ch,
+                None if self.lossy => char::REPLACEMENT_CHARACTER,
+                None => return Err(invalid_data(encoding)),
+            };
+            let mut utf8 = [0; 4];
+            self.decoded.extend_from_slice(ch.encode_utf8(&mut utf8).as_bytes());
+            pos += consumed;
+        }
+        self.raw.drain(..pos);
+
+        if at_eof && !self.raw.is_empty() {
+            if !self.lossy {
+                return Err(invalid_data(encoding));
+            }
+            self.raw.clear();
+            self.decoded.extend_from_slice("\u{FFFD}".as_bytes());
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let encoding = match self.encoding {
+            Some(encoding) => encoding,
+            None => {
+                let encoding = self.detect()?;
+                self.encoding = Some(encoding);
+                encoding
+            }
+        };
+
+        if encoding == Encoding::Utf8 {
+            if self.raw.is_empty() {
+                return self.inner.read(buf);
+            }
+            let n = self.raw.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.raw[..n]);
+            self.raw.drain(..n);
+            return Ok(n);
+        }
+
+        while self.decoded_pos == self.decoded.len() {
+            self.decoded.clear();
+            self.decoded_pos = 0;
+
+            let mut chunk = [0; 4096];
+            let n = self.inner.read(&mut chunk)?;
+            self.raw.extend_from_slice(&chunk[..n]);
+            self.decode_raw(encoding, n == 0)?;
+            if n == 0 && self.decoded.is_empty() {
+                return Ok(0);
+            }
+        }
+
+        let n = (self.decoded.len() - self.decoded_pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.decoded[self.decoded_pos..self.decoded_pos + n]);
+        self.decoded_pos += n;
+        Ok(n)
+    }
+}
+
+fn invalid_data(encoding: Encoding) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("input is not valid {:?}", encoding))
+}
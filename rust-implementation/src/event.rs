@@ -0,0 +1,659 @@
+use std::io::Read;
+use std::mem::size_of;
+use std::ops::Range;
+use crate::lexer::Lexer;
+use crate::metrics::ParseMetrics;
+use crate::number::JsonNumber;
+use crate::options::{Framing, ParserOptions};
+use crate::types::{Context, JsonValue, Key, Position, Span, Token, TokenType, ParseError, ParseResult, ResourceLimit};
+use crate::warning::{Warning, WarningKind};
+
+/// One structural step through a JSON document. A document is a single
+/// scalar event, or a `Start*` event, the events of its contents, and the
+/// matching `End*` event; every object member is a `Key` followed by the
+/// events of its value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(String),
+    String(String),
+    Number(JsonNumber),
+    Boolean(bool),
+    Null,
+}
+
+/// Reads a stream of documents as [`Event`]s, so a huge document can be
+/// processed without ever building a `JsonValue` for it.
+///
+/// Iteration stops after the first error, unless
+/// [`ParserOptions::recover_from_errors`] is set.
+pub struct EventReader<R: Read> {
+    pub(crate) lexer: Lexer<R>,
+    grammar: Grammar,
+    // Position of the last token read.
+    position: Position,
+    failed: bool,
+    record: Record,
+    metrics: ParseMetrics,
+    // Mark the start of every token, so that a read that runs out of input
+    // partway through one can go back and read it again once more arrives.
+    pub(crate) resumable: bool,
+}
+
+/// What the framing checks need to know about the documents read so far.
+#[derive(Default)]
+struct Record {
+    // Where the current document starts, and where the last one ended.
+    start: Option<Position>,
+    last_end: Option<Position>,
+    // A comma was read after the last document.
+    separated: bool,
+    // The length given before the current document.
+    length: Option<usize>,
+}
+
+impl<R: Read> EventReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParserOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParserOptions) -> Self {
+        Self {
+            lexer: Lexer::with_options(reader, options),
+            grammar: Grammar::new(),
+            position: Position::START,
+            failed: false,
+            record: Record::default(),
+            metrics: ParseMetrics::default(),
+            resumable: false,
+        }
+    }
+
+    pub fn options(&self) -> &ParserOptions {
+        &self.lexer.options
+    }
+
+    /// Where the last token read starts.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// The warnings found since the last call; see
+    /// [`ParserOptions::collect_warnings`].
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.lexer.warnings)
+    }
+
+    /// What has been read so far; see [`ParseMetrics`].
+    pub fn metrics(&self) -> ParseMetrics {
+        ParseMetrics { bytes_read: self.lexer.bytes_read(), ..self.metrics }
+    }
+
+    /// Byte offsets of the last token read, for slicing it out of the input.
+    pub fn span(&self) -> Range<usize> {
+        self.lexer.token_span().range()
+    }
+
+    /// Returns the next event, or `None` once the input ends between
+    /// documents.
+    pub fn next_event(&mut self) -> ParseResult<Option<Event>> {
+        let result = self.read_event();
+        if result.is_err() {
+            self.metrics.errors += 1;
+            #[cfg(feature = "tracing")]
+            if let Err(error) = &result {
+                tracing::debug!(code = error.code(), %error, "JSON parse error");
+            }
+            // Whatever follows is read as the start of a new document.
+            self.grammar = Grammar::new();
+            self.record = Record {
+                last_end: self.record.last_end,
+                ..Record::default()
+            };
+            if self.lexer.options.recover_from_errors {
+                self.lexer.rollback();
+                // An error here shows up again on the next read.
+                let _ = self.lexer.skip_line();
+                #[cfg(feature = "tracing")]
+                tracing::debug!(offset = self.lexer.offset(), "resuming after a parse error");
+            }
+        }
+        result
+    }
+
+    /// Reads past the next value without building it. Strings are scanned
+    /// but not decoded, so nothing is allocated for them. Right after a
+    /// `Key` event this skips the member's value; before a key, the whole
+    /// member.
+    pub fn skip_value(&mut self) -> ParseResult<()> {
+        self.lexer.skip_strings = true;
+        let result = self.skip_events(0);
+        self.lexer.skip_strings = false;
+        result
+    }
+
+    /// Reads past the next element of the array being read, or returns
+    /// `false` at its end instead.
+    pub(crate) fn skip_element(&mut self) -> ParseResult<bool> {
+        self.lexer.skip_strings = true;
+        let result = match self.next_event() {
+            Ok(Some(Event::EndArray)) => Ok(false),
+            Ok(Some(Event::StartObject | Event::StartArray)) => self.skip_events(1).map(|()| true),
+            Ok(_) => Ok(true),
+            Err(e) => Err(e),
+        };
+        self.lexer.skip_strings = false;
+        result
+    }
+
+    /// Reads past the rest of the document being read, if one is open, so
+    /// the next event starts the next document.
+    pub(crate) fn skip_document(&mut self) -> ParseResult<()> {
+        let depth = self.grammar.depth();
+        if depth == 0 {
+            return Ok(());
+        }
+        self.lexer.skip_strings = true;
+        let result = self.skip_events(depth);
+        self.lexer.skip_strings = false;
+        result
+    }
+
+    fn skip_events(&mut self, mut depth: usize) -> ParseResult<()> {
+        loop {
+            match self.next_event()? {
+                Some(Event::StartObject | Event::StartArray) => depth += 1,
+                Some(Event::EndObject | Event::EndArray) if depth > 0 => depth -= 1,
+                Some(Event::Key(_)) => continue,
+                Some(Event::EndObject) => return Err(self.missing_value(TokenType::RightBrace)),
+                Some(Event::EndArray) => return Err(self.missing_value(TokenType::RightBracket)),
+                None => return Err(self.missing_value(TokenType::Eof)),
+                Some(_) => {}
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// The error for a value that isn't there because the last token, `found`,
+    /// ended the input or its container instead.
+    pub(crate) fn missing_value(&self, found: TokenType) -> ParseError {
+        if found == TokenType::Eof {
+            let position = self.position;
+            return ParseError::UnexpectedEndOfInput { while_parsing: Context::Value, position };
+        }
+        ParseError::UnexpectedToken {
+            expected: "JSON value".to_string(),
+            found: format!("{:?}", found),
+            span: self.lexer.token_span(),
+            hint: None,
+        }
+    }
+
+    /// Whether the input read so far ends between documents, with nothing
+    /// but whitespace after the last one.
+    pub(crate) fn at_rest(&self) -> bool {
+        self.grammar.at_document_boundary() && !self.lexer.has_pending_input()
+    }
+
+    /// Reads the next event without the error handling of
+    /// [`next_event`](EventReader::next_event).
+    pub(crate) fn read_event(&mut self) -> ParseResult<Option<Event>> {
+        loop {
+            if self.lexer.options.recover_from_errors && self.grammar.at_document_boundary() {
+                // Keep the document's input, to go back to if it fails.
+                self.lexer.skip_whitespace()?;
+                self.lexer.checkpoint();
+            }
+            let mut token = match self.next_token() {
+                Ok(token) => token,
+                Err(error) => {
+                    let start = self.lexer.token_span().start;
+                    return Err(self.grammar.unexpected_character(error, start, &self.lexer.options));
+                }
+            };
+            if self.grammar.at_document_boundary() {
+                match self.frame(token)? {
+                    Some(next) => token = next,
+                    None => continue,
+                }
+            }
+            let eof = token.token_type == TokenType::Eof;
+            let end = self.lexer.token_span().end;
+            let lexer = &mut self.lexer;
+            if let Some(event) = self.grammar.token(token, end, &lexer.options, &mut lexer.warnings)? {
+                let depth = self.grammar.stack.len();
+                self.metrics.max_depth = self.metrics.max_depth.max(depth);
+                if self.grammar.at_document_boundary() {
+                    self.end_record()?;
+                    self.metrics.records += 1;
+                }
+                return Ok(Some(event));
+            }
+            if eof {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Checks a token read between documents against the framing. Returns
+    /// `None` if it was a separator or length prefix rather than the start
+    /// of a document.
+    fn frame(&mut self, token: Token) -> ParseResult<Option<Token>> {
+        let start = self.lexer.token_span().start;
+        let eof = token.token_type == TokenType::Eof;
+        let record = &mut self.record;
+        let first = record.last_end.is_none();
+
+        match self.lexer.options.framing {
+            Framing::Concatenated => {}
+            Framing::NewlineDelimited => {
+                if !eof && record.last_end.is_some_and(|end| end.line == start.line) {
+                    return Err(self.framing_error("each record must start on a new line"));
+                }
+            }
+            Framing::CommaSeparated => {
+                if token.token_type == TokenType::Comma && !first && !record.separated {
+                    record.separated = true;
+                    return Ok(None);
+                }
+                if eof && record.separated && !self.lexer.options.allow_trailing_commas {
+                    return Err(ParseError::TrailingComma(self.position));
+                }
+                if !eof && !first && !record.separated {
+                    return Err(self.framing_error("records must be separated by ','"));
+                }
+                record.separated = false;
+            }
+            Framing::LengthPrefixed if record.length.is_none() => {
+                if eof {
+                    return Ok(Some(token));
+                }
+                let length = match &token.token_type {
+                    TokenType::Number(n) => n.as_u64(),
+                    _ => None,
+                };
+                match length {
+                    Some(length) => {
+                        record.length = Some(length as usize);
+                        return Ok(None);
+                    }
+                    None => return Err(self.framing_error("expected a record length")),
+                }
+            }
+            Framing::LengthPrefixed => {
+                if eof {
+                    return Err(self.missing_value(TokenType::Eof));
+                }
+            }
+        }
+        self.record.start = Some(start);
+        Ok(Some(token))
+    }
+
+    /// Checks the document just completed against the framing.
+    fn end_record(&mut self) -> ParseResult<()> {
+        let end = self.lexer.token_span().end;
+        let start = self.record.start.take().unwrap_or(end);
+        self.record.last_end = Some(end);
+
+        match self.lexer.options.framing {
+            Framing::NewlineDelimited if end.line != start.line => {
+                Err(self.framing_error("each record must fit on one line"))
+            }
+            Framing::LengthPrefixed => {
+                let expected = self.record.length.take().unwrap_or(0);
+                let actual = end.offset - start.offset;
+                if actual == expected {
+                    return Ok(());
+                }
+                Err(self.framing_error(&format!(
+                    "record is {} bytes long, but its prefix says {}",
+                    actual, expected
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn framing_error(&self, reason: &str) -> ParseError {
+        ParseError::InvalidFraming {
+            reason: reason.to_string(),
+            position: self.position,
+        }
+    }
+
+    fn next_token(&mut self) -> ParseResult<Token> {
+        if self.resumable {
+            self.lexer.checkpoint();
+        }
+        let token = self.lexer.next().unwrap_or_else(|| Ok(Token::new(TokenType::Eof, self.lexer.position())))?;
+        if token.token_type != TokenType::Eof {
+            self.metrics.tokens += 1;
+        }
+        self.position = token.position;
+        Ok(token)
+    }
+
+    /// Fails unless the input ends here, for callers that expect exactly one
+    /// document.
+    pub(crate) fn expect_end(&mut self) -> ParseResult<()> {
+        let token = self.next_token()?;
+        if token.token_type != TokenType::Eof {
+            return Err(ParseError::UnexpectedToken {
+                expected: "end of input".to_string(),
+                found: format!("{:?}", token.token_type),
+                span: self.lexer.token_span(),
+                hint: None,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = ParseResult<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.next_event();
+        self.failed = result.is_err() && !self.lexer.options.recover_from_errors;
+        result.transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Value,
+    ValueOrClose,
+    // After a comma inside an array.
+    NextValue,
+    KeyOrClose,
+    // After a comma inside an object.
+    NextKey,
+    Colon,
+    CommaOrClose,
+}
+
+/// A guess at the mistake behind finding `found` where `expect` was due,
+/// inside the container opened by `top`.
+fn hint(expect: Expect, top: Option<char>, found: &TokenType) -> Option<&'static str> {
+    let starts_value = matches!(
+        found,
+        TokenType::LeftBrace
+            | TokenType::LeftBracket
+            | TokenType::String(_)
+            | TokenType::Number(_)
+            | TokenType::Boolean(_)
+            | TokenType::Null
+    );
+    match (expect, top) {
+        (Expect::CommaOrClose, Some('{')) if matches!(found, TokenType::String(_)) => {
+            Some("did you forget a comma after the previous member?")
+        }
+        (Expect::CommaOrClose, Some('[')) if starts_value => {
+            Some("did you forget a comma after the previous element?")
+        }
+        (Expect::Colon, _) if starts_value => Some("did you forget the ':' after the key?"),
+        (Expect::CommaOrClose, Some('{')) if found == &TokenType::RightBracket => {
+            Some("']' closes an array, but the innermost open container is an object")
+        }
+        (Expect::CommaOrClose, Some('[')) if found == &TokenType::RightBrace => {
+            Some("'}' closes an object, but the innermost open container is an array")
+        }
+        _ => None,
+    }
+}
+
+/// Checks tokens against the JSON grammar and turns them into events,
+/// enforcing the depth and size limits from the options. Documents follow
+/// one another, as in a stream.
+pub(crate) struct Grammar {
+    expect: Expect,
+    // Open containers with the number of members or elements read so far.
+    stack: Vec<(char, usize)>,
+    // Where the last comma was, for trailing comma warnings.
+    comma: Position,
+    nodes: usize,
+    // Approximate bytes the document's values take once built.
+    allocated: usize,
+}
+
+impl Grammar {
+    pub(crate) fn new() -> Self {
+        Self {
+            expect: Expect::Value,
+            stack: Vec::new(),
+            comma: Position::START,
+            nodes: 0,
+            allocated: 0,
+        }
+    }
+
+    /// Whether the tokens so far form whole documents, so the input may end.
+    pub(crate) fn at_document_boundary(&self) -> bool {
+        self.stack.is_empty() && self.expect == Expect::Value
+    }
+
+    /// How many containers are open.
+    fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn top(&self) -> Option<char> {
+        self.stack.last().map(|&(open, _)| open)
+    }
+
+    /// Every token that could come next, for error messages.
+    fn expected(&self, options: &ParserOptions) -> &'static str {
+        let trailing = options.allow_trailing_commas;
+        match self.expect {
+            Expect::Value => "JSON value",
+            Expect::NextValue if !trailing => "JSON value",
+            Expect::ValueOrClose | Expect::NextValue => "JSON value or ']'",
+            Expect::NextKey if !trailing => "string key",
+            Expect::KeyOrClose | Expect::NextKey => "string key or '}'",
+            Expect::Colon => "':'",
+            Expect::CommaOrClose if self.top() == Some('{') => "',' or '}'",
+            Expect::CommaOrClose => "',' or ']'",
+        }
+    }
+
+    /// Turns the lexer's error for a character that starts no token into an
+    /// `UnexpectedToken` saying what could have come instead, where that
+    /// tells more: anywhere but where a value goes, or where a hint applies.
+    /// `token_start` is where the lexer began the token it failed on.
+    pub(crate) fn unexpected_character(
+        &self,
+        error: ParseError,
+        token_start: Position,
+        options: &ParserOptions,
+    ) -> ParseError {
+        let (char, position) = match error {
+            ParseError::InvalidCharacter { char, position } if position == token_start => {
+                (char, position)
+            }
+            error => return error,
+        };
+        let hint = match (self.expect, char) {
+            (_, '\'') => Some("JSON strings take double quotes"),
+            (Expect::KeyOrClose | Expect::NextKey, 'a'..='z' | 'A'..='Z' | '_' | '$') => {
+                Some("keys must be strings in double quotes")
+            }
+            (Expect::Colon, '=') => Some("use ':' between a key and its value"),
+            _ => None,
+        };
+        let in_value = matches!(self.expect, Expect::Value | Expect::ValueOrClose | Expect::NextValue);
+        if in_value && hint.is_none() {
+            return ParseError::InvalidCharacter { char, position };
+        }
+        ParseError::UnexpectedToken {
+            expected: self.expected(options).to_string(),
+            found: format!("{:?}", char),
+            span: Span {
+                start: position,
+                end: Position { offset: position.offset + char.len_utf8(), column: position.column + 1, ..position },
+            },
+            hint: hint.map(str::to_string),
+        }
+    }
+
+    /// Feeds one token, returning its event; commas and colons have none.
+    /// `Eof` is only accepted between documents. `end` is the position just
+    /// past the token, for errors.
+    pub(crate) fn token(
+        &mut self,
+        token: Token,
+        end: Position,
+        options: &ParserOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> ParseResult<Option<Event>> {
+        let Token { token_type, position } = token;
+        let expected = self.expected(options);
+        let (expect, top) = (self.expect, self.top());
+        let unexpected = |found: &TokenType| ParseError::UnexpectedToken {
+            expected: expected.to_string(),
+            found: format!("{:?}", found),
+            span: Span { start: position, end },
+            hint: hint(expect, top, found).map(str::to_string),
+        };
+
+        let trailing_comma = matches!(
+            (self.expect, &token_type),
+            (Expect::NextKey, TokenType::RightBrace) | (Expect::NextValue, TokenType::RightBracket)
+        );
+        if trailing_comma && options.allow_trailing_commas && options.collect_warnings {
+            warnings.push(Warning { kind: WarningKind::TrailingComma, position: self.comma });
+        }
+
+        let event = match (self.expect, token_type) {
+            (Expect::Value, TokenType::Eof) if self.stack.is_empty() => return Ok(None),
+            (_, TokenType::Eof) => {
+                let while_parsing = if top == Some('{') { Context::Object } else { Context::Array };
+                return Err(ParseError::UnexpectedEndOfInput { while_parsing, position });
+            }
+            (Expect::Colon, TokenType::Colon) => {
+                self.expect = Expect::Value;
+                return Ok(None);
+            }
+            (Expect::Colon, other) => return Err(unexpected(&other)),
+            (Expect::KeyOrClose | Expect::NextKey, TokenType::String(key)) => {
+                self.allocate(options, size_of::<Key>() + key.len(), position)?;
+                self.expect = Expect::Colon;
+                return Ok(Some(Event::Key(key)));
+            }
+            (Expect::CommaOrClose, TokenType::Comma) => {
+                self.comma = position;
+                self.expect = match self.top() {
+                    Some('{') => Expect::NextKey,
+                    _ => Expect::NextValue,
+                };
+                return Ok(None);
+            }
+            _ if trailing_comma && !options.allow_trailing_commas => {
+                return Err(ParseError::TrailingComma(position));
+            }
+            (Expect::KeyOrClose | Expect::NextKey, TokenType::RightBrace) => {
+                self.stack.pop();
+                Event::EndObject
+            }
+            (Expect::CommaOrClose, TokenType::RightBrace) if self.top() == Some('{') => {
+                self.stack.pop();
+                Event::EndObject
+            }
+            (Expect::ValueOrClose | Expect::NextValue, TokenType::RightBracket) => {
+                self.stack.pop();
+                Event::EndArray
+            }
+            (Expect::CommaOrClose, TokenType::RightBracket) if self.top() == Some('[') => {
+                self.stack.pop();
+                Event::EndArray
+            }
+            (Expect::KeyOrClose | Expect::NextKey | Expect::CommaOrClose, other) => {
+                return Err(unexpected(&other))
+            }
+            (Expect::Value | Expect::ValueOrClose | Expect::NextValue, token_type) => {
+                self.nodes += 1;
+                check_limit(options, ResourceLimit::Nodes, self.nodes, position)?;
+                let text = match &token_type {
+                    TokenType::String(s) => s.len(),
+                    _ => 0,
+                };
+                self.allocate(options, size_of::<JsonValue>() + text, position)?;
+                match token_type {
+                    TokenType::LeftBrace | TokenType::LeftBracket => {
+                        let limit = options.max_depth;
+                        if self.stack.len() >= limit {
+                            return Err(ParseError::DepthLimitExceeded { limit, position });
+                        }
+                        return Ok(Some(if token_type == TokenType::LeftBrace {
+                            self.stack.push(('{', 0));
+                            self.expect = Expect::KeyOrClose;
+                            Event::StartObject
+                        } else {
+                            self.stack.push(('[', 0));
+                            self.expect = Expect::ValueOrClose;
+                            Event::StartArray
+                        }));
+                    }
+                    TokenType::String(s) => Event::String(s),
+                    TokenType::Number(n) => Event::Number(n),
+                    TokenType::Boolean(b) => Event::Boolean(b),
+                    TokenType::Null => Event::Null,
+                    other => return Err(unexpected(&other)),
+                }
+            }
+        };
+
+        // A value is complete: count it in its container, or end the document.
+        match self.stack.last_mut() {
+            Some((_, len)) => {
+                *len += 1;
+                check_limit(options, ResourceLimit::ContainerElements, *len, position)?;
+                self.expect = Expect::CommaOrClose;
+            }
+            None => {
+                self.expect = Expect::Value;
+                self.nodes = 0;
+                self.allocated = 0;
+            }
+        }
+        Ok(Some(event))
+    }
+
+    /// Counts `bytes` more towards the document's memory budget.
+    fn allocate(
+        &mut self,
+        options: &ParserOptions,
+        bytes: usize,
+        position: Position,
+    ) -> ParseResult<()> {
+        self.allocated += bytes;
+        check_limit(options, ResourceLimit::TotalAllocated, self.allocated, position)
+    }
+}
+
+pub(crate) fn check_limit(
+    options: &ParserOptions,
+    limit: ResourceLimit,
+    used: usize,
+    position: Position,
+) -> ParseResult<()> {
+    let max = match limit {
+        ResourceLimit::StringLength => options.max_string_length,
+        ResourceLimit::InputBytes => options.max_input_bytes,
+        ResourceLimit::ContainerElements => options.max_container_elements,
+        ResourceLimit::Nodes => options.max_nodes,
+        ResourceLimit::TotalAllocated => options.max_total_allocated,
+    };
+    match max {
+        Some(max) if used > max => Err(ParseError::LimitExceeded { limit, max, position }),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,279 @@
+use std::borrow::Cow;
+use crate::borrowed::BorrowedParser;
+use crate::number::JsonNumber;
+use crate::path::{path_not_found, Path};
+use crate::types::{JsonValue, ParseError, ParseResult};
+
+/// A document in memory that is parsed only as far as it is navigated, for
+/// pulling a few fields out of a large response.
+///
+/// Nothing is checked up front. Members and elements passed over on the way
+/// to a value are scanned just enough to find where they end, so errors in
+/// them may go unnoticed; whatever is actually read is fully validated.
+pub struct LazyDocument<'a> {
+    input: &'a str,
+}
+
+impl<'a> LazyDocument<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self { input }
+    }
+
+    pub fn root(&self) -> LazyValue<'a> {
+        let mut parser = BorrowedParser::new(self.input);
+        parser.skip_whitespace();
+        LazyValue {
+            input: self.input,
+            start: parser.offset(),
+            path: Path::root(),
+        }
+    }
+
+    /// Shorthand for `root().find(key)`.
+    pub fn find(&self, key: &str) -> ParseResult<LazyValue<'a>> {
+        self.root().find(key)
+    }
+
+    /// Shorthand for `root().array_iter()`.
+    pub fn array_iter(&self) -> ParseResult<LazyArrayIter<'a>> {
+        self.root().array_iter()
+    }
+}
+
+/// A value inside a [`LazyDocument`], not yet parsed.
+#[derive(Debug, Clone)]
+pub struct LazyValue<'a> {
+    input: &'a str,
+    // Byte offset of the value's first character.
+    start: usize,
+    path: Path,
+}
+
+impl<'a> LazyValue<'a> {
+    /// Where the value sits in the document.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.input.as_bytes().get(self.start) {
+            Some(b'{') => "object",
+            Some(b'[') => "array",
+            Some(b'"') => "string",
+            Some(b't' | b'f') => "boolean",
+            Some(b'n') => "null",
+            _ => "number",
+        }
+    }
+
+    fn parser(&self) -> BorrowedParser<'a> {
+        BorrowedParser::at(self.input, self.start)
+    }
+
+    fn expect_type(&self, expected: &str) -> ParseResult<()> {
+        if self.type_name() == expected {
+            return Ok(());
+        }
+        Err(ParseError::TypeMismatch {
+            expected: expected.to_string(),
+            found: self.type_name().to_string(),
+            path: self.path.to_string(),
+        })
+    }
+
+    /// The member named `key` of this object, scanning members in order and
+    /// stopping at the first match.
+    pub fn find(&self, key: &str) -> ParseResult<LazyValue<'a>> {
+        for member in self.object_iter()? {
+            let (name, value) = member?;
+            if name == key {
+                return Ok(value);
+            }
+        }
+        let path = self.path.clone().key(key);
+        Err(path_not_found(&path, self.path.len()).into())
+    }
+
+    /// Element `index` of this array.
+    pub fn at(&self, index: usize) -> ParseResult<LazyValue<'a>> {
+        match self.array_iter()?.nth(index) {
+            Some(element) => element,
+            None => Err(path_not_found(&self.path.clone().index(index), self.path.len()).into()),
+        }
+    }
+
+    pub fn array_iter(&self) -> ParseResult<LazyArrayIter<'a>> {
+        self.expect_type("array")?;
+        Ok(LazyArrayIter {
+            parser: self.parser(),
+            path: self.path.clone(),
+            index: 0,
+            state: IterState::Start,
+        })
+    }
+
+    pub fn object_iter(&self) -> ParseResult<LazyObjectIter<'a>> {
+        self.expect_type("object")?;
+        Ok(LazyObjectIter {
+            parser: self.parser(),
+            path: self.path.clone(),
+            state: IterState::Start,
+        })
+    }
+
+    /// The string's contents, borrowed from the document unless it has
+    /// escape sequences.
+    pub fn as_str(&self) -> ParseResult<Cow<'a, str>> {
+        self.expect_type("string")?;
+        self.parser().parse_string()
+    }
+
+    pub fn as_number(&self) -> ParseResult<JsonNumber> {
+        self.expect_type("number")?;
+        self.parser().parse_number()
+    }
+
+    pub fn as_f64(&self) -> ParseResult<f64> {
+        self.as_number().map(|n| n.as_f64())
+    }
+
+    pub fn as_i64(&self) -> ParseResult<i64> {
+        let number = self.as_number()?;
+        number.as_i64().ok_or_else(|| ParseError::TypeMismatch {
+            expected: "integer".to_string(),
+            found: "number".to_string(),
+            path: self.path.to_string(),
+        })
+    }
+
+    pub fn as_bool(&self) -> ParseResult<bool> {
+        self.expect_type("boolean")?;
+        let mut parser = self.parser();
+        parser
+            .parse_literal("true", true)
+            .or_else(|_| parser.parse_literal("false", false))
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.type_name() == "null"
+    }
+
+    /// Parses the whole value, with containers nested at most
+    /// [`DEFAULT_MAX_DEPTH`](crate::DEFAULT_MAX_DEPTH) deep inside it.
+    pub fn to_value(&self) -> ParseResult<JsonValue> {
+        self.parser().parse_value().map(|value| value.into_owned())
+    }
+
+    /// The value's source text, found by scanning to its end.
+    pub fn raw_json(&self) -> ParseResult<&'a str> {
+        let mut parser = self.parser();
+        parser.skip_value()?;
+        Ok(&self.input[self.start..parser.offset()])
+    }
+}
+
+enum IterState {
+    Start,
+    // A value has been handed out; skip it before reading on.
+    AfterValue,
+    Done,
+}
+
+/// The elements of an array in a [`LazyDocument`].
+pub struct LazyArrayIter<'a> {
+    parser: BorrowedParser<'a>,
+    path: Path,
+    index: usize,
+    state: IterState,
+}
+
+impl<'a> LazyArrayIter<'a> {
+    fn advance(&mut self) -> ParseResult<bool> {
+        match self.state {
+            IterState::Start => Ok(!self.parser.open_container(b']')),
+            IterState::AfterValue => {
+                self.parser.skip_value()?;
+                Ok(!self.parser.parse_separator(b']', "',' or ']'")?)
+            }
+            IterState::Done => Ok(false),
+        }
+    }
+}
+
+impl<'a> Iterator for LazyArrayIter<'a> {
+    type Item = ParseResult<LazyValue<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.advance() {
+            Ok(true) => {}
+            Ok(false) => {
+                self.state = IterState::Done;
+                return None;
+            }
+            Err(e) => {
+                self.state = IterState::Done;
+                return Some(Err(e));
+            }
+        }
+        self.parser.skip_whitespace();
+        self.state = IterState::AfterValue;
+        let element = LazyValue {
+            input: self.parser.input(),
+            start: self.parser.offset(),
+            path: self.path.clone().index(self.index),
+        };
+        self.index += 1;
+        Some(Ok(element))
+    }
+}
+
+/// The members of an object in a [`LazyDocument`], in document order.
+pub struct LazyObjectIter<'a> {
+    parser: BorrowedParser<'a>,
+    path: Path,
+    state: IterState,
+}
+
+impl<'a> LazyObjectIter<'a> {
+    fn advance(&mut self) -> ParseResult<Option<Cow<'a, str>>> {
+        let more = match self.state {
+            IterState::Start => !self.parser.open_container(b'}'),
+            IterState::AfterValue => {
+                self.parser.skip_value()?;
+                !self.parser.parse_separator(b'}', "',' or '}'")?
+            }
+            IterState::Done => false,
+        };
+        if !more {
+            return Ok(None);
+        }
+        let key = self.parser.parse_key()?;
+        self.parser.skip_whitespace();
+        Ok(Some(key))
+    }
+}
+
+impl<'a> Iterator for LazyObjectIter<'a> {
+    type Item = ParseResult<(Cow<'a, str>, LazyValue<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = match self.advance() {
+            Ok(Some(key)) => key,
+            Ok(None) => {
+                self.state = IterState::Done;
+                return None;
+            }
+            Err(e) => {
+                self.state = IterState::Done;
+                return Some(Err(e));
+            }
+        };
+        self.state = IterState::AfterValue;
+        let value = LazyValue {
+            input: self.parser.input(),
+            start: self.parser.offset(),
+            path: self.path.clone().key(key.as_ref()),
+        };
+        Some(Ok((key, value)))
+    }
+}
@@ -1,226 +1,794 @@
-use std::io::{Read, BufRead, BufReader};
-use std::str::Chars;
-use std::iter::Peekable;
-use crate::types::{Token, TokenType, ParseError, ParseResult};
+use std::io::{self, Read};
+use crate::encoding::Decoder;
+use crate::number::JsonNumber;
+use crate::options::{Dialect, ParserOptions, MIN_BUFFER_SIZE};
+use crate::types::{Context, IoError, Position, Span, SpannedToken, Token, TokenType, ParseError, ParseResult, ResourceLimit};
+use crate::warning::{Warning, WarningKind};
 
 pub struct Lexer<R: Read> {
-    reader: BufReader<R>,
-    current_line: String,
-    line_chars: Peekable<Chars<'static>>,
-    position: usize,
-    line_position: usize,
+    reader: Decoder<R>,
+    // Input read but not yet consumed is `buffer[pos..]`. Characters are
+    // decoded from it one at a time, so invalid UTF-8 is reported where it
+    // occurs.
+    buffer: Vec<u8>,
+    pos: usize,
+    location: Position,
+    // Where the last token started, and where it ended if that isn't where
+    // lexing stopped (a bare key is followed by a look for its colon).
+    token_start: Position,
+    token_end: Option<Position>,
+    // Where a push parser rolls back to if the input runs out mid-token:
+    // a buffer index and the position there. Bytes from it on are kept.
+    // With the length of the recording then, if there was one.
+    checkpoint: Option<(usize, Position, usize)>,
+    // Scan strings without decoding them, returning them empty.
+    pub(crate) skip_strings: bool,
+    // Bytes consumed since recording started, for keeping source text.
+    recording: Option<Vec<u8>>,
     finished: bool,
+    bytes_read: usize,
+    pub(crate) options: ParserOptions,
+    pub(crate) warnings: Vec<Warning>,
 }
 
 impl<R: Read> Lexer<R> {
     pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParserOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParserOptions) -> Self {
         Self {
-            reader: BufReader::new(reader),
-            current_line: String::new(),
-            line_chars: "".chars().peekable(),
-            position: 0,
-            line_position: 0,
+            reader: Decoder::new(reader, options.replace_invalid_utf8),
+            buffer: Vec::new(),
+            pos: 0,
+            location: Position::START,
+            token_start: Position::START,
+            token_end: None,
+            checkpoint: None,
+            skip_strings: false,
+            recording: None,
             finished: false,
+            bytes_read: 0,
+            options,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn set_preserve_number_text(&mut self, preserve: bool) {
+        self.options.preserve_number_text = preserve;
+    }
+
+    pub fn set_allow_non_finite(&mut self, allow: bool) {
+        self.options.allow_non_finite_numbers = allow;
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        self.reader.get_mut()
+    }
+
+    /// Remembers the current point, so a token that fails for want of input
+    /// can be read again once more has arrived.
+    pub(crate) fn checkpoint(&mut self) {
+        let recorded = self.recording.as_ref().map_or(0, Vec::len);
+        self.checkpoint = Some((self.pos, self.location, recorded));
+    }
+
+    pub(crate) fn rollback(&mut self) {
+        if let Some((pos, location, recorded)) = self.checkpoint {
+            self.pos = pos;
+            self.location = location;
+            if let Some(recording) = &mut self.recording {
+                recording.truncate(recorded);
+            }
+            // They will be found again.
+            self.warnings.retain(|warning| warning.position.offset < location.offset);
         }
     }
 
-    fn load_next_line(&mut self) -> ParseResult<bool> {
+    /// Skips past the next line break or ASCII record separator, for
+    /// resuming after an error at the start of the next record.
+    pub(crate) fn skip_line(&mut self) -> ParseResult<()> {
+        self.checkpoint = None;
+        while let Some(ch) = self.advance()? {
+            if ch == '\n' || ch == '\u{1E}' {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts keeping a copy of the input consumed from here on.
+    pub(crate) fn start_recording(&mut self) {
+        self.recording = Some(Vec::new());
+    }
+
+    pub(crate) fn stop_recording(&mut self) -> Vec<u8> {
+        self.recording.take().unwrap_or_default()
+    }
+
+    /// Bytes read from the input so far, consumed or not.
+    pub(crate) fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.location.offset
+    }
+
+    pub(crate) fn position(&self) -> Position {
+        self.location
+    }
+
+    /// Where the token last returned starts and ends.
+    pub(crate) fn token_span(&self) -> Span {
+        Span { start: self.token_start, end: self.token_end.unwrap_or(self.location) }
+    }
+
+    /// Whether anything but whitespace has been read and not yet consumed.
+    pub(crate) fn has_pending_input(&self) -> bool {
+        self.reader.has_pending()
+            || self.buffer[self.pos..].iter().any(|byte| !byte.is_ascii_whitespace())
+    }
+
+    /// Reads the next chunk of input after the unconsumed bytes, which are
+    /// moved to the front of the buffer first. Only the start of a character
+    /// split across reads is ever carried over, so memory stays bounded by
+    /// the chunk size however long a line is. Returns `false` at the end of
+    /// the input.
+    fn fill(&mut self) -> ParseResult<bool> {
         if self.finished {
             return Ok(false);
         }
+        // Input kept to go back to counts against the memory budget, and is
+        // given up once it is over.
+        let kept = self.checkpoint.map_or(0, |(start, ..)| self.pos - start);
+        let over_budget = self.options.max_total_allocated.filter(|&max| kept > max);
+        if over_budget.is_some() {
+            self.checkpoint = None;
+        }
+        let consumed = self.checkpoint.map_or(self.pos, |(start, ..)| start);
+        self.buffer.drain(..consumed);
+        self.pos -= consumed;
+        if let Some((start, ..)) = &mut self.checkpoint {
+            *start = 0;
+        }
+        if let Some(max) = over_budget {
+            return Err(ParseError::LimitExceeded {
+                limit: ResourceLimit::TotalAllocated,
+                max,
+                position: self.location,
+            });
+        }
 
-        self.current_line.clear();
-        match self.reader.read_line(&mut self.current_line) {
-            Err(e) => return Err(ParseError::Io(e.to_string())),
-            Ok(result) => match result {
-                0 => {
-                    self.finished = true;
-                    Ok(false)
-                }
-                _ => {
-                let line_ref: &'static str = unsafe {
-                    std::mem::transmute(self.current_line.as_str())
-                };
-                    self.line_chars = line_ref.chars().peekable();
-                    self.line_position = 0;
-                    Ok(true)
+        // Read no further than the limit; once it is reached, a one-byte read
+        // tells whether the input goes on past it.
+        let max = self.options.max_input_bytes;
+        let budget = max.map_or(usize::MAX, |max| max.saturating_sub(self.bytes_read).max(1));
+        let start = self.buffer.len();
+        let chunk = self.options.buffer_size.max(MIN_BUFFER_SIZE);
+        self.buffer.resize(start + chunk.min(budget), 0);
+        let read = loop {
+            match self.reader.read(&mut self.buffer[start..]) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                read => break read,
+            }
+        };
+        self.buffer.truncate(start + *read.as_ref().unwrap_or(&0));
+
+        match read {
+            // Only a read that would block may succeed when tried again.
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Err(ParseError::Io(IoError::from(e).at(self.location))),
+            Err(e) => {
+                self.finished = true;
+                Err(ParseError::Io(IoError::from(e).at(self.location)))
+            }
+            Ok(0) => {
+                self.finished = true;
+                Ok(false)
+            }
+            Ok(read) => {
+                self.bytes_read += read;
+                match max.filter(|&max| self.bytes_read > max) {
+                    Some(max) => {
+                        self.finished = true;
+                        self.buffer.clear();
+                        Err(ParseError::LimitExceeded {
+                            limit: ResourceLimit::InputBytes,
+                            max,
+                            position: self.location,
+                        })
+                    }
+                    None => Ok(true),
                 }
             }
         }
     }
 
-    fn current_char(&mut self) -> ParseResult<Option<char>> {
+    /// Decodes the next character, returning it with its length in bytes.
+    /// Invalid UTF-8 ends the input, like a failed read.
+    fn peek_char(&mut self) -> ParseResult<Option<(char, usize)>> {
         loop {
-            if let Some(&ch) = self.line_chars.peek() {
-                return Ok(Some(ch));
+            let rest = &self.buffer[self.pos..];
+            let len = match rest.first() {
+                Some(&byte) if byte.is_ascii() => return Ok(Some((byte as char, 1))),
+                Some(0xC2..=0xDF) => 2,
+                Some(0xE0..=0xEF) => 3,
+                Some(0xF0..=0xF4) => 4,
+                Some(_) => 1,
+                None => 0,
+            };
+            // An empty buffer, or a character split across reads.
+            if rest.len() < len.max(1) && self.fill()? {
+                continue;
             }
-            
-            if !self.load_next_line()? {
+            let rest = &self.buffer[self.pos..];
+            if rest.is_empty() {
                 return Ok(None);
             }
+
+            let bytes = &rest[..len.min(rest.len())];
+            return match std::str::from_utf8(bytes) {
+                Ok(s) => Ok(s.chars().next().map(|ch| (ch, len))),
+                // Like `String::from_utf8_lossy`, one U+FFFD per maximal
+                // invalid sequence.
+                Err(e) if self.options.replace_invalid_utf8 => {
+                    Ok(Some((char::REPLACEMENT_CHARACTER, e.error_len().unwrap_or(bytes.len()))))
+                }
+                Err(_) => {
+                    self.finished = true;
+                    self.buffer.clear();
+                    self.pos = 0;
+                    let message = "stream did not contain valid UTF-8";
+                    Err(ParseError::Io(IoError::new(io::ErrorKind::InvalidData, message).at(self.location)))
+                }
+            };
         }
     }
 
+    fn current_char(&mut self) -> ParseResult<Option<char>> {
+        Ok(self.peek_char()?.map(|(ch, _)| ch))
+    }
+
     fn advance(&mut self) -> ParseResult<Option<char>> {
-        if let Some(ch) = self.line_chars.next() {
-            self.position += 1;
-            self.line_position += 1;
-            Ok(Some(ch))
-        } else if self.load_next_line()? {
-            self.advance()
-        } else {
-            Ok(None)
+        let next = self.peek_char()?;
+        if let Some((ch, len)) = next {
+            if let Some(recording) = &mut self.recording {
+                recording.extend_from_slice(&self.buffer[self.pos..self.pos + len]);
+            }
+            self.pos += len;
+            self.location.offset += len;
+            if ch == '\n' {
+                self.location.line += 1;
+                self.location.column = 1;
+            } else {
+                self.location.column += 1;
+            }
         }
+        Ok(next.map(|(ch, _)| ch))
     }
 
-    fn skip_whitespace(&mut self) -> ParseResult<()> {
-        while let Some(ch) = self.current_char()? {
-            if ch.is_whitespace() {
-                self.advance()?;
-            } else {
-                break;
+    pub(crate) fn skip_whitespace(&mut self) -> ParseResult<()> {
+        loop {
+            self.skip_ascii_whitespace();
+            match self.current_char()? {
+                // JSON5 whitespace also takes in the byte order mark.
+                Some(ch) if ch.is_whitespace() || (ch == '\u{FEFF}' && self.options.dialect == Dialect::Json5) => {
+                    self.advance()?;
+                }
+                Some('/') if self.options.allow_comments => {
+                    let start = self.location;
+                    self.skip_comment()?;
+                    self.warn(WarningKind::Comment, start);
+                }
+                _ => return Ok(()),
             }
         }
+    }
+
+    /// Consumes, in one step, the ASCII whitespace at the front of the
+    /// buffered input, such as the indentation of pretty-printed documents.
+    fn skip_ascii_whitespace(&mut self) {
+        let rest = &self.buffer[self.pos..];
+        let len = rest
+            .iter()
+            .position(|byte| !matches!(byte, b' ' | b'\t' | b'\n' | b'\r'))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return;
+        }
+        let run = &rest[..len];
+        match memchr::memrchr(b'\n', run) {
+            Some(last) => {
+                self.location.line += memchr::memchr_iter(b'\n', run).count();
+                self.location.column = len - last;
+            }
+            None => self.location.column += len,
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.extend_from_slice(run);
+        }
+        self.location.offset += len;
+        self.pos += len;
+    }
+
+    fn warn(&mut self, kind: WarningKind, position: Position) {
+        if self.options.collect_warnings {
+            self.warnings.push(Warning { kind, position });
+        }
+    }
+
+    /// `error`, or `UnexpectedEndOfInput` if the input ended partway through
+    /// the token, as it would when a document is cut short.
+    fn truncated(&mut self, error: ParseError, while_parsing: Context) -> ParseError {
+        match self.current_char() {
+            Ok(None) => ParseError::UnexpectedEndOfInput { while_parsing, position: self.location },
+            _ => error,
+        }
+    }
+
+    /// Skips the byte order mark that Windows tools put at the start of
+    /// UTF-8 files.
+    fn skip_bom(&mut self) -> ParseResult<()> {
+        if self.current_char()? == Some('\u{FEFF}') {
+            self.advance()?;
+        }
         Ok(())
     }
 
-    fn read_string(&mut self) -> ParseResult<String> {
-        let start_pos = self.position;
+    /// Skips a `// line` or `/* block */` comment starting at the current `/`.
+    fn skip_comment(&mut self) -> ParseResult<()> {
+        let start_pos = self.location;
+        self.advance()?;
+        match self.advance()? {
+            Some('/') => {
+                // A line comment runs to any line terminator, which JSON5
+                // takes from ECMAScript.
+                while let Some(ch) = self.current_char()? {
+                    if matches!(ch, '\n' | '\r' | '\u{2028}' | '\u{2029}') {
+                        break;
+                    }
+                    self.advance()?;
+                }
+                Ok(())
+            }
+            Some('*') => {
+                let mut star = false;
+                while let Some(ch) = self.advance()? {
+                    if star && ch == '/' {
+                        return Ok(());
+                    }
+                    star = ch == '*';
+                }
+                Err(ParseError::UnterminatedComment(start_pos))
+            }
+            _ => Err(ParseError::InvalidCharacter {
+                char: '/',
+                position: start_pos,
+            }),
+        }
+    }
+
+    fn read_string(&mut self, quote: char) -> ParseResult<String> {
+        let start_pos = self.location;
         
-        if self.advance()? != Some('"') {
+        if self.advance()? != Some(quote) {
             return Err(ParseError::InvalidCharacter {
-                char: '"',
+                char: quote,
                 position: start_pos,
             });
         }
 
         let mut result = String::new();
         let mut escaped = false;
+        // Where the backslash of the escape being read is.
+        let mut escape_start = start_pos;
+        let mut raw_control = false;
 
-        while let Some(ch) = self.advance()? {
+        loop {
+            if !escaped {
+                self.take_plain(quote, Some(&mut result));
+                self.check_string_length(&result, start_pos)?;
+            }
+            let at = self.location;
+            let Some(ch) = self.advance()? else { break };
             if escaped {
-                match ch {
-                    '"' => result.push('"'),
-                    '\\' => result.push('\\'),
-                    '/' => result.push('/'),
-                    'b' => result.push('\u{0008}'),
-                    'f' => result.push('\u{000C}'),
-                    'n' => result.push('\n'),
-                    'r' => result.push('\r'),
-                    't' => result.push('\t'),
-                    'u' => {
-                        let mut hex_digits = String::new();
-                        for _ in 0..4 {
-                            match self.advance()? {
-                                Some(hex_ch) if hex_ch.is_ascii_hexdigit() => {
-                                    hex_digits.push(hex_ch);
-                                }
-                                _ => return Err(ParseError::InvalidEscape(self.position)),
-                            }
-                        }
-                        let code_point = u32::from_str_radix(&hex_digits, 16)
-                            .map_err(|_| ParseError::InvalidEscape(self.position))?;
-                        if let Some(unicode_char) = char::from_u32(code_point) {
-                            result.push(unicode_char);
-                        } else {
-                            return Err(ParseError::InvalidEscape(self.position));
-                        }
+                if let Some(unescaped) = simple_escape(ch) {
+                    result.push(unescaped);
+                } else if ch == 'u' {
+                    if self.read_unicode_escape(escape_start, &mut result)? {
+                        // A lone high surrogate was followed by some other escape.
+                        continue;
                     }
-                    _ => return Err(ParseError::InvalidEscape(self.position)),
+                } else if self.options.dialect == Dialect::Json5 {
+                    self.read_json5_escape(ch, &mut result)?;
+                    self.warn(WarningKind::NonStandardEscape, escape_start);
+                } else {
+                    return Err(ParseError::InvalidEscape(self.location));
                 }
                 escaped = false;
             } else if ch == '\\' {
                 escaped = true;
-            } else if ch == '"' {
+                escape_start = at;
+            } else if ch == quote {
                 return Ok(result);
+            } else if self.must_escape(ch) {
+                return Err(ParseError::InvalidCharacter { char: ch, position: at });
             } else {
+                if ch < ' ' && !raw_control {
+                    raw_control = true;
+                    self.warn(WarningKind::ControlCharacter, at);
+                }
                 result.push(ch);
             }
+            self.check_string_length(&result, start_pos)?;
         }
 
         Err(ParseError::UnterminatedString(start_pos))
     }
 
-    fn read_number(&mut self) -> ParseResult<f64> {
-        let start_pos = self.position;
+    /// Consumes, in one step, the buffered characters up to the next quote,
+    /// backslash or control character, adding them to `result`. Only whole,
+    /// valid characters are taken; whatever ends the run is left to be read
+    /// one character at a time.
+    fn take_plain(&mut self, quote: char, result: Option<&mut String>) {
+        let rest = &self.buffer[self.pos..];
+        let end = memchr::memchr2(quote as u8, b'\\', rest).unwrap_or(rest.len());
+        let end = rest[..end].iter().position(|&byte| byte < b' ').unwrap_or(end);
+        let run = match std::str::from_utf8(&rest[..end]) {
+            Ok(run) => run,
+            // Stop before a character split across reads, or invalid UTF-8.
+            Err(e) => std::str::from_utf8(&rest[..e.valid_up_to()]).expect("valid up to here"),
+        };
+        if run.is_empty() {
+            return;
+        }
+        if let Some(result) = result {
+            result.push_str(run);
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.extend_from_slice(run.as_bytes());
+        }
+        self.location.column += run.chars().count();
+        self.location.offset += run.len();
+        self.pos += run.len();
+    }
+
+    fn check_string_length(&self, string: &str, start: Position) -> ParseResult<()> {
+        let limits = [
+            (ResourceLimit::StringLength, self.options.max_string_length),
+            (ResourceLimit::TotalAllocated, self.options.max_total_allocated),
+        ];
+        for (limit, max) in limits {
+            if let Some(max) = max.filter(|&max| string.len() > max) {
+                return Err(ParseError::LimitExceeded { limit, max, position: start });
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads past a string without decoding it or allocating, for values
+    /// being skipped. Only the character after each backslash is skipped
+    /// over; escapes aren't otherwise checked.
+    fn skip_string(&mut self, quote: char) -> ParseResult<String> {
+        let start_pos = self.location;
+        self.advance()?;
+        let mut raw_control = false;
+
+        loop {
+            self.take_plain(quote, None);
+            let at = self.location;
+            let Some(ch) = self.advance()? else { break };
+            if ch == '\\' {
+                if self.advance()?.is_none() {
+                    break;
+                }
+            } else if ch == quote {
+                return Ok(String::new());
+            } else if self.must_escape(ch) {
+                return Err(ParseError::InvalidCharacter { char: ch, position: at });
+            } else if ch < ' ' && !raw_control {
+                raw_control = true;
+                self.warn(WarningKind::ControlCharacter, at);
+            }
+        }
+
+        Err(ParseError::UnterminatedString(start_pos))
+    }
+
+    fn must_escape(&self, ch: char) -> bool {
+        must_escape(ch, self.options.allow_control_characters, self.options.dialect == Dialect::Json5)
+    }
+
+    /// Reads the digits of a `\u` escape, combining a UTF-16 surrogate pair
+    /// written as two escapes into one character. Returns `true` when it has
+    /// consumed the backslash of a following escape that isn't `\u`.
+    fn read_unicode_escape(&mut self, start: Position, result: &mut String) -> ParseResult<bool> {
+        let mut start = start;
+        let mut code_point = self.read_hex_escape(4)?;
+
+        loop {
+            let escape = Span { start, end: self.location };
+            if !is_high_surrogate(code_point) {
+                match char::from_u32(code_point) {
+                    Some(ch) => result.push(ch),
+                    None => self.unpaired_surrogate(escape, result)?,
+                }
+                return Ok(false);
+            }
+
+            // A high surrogate needs a low one right after it.
+            let low_start = self.location;
+            if self.current_char()? != Some('\\') {
+                self.unpaired_surrogate(escape, result)?;
+                return Ok(false);
+            }
+            self.advance()?;
+            if self.current_char()? != Some('u') {
+                self.unpaired_surrogate(escape, result)?;
+                return Ok(true);
+            }
+            self.advance()?;
+
+            let low = self.read_hex_escape(4)?;
+            if let Some(ch) = combine_surrogates(code_point, low) {
+                result.push(ch);
+                return Ok(false);
+            }
+            self.unpaired_surrogate(escape, result)?;
+            start = low_start;
+            code_point = low;
+        }
+    }
+
+    fn unpaired_surrogate(&self, escape: Span, result: &mut String) -> ParseResult<()> {
+        if self.options.replace_unpaired_surrogates {
+            result.push(char::REPLACEMENT_CHARACTER);
+            Ok(())
+        } else {
+            Err(ParseError::UnpairedSurrogate(escape))
+        }
+    }
+
+    fn read_hex_escape(&mut self, digits: usize) -> ParseResult<u32> {
+        let mut code_point = 0;
+        for _ in 0..digits {
+            match self.advance()?.and_then(|hex_ch| hex_ch.to_digit(16)) {
+                Some(digit) => code_point = code_point * 16 + digit,
+                None => return Err(ParseError::InvalidEscape(self.location)),
+            }
+        }
+        Ok(code_point)
+    }
+
+    /// The escapes JSON5 adds to JSON's: `\'`, `\v`, `\0`, `\xHH`, an escaped
+    /// line break (a line continuation), and any other non-digit standing for
+    /// itself.
+    fn read_json5_escape(&mut self, ch: char, result: &mut String) -> ParseResult<()> {
+        match ch {
+            'v' => result.push('\u{000B}'),
+            '0' if !matches!(self.current_char()?, Some('0'..='9')) => result.push('\0'),
+            'x' => {
+                let code_point = self.read_hex_escape(2)?;
+                result.push(char::from_u32(code_point).expect("two hex digits are a valid char"));
+            }
+            '\r' => {
+                if self.current_char()? == Some('\n') {
+                    self.advance()?;
+                }
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => {}
+            '0'..='9' => return Err(ParseError::InvalidEscape(self.location)),
+            other => result.push(other),
+        }
+        Ok(())
+    }
+
+    fn read_number(&mut self) -> ParseResult<JsonNumber> {
+        let start_pos = self.location;
         let mut number_str = String::new();
+        let mut grammar = NumberGrammar::Start;
 
         if let Some('-') = self.current_char()? {
+            grammar.accept('-');
             number_str.push('-');
             self.advance()?;
         }
 
-        if let Some(ch) = self.current_char()? {
-            if ch == '0' {
-                number_str.push('0');
-                self.advance()?;
-            } else if ch.is_ascii_digit() {
-                while let Some(digit) = self.current_char()? {
-                    if digit.is_ascii_digit() {
-                        number_str.push(digit);
-                        self.advance()?;
-                    } else {
-                        break;
-                    }
+        if self.options.allow_non_finite_numbers && self.current_char()? == Some('I') {
+            return match self.read_literal()?.as_str() {
+                "Infinity" => {
+                    self.warn(WarningKind::NonStandardNumber, start_pos);
+                    Ok(JsonNumber::from_f64(f64::NEG_INFINITY))
                 }
-            } else {
-                return Err(ParseError::InvalidNumber(start_pos));
+                _ => Err(ParseError::InvalidNumber(start_pos)),
+            };
+        }
+
+        while let Some(ch) = self.current_char()? {
+            if !grammar.accept(ch) {
+                break;
             }
-        } else {
+            number_str.push(ch);
+            self.advance()?;
+        }
+        if !grammar.is_complete() {
             return Err(ParseError::InvalidNumber(start_pos));
         }
 
-        if let Some('.') = self.current_char()? {
-            number_str.push('.');
-            self.advance()?;
-            
-            let mut has_fraction = false;
-            while let Some(digit) = self.current_char()? {
-                if digit.is_ascii_digit() {
-                    number_str.push(digit);
-                    self.advance()?;
-                    has_fraction = true;
-                } else {
-                    break;
+        let number = JsonNumber::parse_lexeme(&number_str)
+            .ok_or(ParseError::InvalidNumber(start_pos))?;
+
+        if self.options.preserve_number_text && number.as_raw_str().is_none() {
+            Ok(JsonNumber::with_raw(number.as_f64(), number_str))
+        } else {
+            Ok(number)
+        }
+    }
+
+    /// Reads a JSON5 number: JSON's grammar plus a leading `+`, hexadecimal
+    /// integers, a decimal point with digits on only one side, and signed
+    /// `Infinity` and `NaN`. The value is parsed from the text normalized
+    /// to a JSON lexeme; `preserve_number_text` keeps the text as written.
+    fn read_json5_number(&mut self) -> ParseResult<JsonNumber> {
+        let start_pos = self.location;
+        let mut source = String::new();
+        let number = self.json5_number(&mut source)?;
+        let mut grammar = NumberGrammar::Start;
+        if !(source.chars().all(|ch| grammar.accept(ch)) && grammar.is_complete()) {
+            self.warn(WarningKind::NonStandardNumber, start_pos);
+        }
+        if self.options.preserve_number_text {
+            Ok(number.with_source_text(source))
+        } else {
+            Ok(number)
+        }
+    }
+
+    /// Reads a JSON5 number, copying the text read to `source`.
+    fn json5_number(&mut self, source: &mut String) -> ParseResult<JsonNumber> {
+        let start_pos = self.location;
+        let invalid = || ParseError::InvalidNumber(start_pos);
+
+        let sign = match self.current_char()? {
+            Some(sign @ ('+' | '-')) => {
+                self.advance()?;
+                source.push(sign);
+                if sign == '-' { "-" } else { "" }
+            }
+            _ => "",
+        };
+
+        if matches!(self.current_char()?, Some('I' | 'N')) {
+            let literal = self.read_literal()?;
+            source.push_str(&literal);
+            let value = match literal.as_str() {
+                "Infinity" => f64::INFINITY,
+                "NaN" => f64::NAN,
+                _ => return Err(invalid()),
+            };
+            return Ok(JsonNumber::from_f64(if sign.is_empty() { value } else { -value }));
+        }
+
+        let integer = self.read_while(|ch| ch.is_ascii_digit())?;
+        source.push_str(&integer);
+        if integer == "0" && matches!(self.current_char()?, Some('x' | 'X')) {
+            source.push(self.advance()?.expect("checked above"));
+            let digits = self.read_while(|ch| ch.is_ascii_hexdigit())?;
+            source.push_str(&digits);
+            if digits.is_empty() {
+                return Err(invalid());
+            }
+            return match u64::from_str_radix(&digits, 16) {
+                Ok(value) => JsonNumber::parse_lexeme(&format!("{}{}", sign, value)).ok_or_else(invalid),
+                Err(_) => {
+                    let value = digits
+                        .chars()
+                        .filter_map(|digit| digit.to_digit(16))
+                        .fold(0.0, |acc, digit| acc * 16.0 + f64::from(digit));
+                    Ok(JsonNumber::from_f64(if sign.is_empty() { value } else { -value }))
                 }
+            };
+        }
+        if integer.len() > 1 && integer.starts_with('0') {
+            return Err(invalid());
+        }
+
+        let mut fraction = String::new();
+        if self.current_char()? == Some('.') {
+            self.advance()?;
+            fraction = self.read_while(|ch| ch.is_ascii_digit())?;
+            source.push('.');
+            source.push_str(&fraction);
+        }
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut lexeme = format!("{}{}", sign, if integer.is_empty() { "0" } else { &integer });
+        if !fraction.is_empty() {
+            lexeme.push('.');
+            lexeme.push_str(&fraction);
+        }
+        let exponent_start = lexeme.len();
+        if let Some(e @ ('e' | 'E')) = self.current_char()? {
+            self.advance()?;
+            lexeme.push(e);
+            if let Some(sign @ ('+' | '-')) = self.current_char()? {
+                self.advance()?;
+                lexeme.push(sign);
             }
-            
-            if !has_fraction {
-                return Err(ParseError::InvalidNumber(start_pos));
+            let exponent = self.read_while(|ch| ch.is_ascii_digit())?;
+            if exponent.is_empty() {
+                return Err(invalid());
             }
+            lexeme.push_str(&exponent);
         }
+        source.push_str(&lexeme[exponent_start..]);
+
+        JsonNumber::parse_lexeme(&lexeme).ok_or_else(invalid)
+    }
 
-        if let Some(ch) = self.current_char()? {
-            if ch == 'e' || ch == 'E' {
-                number_str.push(ch);
+    fn read_while(&mut self, accept: impl Fn(char) -> bool) -> ParseResult<String> {
+        let mut text = String::new();
+        while let Some(ch) = self.current_char()? {
+            if !accept(ch) {
+                break;
+            }
+            text.push(ch);
+            self.advance()?;
+        }
+        Ok(text)
+    }
+
+    /// Reads an ECMAScript identifier name: a Unicode letter, `_` or `$`,
+    /// then any of those, digits, combining marks and connectors. Under
+    /// [`Dialect::Json5`], any of its characters may be a `\uXXXX` escape.
+    fn read_identifier(&mut self) -> ParseResult<String> {
+        let mut identifier = String::new();
+        while let Some(ch) = self.current_char()? {
+            let ch = if ch == '\\' && self.options.dialect == Dialect::Json5 {
+                let start = self.location;
                 self.advance()?;
-                
-                if let Some(sign) = self.current_char()? {
-                    if sign == '+' || sign == '-' {
-                        number_str.push(sign);
-                        self.advance()?;
-                    }
-                }
-                
-                let mut has_exponent = false;
-                while let Some(digit) = self.current_char()? {
-                    if digit.is_ascii_digit() {
-                        number_str.push(digit);
-                        self.advance()?;
-                        has_exponent = true;
-                    } else {
-                        break;
-                    }
+                if self.advance()? != Some('u') {
+                    return Err(ParseError::InvalidEscape(start));
                 }
-                
-                if !has_exponent {
-                    return Err(ParseError::InvalidNumber(start_pos));
+                let escaped = char::from_u32(self.read_hex_escape(4)?);
+                match escaped.filter(|&ch| is_identifier_char(ch, identifier.is_empty())) {
+                    Some(ch) => ch,
+                    None => return Err(ParseError::InvalidEscape(start)),
                 }
-            }
+            } else if is_identifier_char(ch, identifier.is_empty()) {
+                self.advance()?;
+                ch
+            } else {
+                break;
+            };
+            identifier.push(ch);
         }
+        Ok(identifier)
+    }
 
-        number_str.parse::<f64>()
-            .map_err(|_| ParseError::InvalidNumber(start_pos))
+    fn literal_token(&mut self, literal: &str, first: char, position: Position) -> ParseResult<Token> {
+        if matches!(literal, "NaN" | "Infinity") && self.options.allow_non_finite_numbers {
+            self.warn(WarningKind::NonStandardNumber, position);
+        }
+        match literal {
+            "true" => Ok(Token::new(TokenType::Boolean(true), position)),
+            "false" => Ok(Token::new(TokenType::Boolean(false), position)),
+            "null" => Ok(Token::new(TokenType::Null, position)),
+            "NaN" if self.options.allow_non_finite_numbers => Ok(Token::new(
+                TokenType::Number(JsonNumber::from_f64(f64::NAN)),
+                position,
+            )),
+            "Infinity" if self.options.allow_non_finite_numbers => Ok(Token::new(
+                TokenType::Number(JsonNumber::from_f64(f64::INFINITY)),
+                position,
+            )),
+            _ => Err(ParseError::InvalidCharacter {
+                char: first,
+                position,
+            }),
+        }
     }
 
     fn read_literal(&mut self) -> ParseResult<String> {
@@ -243,13 +811,19 @@ impl<R: Read> Iterator for Lexer<R> {
     type Item = ParseResult<Token>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.skip_whitespace() {
-            Err(e) => return Some(Err(e)),
-            Ok(()) => {}
+        if self.location.offset == 0 && self.options.allow_bom {
+            if let Err(e) = self.skip_bom() {
+                return Some(Err(e));
+            }
+        }
+        if let Err(e) = self.skip_whitespace() {
+            return Some(Err(e));
         }
 
-        let current_pos = self.position;
-        
+        let current_pos = self.location;
+        self.token_start = self.location;
+        self.token_end = None;
+
         let ch = match self.current_char() {
             Ok(Some(ch)) => ch,
             Ok(None) => return Some(Ok(Token::new(TokenType::Eof, current_pos))),
@@ -281,31 +855,57 @@ impl<R: Read> Iterator for Lexer<R> {
                 self.advance().ok()?;
                 Ok(Token::new(TokenType::Colon, current_pos))
             }
+            '"' | '\'' if self.skip_strings && (ch == '"' || self.options.dialect == Dialect::Json5) => {
+                if ch == '\'' {
+                    self.warn(WarningKind::SingleQuotedString, current_pos);
+                }
+                self.skip_string(ch).map(|s| Token::new(TokenType::String(s), current_pos))
+            }
             '"' => {
-                match self.read_string() {
+                match self.read_string('"') {
                     Ok(s) => Ok(Token::new(TokenType::String(s), current_pos)),
                     Err(e) => Err(e),
                 }
             }
+            '\'' if self.options.dialect == Dialect::Json5 => {
+                self.warn(WarningKind::SingleQuotedString, current_pos);
+                self.read_string('\'').map(|s| Token::new(TokenType::String(s), current_pos))
+            }
+            '+' | '-' | '.' | '0'..='9' if self.options.dialect == Dialect::Json5 => {
+                self.read_json5_number().map(|n| Token::new(TokenType::Number(n), current_pos))
+            }
             '-' | '0'..='9' => {
                 match self.read_number() {
                     Ok(n) => Ok(Token::new(TokenType::Number(n), current_pos)),
+                    Err(e @ ParseError::InvalidNumber(_)) => Err(self.truncated(e, Context::Number)),
                     Err(e) => Err(e),
                 }
             }
+            ch if self.options.allow_unquoted_keys
+                && (is_identifier_char(ch, true) || (ch == '\\' && self.options.dialect == Dialect::Json5)) =>
+            {
+                self.read_identifier().and_then(|identifier| {
+                    // A bare word is a key only where a key can appear, that
+                    // is, right before a colon.
+                    self.token_end = Some(self.location);
+                    self.skip_whitespace()?;
+                    if self.current_char()? == Some(':') {
+                        self.warn(WarningKind::UnquotedKey, current_pos);
+                        Ok(Token::new(TokenType::String(identifier), current_pos))
+                    } else {
+                        self.literal_token(&identifier, ch, current_pos)
+                    }
+                })
+            }
             'a'..='z' | 'A'..='Z' => {
                 match self.read_literal() {
-                    Ok(literal) => {
-                        match literal.as_str() {
-                            "true" => Ok(Token::new(TokenType::Boolean(true), current_pos)),
-                            "false" => Ok(Token::new(TokenType::Boolean(false), current_pos)),
-                            "null" => Ok(Token::new(TokenType::Null, current_pos)),
-                            _ => Err(ParseError::InvalidCharacter {
-                                char: ch,
-                                position: current_pos,
-                            }),
+                    Ok(literal) => self.literal_token(&literal, ch, current_pos).map_err(|e| {
+                        if ["true", "false", "null"].iter().any(|word| word.starts_with(&literal)) {
+                            self.truncated(e, Context::Literal)
+                        } else {
+                            e
                         }
-                    }
+                    }),
                     Err(e) => Err(e),
                 }
             }
@@ -317,4 +917,138 @@ impl<R: Read> Iterator for Lexer<R> {
 
         Some(token_result)
     }
-}
\ No newline at end of file
+}
+
+/// Iterator over the tokens of a JSON text with their spans, returned by
+/// [`tokens`]. Ends after the last token or the first error.
+pub struct Tokens<R: Read> {
+    lexer: Lexer<R>,
+    done: bool,
+}
+
+impl<R: Read> Iterator for Tokens<R> {
+    type Item = ParseResult<SpannedToken>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let token = match self.lexer.next() {
+            Some(Ok(token)) if token.token_type != TokenType::Eof => token,
+            Some(Err(e)) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+            _ => {
+                self.done = true;
+                return None;
+            }
+        };
+        Some(Ok(SpannedToken {
+            token_type: token.token_type,
+            span: self.lexer.token_span(),
+        }))
+    }
+}
+
+/// Splits a JSON text into tokens without checking how they fit together,
+/// for formatters, highlighters and linters. Whitespace is skipped.
+pub fn tokens<R: Read>(reader: R) -> Tokens<R> {
+    tokens_with(reader, ParserOptions::default())
+}
+
+pub fn tokens_with<R: Read>(reader: R, options: ParserOptions) -> Tokens<R> {
+    Tokens {
+        lexer: Lexer::with_options(reader, options),
+        done: false,
+    }
+}
+
+/// Whether `ch` may start (`first`) or continue an ECMAScript identifier
+/// name, using Unicode's ID_Start and ID_Continue.
+fn is_identifier_char(ch: char, first: bool) -> bool {
+    match ch {
+        '$' | '_' => true,
+        // Zero-width non-joiner and joiner.
+        '\u{200C}' | '\u{200D}' => !first,
+        _ if first => unicode_ident::is_xid_start(ch),
+        _ => unicode_ident::is_xid_continue(ch),
+    }
+}
+
+/// The character a one-letter escape such as `\n` stands for.
+pub(crate) fn simple_escape(ch: char) -> Option<char> {
+    Some(match ch {
+        '"' => '"',
+        '\\' => '\\',
+        '/' => '/',
+        'b' => '\u{0008}',
+        'f' => '\u{000C}',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        _ => return None,
+    })
+}
+
+/// Whether `ch` has to be escaped inside a string. RFC 8259 requires it of
+/// every control character U+0000 to U+001F; `allow_control` lets them all
+/// through, and JSON5 only insists on escaping line breaks.
+pub(crate) fn must_escape(ch: char, allow_control: bool, json5: bool) -> bool {
+    ch < ' ' && !allow_control && (!json5 || ch == '\n' || ch == '\r')
+}
+
+/// Whether a `\u` escape's code unit is the first half of a UTF-16
+/// surrogate pair.
+pub(crate) fn is_high_surrogate(unit: u32) -> bool {
+    (0xD800..=0xDBFF).contains(&unit)
+}
+
+/// The character a surrogate pair written as two `\u` escapes stands for, or
+/// `None` if the two code units don't make a pair.
+pub(crate) fn combine_surrogates(high: u32, low: u32) -> Option<char> {
+    if !is_high_surrogate(high) || !(0xDC00..=0xDFFF).contains(&low) {
+        return None;
+    }
+    char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+}
+
+/// The JSON number grammar, fed one character at a time, so the lexer and
+/// the in-memory parsers accept exactly the same numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NumberGrammar {
+    Start,
+    Minus,
+    Zero,
+    Integer,
+    Point,
+    Fraction,
+    Exponent,
+    ExponentSign,
+    ExponentDigits,
+}
+
+impl NumberGrammar {
+    /// Moves past `ch`, or returns false if the number can't go on with it.
+    pub(crate) fn accept(&mut self, ch: char) -> bool {
+        use NumberGrammar::*;
+        *self = match (*self, ch) {
+            (Start, '-') => Minus,
+            (Start | Minus, '0') => Zero,
+            (Start | Minus | Integer, '0'..='9') => Integer,
+            (Zero | Integer, '.') => Point,
+            (Point | Fraction, '0'..='9') => Fraction,
+            (Zero | Integer | Fraction, 'e' | 'E') => Exponent,
+            (Exponent, '+' | '-') => ExponentSign,
+            (Exponent | ExponentSign | ExponentDigits, '0'..='9') => ExponentDigits,
+            _ => return false,
+        };
+        true
+    }
+
+    /// Whether what was accepted so far is a whole number.
+    pub(crate) fn is_complete(self) -> bool {
+        use NumberGrammar::*;
+        matches!(self, Zero | Integer | Fraction | ExponentDigits)
+    }
+}
@@ -1,20 +1,105 @@
+// Lets the derive macros' `::streaming_json_parser` paths resolve in this
+// crate's own tests.
+extern crate self as streaming_json_parser;
+
 pub mod types;
+pub mod number;
+pub mod encoding;
 pub mod lexer;
 pub mod parser;
+pub mod options;
+pub mod merge;
+pub mod visit;
+pub mod prune;
+pub mod stats;
+pub mod metrics;
+pub mod canonical;
+pub mod compare;
+pub mod path;
+pub mod borrowed;
+pub mod lazy;
+pub mod tape;
+pub mod lossy;
+pub mod warning;
+pub mod diagnostic;
+pub mod ser;
+pub mod minify;
+pub mod ndjson;
+pub mod encoder;
+pub mod event;
+pub mod push;
+pub mod chunk;
+pub mod convert;
+pub mod schema;
+#[cfg(feature = "query")]
+pub mod query;
+pub mod pipeline;
+#[cfg(feature = "arena")]
+pub mod arena;
+#[cfg(feature = "async")]
+pub mod async_parser;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub mod compression;
+#[cfg(feature = "serde")]
+mod serde_value;
 
-pub use types::{JsonValue, ParseError, ParseResult};
-pub use parser::{StreamingJsonParser, parse_json_string, parse_json_stream};
+pub use types::{Context, Entry, IoError, JsonValue, Key, Map, ParseError, ParseResult, Position, RawJson, ResourceLimit, SortedKeys, Span, SpannedToken, TokenType};
+pub use lexer::{Tokens, tokens, tokens_with};
+pub use number::{JsonNumber, NumberFormat};
+pub use parser::{ArrayElements, JsonItems, Progress, StreamingJsonParser, parse_json_bytes, parse_json_bytes_with, parse_json_file, parse_json_file_with, parse_json_items, parse_json_items_with, parse_json_reader, parse_json_reader_with, parse_json_string, parse_json_string_with, parse_json_string_with_raw_values, parse_json_stream, parse_json_stream_with};
+pub use options::{DEFAULT_BUFFER_SIZE, DEFAULT_MAX_DEPTH, Dialect, Framing, ParserOptions};
+pub use encoding::{Encoding, detect_encoding};
+pub use path::{JsonPointer, Leaves, Path, PathError, PathSegment};
+pub use merge::{ArrayMerge, MergePolicy, NullMerge};
+pub use visit::{Visitor, Walk};
+pub use stats::DocumentStats;
+pub use metrics::ParseMetrics;
+pub use canonical::{CanonicalValue, to_canonical_string};
+pub use minify::minify;
+pub use ndjson::{FlushPolicy, NdjsonWriter};
+pub use encoder::JsonEncoder;
+pub use event::{Event, EventReader};
+pub use push::{PushParser, Pushed};
+pub use chunk::ChunkParser;
+pub use convert::{FromJson, ToJson};
+pub use schema::SchemaBuilder;
+#[cfg(feature = "schema")]
+pub use schema::{Validator, Violation};
+#[cfg(feature = "query")]
+pub use query::JsonPath;
+#[cfg(feature = "derive")]
+pub use streaming_json_parser_derive::{FromJson, ToJson};
+pub use pipeline::{DEFAULT_PIPELINE_CAPACITY, spawn_json_stream, spawn_json_stream_with};
+#[cfg(feature = "color")]
+pub use ser::ColorWriter;
+pub use compare::CompareOptions;
+pub use ser::{CompactFormatter, EscapePolicy, Formatter, IndentStyle, LineEnding, PrettyFormatter, SerializeError, Serializer, to_string, to_string_pretty, to_string_with, to_writer, to_writer_pretty, to_writer_with};
+pub use borrowed::{JsonValueRef, MapRef, parse_json_str_borrowed};
+pub use lazy::{LazyArrayIter, LazyDocument, LazyObjectIter, LazyValue};
+pub use tape::{Tape, TapeChildren, TapeEntry, TapeKind, TapeNode};
+pub use lossy::{parse_json_string_lossy, PartialValue};
+pub use warning::{Warning, WarningKind, parse_json_string_with_warnings};
+pub use diagnostic::Diagnostic;
+#[cfg(feature = "arena")]
+pub use arena::{ArenaValue, parse_json_in};
+#[cfg(feature = "async")]
+pub use async_parser::AsyncStreamingJsonParser;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub use compression::Decompressor;
+#[cfg(feature = "serde")]
+pub use serde_value::{from_value, to_value};
 
 use std::io::Read;
 
 pub fn validate_json_string(input: &str) -> bool {
-    match parse_json_string(input) {
-        Ok(_) => true,
-        Err(_) => false,
-    }
+    parse_json_string(input).is_ok()
 }
 
+/// The documents of `reader`, one at a time. With the `gzip` or `zstd`
+/// feature, input in that format is decompressed on the fly.
 pub fn stream_json_objects<R: Read>(reader: R) -> impl Iterator<Item = ParseResult<JsonValue>> {
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    let reader = Decompressor::new(reader);
     parse_json_stream(reader)
 }
 
@@ -23,6 +108,10 @@ mod tests {
     use super::*;
     use std::collections::HashMap;
 
+    fn at(offset: usize, line: usize, column: usize) -> Position {
+        Position { offset, line, column }
+    }
+
     #[test]
     fn test_parse_simple_string() {
         let result = parse_json_string("\"hello\"").unwrap();
@@ -32,7 +121,7 @@ mod tests {
     #[test]  
     fn test_parse_number() {
         let result = parse_json_string("42").unwrap();
-        assert_eq!(result, JsonValue::Number(42.0));
+        assert_eq!(result, JsonValue::Number(42.0.into()));
     }
 
     #[test]
@@ -50,7 +139,7 @@ mod tests {
     #[test]
     fn test_parse_empty_object() {
         let result = parse_json_string("{}").unwrap();
-        assert_eq!(result, JsonValue::Object(HashMap::new()));
+        assert_eq!(result, JsonValue::Object(Map::new()));
     }
 
     #[test]
@@ -62,8 +151,8 @@ mod tests {
     #[test]
     fn test_parse_simple_object() {
         let result = parse_json_string("{\"key\": \"value\"}").unwrap();
-        let mut expected = HashMap::new();
-        expected.insert("key".to_string(), JsonValue::String("value".to_string()));
+        let mut expected = Map::new();
+        expected.insert("key".into(), JsonValue::String("value".to_string()));
         assert_eq!(result, JsonValue::Object(expected));
     }
 
@@ -71,9 +160,9 @@ mod tests {
     fn test_parse_simple_array() {
         let result = parse_json_string("[1, 2, 3]").unwrap();
         let expected = JsonValue::Array(vec![
-            JsonValue::Number(1.0),
-            JsonValue::Number(2.0),
-            JsonValue::Number(3.0),
+            JsonValue::Number(1.0.into()),
+            JsonValue::Number(2.0.into()),
+            JsonValue::Number(3.0.into()),
         ]);
         assert_eq!(result, expected);
     }
@@ -109,4 +198,2792 @@ mod tests {
             panic!("Expected TrailingComma error");
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_get_path_with_defaults() {
+        let config = parse_json_string(r#"{"server": {"port": 9000, "hosts": ["a", "b"]}}"#).unwrap();
+        assert_eq!(config.get_path(["server", "port"]).unwrap().as_u16_or(8080), 9000);
+        assert_eq!(config.get_str_at(Path::root().key("server").key("hosts").index(1)).unwrap(), "b");
+        assert_eq!(config.get_str_at(["server", "hosts", "0"]).unwrap(), "a");
+        assert!(config.get_path(["server"]).unwrap().as_bool_or(true));
+    }
+
+    #[test]
+    fn test_get_path_errors() {
+        let config = parse_json_string(r#"{"server": {"port": "9000"}}"#).unwrap();
+
+        match config.get_path(["server", "tls", "cert"]) {
+            Err(PathError::PathNotFound { path, parent, segment }) => {
+                assert_eq!(path, "$.server.tls.cert");
+                assert_eq!(parent, "$.server");
+                assert_eq!(segment, "key \"tls\"");
+            }
+            other => panic!("Expected PathNotFound, got {:?}", other),
+        }
+
+        match config.get_f64_at(["server", "port"]) {
+            Err(PathError::TypeMismatch { expected, found, path }) => {
+                assert_eq!(expected, "number");
+                assert_eq!(found, "string");
+                assert_eq!(path, "$.server.port");
+            }
+            other => panic!("Expected TypeMismatch, got {:?}", other),
+        }
+
+        // `?` turns a PathError into the ParseError variant of the same name.
+        let port = || -> ParseResult<f64> { Ok(config.get_f64_at(["server", "port"])?) };
+        assert!(matches!(port(), Err(ParseError::TypeMismatch { .. })));
+        assert_eq!("x".parse::<JsonPointer>(), Err(PathError::InvalidPointer("x".to_string())));
+    }
+
+    #[test]
+    fn test_entry_builds_nested_objects() {
+        let mut doc = JsonValue::Null;
+        for _ in 0..3 {
+            let hits = doc
+                .entry("counters")
+                .or_insert(JsonValue::Null)
+                .entry("hits")
+                .or_insert(JsonValue::Number(0.0.into()));
+            *hits = JsonValue::from(hits.as_f64_or(0.0) + 1.0);
+        }
+        doc.entry("name")
+            .or_insert_with(|| JsonValue::String("metrics".to_string()));
+
+        assert_eq!(doc.get_f64_at(["counters", "hits"]).unwrap(), 3.0);
+        assert_eq!(doc.get_str_at(["name"]).unwrap(), "metrics");
+    }
+
+    #[test]
+    fn test_set_creates_intermediate_containers() {
+        let mut doc = JsonValue::Null;
+        assert_eq!(doc.set("/a/b/0", JsonValue::Boolean(true)).unwrap(), None);
+        assert_eq!(doc.set("/a/b/-", JsonValue::Null).unwrap(), None);
+        assert_eq!(doc.set("/a/c~1d", JsonValue::Number(1.0.into())).unwrap(), None);
+        assert_eq!(
+            doc.set("/a/b/0", JsonValue::Boolean(false)).unwrap(),
+            Some(JsonValue::Boolean(true))
+        );
+
+        assert_eq!(
+            doc.get_path(["a", "b"]).unwrap(),
+            &JsonValue::Array(vec![JsonValue::Boolean(false), JsonValue::Null])
+        );
+        assert_eq!(doc.get_f64_at(["a", "c/d"]).unwrap(), 1.0);
+        assert!(doc.set("/a/c~1d/x", JsonValue::Null).is_err());
+        assert!(doc.set("a/b", JsonValue::Null).is_err());
+
+        // Indices past the end are not padded with nulls.
+        assert!(matches!(
+            doc.set("/a/b/4000000000", JsonValue::Null),
+            Err(PathError::PathNotFound { .. })
+        ));
+        assert!(doc.set("/a/b/18446744073709551615", JsonValue::Null).is_err());
+        assert_eq!(doc.get_path(["a", "b"]).unwrap().as_array().unwrap().len(), 2);
+
+        // A failed set creates nothing on the way.
+        let mut empty = JsonValue::Object(Default::default());
+        assert!(matches!(empty.set("/a/5", JsonValue::from(1)), Err(PathError::PathNotFound { .. })));
+        assert!(empty.set("/a/b/c", JsonValue::Null).is_ok());
+        assert!(empty.set("/x/y/1", JsonValue::Null).is_err());
+        assert_eq!(empty.to_string(), r#"{"a":{"b":{"c":null}}}"#);
+        assert_eq!(
+            doc.set("/a/b/foo", JsonValue::Null),
+            Err(PathError::TypeMismatch {
+                expected: "array index".to_string(),
+                found: "key \"foo\"".to_string(),
+                path: "$.a.b".to_string(),
+            })
+        );
+
+        // RFC 6901 indices have no sign or leading zero.
+        let array = parse_json_string("[10, 20]").unwrap();
+        assert_eq!(array.get_path(Path::from_pointer("/1").unwrap()).unwrap(), &JsonValue::from(20));
+        for pointer in ["/+1", "/01", "/1.0"] {
+            assert!(array.get_path(Path::from_pointer(pointer).unwrap()).is_err(), "{}", pointer);
+        }
+        assert!(doc.set("/a/b/00", JsonValue::Null).is_err());
+    }
+
+    #[test]
+    fn test_remove_path_returns_removed_value() {
+        let mut doc = parse_json_string(r#"{"a": {"b": [1, 2, 3]}}"#).unwrap();
+        assert_eq!(doc.remove_path("/a/b/1").unwrap(), JsonValue::Number(2.0.into()));
+        assert_eq!(doc.get_path(["a", "b"]).unwrap().as_array().unwrap().len(), 2);
+        assert!(doc.remove_path("/a/missing").is_err());
+        assert!(doc.remove_path("/a/b/5").is_err());
+    }
+
+    #[test]
+    fn test_from_primitives_and_collections() {
+        assert_eq!(JsonValue::from("hi"), JsonValue::String("hi".to_string()));
+        assert_eq!(JsonValue::from(42), JsonValue::Number(42.0.into()));
+        assert_eq!(JsonValue::from(-7i64), JsonValue::Number((-7.0).into()));
+        assert_eq!(JsonValue::from(1.5), JsonValue::Number(1.5.into()));
+        assert_eq!(JsonValue::from(true), JsonValue::Boolean(true));
+        assert_eq!(JsonValue::from(None::<bool>), JsonValue::Null);
+        assert_eq!(
+            JsonValue::from(vec![1, 2]),
+            JsonValue::Array(vec![JsonValue::Number(1.0.into()), JsonValue::Number(2.0.into())])
+        );
+
+        let mut map = HashMap::new();
+        map.insert("name".to_string(), "cc");
+        let mut expected = Map::new();
+        expected.insert("name".into(), JsonValue::String("cc".to_string()));
+        assert_eq!(JsonValue::from(map), JsonValue::Object(expected));
+    }
+
+    #[test]
+    fn test_collect_into_arrays_and_objects() {
+        let array: JsonValue = (1..=3).map(JsonValue::from).collect();
+        assert_eq!(array, parse_json_string("[1, 2, 3]").unwrap());
+
+        let mut object: JsonValue = vec![("a".to_string(), JsonValue::from(1))]
+            .into_iter()
+            .collect();
+        object.extend(vec![("b".to_string(), JsonValue::from(2))]);
+        assert_eq!(object, parse_json_string(r#"{"a": 1, "b": 2}"#).unwrap());
+
+        let mut lazy = JsonValue::Null;
+        lazy.extend(vec![JsonValue::Null]);
+        assert_eq!(lazy, JsonValue::Array(vec![JsonValue::Null]));
+    }
+
+    #[test]
+    fn test_take_and_replace() {
+        let mut doc = parse_json_string(r#"{"payload": [1, 2], "id": 7}"#).unwrap();
+
+        let payload = doc.get_path_mut(["payload"]).unwrap().take();
+        assert_eq!(payload, parse_json_string("[1, 2]").unwrap());
+        assert!(doc.get_path(["payload"]).unwrap().is_null());
+
+        let old_id = doc.get_path_mut(["id"]).unwrap().replace(JsonValue::from("seven"));
+        assert_eq!(old_id, JsonValue::Number(7.0.into()));
+        assert_eq!(doc.get_str_at(["id"]).unwrap(), "seven");
+    }
+
+    #[test]
+    fn test_merge_layered_config() {
+        let mut base = parse_json_string(
+            r#"{"server": {"port": 80, "tags": ["a"]}, "debug": true, "name": "base"}"#,
+        )
+        .unwrap();
+        let overlay = parse_json_string(
+            r#"{"server": {"tags": ["a", "b"]}, "debug": null, "name": null}"#,
+        )
+        .unwrap();
+
+        let mut ignored = base.clone();
+        ignored.merge(
+            overlay.clone(),
+            MergePolicy::new().arrays(ArrayMerge::Concat).nulls(NullMerge::Ignore),
+        );
+        assert_eq!(
+            ignored,
+            parse_json_string(
+                r#"{"server": {"port": 80, "tags": ["a", "a", "b"]}, "debug": true, "name": "base"}"#,
+            )
+            .unwrap()
+        );
+
+        base.merge(overlay, MergePolicy::merge_patch().arrays(ArrayMerge::Union));
+        assert_eq!(
+            base,
+            parse_json_string(r#"{"server": {"port": 80, "tags": ["a", "b"]}}"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_shallow_merge_replaces_objects() {
+        let mut base = parse_json_string(r#"{"server": {"port": 80, "host": "x"}}"#).unwrap();
+        let overlay = parse_json_string(r#"{"server": {"port": 81}}"#).unwrap();
+        base.merge(overlay.clone(), MergePolicy::new().deep_objects(false));
+        assert_eq!(base, overlay);
+    }
+
+    #[test]
+    fn test_sorted_keys_output_is_deterministic() {
+        let doc = parse_json_string(r#"{"b": {"z": 1, "y": [{"d": 1, "c": 2}]}, "a": null}"#)
+            .unwrap();
+        assert_eq!(
+            doc.sorted_keys().to_string(),
+            r#"{"a":null,"b":{"y":[{"c":2,"d":1}],"z":1}}"#
+        );
+
+        // Whatever order the map iterates in, members come out sorted.
+        let keys = ["d", "b", "e", "a", "c"];
+        let doc: JsonValue = keys.iter().map(|key| (*key, JsonValue::Array(vec![]))).collect();
+        assert_eq!(
+            format!("{:#}", doc.sorted_keys()),
+            "{\n  \"a\": [],\n  \"b\": [],\n  \"c\": [],\n  \"d\": [],\n  \"e\": []\n}"
+        );
+    }
+
+    #[test]
+    fn test_walk_counts_and_stops_early() {
+        struct Counter {
+            numbers: usize,
+            skipped_key: &'static str,
+            stop_at: f64,
+        }
+
+        impl Visitor for Counter {
+            fn visit_key(&mut self, key: &str) -> Walk {
+                if key == self.skipped_key {
+                    Walk::SkipChildren
+                } else {
+                    Walk::Continue
+                }
+            }
+
+            fn visit_number(&mut self, value: &JsonNumber) -> Walk {
+                self.numbers += 1;
+                if value.as_f64() == self.stop_at {
+                    Walk::Stop
+                } else {
+                    Walk::Continue
+                }
+            }
+        }
+
+        let doc = parse_json_string(r#"{"a": [1, 2, [3]], "skip": [4, 5]}"#).unwrap();
+
+        let mut counter = Counter { numbers: 0, skipped_key: "skip", stop_at: -1.0 };
+        assert_eq!(doc.walk(&mut counter), Walk::Continue);
+        assert_eq!(counter.numbers, 3);
+
+        let mut counter = Counter { numbers: 0, skipped_key: "", stop_at: 2.0 };
+        let array = doc.get_path(["a"]).unwrap();
+        assert_eq!(array.walk(&mut counter), Walk::Stop);
+        assert_eq!(counter.numbers, 2);
+    }
+
+    #[test]
+    fn test_iter_leaves_with_pointers() {
+        let doc = parse_json_string(r#"[{"a/b": 1}, [], [true, null], "x"]"#).unwrap();
+        let leaves: Vec<(String, JsonValue)> = doc
+            .iter_leaves()
+            .map(|(pointer, value)| (pointer.to_string(), value.clone()))
+            .collect();
+
+        assert_eq!(
+            leaves,
+            vec![
+                ("/0/a~1b".to_string(), JsonValue::Number(1.0.into())),
+                ("/1".to_string(), JsonValue::Array(Vec::new())),
+                ("/2/0".to_string(), JsonValue::Boolean(true)),
+                ("/2/1".to_string(), JsonValue::Null),
+                ("/3".to_string(), JsonValue::from("x")),
+            ]
+        );
+
+        let pointer: JsonPointer = "/2/0".parse().unwrap();
+        assert_eq!(doc.get_path(&pointer).unwrap(), &JsonValue::Boolean(true));
+
+        let doc = parse_json_string(r#"{"b": 1, "c": {"y": 2, "x": 3}, "a": 4}"#).unwrap();
+        let pointers: Vec<String> = doc.iter_leaves().map(|(pointer, _)| pointer.to_string()).collect();
+        #[cfg(not(feature = "preserve_order"))]
+        assert_eq!(pointers, ["/a", "/b", "/c/x", "/c/y"]);
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(pointers, ["/b", "/c/y", "/c/x", "/a"]);
+    }
+
+    #[test]
+    fn test_retain_strips_debug_fields() {
+        let mut doc = parse_json_string(
+            r#"{"id": 1, "debug": {"trace": []}, "items": [{"v": 1, "debug": 2}, null, 3]}"#,
+        )
+        .unwrap();
+
+        doc.retain(|path, value| {
+            !matches!(path.segments().last(), Some(PathSegment::Key(key)) if key == "debug")
+                && path.to_string() != "$.items[1]"
+                && !value.is_null()
+        });
+
+        assert_eq!(
+            doc,
+            parse_json_string(r#"{"id": 1, "items": [{"v": 1}, 3]}"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_filter_map_rewrites_bottom_up() {
+        let mut doc = parse_json_string(r#"{"a": [1, 2, 3], "b": {"c": 4}}"#).unwrap();
+
+        doc.filter_map(|_, value| match value {
+            JsonValue::Number(n) if n.as_f64() % 2.0 == 0.0 => None,
+            JsonValue::Number(n) => Some(JsonValue::from(n.as_f64() * 10.0)),
+            JsonValue::Object(obj) if obj.is_empty() => None,
+            other => Some(other),
+        });
+
+        assert_eq!(doc, parse_json_string(r#"{"a": [10, 30]}"#).unwrap());
+    }
+
+    #[test]
+    fn test_document_stats() {
+        let doc = parse_json_string(r#"{"name": "abc", "tags": [true, null, [1.5]], "id": 7}"#)
+            .unwrap();
+        let stats = doc.stats();
+
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!((stats.objects, stats.arrays), (1, 2));
+        assert_eq!((stats.strings, stats.numbers, stats.booleans, stats.nulls), (1, 2, 1, 1));
+        assert_eq!(stats.object_members, 3);
+        assert_eq!(stats.string_bytes, 3);
+        assert_eq!(stats.key_bytes, "name".len() + "tags".len() + "id".len());
+        assert_eq!(stats.node_count(), 8);
+        assert!(stats.approx_heap_bytes > stats.string_bytes + stats.key_bytes);
+
+        assert_eq!(JsonValue::Null.stats().max_depth, 0);
+    }
+
+    #[test]
+    fn test_canonical_value_dedup_and_order() {
+        use std::collections::{BTreeSet, HashSet};
+
+        let docs = [
+            r#"{"a": 1, "b": [true, null]}"#,
+            r#"{"b": [true, null], "a": 1}"#,
+            r#"{"a": -0.0}"#,
+            r#"{"a": 0}"#,
+            r#""text""#,
+            "null",
+        ];
+        let values: Vec<CanonicalValue> = docs
+            .iter()
+            .map(|doc| CanonicalValue(parse_json_string(doc).unwrap()))
+            .collect();
+
+        let unique: HashSet<_> = values.iter().cloned().collect();
+        assert_eq!(unique.len(), 4);
+
+        let ordered: BTreeSet<_> = values.into_iter().collect();
+        let first = ordered.iter().next().unwrap();
+        let last = ordered.iter().last().unwrap();
+        assert!(first.is_null());
+        assert_eq!(last.get_f64_at(["a"]).unwrap(), 1.0);
+
+        // Numbers that kept their text compare exactly, past `f64` precision.
+        let exact = |text: &str| {
+            let options = ParserOptions::new().preserve_number_text(true);
+            CanonicalValue(parse_json_string_with(text, options).unwrap())
+        };
+        let (low, high) = (exact("0.10000000000000000001"), exact("0.10000000000000000002"));
+        assert_eq!(low.as_f64(), high.as_f64());
+        assert!(low < high);
+        assert_eq!(HashSet::from([low.clone(), high.clone()]).len(), 2);
+        assert!(exact("-0.0015000001") < exact("-1.5e-3"));
+        assert!(exact("99e-1") < exact("10"));
+        assert_eq!(exact("1.50e2"), exact("150"));
+        assert_eq!(exact("0.1"), CanonicalValue(JsonValue::Number(0.1.into())));
+        assert_eq!(exact("-0"), exact("0.0e5"));
+        let unique: HashSet<_> = ["1.0", "1", "10e-1", "0.1e1"].map(exact).into_iter().collect();
+        assert_eq!(unique.len(), 1);
+    }
+
+    #[test]
+    fn test_approx_eq_tolerates_float_noise() {
+        let expected = parse_json_string(r#"{"total": 0.3, "items": [0.1, 1e20]}"#).unwrap();
+        let actual = JsonValue::from_iter(vec![
+            ("total".to_string(), JsonValue::Number((0.1 + 0.2).into())),
+            (
+                "items".to_string(),
+                JsonValue::Array(vec![
+                    JsonValue::Number(0.1.into()),
+                    JsonValue::Number((1e20 + 1e5).into()),
+                ]),
+            ),
+        ]);
+
+        assert_ne!(expected, actual);
+        assert!(expected.approx_eq(&actual, 1e-9));
+        let off = parse_json_string(r#"{"total": 0.31, "items": [0.1, 1e20]}"#).unwrap();
+        assert!(!expected.approx_eq(&off, 1e-9));
+        assert!(!JsonValue::from("1").approx_eq(&JsonValue::Number(1.0.into()), 1.0));
+    }
+
+    #[test]
+    fn test_equivalent_with_unordered_arrays() {
+        let expected = parse_json_string(r#"{"ids": [1, 2, 2, {"x": [3, 4]}]}"#).unwrap();
+        let actual = parse_json_string(r#"{"ids": [{"x": [4, 3]}, 2, 1, 2]}"#).unwrap();
+        let unordered = CompareOptions::new().unordered_arrays(true);
+
+        assert!(!expected.equivalent(&actual, CompareOptions::new()));
+        assert!(expected.equivalent(&actual, unordered));
+
+        let missing_duplicate = parse_json_string(r#"{"ids": [{"x": [4, 3]}, 2, 1, 1]}"#).unwrap();
+        assert!(!expected.equivalent(&missing_duplicate, unordered));
+    }
+
+    #[test]
+    fn test_preserve_number_text_round_trips() {
+        let input = r#"[1.2300000000000000001,1e400,-0.0,12345678901234567890]"#;
+        let cursor = std::io::Cursor::new(input);
+        let value = StreamingJsonParser::new(cursor)
+            .preserve_number_text(true)
+            .parse_single()
+            .unwrap();
+
+        assert_eq!(value.to_string(), input);
+        let numbers = value.as_array().unwrap();
+        assert_eq!(numbers[1].as_number().unwrap().as_raw_str(), Some("1e400"));
+        assert_eq!(numbers[3].as_u64(), Some(12345678901234567890));
+
+        let lossy = parse_json_string(input).unwrap();
+        assert_eq!(lossy.as_array().unwrap()[2].as_number().unwrap().as_raw_str(), None);
+        assert_eq!(lossy, value);
+    }
+
+    #[cfg(feature = "bignum")]
+    #[test]
+    fn test_bignum_keeps_out_of_range_numbers_exact() {
+        let value = parse_json_string(
+            r#"[123456789012345678901234567890, 1e400, 0.10000000000000000000000001, 42]"#,
+        )
+        .unwrap();
+        let numbers: Vec<&JsonNumber> = value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n.as_number().unwrap())
+            .collect();
+
+        assert_eq!(
+            numbers[0].as_big_decimal().unwrap().to_string(),
+            "123456789012345678901234567890"
+        );
+        assert!(numbers[1].is_big());
+        assert!(numbers[2].is_big());
+        assert!(!numbers[3].is_big());
+        assert_ne!(numbers[2], &JsonNumber::from(0.1));
+        assert_eq!(
+            value.to_string(),
+            "[123456789012345678901234567890,1e400,0.10000000000000000000000001,42]"
+        );
+    }
+
+    #[test]
+    fn test_non_finite_literals_are_opt_in() {
+        let input = "[NaN, Infinity, -Infinity, 1]";
+        assert!(parse_json_string(input).is_err());
+
+        let value = StreamingJsonParser::new(std::io::Cursor::new(input))
+            .allow_non_finite_numbers(true)
+            .parse_single()
+            .unwrap();
+        let numbers: Vec<f64> = value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n.as_f64().unwrap())
+            .collect();
+
+        assert!(numbers[0].is_nan());
+        assert_eq!(&numbers[1..], &[f64::INFINITY, f64::NEG_INFINITY, 1.0]);
+        assert_eq!(value.to_string(), "[null,null,null,1]");
+        let lenient = Serializer::new().allow_non_finite(true).to_string(&value);
+        assert_eq!(lenient, "[NaN,Infinity,-Infinity,1]");
+
+        // Cut short, `-Inf` could still become `-Infinity`.
+        let bad = StreamingJsonParser::new(std::io::Cursor::new("-Inf"))
+            .allow_non_finite_numbers(true)
+            .parse_single();
+        assert_eq!(bad, Err(ParseError::UnexpectedEndOfInput { while_parsing: Context::Number, position: at(4, 1, 5) }));
+        let bad = StreamingJsonParser::new(std::io::Cursor::new("[-Inf]"))
+            .allow_non_finite_numbers(true)
+            .parse_single();
+        assert_eq!(bad, Err(ParseError::InvalidNumber(at(1, 1, 2))));
+    }
+
+    #[test]
+    fn test_number_formatting() {
+        let shortest = |n: f64| JsonNumber::from(n).to_string();
+        assert_eq!(shortest(42.0), "42");
+        assert_eq!(shortest(-0.5), "-0.5");
+        assert_eq!(shortest(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(shortest(1e300), "1e300");
+        assert_eq!(shortest(1.5e-7), "1.5e-7");
+        assert_eq!(shortest(123456789012345680000.0), "123456789012345680000");
+
+        let pi = JsonNumber::from(std::f64::consts::PI);
+        assert_eq!(pi.format(NumberFormat::Precision(3)), "3.14");
+        assert_eq!(JsonNumber::from(1200.0).format(NumberFormat::Precision(2)), "1200");
+
+        let value = parse_json_string("[3.14159, 0.1, 1234.5678, 2]").unwrap();
+        let mut serializer = Serializer::new().number_format(NumberFormat::Precision(3));
+        assert_eq!(serializer.to_string(&value), "[3.14,0.1,1230,2]");
+        let mut pretty = Serializer::pretty().number_format(NumberFormat::Precision(2));
+        assert_eq!(pretty.to_string(&parse_json_string(r#"{"x": 0.123}"#).unwrap()), "{\n  \"x\": 0.12\n}");
+        assert_eq!(Serializer::new().to_string(&value), "[3.14159,0.1,1234.5678,2]");
+
+        for text in ["0.1", "2.5e-8", "1.7976931348623157e308", "5e-324"] {
+            let value = parse_json_string(text).unwrap();
+            assert_eq!(parse_json_string(&value.to_string()).unwrap(), value);
+        }
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_preserve_order_keeps_insertion_order() {
+        let input = r#"{"zeta":1,"alpha":{"y":true,"b":null},"mid":[{"k2":1,"k1":2}]}"#;
+        let mut value = parse_json_string(input).unwrap();
+        assert_eq!(value.to_string(), input);
+
+        value.remove_path("/alpha/y").unwrap();
+        value.set("/beta", JsonValue::from("new")).unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"{"zeta":1,"alpha":{"b":null},"mid":[{"k2":1,"k1":2}],"beta":"new"}"#
+        );
+
+        value.sort_keys();
+        assert_eq!(
+            value.to_string(),
+            r#"{"alpha":{"b":null},"beta":"new","mid":[{"k1":2,"k2":1}],"zeta":1}"#
+        );
+    }
+
+    #[cfg(feature = "intern_keys")]
+    #[test]
+    fn test_intern_keys_shares_allocations_across_records() {
+        use std::sync::Arc;
+
+        let input = "{\"id\":1,\"tags\":{\"id\":2}}\n{\"id\":3}";
+        let values: Vec<JsonValue> = parse_json_stream(std::io::Cursor::new(input))
+            .collect::<ParseResult<_>>()
+            .unwrap();
+        let key = |value: &JsonValue| {
+            value.as_object().unwrap().get_key_value("id").unwrap().0.clone()
+        };
+
+        let first = key(&values[0]);
+        assert!(Arc::ptr_eq(&first, &key(&values[1])));
+        assert!(Arc::ptr_eq(&first, &key(values[0].get("tags").unwrap())));
+    }
+
+    #[test]
+    fn test_parse_borrowed_only_copies_escaped_strings() {
+        use std::borrow::Cow;
+
+        let input = r#"{"name": "plain", "quote\"d": "line\nbreak", "n": [1.5, true, null]}"#;
+        let value = parse_json_str_borrowed(input).unwrap();
+
+        let obj = value.as_object().unwrap();
+        let (name_key, name) = obj.get_key_value("name").unwrap();
+        assert!(matches!(name_key, Cow::Borrowed(_)));
+        assert!(matches!(name, JsonValueRef::String(Cow::Borrowed("plain"))));
+        assert!(matches!(
+            obj.get("quote\"d"),
+            Some(JsonValueRef::String(Cow::Owned(s))) if s == "line\nbreak"
+        ));
+
+        assert_eq!(value.clone().into_owned(), parse_json_string(input).unwrap());
+        assert!(matches!(
+            parse_json_str_borrowed("[1,]"),
+            Err(ParseError::TrailingComma(_))
+        ));
+        assert!(parse_json_str_borrowed(r#""unterminated"#).is_err());
+        assert!(parse_json_str_borrowed("{} x").is_err());
+    }
+
+    #[cfg(feature = "arena")]
+    #[test]
+    fn test_parse_json_in_arena() {
+        let bump = bumpalo::Bump::new();
+        let input = r#"{"id": 7, "id": 8, "tags": ["a\tb", null, false], "empty": {}}"#;
+        let value = parse_json_in(input, &bump).unwrap();
+
+        assert_eq!(value.as_object().unwrap().len(), 4);
+        assert_eq!(value.get("id").and_then(ArenaValue::as_f64), Some(8.0));
+        let tags = value.get("tags").and_then(ArenaValue::as_array).unwrap();
+        assert_eq!(tags[0].as_str(), Some("a\tb"));
+        assert!(tags[1].is_null());
+        assert_eq!(value.to_json_value(), parse_json_string(input).unwrap());
+
+        assert!(matches!(
+            parse_json_in("{\"a\": 1,}", &bump),
+            Err(ParseError::TrailingComma(_))
+        ));
+        assert!(matches!(
+            parse_json_in(&"[".repeat(1_000_000), &bump),
+            Err(ParseError::DepthLimitExceeded { limit: DEFAULT_MAX_DEPTH, .. })
+        ));
+    }
+
+    #[test]
+    fn test_serializer_escapes_strings() {
+        let value = parse_json_string(r#"{"say \"hi\"": ["line\nbreak", "tab\t", "\u0001", "é"]}"#).unwrap();
+        let compact = to_string(&value);
+        assert_eq!(compact, r#"{"say \"hi\"":["line\nbreak","tab\t","\u0001","é"]}"#);
+        assert_eq!(value.to_string(), compact);
+        assert_eq!(parse_json_string(&compact).unwrap(), value);
+
+        let pretty = to_string_pretty(&value);
+        assert_eq!(
+            pretty,
+            "{\n  \"say \\\"hi\\\"\": [\n    \"line\\nbreak\",\n    \"tab\\t\",\n    \"\\u0001\",\n    \"é\"\n  ]\n}"
+        );
+        assert_eq!(parse_json_string(&pretty).unwrap(), value);
+        assert_eq!(to_string_pretty(&parse_json_string("[{}, []]").unwrap()), "[\n  {},\n  []\n]");
+    }
+
+    #[test]
+    fn test_custom_formatter() {
+        use std::fmt::{self, Write};
+
+        // Single-line output with a space after every separator.
+        struct Spaced;
+        impl Formatter for Spaced {
+            fn begin_object_key<W: Write + ?Sized>(&mut self, out: &mut W, first: bool) -> fmt::Result {
+                out.write_str(if first { "" } else { ", " })
+            }
+            fn begin_object_value<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+                out.write_str(": ")
+            }
+            fn begin_array_value<W: Write + ?Sized>(&mut self, out: &mut W, first: bool) -> fmt::Result {
+                out.write_str(if first { "" } else { ", " })
+            }
+        }
+
+        let value = parse_json_string(r#"{"a": [1, "x\"y", null]}"#).unwrap();
+        assert_eq!(to_string_with(&value, &mut Spaced), r#"{"a": [1, "x\"y", null]}"#);
+        assert_eq!(
+            to_string_with(&value, &mut PrettyFormatter::with_indent("\t")),
+            "{\n\t\"a\": [\n\t\t1,\n\t\t\"x\\\"y\",\n\t\tnull\n\t]\n}"
+        );
+    }
+
+    #[test]
+    fn test_to_writer_matches_to_string() {
+        let value = parse_json_string(r#"{"list": [1, 2.5, "three"], "ok": true}"#).unwrap();
+
+        let mut compact = Vec::new();
+        to_writer(&mut compact, &value).unwrap();
+        assert_eq!(String::from_utf8(compact).unwrap(), to_string(&value));
+
+        let mut pretty = Vec::new();
+        to_writer_pretty(&mut pretty, &value).unwrap();
+        assert_eq!(String::from_utf8(pretty).unwrap(), to_string_pretty(&value));
+
+        let mut full = [0u8; 8];
+        let err = to_writer(&mut full[..], &value).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn test_pretty_indent_style_and_inline_containers() {
+        let value = parse_json_string(r#"{"point": {"x": 1, "y": 2}, "tags": ["a", "b"], "rows": [[1, 2], [3, 4, 5, 6, 7, 8, 9]]}"#).unwrap();
+        let mut formatter = PrettyFormatter::new()
+            .indent_style(IndentStyle::Spaces(4))
+            .max_inline_length(24);
+        let out = to_string_with(&value, &mut formatter);
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(
+            out,
+            "{\n    \"point\": {\"x\": 1, \"y\": 2},\n    \"tags\": [\"a\", \"b\"],\n    \"rows\": [\n        [1, 2],\n        [3, 4, 5, 6, 7, 8, 9]\n    ]\n}"
+        );
+        assert!(
+            out.contains("\"point\": {\"x\": 1, \"y\": 2}")
+                || out.contains("\"point\": {\"y\": 2, \"x\": 1}")
+        );
+        assert!(out.contains("\n    \"rows\": [\n        [1, 2],\n        [3, 4, 5, 6, 7, 8, 9]\n    ]"));
+        assert_eq!(parse_json_string(&out).unwrap(), value);
+
+        let mut tabs = PrettyFormatter::new().indent_style(IndentStyle::Tabs);
+        assert_eq!(to_string_with(&parse_json_string("[1]").unwrap(), &mut tabs), "[\n\t1\n]");
+
+        // Containers are measured as the serializer writes them.
+        let inline = |max| PrettyFormatter::new().max_inline_length(max);
+        let accented = parse_json_string(r#"["é"]"#).unwrap();
+        assert_eq!(Serializer::with_formatter(inline(6)).to_string(&accented), r#"["é"]"#);
+        let mut ascii = Serializer::with_formatter(inline(6)).ensure_ascii(true);
+        assert_eq!(ascii.to_string(&accented), "[\n  \"\\u00e9\"\n]");
+        let pi = parse_json_string("[3.14159]").unwrap();
+        assert_eq!(Serializer::with_formatter(inline(6)).to_string(&pi), "[\n  3.14159\n]");
+        let mut rounded = Serializer::with_formatter(inline(6)).number_format(NumberFormat::Precision(2));
+        assert_eq!(rounded.to_string(&pi), "[3.1]");
+        let long = parse_json_string("[[1, 2, 3, 4, 5, 6, 7, 8, 9]]").unwrap();
+        assert!(Serializer::with_formatter(inline(18)).to_string(&long).starts_with("[\n"));
+        let mut preview = Serializer::with_formatter(inline(18)).max_array_items(2);
+        assert_eq!(preview.to_string(&long), "[[1, 2, … 7 more]]");
+    }
+
+    #[test]
+    fn test_serializer_sort_keys() {
+        let value = parse_json_string(r#"{"b": 1, "a": {"z": [{"d": 0, "c": 0}], "y": null}}"#).unwrap();
+
+        let compact = Serializer::new().sort_keys(true).to_string(&value);
+        assert_eq!(compact, r#"{"a":{"y":null,"z":[{"c":0,"d":0}]},"b":1}"#);
+        assert_eq!(value.sorted_keys().to_string(), compact);
+
+        let mut pretty = Serializer::pretty().sort_keys(true);
+        let mut out = Vec::new();
+        pretty.to_writer(&mut out, &value).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\n  \"a\": {\n    \"y\": null,\n    \"z\": [\n      {\n        \"c\": 0,\n        \"d\": 0\n      }\n    ]\n  },\n  \"b\": 1\n}"
+        );
+    }
+
+    #[test]
+    fn test_serializer_ensure_ascii() {
+        let value = JsonValue::from(vec!["café", "日本", "😀", "tab\t"]);
+        let ascii = Serializer::new().ensure_ascii(true).to_string(&value);
+        assert_eq!(ascii, r#"["caf\u00e9","\u65e5\u672c","\ud83d\ude00","tab\t"]"#);
+        assert_eq!(to_string(&value), r#"["café","日本","😀","tab\t"]"#);
+    }
+
+    #[test]
+    fn test_to_canonical_string_rfc8785() {
+        // Sample from RFC 8785 section 3.2.2.
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        let value = parse_json_string(input).unwrap();
+        assert_eq!(
+            to_canonical_string(&value).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+
+        // Keys sort by UTF-16 code units, so U+1F600 (a surrogate pair) sorts
+        // before U+FB33.
+        let value: JsonValue = [("\u{fb33}", JsonValue::Null), ("\u{1f600}", JsonValue::Null)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            to_canonical_string(&value).unwrap(),
+            "{\"\u{1f600}\":null,\"\u{fb33}\":null}"
+        );
+        assert_eq!(to_canonical_string(&JsonValue::from(-0.0)).unwrap(), "0");
+        assert!(matches!(
+            to_canonical_string(&JsonValue::from(f64::NAN)),
+            Err(SerializeError::NonFiniteNumber(_))
+        ));
+    }
+
+    #[test]
+    fn test_minify() {
+        let input = "{\n  \"a\" : [ 1.50 , -2e10, \"x y\\n\" ],\n  \"b\": { },\t\"c\": null\n}\n";
+        assert_eq!(
+            minify(input).unwrap(),
+            r#"{"a":[1.50,-2e10,"x y\n"],"b":{},"c":null}"#
+        );
+
+        assert!(matches!(minify("[1, 2,]"), Err(ParseError::TrailingComma(_))));
+        assert!(matches!(minify("{\"a\" 1}"), Err(ParseError::UnexpectedToken { .. })));
+        assert!(matches!(minify("[1 2]"), Err(ParseError::UnexpectedToken { .. })));
+        assert!(matches!(minify("[1}"), Err(ParseError::UnexpectedToken { .. })));
+        assert!(matches!(minify("{\"a\": [1"), Err(ParseError::UnexpectedEndOfInput { .. })));
+        assert!(matches!(minify("1 2"), Err(ParseError::UnexpectedToken { .. })));
+        assert!(matches!(minify("{\"a\": ]"), Err(ParseError::UnexpectedToken { .. })));
+    }
+
+    #[test]
+    fn test_ndjson_writer_round_trip_and_flush_policy() {
+        use std::io::Write;
+
+        #[derive(Default)]
+        struct CountingWriter {
+            bytes: Vec<u8>,
+            flushes: usize,
+        }
+        impl Write for CountingWriter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.bytes.write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                self.flushes += 1;
+                Ok(())
+            }
+        }
+
+        let records = vec![
+            parse_json_string(r#"{"msg": "two\nlines"}"#).unwrap(),
+            JsonValue::from(vec![1, 2]),
+            JsonValue::Null,
+        ];
+        let mut writer =
+            NdjsonWriter::new(CountingWriter::default()).flush_policy(FlushPolicy::Every(2));
+        writer.write_values(&records).unwrap();
+        assert_eq!(writer.records(), 3);
+        assert_eq!(writer.get_ref().flushes, 1);
+
+        let out = writer.into_inner().bytes;
+        assert_eq!(String::from_utf8_lossy(&out).lines().count(), 3);
+        let parsed: Vec<JsonValue> =
+            parse_json_stream(&out[..]).collect::<ParseResult<_>>().unwrap();
+        assert_eq!(parsed, records);
+    }
+
+    #[test]
+    fn test_json_encoder() {
+        let mut encoder = JsonEncoder::new(Vec::new());
+        encoder.begin_object().unwrap();
+        encoder.key("rows").unwrap();
+        encoder.begin_array().unwrap();
+        for i in 0..3 {
+            encoder.number(i).unwrap();
+        }
+        encoder.end_array().unwrap();
+        encoder.key("name").unwrap();
+        encoder.string("a \"quoted\" name").unwrap();
+        encoder.key("extra").unwrap();
+        encoder.value(&JsonValue::from(vec![true])).unwrap();
+        assert_eq!(encoder.end_array().unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        assert!(encoder.boolean(false).is_err());
+        encoder.end_object().unwrap();
+        assert!(encoder.null().is_err());
+
+        let out = String::from_utf8(encoder.finish().unwrap()).unwrap();
+        assert_eq!(out, r#"{"rows":[0,1,2],"name":"a \"quoted\" name","extra":[true]}"#);
+
+        let mut pretty = JsonEncoder::with_formatter(Vec::new(), PrettyFormatter::new());
+        pretty.begin_array().unwrap();
+        pretty.null().unwrap();
+        pretty.begin_object().unwrap();
+        pretty.end_object().unwrap();
+        pretty.end_array().unwrap();
+        assert_eq!(pretty.finish().unwrap(), b"[\n  null,\n  {}\n]");
+
+        let mut unfinished = JsonEncoder::new(Vec::new());
+        unfinished.begin_array().unwrap();
+        assert!(unfinished.finish().is_err());
+    }
+
+    #[test]
+    fn test_display_alternate_pretty_prints() {
+        let value = parse_json_string(r#"{"k": ["a\"b", 1]}"#).unwrap();
+        assert_eq!(format!("{}", value), r#"{"k":["a\"b",1]}"#);
+        assert_eq!(format!("{:#}", value), to_string_pretty(&value));
+        assert_eq!(format!("{:#}", value), "{\n  \"k\": [\n    \"a\\\"b\",\n    1\n  ]\n}");
+        assert_eq!(format!("{:#}", value.sorted_keys()), format!("{:#}", value));
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn test_color_writer() {
+        let value = parse_json_string(r#"{"k": ["s", 1, true, null]}"#).unwrap();
+        let colored = to_string_with(&value, &mut ColorWriter::with_formatter(CompactFormatter));
+        assert_eq!(
+            colored,
+            "{\x1b[1;34m\"k\"\x1b[0m:[\x1b[32m\"s\"\x1b[0m,\x1b[36m1\x1b[0m,\x1b[33mtrue\x1b[0m,\x1b[90mnull\x1b[0m]}"
+        );
+
+        let pretty = to_string_with(&value, &mut ColorWriter::new());
+        let codes = ["\x1b[1;34m", "\x1b[32m", "\x1b[36m", "\x1b[33m", "\x1b[90m", "\x1b[0m"];
+        let stripped = codes.iter().fold(pretty, |text, code| text.replace(code, ""));
+        assert_eq!(stripped, to_string_pretty(&value));
+    }
+
+    #[test]
+    fn test_serializer_line_endings() {
+        let value = parse_json_string(r#"{"text": "a\nb", "list": [1]}"#).unwrap();
+        let lf = Serializer::pretty().trailing_newline(true).to_string(&value);
+        assert_eq!(lf, format!("{:#}\n", value));
+
+        let crlf = Serializer::pretty()
+            .line_ending(LineEnding::CrLf)
+            .trailing_newline(true)
+            .to_string(&value);
+        assert_eq!(crlf, lf.replace('\n', "\r\n"));
+        assert!(crlf.contains(r#""a\nb""#));
+        assert_eq!(parse_json_string(&crlf).unwrap(), value);
+
+        assert_eq!(Serializer::new().trailing_newline(true).to_string(&JsonValue::Null), "null\n");
+    }
+
+    #[test]
+    fn test_escape_policy_slash_and_line_separators() {
+        let value = JsonValue::from("</script>\u{2028}\u{2029}");
+        assert_eq!(to_string(&value), "\"</script>\u{2028}\u{2029}\"");
+
+        let policy = EscapePolicy::new().escape_slash(true).escape_line_separators(true);
+        let escaped = Serializer::new().escape_policy(policy).to_string(&value);
+        assert_eq!(escaped, r#""<\/script>\u2028\u2029""#);
+        assert_eq!(parse_json_string(&escaped).unwrap(), value);
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn test_digest_sha256_ignores_key_order_and_whitespace() {
+        let a = parse_json_string(r#"{"b": [1.0, 2], "a": "x"}"#).unwrap();
+        let b = parse_json_string("{\"a\":\"x\",\n \"b\":[1,2e0]}").unwrap();
+        let c = parse_json_string(r#"{"a": "y", "b": [1, 2]}"#).unwrap();
+
+        assert_eq!(a.digest_sha256().unwrap(), b.digest_sha256().unwrap());
+        assert_ne!(a.digest_sha256().unwrap(), c.digest_sha256().unwrap());
+
+        // sha256 of the canonical text `{}`.
+        let hex: String = JsonValue::Object(Map::new())
+            .digest_sha256()
+            .unwrap()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        assert_eq!(hex, "44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a");
+    }
+
+    #[test]
+    fn test_serializer_truncates_deep_and_long_content() {
+        let value = parse_json_string(
+            r#"{"items": [1, 2, 3, 4, 5], "nested": {"a": {"b": 1}, "c": [1], "d": {}}}"#,
+        )
+        .unwrap();
+
+        let preview = Serializer::new()
+            .sort_keys(true)
+            .max_depth(2)
+            .max_array_items(2)
+            .to_string(&value);
+        assert_eq!(
+            preview,
+            r#"{"items":[1,2,… 3 more],"nested":{"a":{… 1 member},"c":[… 1 item],"d":{}}}"#
+        );
+
+        let nested = parse_json_string("[[1, 2], 3]").unwrap();
+        let pretty = Serializer::pretty().max_array_items(1).to_string(&nested);
+        assert_eq!(pretty, "[\n  [\n    1,\n    … 1 more\n  ],\n  … 1 more\n]");
+        assert_eq!(Serializer::new().max_depth(0).to_string(&value), "{… 2 members}");
+    }
+
+    #[test]
+    fn test_parser_options_apply_to_every_entry_point() {
+        let options = ParserOptions::new()
+            .allow_non_finite_numbers(true)
+            .preserve_number_text(true);
+
+        let value = parse_json_string_with("[NaN, 1.50]", options.clone()).unwrap();
+        assert!(value.as_array().unwrap()[0].as_f64().unwrap().is_nan());
+        assert_eq!(value.to_string(), "[null,1.50]");
+        assert!(parse_json_string_with("[NaN]", ParserOptions::new()).is_err());
+
+        let stream = parse_json_stream_with(std::io::Cursor::new("Infinity 2.0"), options.clone());
+        assert_eq!(stream.options(), &options);
+        let mut lenient = Serializer::new().allow_non_finite(true);
+        let values: Vec<String> = stream.map(|v| lenient.to_string(&v.unwrap())).collect();
+        assert_eq!(values, vec!["Infinity", "2.0"]);
+    }
+
+    #[test]
+    fn test_max_depth_rejects_deep_nesting() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+        assert!(parse_json_string(&nested(DEFAULT_MAX_DEPTH)).is_ok());
+        assert_eq!(
+            parse_json_string(&nested(100_000)),
+            Err(ParseError::DepthLimitExceeded {
+                limit: DEFAULT_MAX_DEPTH,
+                position: at(DEFAULT_MAX_DEPTH, 1, DEFAULT_MAX_DEPTH + 1),
+            })
+        );
+
+        let options = ParserOptions::new().max_depth(2);
+        assert!(parse_json_string_with(r#"{"a": [1]}"#, options.clone()).is_ok());
+        assert_eq!(
+            parse_json_string_with(r#"{"a": [{}]}"#, options.clone()),
+            Err(ParseError::DepthLimitExceeded { limit: 2, position: at(7, 1, 8) })
+        );
+
+        let mut stream = parse_json_stream_with(std::io::Cursor::new("[[[]]] [[]]"), options);
+        assert!(stream.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_deep_nesting_does_not_use_the_call_stack() {
+        // Parsing keeps open containers on the heap, so a small thread stack
+        // is enough however deep the input goes.
+        let depth = 256;
+        let input = format!("{}{}", r#"{"a":["#.repeat(depth), "]}".repeat(depth));
+        let options = ParserOptions::new().max_depth(2 * depth);
+        let parse = {
+            let input = input.clone();
+            std::thread::Builder::new()
+                .stack_size(64 * 1024)
+                .spawn(move || parse_json_string_with(&input, options))
+                .unwrap()
+        };
+        let value = parse.join().unwrap().unwrap();
+
+        // Dropping, cloning, comparing and printing recurse, within the
+        // depth limit.
+        assert_eq!(value.clone(), value);
+        assert_eq!(value.to_string(), input);
+    }
+
+    #[test]
+    fn test_resource_limits() {
+        let exceeded = |input: &str, options: ParserOptions| {
+            match parse_json_string_with(input, options) {
+                Err(ParseError::LimitExceeded { limit, max, position }) => Some((limit, max, position)),
+                _ => None,
+            }
+        };
+
+        let input = r#"{"name": "abcdef", "tags": [1, 2, 3]}"#;
+        assert!(parse_json_string_with(input, ParserOptions::new().max_string_length(6)).is_ok());
+        assert_eq!(
+            exceeded(input, ParserOptions::new().max_string_length(5)),
+            Some((ResourceLimit::StringLength, 5, at(9, 1, 10)))
+        );
+        assert_eq!(
+            exceeded(input, ParserOptions::new().max_container_elements(2)),
+            Some((ResourceLimit::ContainerElements, 2, at(34, 1, 35)))
+        );
+        assert_eq!(
+            exceeded(input, ParserOptions::new().max_nodes(5)),
+            Some((ResourceLimit::Nodes, 5, at(34, 1, 35)))
+        );
+        assert!(parse_json_string_with(input, ParserOptions::new().max_nodes(6)).is_ok());
+
+        let lines = "[1]\n[2]\n[3]\n";
+        assert!(parse_json_string_with("[1]", ParserOptions::new().max_input_bytes(3)).is_ok());
+        let options = ParserOptions::new().max_input_bytes(8);
+        let values: Vec<_> = parse_json_stream_with(lines.as_bytes(), options).collect();
+        assert_eq!(values.len(), 3);
+        assert!(values[0].is_ok() && values[1].is_ok());
+        assert!(matches!(
+            values[2],
+            Err(ParseError::LimitExceeded { limit: ResourceLimit::InputBytes, max: 8, .. })
+        ));
+    }
+
+    #[test]
+    fn test_comments_are_opt_in() {
+        let input = "// settings\n{\n  \"a\": 1, /* inline */ \"b\": [2 // two\n]\n} /* end */";
+        assert_eq!(
+            parse_json_string(input),
+            Err(ParseError::InvalidCharacter { char: '/', position: at(0, 1, 1) })
+        );
+
+        let jsonc = ParserOptions::new().allow_comments(true);
+        let value = parse_json_string_with(input, jsonc.clone()).unwrap();
+        assert_eq!(value, parse_json_string(r#"{"a": 1, "b": [2]}"#).unwrap());
+
+        assert!(parse_json_string_with(r#"{"url": "http://x"}"#, jsonc.clone()).is_ok());
+        assert_eq!(
+            parse_json_string_with("[1] /* open", jsonc.clone()),
+            Err(ParseError::UnterminatedComment(at(4, 1, 5)))
+        );
+        assert_eq!(
+            parse_json_string_with("[1, /x]", jsonc),
+            Err(ParseError::InvalidCharacter { char: '/', position: at(4, 1, 5) })
+        );
+    }
+
+    #[test]
+    fn test_trailing_commas_are_opt_in() {
+        assert_eq!(parse_json_string("[1, 2, 3,]"), Err(ParseError::TrailingComma(at(9, 1, 10))));
+
+        let lenient = ParserOptions::new().allow_trailing_commas(true);
+        let value = parse_json_string_with(r#"{"a": [1, 2, 3,], "b": {},}"#, lenient.clone()).unwrap();
+        assert_eq!(value, parse_json_string(r#"{"a": [1, 2, 3], "b": {}}"#).unwrap());
+
+        assert!(parse_json_string_with("[1,,]", lenient.clone()).is_err());
+        assert!(parse_json_string_with("[,]", lenient.clone()).is_err());
+        assert!(parse_json_string_with("{,}", lenient).is_err());
+    }
+
+    #[test]
+    fn test_unquoted_keys_are_opt_in() {
+        let input = "{port: 8080, $ref : \"a\", _private_1: true, null: null}";
+        assert!(parse_json_string(input).is_err());
+
+        let lenient = ParserOptions::new().allow_unquoted_keys(true);
+        let value = parse_json_string_with(input, lenient.clone()).unwrap();
+        let quoted = r#"{"port": 8080, "$ref": "a", "_private_1": true, "null": null}"#;
+        assert_eq!(value, parse_json_string(quoted).unwrap());
+
+        assert!(parse_json_string_with("[true, false, null]", lenient.clone()).is_ok());
+        assert_eq!(
+            parse_json_string_with(r#"{"a": port}"#, lenient.clone()),
+            Err(ParseError::InvalidCharacter { char: 'p', position: at(6, 1, 7) })
+        );
+        assert!(parse_json_string_with("{1a: 1}", lenient.clone()).is_err());
+
+        // Identifiers follow Unicode ID_Start and ID_Continue, as in JSON5.
+        let value = parse_json_string_with("{café: 1, ūńîċõďë_2: 2, π: 3}", lenient.clone()).unwrap();
+        assert_eq!(value, parse_json_string(r#"{"café": 1, "ūńîċõďë_2": 2, "π": 3}"#).unwrap());
+        assert!(parse_json_string_with("{\u{300}a: 1}", lenient.clone()).is_err());
+        assert!(parse_json_string_with("{a\u{A0}b: 1}", lenient).is_err());
+    }
+
+    #[test]
+    fn test_json5_dialect() {
+        let json5 = ParserOptions::new().dialect(Dialect::Json5);
+        let parse = |input: &str| parse_json_string_with(input, json5.clone());
+        let compact = |input: &str| {
+            parse(input).map(|value| Serializer::new().allow_non_finite(true).to_string(&value))
+        };
+
+        // The example from json5.org.
+        let config = r#"
+            // comments
+            {
+              unquoted: 'and you can quote me on that',
+              singleQuotes: 'I can use "double quotes" here',
+              lineBreaks: "Look, Mom! \
+No \\n's!",
+              hexadecimal: 0xdecaf,
+              leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+              positiveSign: +1,
+              trailingComma: 'in objects', andIn: ['arrays',],
+              "backwardsCompatible": "with JSON",
+            }
+        "#;
+        let value = parse(config).unwrap();
+        let field = |key: &str| value.get(key).unwrap().clone();
+        assert_eq!(field("singleQuotes").as_str(), Some(r#"I can use "double quotes" here"#));
+        assert_eq!(field("lineBreaks").as_str(), Some(r"Look, Mom! No \n's!"));
+        assert_eq!(field("hexadecimal").as_u64(), Some(0xdecaf));
+        assert_eq!(field("leadingDecimalPoint").as_f64(), Some(0.8675309));
+        assert_eq!(field("andTrailing").as_f64(), Some(8675309.0));
+        assert_eq!(field("positiveSign").as_f64(), Some(1.0));
+
+        assert_eq!(
+            compact("[-0xC8, 0XFF, +.5, -5., 1e-2, +Infinity, -Infinity]").unwrap(),
+            "[-200,255,0.5,-5,0.01,Infinity,-Infinity]"
+        );
+        assert!(parse("NaN").unwrap().as_f64().unwrap().is_nan());
+        assert_eq!(compact(r"'\x41\v\0\'\q'").unwrap(), r#""A\u000b\u0000'q""#);
+        assert!(parse("{$_: 1, a1: 2, null: 3}").is_ok());
+
+        let invalid = ["0x", ".", "+", "010", "1.e", "-", r"'\1'", "[1,,]", "{a: 1,,}", "'open", "+-1"];
+        for input in invalid {
+            assert!(parse(input).is_err(), "{} should be rejected", input);
+        }
+        assert!(parse_json_string("'single'").is_err());
+        assert!(parse_json_string("+1").is_err());
+
+        let preserved = parse_json_string_with("[0xFF, +.5, 5., -Infinity, 1E+2]", json5.clone().preserve_number_text(true));
+        let texts: Vec<_> = preserved
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n.as_number().unwrap().as_raw_str().unwrap().to_string())
+            .collect();
+        assert_eq!(texts, ["0xFF", "+.5", "5.", "-Infinity", "1E+2"]);
+        let strict = json5.clone().dialect(Dialect::Json);
+        assert!(parse_json_string_with("[1, // no\n]", strict).is_err());
+    }
+
+    #[test]
+    fn test_json5_test_suite() {
+        // Cases from the json5-tests suite, by directory.
+        let json5 = ParserOptions::new().dialect(Dialect::Json5);
+        let compact = |input: &str| {
+            parse_json_string_with(input, json5.clone()).map(|value| Serializer::new().allow_non_finite(true).to_string(&value))
+        };
+        let valid = [
+            // arrays
+            ("[]", "[]"),
+            ("[\n1,\n2,\n]", "[1,2]"),
+            // comments
+            ("// comment\n{}", "{}"),
+            ("{/* c */}", "{}"),
+            ("/**/ 1 /***/", "1"),
+            ("[1 // a\r\n, 2 // b\r]", "[1,2]"),
+            // misc and new-lines: JSON5 whitespace is ECMAScript's
+            ("\u{A0}[\u{2028}1\u{2029},\u{B}\u{C}2\u{FEFF}\u{3000}]", "[1,2]"),
+            ("{\r\n}", "{}"),
+            // numbers
+            (".5", "0.5"),
+            ("5.", "5"),
+            ("5.e4", "50000"),
+            ("0xC8", "200"),
+            ("0xc8e4", "51428"),
+            ("+0x1", "1"),
+            ("-0", "-0"),
+            ("1e-2", "0.01"),
+            ("+Infinity", "Infinity"),
+            ("-Infinity", "-Infinity"),
+            // objects
+            ("[{while: 1}, {ūńîċõďë: 2}]", r#"[{"while":1},{"ūńîċõďë":2}]"#),
+            ("[{$_: 1}, {_$: 2}, {a\u{200C}: 3}]", "[{\"$_\":1},{\"_$\":2},{\"a\u{200C}\":3}]"),
+            (r"{ab: 1}", r#"{"ab":1}"#),
+            (r#"[{'a': 1,}, {"b": 2,},]"#, r#"[{"a":1},{"b":2}]"#),
+            // strings
+            (r"'\''", r#""'""#),
+            (r#"'"'"#, r#""\"""#),
+            ("'a\\\nb'", r#""ab""#),
+            ("'\u{2028}\u{2029}'", "\"\u{2028}\u{2029}\""),
+        ];
+        for (input, expected) in valid {
+            assert_eq!(compact(input).as_deref(), Ok(expected), "{:?}", input);
+        }
+        let invalid = [
+            "[,]", "[1,,2]", "{,}", "{'a'}", "{1: 2}", "{a-b: 1}", "{-: 1}", r"{0a: 1}", "/* open",
+            "/* /* */ */", "0x", "080", "1e", "0x1.5", "infinity", "'a\nb'", r"'\x4'",
+        ];
+        for input in invalid {
+            assert!(compact(input).is_err(), "{:?} should be rejected", input);
+        }
+    }
+
+    #[test]
+    fn test_raw_control_characters_in_strings() {
+        // From JSONTestSuite: n_string_unescaped_tab, n_string_unescaped_newline,
+        // n_string_unescaped_ctrl_char; y_string_escaped_control_character.
+        let rejected = |result: ParseResult<JsonValue>| match result {
+            Err(ParseError::InvalidCharacter { char, position }) => Some((char, position)),
+            _ => None,
+        };
+        let cases = [
+            ("[\"\t\"]", '\t', at(2, 1, 3)),
+            ("[\"new\nline\"]", '\n', at(5, 1, 6)),
+            ("[\"a\u{0}a\"]", '\0', at(3, 1, 4)),
+        ];
+        for (input, char, position) in cases {
+            let expected = Some((char, position));
+            assert_eq!(rejected(parse_json_string(input)), expected, "{:?}", input);
+            let borrowed = parse_json_str_borrowed(input).map(JsonValue::from);
+            assert_eq!(rejected(borrowed), expected, "{:?}", input);
+        }
+        let escaped = parse_json_string(r#""\u0012\t""#).unwrap();
+        assert_eq!(escaped.as_str(), Some("\u{12}\t"));
+
+        let lenient = ParserOptions::new().allow_control_characters(true);
+        let value = parse_json_string_with("\"a\tb\nc\"", lenient).unwrap();
+        assert_eq!(value.as_str(), Some("a\tb\nc"));
+
+        let json5 = ParserOptions::new().dialect(Dialect::Json5);
+        assert!(parse_json_string_with("'a\tb'", json5.clone()).is_ok());
+        assert!(parse_json_string_with("'a\nb'", json5).is_err());
+    }
+
+    #[test]
+    fn test_surrogate_pairs() {
+        let emoji = parse_json_string(r#""\uD83D\uDE00 \uD834\uDD1E""#).unwrap();
+        assert_eq!(emoji.as_str(), Some("\u{1F600} \u{1D11E}"));
+        let borrowed = parse_json_str_borrowed(r#""x\uD83D\uDE00""#).unwrap();
+        assert_eq!(borrowed.as_str(), Some("x\u{1F600}"));
+
+        let unpaired = [
+            (r#""\uD83D""#, at(1, 1, 2)),
+            (r#""ab\uDE00""#, at(3, 1, 4)),
+            (r#""\uD83Dx""#, at(1, 1, 2)),
+            (r#""\uD83D\n""#, at(1, 1, 2)),
+        ];
+        for (input, position) in unpaired {
+            assert!(
+                matches!(parse_json_string(input), Err(ParseError::UnpairedSurrogate(span)) if span.start == position),
+                "{}",
+                input
+            );
+            let error = parse_json_string(input).unwrap_err();
+            assert_eq!(input[error.span().unwrap()].len(), 6);
+            assert_eq!(parse_json_str_borrowed(input).unwrap_err(), error);
+        }
+
+        let lossy = ParserOptions::new().replace_unpaired_surrogates(true);
+        let parse = |input: &str| parse_json_string_with(input, lossy.clone()).unwrap();
+        assert_eq!(parse(r#""\uD83D\n""#).as_str(), Some("\u{FFFD}\n"));
+        assert_eq!(parse(r#""\uD800\uD83D\uDE00\uDC00""#).as_str(), Some("\u{FFFD}\u{1F600}\u{FFFD}"));
+        assert_eq!(parse(r#""\uD800""#).as_str(), Some("\u{FFFD}"));
+    }
+
+    #[test]
+    fn test_leading_bom_is_skipped() {
+        let input = "\u{FEFF}{\"a\": [1]}";
+        let expected = parse_json_string(r#"{"a": [1]}"#).unwrap();
+        assert_eq!(parse_json_string(input).unwrap(), expected);
+        assert_eq!(JsonValue::from(parse_json_str_borrowed(input).unwrap()), expected);
+        assert_eq!(
+            parse_json_string("\u{FEFF}[1, x]"),
+            Err(ParseError::InvalidCharacter { char: 'x', position: at(7, 1, 6) })
+        );
+
+        let stream = parse_json_stream("\u{FEFF}1\n2".as_bytes());
+        assert_eq!(stream.map(Result::unwrap).count(), 2);
+        assert!(parse_json_string("[\u{FEFF}1]").is_err());
+
+        let strict = ParserOptions::new().allow_bom(false);
+        assert_eq!(
+            parse_json_string_with(input, strict),
+            Err(ParseError::InvalidCharacter { char: '\u{FEFF}', position: at(0, 1, 1) })
+        );
+    }
+
+    #[test]
+    fn test_utf16_and_utf32_input_is_transcoded() {
+        let text = "\u{FEFF}{\"name\": \"caf\u{e9} \u{1F600}\"}";
+        let expected = parse_json_string(text).unwrap();
+
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+        let utf16le: Vec<u8> = utf16.iter().flat_map(|unit| unit.to_le_bytes()).collect();
+        let utf16be: Vec<u8> = utf16[1..].iter().flat_map(|unit| unit.to_be_bytes()).collect();
+        let utf32le: Vec<u8> = text[3..].chars().flat_map(|ch| (ch as u32).to_le_bytes()).collect();
+        let utf32be: Vec<u8> = text.chars().flat_map(|ch| (ch as u32).to_be_bytes()).collect();
+
+        for (bytes, encoding) in [
+            (&utf16le, Encoding::Utf16Le),
+            (&utf16be, Encoding::Utf16Be),
+            (&utf32le, Encoding::Utf32Le),
+            (&utf32be, Encoding::Utf32Be),
+        ] {
+            assert_eq!(detect_encoding(bytes), encoding);
+            let value = StreamingJsonParser::new(bytes.as_slice()).parse_single().unwrap();
+            assert_eq!(value, expected, "{:?}", encoding);
+        }
+        assert_eq!(detect_encoding(text.as_bytes()), Encoding::Utf8);
+        assert_eq!(detect_encoding(b"1"), Encoding::Utf8);
+
+        let utf16_lines = b"1\x00\n\x002\x00";
+        let values: Vec<_> = parse_json_stream(&utf16_lines[..]).map(Result::unwrap).collect();
+        assert_eq!(values, vec![JsonValue::from(1), JsonValue::from(2)]);
+        let truncated = StreamingJsonParser::new(&utf16le[..utf16le.len() - 1]).parse_single();
+        assert!(matches!(truncated, Err(ParseError::Io(_))));
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_rejected_or_replaced() {
+        let input = b"{\"msg\": \"bad \xff\xfe byte\"}\n[\"ok\"]\n";
+        let strict: Vec<_> = parse_json_stream(&input[..]).collect();
+        assert!(matches!(strict[0], Err(ParseError::Io(_))));
+
+        let lossy = ParserOptions::new().replace_invalid_utf8(true);
+        let values: Vec<_> = parse_json_stream_with(&input[..], lossy.clone())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(values[0].get("msg").unwrap().as_str(), Some("bad \u{FFFD}\u{FFFD} byte"));
+        assert_eq!(values[1], JsonValue::Array(vec!["ok".into()]));
+
+        // An unpaired surrogate and a truncated final code unit in UTF-16LE.
+        let utf16 = b"\"\x00\x00\xd8\"\x00\x00";
+        let value = StreamingJsonParser::with_options(&utf16[..6], lossy.clone()).parse_single();
+        assert_eq!(value.unwrap().as_str(), Some("\u{FFFD}"));
+        assert!(StreamingJsonParser::new(&utf16[..6]).parse_single().is_err());
+        assert!(StreamingJsonParser::with_options(&utf16[..], lossy).parse_single().is_err());
+    }
+
+    #[test]
+    fn test_parse_json_bytes() {
+        let value = parse_json_bytes(b"{\"caf\xc3\xa9\": [1, \"\xe2\x82\xac\"]}").unwrap();
+        assert_eq!(value, parse_json_string("{\"café\": [1, \"€\"]}").unwrap());
+
+        // Invalid UTF-8 is reported where it occurs, not for the whole line.
+        assert!(matches!(parse_json_bytes(b"[1, \"\xc3\"]"), Err(ParseError::Io(_))));
+        assert_eq!(
+            parse_json_bytes(b"[1, \xc3\xa9]"),
+            Err(ParseError::InvalidCharacter { char: 'é', position: at(4, 1, 5) })
+        );
+
+        let lossy = ParserOptions::new().replace_invalid_utf8(true);
+        let value = parse_json_bytes_with(b"\"\xe2\x82 \xf0\x9f\x98\"", lossy).unwrap();
+        assert_eq!(value.as_str(), Some("\u{FFFD} \u{FFFD}"));
+    }
+
+    #[test]
+    fn test_reads_split_characters() {
+        // Hands out one byte per read, splitting every multi-byte character.
+        struct Trickle<'a>(&'a [u8]);
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = self.0.len().min(buf.len()).min(1);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        let input = "[\"caf\u{e9} \u{20ac} \u{1F600}\", 1.5]\n{\"\u{e9}\": null}\n";
+        let values: Vec<_> = parse_json_stream(Trickle(input.as_bytes()))
+            .map(Result::unwrap)
+            .collect();
+        let expected: Vec<_> = parse_json_stream(input.as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(values, expected);
+        let text = values[0].as_array().unwrap()[0].as_str();
+        assert_eq!(text, Some("caf\u{e9} \u{20ac} \u{1F600}"));
+    }
+
+    #[test]
+    fn test_stream_without_newlines() {
+        // `[1] [2] [3] ...` forever, on a single line.
+        struct Endless(usize);
+        impl std::io::Read for Endless {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                for byte in buf.iter_mut() {
+                    *byte = b"[1] "[self.0 % 4];
+                    self.0 += 1;
+                }
+                Ok(buf.len())
+            }
+        }
+
+        let values: Vec<_> = parse_json_stream(Endless(0)).take(5000).map(Result::unwrap).collect();
+        assert_eq!(values.len(), 5000);
+        assert_eq!(values[4999], JsonValue::Array(vec![1.into()]));
+    }
+
+    #[test]
+    fn test_push_parser() {
+        let input = r#"{"name": "café", "n": [12, true, null]} "next" 3"#.as_bytes();
+        let mut parser = PushParser::new();
+        let mut events = Vec::new();
+        // One byte at a time splits every token, including `é`.
+        for byte in input {
+            events.extend(parser.feed(std::slice::from_ref(byte)).unwrap());
+        }
+        // `3` may yet continue, so it only arrives with `finish()`.
+        assert_eq!(parser.feed(b"").unwrap(), Pushed::NeedMoreData(Vec::new()));
+        assert_eq!(events.last(), Some(&Event::String("next".into())));
+        events.extend(parser.finish().unwrap());
+        assert_eq!(
+            events,
+            vec![
+                Event::StartObject,
+                Event::Key("name".into()),
+                Event::String("caf\u{e9}".into()),
+                Event::Key("n".into()),
+                Event::StartArray,
+                Event::Number(12.into()),
+                Event::Boolean(true),
+                Event::Null,
+                Event::EndArray,
+                Event::EndObject,
+                Event::String("next".into()),
+                Event::Number(3.into()),
+            ]
+        );
+
+        let mut parser = PushParser::new();
+        let progress = parser.feed(b"[1, ").unwrap();
+        assert_eq!(progress, Pushed::NeedMoreData(vec![Event::StartArray, Event::Number(1.into())]));
+        assert!(matches!(parser.finish(), Err(ParseError::UnexpectedEndOfInput { .. })));
+        assert!(matches!(PushParser::new().feed(b"[1 2 "), Err(ParseError::UnexpectedToken { .. })));
+        assert_eq!(PushParser::new().feed(b"[1,]"), Err(ParseError::TrailingComma(at(3, 1, 4))));
+        assert_eq!(PushParser::new().feed(b"[1] \n").unwrap().events().len(), 3);
+        assert!(matches!(PushParser::new().feed(b"[1] \n"), Ok(Pushed::Complete(_))));
+    }
+
+    #[test]
+    fn test_event_reader() {
+        let input = r#"{"a": [1, {"b": null}], "c": "x"} true"#;
+        let events: Vec<_> = EventReader::new(input.as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(
+            events,
+            vec![
+                Event::StartObject,
+                Event::Key("a".into()),
+                Event::StartArray,
+                Event::Number(1.into()),
+                Event::StartObject,
+                Event::Key("b".into()),
+                Event::Null,
+                Event::EndObject,
+                Event::EndArray,
+                Event::Key("c".into()),
+                Event::String("x".into()),
+                Event::EndObject,
+                Event::Boolean(true),
+            ]
+        );
+
+        // Iteration stops at the first error.
+        let events: Vec<_> = EventReader::new(&b"[1, }] [2]"[..]).collect();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[2], Err(ParseError::UnexpectedToken { span, .. }) if span.start == at(4, 1, 5)));
+    }
+
+    #[test]
+    fn test_tokens_with_spans() {
+        let input = "{\"caf\u{e9}\": [1,\n  true]}";
+        let spanned: Vec<_> = tokens(input.as_bytes()).map(Result::unwrap).collect();
+        let spans: Vec<_> = spanned
+            .iter()
+            .map(|t| (t.span.start.offset, t.span.end.offset, t.span.start.line, t.span.start.column))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![(0, 1, 1, 1), (1, 8, 1, 2), (8, 9, 1, 8), (10, 11, 1, 10), (11, 12, 1, 11),
+                 (12, 13, 1, 12), (16, 20, 2, 3), (20, 21, 2, 7), (21, 22, 2, 8)]
+        );
+        assert_eq!(&input[spanned[1].span.range()], "\"caf\u{e9}\"");
+        // The string's end column counts characters, like its offsets count bytes.
+        assert_eq!((spanned[1].span.end.line, spanned[1].span.end.column), (1, 8));
+        assert_eq!(spanned[7].token_type, TokenType::RightBracket);
+
+        // A bare key's span stops before the whitespace ahead of its colon.
+        let options = ParserOptions::new().allow_unquoted_keys(true);
+        let key = tokens_with(&b"{port :1}"[..], options).nth(1).unwrap().unwrap();
+        assert_eq!((key.token_type, key.span.range()), (TokenType::String("port".into()), 1..5));
+
+        let results: Vec<_> = tokens(&b"[1, @, 2]"[..]).collect();
+        assert_eq!(results.len(), 4);
+        assert!(results[3].is_err());
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let input = r#"{"big": {"a": ["x\"]", {"b": "é"}], "c": 1}, "id": 7} [1] 2"#;
+        let mut events = EventReader::new(input.as_bytes());
+        assert_eq!(events.next_event().unwrap(), Some(Event::StartObject));
+        assert_eq!(events.next_event().unwrap(), Some(Event::Key("big".into())));
+        events.skip_value().unwrap();
+        assert_eq!(events.next_event().unwrap(), Some(Event::Key("id".into())));
+        assert_eq!(events.next_event().unwrap(), Some(Event::Number(7.into())));
+        assert_eq!(events.next_event().unwrap(), Some(Event::EndObject));
+
+        let mut parser = StreamingJsonParser::new(input.as_bytes());
+        parser.skip_value().unwrap();
+        parser.skip_value().unwrap();
+        assert_eq!(parser.next().unwrap().unwrap(), JsonValue::from(2));
+        assert!(matches!(
+            parser.skip_value(),
+            Err(ParseError::UnexpectedEndOfInput { while_parsing: Context::Value, .. })
+        ));
+
+        // Skipped values are still checked against the grammar.
+        let mut parser = StreamingJsonParser::new(&br#"{"a": [1 2]}"#[..]);
+        assert!(matches!(parser.skip_value(), Err(ParseError::UnexpectedToken { .. })));
+    }
+
+    #[test]
+    fn test_lazy_document() {
+        let input = r#"{"skip": [{"x": "a\"}"}, 1e5], "results": [{"id": 1, "tags": ["a"]},
+            {"id": 2, "name": "café"}], "ok": true}"#;
+        let doc = LazyDocument::new(input);
+
+        let results = doc.find("results").unwrap();
+        assert_eq!(results.path().to_string(), "$.results");
+        let ids: Vec<_> = results
+            .array_iter()
+            .unwrap()
+            .map(|result| result.unwrap().find("id").unwrap().as_i64().unwrap())
+            .collect();
+        assert_eq!(ids, [1, 2]);
+
+        let second = results.at(1).unwrap();
+        assert_eq!(second.find("name").unwrap().as_str().unwrap(), "caf\u{e9}");
+        assert_eq!(second.raw_json().unwrap(), r#"{"id": 2, "name": "café"}"#);
+        assert_eq!(results.at(0).unwrap().to_value().unwrap(), parse_json_string(
+            r#"{"id": 1, "tags": ["a"]}"#).unwrap());
+        assert!(doc.find("ok").unwrap().as_bool().unwrap());
+
+        assert!(matches!(doc.find("missing"), Err(ParseError::PathNotFound { .. })));
+        assert!(matches!(results.at(2), Err(ParseError::PathNotFound { .. })));
+        assert!(matches!(results.find("id"), Err(ParseError::TypeMismatch { .. })));
+        // Only what is read gets validated.
+        let doc = LazyDocument::new(r#"{"a": 1, "bad": [1, }"#);
+        assert_eq!(doc.find("a").unwrap().as_i64().unwrap(), 1);
+        assert!(doc.find("bad").unwrap().to_value().is_err());
+
+        let deep = format!(r#"{{"a": {}}}"#, "[".repeat(1_000_000));
+        assert!(matches!(
+            LazyDocument::new(&deep).find("a").unwrap().to_value(),
+            Err(ParseError::DepthLimitExceeded { limit: DEFAULT_MAX_DEPTH, .. })
+        ));
+    }
+
+    #[test]
+    fn test_tape() {
+        let input = r#" {"a": [1, {"b\n": null}, []], "c": "x", "d": {}} "#;
+        let tape = Tape::build(input).unwrap();
+        let kinds: Vec<_> = tape.entries().iter().map(|entry| entry.kind).collect();
+        assert_eq!(kinds.len(), 12);
+        assert_eq!(&kinds[..4], &[TapeKind::Object, TapeKind::Key, TapeKind::Array, TapeKind::Number]);
+
+        let root = tape.root();
+        assert_eq!(root.raw_json(), input.trim());
+        let array = root.get("a").unwrap();
+        assert_eq!(array.children().count(), 3);
+        assert_eq!(array.at(1).unwrap().raw_json(), r#"{"b\n": null}"#);
+        assert_eq!(array.at(1).unwrap().get("b\n").unwrap().kind(), TapeKind::Null);
+        assert_eq!(array.at(2).unwrap().raw_json(), "[]");
+        assert_eq!(root.get("c").unwrap().as_str().unwrap(), "x");
+        assert_eq!(root.get("d").unwrap().to_value().unwrap(), JsonValue::Object(Map::new()));
+        assert!(root.get("e").is_none() && array.at(3).is_none());
+
+        assert!(matches!(Tape::build("[1, 2"), Err(ParseError::UnexpectedEndOfInput { .. })));
+        assert!(matches!(Tape::build("[1,]"), Err(ParseError::TrailingComma(_))));
+        assert!(Tape::build(r#"{"a" 1}"#).is_err() && Tape::build("[] []").is_err());
+        assert!(matches!(
+            Tape::build(&"[".repeat(1_000_000)),
+            Err(ParseError::DepthLimitExceeded { limit: DEFAULT_MAX_DEPTH, .. })
+        ));
+    }
+
+    #[test]
+    fn test_raw_value() {
+        let options = ParserOptions::new()
+            .raw_value_at(Path::root().key("payload"))
+            .raw_value_at(Path::root().key("items").index(1));
+        let input = r#"{"id": 1, "payload": {"a" : [1, 2]}, "items": [true, "x\n" ]}"#;
+        let mut parser = StreamingJsonParser::with_options(input.as_bytes(), options.clone());
+        let value = parser.parse_single().unwrap();
+
+        assert!(value.get_path(Path::root().key("payload")).unwrap().is_null());
+        let items = value.get_path(Path::root().key("items")).unwrap();
+        assert_eq!(items.to_string(), "[true,null]");
+        let raw = parser.take_raw_values();
+        assert_eq!(raw.len(), 2);
+        assert_eq!(raw[0].0, Path::root().key("payload"));
+        assert_eq!(raw[0].1.as_str(), r#"{"a" : [1, 2]}"#);
+        assert_eq!(raw[0].1.parse().unwrap().to_string(), r#"{"a":[1,2]}"#);
+        assert_eq!(raw[1].0, Path::root().key("items").index(1));
+        assert_eq!(raw[1].1.as_str(), r#""x\n""#);
+        assert!(parser.take_raw_values().is_empty());
+
+        assert!(parse_json_string_with_raw_values(r#"{"payload": {"a": }}"#, options.clone()).is_err());
+        assert!(RawJson::from_string("[1,".to_string()).is_err());
+        let (empty, raw) = parse_json_string_with_raw_values(r#"{"items": []}"#, options.clone()).unwrap();
+        assert_eq!(empty.to_string(), r#"{"items":[]}"#);
+        assert!(raw.is_empty());
+        let (_, raw) = parse_json_string_with_raw_values(r#"{"id":1,"payload":{"x":[1,2]}}"#, options.clone()).unwrap();
+        assert_eq!(raw[0].1.as_str(), r#"{"x":[1,2]}"#);
+
+        // Functions that return only the value refuse rather than drop it.
+        for result in [
+            parse_json_string_with("{}", options.clone()),
+            parse_json_bytes_with(b"{}", options.clone()),
+            parse_json_reader_with(&b"{}"[..], options.clone()),
+        ] {
+            assert!(matches!(result, Err(ParseError::UnsupportedOption(_))));
+        }
+        let error = spawn_json_stream_with(&b"{}"[..], options, 1).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_array_elements() {
+        let input = r#"[{"id": 1}, {"id": 2}, [3]] {"next": true}"#;
+        let mut parser = parse_json_stream(input.as_bytes());
+        let ids: Vec<String> = parser
+            .array_elements()
+            .map(|element| element.unwrap().to_string())
+            .collect();
+        assert_eq!(ids, vec![r#"{"id":1}"#, r#"{"id":2}"#, "[3]"]);
+        assert_eq!(parser.next().unwrap().unwrap().to_string(), r#"{"next":true}"#);
+
+        let mut empty = parse_json_stream("[]".as_bytes());
+        assert_eq!(empty.array_elements().count(), 0);
+
+        let mut not_array = parse_json_stream(r#"{"a": [1, "]"]} "s" [2]"#.as_bytes());
+        let mut elements = not_array.array_elements();
+        assert!(matches!(elements.next(), Some(Err(ParseError::TypeMismatch { .. }))));
+        assert!(elements.next().is_none());
+        // The mismatched document is skipped, whether a container or not.
+        assert!(matches!(not_array.array_elements().next(), Some(Err(ParseError::TypeMismatch { .. }))));
+        assert_eq!(not_array.array_elements().map(Result::unwrap).collect::<Vec<_>>(), vec![JsonValue::from(2)]);
+
+        let mut broken = parse_json_stream("[1, 2 3]".as_bytes());
+        let results: Vec<_> = broken.array_elements().collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[2].is_err());
+    }
+
+    #[test]
+    fn test_parse_json_items() {
+        let input = r#"{"meta": {"note": "skip \"me\""}, "results": [
+            {"items": [0]},
+            {"other": 1, "items": [{"id": 1}, {"id": 2}], "after": [3]}
+        ]}"#;
+        let items: Vec<String> = parse_json_items(input.as_bytes(), "/results/1/items")
+            .unwrap()
+            .map(|item| item.unwrap().to_string())
+            .collect();
+        assert_eq!(items, vec![r#"{"id":1}"#, r#"{"id":2}"#]);
+
+        let missing = parse_json_items(input.as_bytes(), "/results/2/items").unwrap().next();
+        assert!(matches!(missing, Some(Err(ParseError::PathNotFound { .. }))));
+        let scalar = parse_json_items(input.as_bytes(), "/meta/note").unwrap().next();
+        assert!(matches!(scalar, Some(Err(ParseError::TypeMismatch { .. }))));
+        let past_end = parse_json_items(input.as_bytes(), "/results/1/after/1").unwrap().next();
+        assert!(matches!(past_end, Some(Err(ParseError::PathNotFound { .. }))));
+        assert!(parse_json_items(input.as_bytes(), "results").is_err());
+    }
+
+    #[test]
+    fn test_stream_framing() {
+        let read = |input: &str, framing: Framing| -> Vec<ParseResult<JsonValue>> {
+            parse_json_stream_with(input.as_bytes(), ParserOptions::new().framing(framing)).collect()
+        };
+        let ok = |results: Vec<ParseResult<JsonValue>>| -> Vec<String> {
+            results.into_iter().map(|value| value.unwrap().to_string()).collect()
+        };
+
+        let lines = read("{\"a\": 1}\n\n[2]\n3\n", Framing::NewlineDelimited);
+        assert_eq!(ok(lines), vec![r#"{"a":1}"#, "[2]", "3"]);
+        let shared = read("1 2\n", Framing::NewlineDelimited);
+        assert!(matches!(shared[1], Err(ParseError::InvalidFraming { .. })));
+        let spanning = read("[1,\n2]\n", Framing::NewlineDelimited);
+        assert!(matches!(spanning[0], Err(ParseError::InvalidFraming { .. })));
+
+        let commas = read(r#"{"a": 1}, [2] ,3"#, Framing::CommaSeparated);
+        assert_eq!(ok(commas), vec![r#"{"a":1}"#, "[2]", "3"]);
+        assert!(read("1 2", Framing::CommaSeparated)[1].is_err());
+        assert!(matches!(read("1,", Framing::CommaSeparated)[1], Err(ParseError::TrailingComma(_))));
+
+        let prefixed = read("7 {\"a\":1}\n3\n[2]", Framing::LengthPrefixed);
+        assert_eq!(ok(prefixed), vec![r#"{"a":1}"#, "[2]"]);
+        let wrong = read("5 [1, 2]", Framing::LengthPrefixed);
+        assert!(matches!(wrong[0], Err(ParseError::InvalidFraming { .. })));
+        assert!(read("[1]", Framing::LengthPrefixed)[0].is_err());
+    }
+
+    #[test]
+    fn test_recover_from_errors() {
+        let input = "{\"a\": 1}\n{\"a\": 2\n{\"a\": x}\n[1 2]\n\"open\n{\"a\": 3}\n";
+        let options = ParserOptions::new()
+            .framing(Framing::NewlineDelimited)
+            .recover_from_errors(true);
+        let results: Vec<_> = parse_json_stream_with(input.as_bytes(), options).collect();
+        assert_eq!(results.len(), 6);
+        let good: Vec<String> = results
+            .iter()
+            .filter_map(|result| result.as_ref().ok().map(|value| value.to_string()))
+            .collect();
+        assert_eq!(good, vec![r#"{"a":1}"#, r#"{"a":3}"#]);
+
+        let options = ParserOptions::new().recover_from_errors(true);
+        let events: Vec<_> = EventReader::with_options("x\n[1]".as_bytes(), options).collect();
+        assert!(events[0].is_err());
+        assert_eq!(events[1..].iter().filter(|event| event.is_ok()).count(), 3);
+
+        // A failing document isn't kept past the memory budget.
+        let long = format!("[1,{}\n2]\n{{\"a\": 4}}\n", " ".repeat(1000));
+        let options = ParserOptions::new().recover_from_errors(true).max_total_allocated(300).buffer_size(64);
+        let results: Vec<_> = parse_json_stream_with(long.as_bytes(), options).collect();
+        assert!(matches!(
+            results[0],
+            Err(ParseError::LimitExceeded { limit: ResourceLimit::TotalAllocated, max: 300, .. })
+        ));
+        assert_eq!(results.last().unwrap().as_ref().unwrap().to_string(), r#"{"a":4}"#);
+
+        // Without recovery a stream ends after its first error.
+        let results: Vec<_> = parse_json_stream("{\"a\":1}\n@\n{\"b\":2}".as_bytes()).collect();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[1], Err(ParseError::InvalidCharacter { char: '@', .. })));
+    }
+
+    #[test]
+    fn test_parse_json_string_lossy() {
+        let (value, errors) = parse_json_string_lossy(r#"{"a": 1, "b": tru, "c": [1 2, 3}"#);
+        // `tru`, the missing comma, the wrong bracket and the unclosed object.
+        assert_eq!(errors.len(), 4);
+        assert_eq!(value.get("a"), Some(&PartialValue::Number(1.into())));
+        assert!(value.get("b").unwrap().is_error());
+        assert_eq!(value.get("c").unwrap().to_value().unwrap().to_string(), "[1,2,3]");
+        assert_eq!(value.to_value(), None);
+        assert!(matches!(errors[0], ParseError::InvalidCharacter { char: 't', .. }));
+
+        let (value, errors) = parse_json_string_lossy(r#"[{"k": "v"}, {"k""#);
+        let end = ParseError::UnexpectedEndOfInput {
+            while_parsing: Context::Object,
+            position: at(17, 1, 18),
+        };
+        let PartialValue::Array(elements) = value else { panic!("expected an array") };
+        assert_eq!(elements[0].to_value().unwrap().to_string(), r#"{"k":"v"}"#);
+        assert_eq!(elements[1].get("k"), Some(&PartialValue::Error(end.clone())));
+        assert_eq!(errors, vec![end]);
+
+        let (value, errors) = parse_json_string_lossy(r#"{"ok": [true]}"#);
+        assert!(errors.is_empty());
+        assert_eq!(value.to_value().unwrap(), parse_json_string(r#"{"ok": [true]}"#).unwrap());
+    }
+
+    #[test]
+    fn test_error_line_and_column() {
+        let input = "{\n  \"a\": 1,\n  \"b\": x\n}";
+        let expected = ParseError::InvalidCharacter { char: 'x', position: at(19, 3, 8) };
+        assert_eq!(parse_json_string(input), Err(expected.clone()));
+        assert_eq!(parse_json_str_borrowed(input).map(JsonValue::from), Err(expected.clone()));
+        assert_eq!(expected.to_string(), "Invalid character 'x' at line 3, column 8");
+
+        // Offsets count bytes; columns count characters.
+        let error = parse_json_string("[\"é\",\n \"ü\" 1]").unwrap_err();
+        assert!(matches!(error, ParseError::UnexpectedToken { span, .. }
+            if span.start == at(13, 2, 6)));
+    }
+
+    #[test]
+    fn test_error_span() {
+        let input = r#"{"name": "é", "tags": ["a" true]}"#;
+        let error = parse_json_string(input).unwrap_err();
+        assert_eq!(&input[error.span().unwrap()], "true");
+        let error = parse_json_str_borrowed(input).unwrap_err();
+        assert_eq!(&input[error.span().unwrap()], "true");
+        let input = r#"["a" "bé\"c" 1]"#;
+        for error in [parse_json_string(input).unwrap_err(), parse_json_str_borrowed(input).unwrap_err()] {
+            assert_eq!(&input[error.span().unwrap()], r#""bé\"c""#);
+        }
+
+        let input = "[1, 2,]";
+        let error = parse_json_string(input).unwrap_err();
+        assert_eq!(error.span(), Some(6..7));
+        let end = ParseError::UnexpectedEndOfInput { while_parsing: Context::Array, position: at(3, 1, 4) };
+        assert_eq!(end.span(), Some(3..3));
+        let closed = IoError::new(std::io::ErrorKind::BrokenPipe, "closed");
+        assert_eq!(ParseError::Io(closed).span(), None);
+
+        let mut reader = EventReader::new(r#"{"key": 12.5}"#.as_bytes());
+        reader.next_event().unwrap();
+        reader.next_event().unwrap();
+        assert_eq!(reader.span(), 1..6);
+        reader.next_event().unwrap();
+        assert_eq!(reader.span(), 8..12);
+    }
+
+    #[test]
+    fn test_error_render() {
+        let input = "{\n  \"a\": 1,\n  \"b\": [1 true]\n}";
+        let error = parse_json_string(input).unwrap_err();
+        let expected = concat!(
+            "error: Expected ',' or ']', found Boolean(true) at line 3, column 11 ",
+            "— did you forget a comma after the previous element?\n",
+            "  |\n",
+            "3 |   \"b\": [1 true]\n",
+            "  |           ^^^^",
+        );
+        assert_eq!(error.render(input), expected);
+        let error = ParseError::Io(IoError::new(std::io::ErrorKind::BrokenPipe, "closed"));
+        assert_eq!(error.render(input), "error: IO error: closed");
+    }
+
+    #[test]
+    fn test_error_path() {
+        let input = r#"{"servers": [{"tls": {}}, {"tls": {"cert": "a.pem", "key": nope}}]}"#;
+        let mut parser = parse_json_stream(input.as_bytes());
+        assert_eq!(parser.error_path(), None);
+        let error = parser.parse_single().unwrap_err();
+        assert_eq!(error, ParseError::InvalidCharacter { char: 'n', position: at(59, 1, 60) });
+        let path = Path::root().key("servers").index(1).key("tls").key("key");
+        assert_eq!(parser.error_path(), Some(&path));
+        assert_eq!(path.to_string(), "$.servers[1].tls.key");
+
+        // The path comes from the parser's own stack, whatever the options.
+        let jsonc = "{\"a\": [1, /* ] */ {\"b\": x}]}";
+        let options = ParserOptions::new().allow_comments(true);
+        let mut parser = parse_json_stream_with(jsonc.as_bytes(), options);
+        assert!(parser.parse_single().is_err());
+        assert_eq!(parser.error_path(), Some(&Path::root().key("a").index(1).key("b")));
+
+        // Errors between documents, or in reading a key, name the container.
+        let mut parser = parse_json_stream("[1] x".as_bytes());
+        assert!(parser.parse_single().is_err());
+        assert_eq!(parser.error_path(), Some(&Path::root()));
+        let mut parser = parse_json_stream(r#"{"a": {"b": 1, 2}}"#.as_bytes());
+        assert!(parser.parse_single().is_err());
+        assert_eq!(parser.error_path(), Some(&Path::root().key("a")));
+
+        // Streams keep the path of the last error, in the document it was in.
+        let mut parser = parse_json_stream_with(
+            "[1]\n{\"a\": [true, nul]}\n".as_bytes(),
+            ParserOptions::new().recover_from_errors(true),
+        );
+        assert!(parser.next().unwrap().is_ok());
+        assert!(parser.next().unwrap().is_err());
+        assert_eq!(parser.error_path(), Some(&Path::root().key("a").index(1)));
+
+        // Elements read one at a time have their path in the whole document.
+        let mut items = parse_json_items(r#"{"rows": [{"id": 1}, {"id": x}]}"#.as_bytes(), "/rows").unwrap();
+        assert!(items.next().unwrap().is_ok());
+        assert!(items.next().unwrap().is_err());
+        assert_eq!(items.error_path(), Some(&Path::root().key("rows").index(1).key("id")));
+        let mut parser = parse_json_stream("[[1], [2 3]]".as_bytes());
+        assert!(parser.array_elements().nth(1).unwrap().is_err());
+        assert_eq!(parser.error_path(), Some(&Path::root().index(1).index(1)));
+    }
+
+    #[test]
+    fn test_parse_json_string_with_warnings() {
+        let input = "{\n  // port\n  \"port\": 80,\n  \"port\": 8080,\n  hosts: [\"a\",],\n}";
+        let options = ParserOptions::new().dialect(Dialect::Json5);
+        let (value, warnings) = parse_json_string_with_warnings(input, options.clone()).unwrap();
+        assert_eq!(value, parse_json_string_with(input, options.clone()).unwrap());
+        let found: Vec<_> = warnings.iter().map(|w| (w.kind.clone(), w.position)).collect();
+        assert_eq!(found, vec![
+            (WarningKind::Comment, at(4, 2, 3)),
+            (WarningKind::DuplicateKey("port".to_string()), at(28, 4, 3)),
+            (WarningKind::UnquotedKey, at(44, 5, 3)),
+            (WarningKind::TrailingComma, at(55, 5, 14)),
+            (WarningKind::TrailingComma, at(57, 5, 16)),
+        ]);
+        assert_eq!(warnings[0].to_string(), "Comment at line 2, column 3");
+
+        let input = "['a', 1, 0xFF, +1, .5, -Infinity, NaN, 'b\\x41', \"c\td\te\"]";
+        let (_, warnings) = parse_json_string_with_warnings(input, options).unwrap();
+        let kinds: Vec<_> = warnings.iter().map(|w| w.kind.clone()).collect();
+        assert_eq!(kinds, vec![
+            WarningKind::SingleQuotedString,
+            WarningKind::NonStandardNumber,
+            WarningKind::NonStandardNumber,
+            WarningKind::NonStandardNumber,
+            WarningKind::NonStandardNumber,
+            WarningKind::NonStandardNumber,
+            WarningKind::SingleQuotedString,
+            WarningKind::NonStandardEscape,
+            WarningKind::ControlCharacter,
+        ]);
+        assert_eq!(warnings[7].position, at(41, 1, 42));
+        assert_eq!(warnings[8].position, at(50, 1, 51));
+        let lenient = ParserOptions::new().allow_non_finite_numbers(true).allow_control_characters(true);
+        let (_, warnings) = parse_json_string_with_warnings("[NaN, -Infinity, \"\n\"]", lenient).unwrap();
+        assert_eq!(warnings.len(), 3);
+
+        let strict = parse_json_string_with_warnings("[1]", ParserOptions::new()).unwrap();
+        assert!(strict.1.is_empty());
+
+        // Nothing is collected unless asked for.
+        let mut parser = parse_json_stream(r#"{"a": 1, "a": 2}"#.as_bytes());
+        parser.next().unwrap().unwrap();
+        assert!(parser.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_io_error_kind() {
+        use std::io::ErrorKind;
+
+        struct Blocked;
+        impl std::io::Read for Blocked {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(ErrorKind::WouldBlock.into())
+            }
+        }
+
+        let reader = std::io::Read::chain(&b"[1, "[..], Blocked);
+        let error = StreamingJsonParser::new(reader).parse_single().unwrap_err();
+        assert!(matches!(&error, ParseError::Io(io) if io.kind == ErrorKind::WouldBlock));
+        assert_eq!(error.position(), Some(at(4, 1, 5)));
+
+        // The encoding is settled by `[1`, without waiting for a fourth byte.
+        let reader = std::io::Read::chain(&b"[1]"[..], Blocked);
+        assert_eq!(parse_json_stream(reader).next(), Some(Ok(JsonValue::Array(vec![1.into()]))));
+        let reader = std::io::Read::chain(&b"\xff\xfe"[..], Blocked);
+        let error = StreamingJsonParser::new(reader).parse_single().unwrap_err();
+        assert!(matches!(&error, ParseError::Io(io) if io.kind == ErrorKind::WouldBlock));
+
+        let error = parse_json_bytes(b"[\"\xff\"]").unwrap_err();
+        assert!(matches!(&error, ParseError::Io(io) if io.kind == ErrorKind::InvalidData));
+        assert_eq!(error.position(), Some(at(2, 1, 3)));
+        let ParseError::Io(io) = error else { unreachable!() };
+        assert_eq!(std::io::Error::from(io).kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_error_diagnostic() {
+        let input = "{\n  \"a\": 1,\n  \"b\": x\n}";
+        let error = parse_json_string(input).unwrap_err();
+        assert_eq!(error.diagnostic().path, None);
+        let diagnostic = error.diagnostic_at(Some(&Path::root().key("b")));
+        assert_eq!(diagnostic, Diagnostic {
+            code: "invalid_character",
+            message: "Invalid character 'x' at line 3, column 8".to_string(),
+            line: Some(3),
+            column: Some(8),
+            span: Some(19..20),
+            path: Some("$.b".to_string()),
+        });
+        let json = diagnostic.to_json();
+        assert_eq!(json.get("code"), Some(&JsonValue::from("invalid_character")));
+        assert_eq!(json.get("offset"), Some(&JsonValue::from(19)));
+        assert_eq!(json.get("path"), Some(&JsonValue::from("$.b")));
+
+        let json = ParseError::InvalidPointer("a".to_string()).to_json();
+        assert_eq!(json.get("code"), Some(&JsonValue::from("invalid_pointer")));
+        assert!(json.get("line").unwrap().is_null() && json.get("path").unwrap().is_null());
+    }
+
+    #[test]
+    fn test_unexpected_token_hints() {
+        let hint = |input: &str| match parse_json_string(input).unwrap_err() {
+            ParseError::UnexpectedToken { expected, hint, .. } => (expected, hint),
+            other => panic!("{:?}", other),
+        };
+        let expect = |expected: &str, hint: &str| (expected.to_string(), Some(hint.to_string()));
+
+        let comma = "did you forget a comma after the previous member?";
+        assert_eq!(hint(r#"{"a": 1 "b": 2}"#), expect("',' or '}'", comma));
+        let comma = "did you forget a comma after the previous element?";
+        assert_eq!(hint("[1 2]"), expect("',' or ']'", comma));
+        let colon = "did you forget the ':' after the key?";
+        assert_eq!(hint(r#"{"a" 1}"#), expect("':'", colon));
+        let quotes = "JSON strings take double quotes";
+        assert_eq!(hint("{'a': 1}"), expect("string key or '}'", quotes));
+        assert_eq!(hint(r#"["a", 'b']"#), expect("JSON value", quotes));
+        let unquoted = "keys must be strings in double quotes";
+        assert_eq!(hint(r#"{"a": 1, b: 2}"#), expect("string key", unquoted));
+        assert_eq!(hint(r#"{"a" = 1}"#), expect("':'", "use ':' between a key and its value"));
+        assert_eq!(hint(r#"{"a": 1;}"#), ("',' or '}'".to_string(), None));
+
+        let error = parse_json_string("[1 true]").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Expected ',' or ']', found Boolean(true) at line 1, column 4 \
+             — did you forget a comma after the previous element?"
+        );
+        // Elsewhere in a value, a stray character is still just invalid.
+        assert!(matches!(parse_json_string("[1, ?]").unwrap_err(),
+            ParseError::InvalidCharacter { char: '?', .. }));
+        let pushed = PushParser::new().feed(b"{\"a\" = 1}");
+        assert!(matches!(pushed, Err(ParseError::UnexpectedToken { hint: Some(_), .. })));
+    }
+
+    #[test]
+    fn test_unexpected_end_of_input() {
+        let context = |input: &str| match parse_json_string(input) {
+            Err(ParseError::UnexpectedEndOfInput { while_parsing, .. }) => Some(while_parsing),
+            _ => None,
+        };
+        assert_eq!(context(""), Some(Context::Value));
+        assert_eq!(context("[1, 2"), Some(Context::Array));
+        assert_eq!(context(r#"{"a": 1"#), Some(Context::Object));
+        assert_eq!(context("[tr"), Some(Context::Literal));
+        assert_eq!(context("[1."), Some(Context::Number));
+        assert_eq!(context("[1, 2]]"), None);
+
+        let error = parse_json_string("[1, 2").unwrap_err();
+        assert!(error.is_end_of_input());
+        assert_eq!(error.position(), Some(at(5, 1, 6)));
+        assert_eq!(
+            error.to_string(),
+            "Unexpected end of input while parsing an array at line 1, column 6"
+        );
+        assert!(parse_json_string(r#"["abc"#).unwrap_err().is_end_of_input());
+        assert!(!parse_json_string("[1, x]").unwrap_err().is_end_of_input());
+    }
+
+    #[test]
+    fn test_progress_hook() {
+        use std::sync::{Arc, Mutex};
+
+        let input = "{\"id\": 1}\n".repeat(100);
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reports);
+        let parser = parse_json_stream(input.as_bytes())
+            .on_progress(300, move |progress| sink.lock().unwrap().push(progress));
+        assert_eq!(parser.count(), 100);
+
+        let reports = reports.lock().unwrap();
+        // 1000 bytes in all: reported once past each multiple of 300.
+        assert_eq!(reports.len(), 3);
+        assert!(reports.windows(2).all(|pair| pair[0].bytes < pair[1].bytes));
+        assert!(reports[0].bytes >= 300 && reports[0].bytes < 320);
+        assert_eq!(reports[0].records, reports[0].bytes / 10);
+    }
+
+    #[test]
+    fn test_parse_metrics() {
+        let input = "{\"a\": [1, [2]]}\ntru\n[3]";
+        let options = ParserOptions::new().recover_from_errors(true);
+        let mut parser = parse_json_stream_with(input.as_bytes(), options);
+        assert_eq!(parser.metrics(), ParseMetrics::default());
+        let results: Vec<_> = parser.by_ref().collect();
+        assert_eq!(results.len(), 3);
+        let metrics = parser.metrics();
+        assert_eq!(metrics.bytes_read, input.len());
+        assert_eq!(metrics.max_depth, 3);
+        assert_eq!(metrics.records, 2);
+        assert_eq!(metrics.errors, 1);
+        // `{"a":[1,[2]]}` is 11 tokens and `[3]` 3; `tru` is none.
+        assert_eq!(metrics.tokens, 14);
+
+        let mut parser = parse_json_stream("[1, 2, 3]".as_bytes());
+        assert_eq!(parser.array_elements().count(), 3);
+        assert_eq!(parser.metrics().records, 3);
+    }
+
+    #[test]
+    fn test_max_total_allocated() {
+        let budget = |bytes| ParserOptions::new().max_total_allocated(bytes);
+        let exceeded = |input: &str, bytes| {
+            matches!(
+                parse_json_string_with(input, budget(bytes)),
+                Err(ParseError::LimitExceeded { limit: ResourceLimit::TotalAllocated, .. })
+            )
+        };
+        let value_size = std::mem::size_of::<JsonValue>();
+
+        // One array and three numbers.
+        assert!(parse_json_string_with("[1, 2, 3]", budget(4 * value_size)).is_ok());
+        assert!(exceeded("[1, 2, 3]", 4 * value_size - 1));
+
+        let huge = format!("[\"{}\"]", "x".repeat(10_000));
+        assert!(exceeded(&huge, 1_000));
+        let error = parse_json_string_with(&huge, budget(1_000)).unwrap_err();
+        assert_eq!(error.position(), Some(at(1, 1, 2)));
+
+        // The budget is per document.
+        let lines = "[1, 2, 3]\n".repeat(10);
+        let options = budget(4 * value_size);
+        assert!(parse_json_stream_with(lines.as_bytes(), options).all(|result| result.is_ok()));
+    }
+
+    #[test]
+    fn test_buffer_size() {
+        struct Recording<'a> {
+            input: &'a [u8],
+            requests: Vec<usize>,
+        }
+        impl std::io::Read for Recording<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.requests.push(buf.len());
+                self.input.read(buf)
+            }
+        }
+
+        let input = format!("[{}0]", "1, ".repeat(1000));
+        let read_sizes = |options: ParserOptions| {
+            let mut reader = Recording { input: input.as_bytes(), requests: Vec::new() };
+            let value = StreamingJsonParser::with_options(&mut reader, options).parse_single();
+            assert_eq!(value.unwrap().as_array().map(Vec::len), Some(1001));
+            // The first read takes just the bytes that give away the encoding.
+            assert_eq!(reader.requests[0], 4);
+            reader.requests.split_off(1)
+        };
+
+        assert!(read_sizes(ParserOptions::new()).iter().all(|&size| size == DEFAULT_BUFFER_SIZE));
+        assert!(read_sizes(ParserOptions::new().buffer_size(100)).iter().all(|&size| size == 100));
+        assert!(read_sizes(ParserOptions::new().buffer_size(1)).iter().all(|&size| size == 64));
+    }
+
+    #[test]
+    fn test_string_runs_across_reads() {
+        let text = "plain ascii, é and 😀, ".repeat(20);
+        let input = format!(r#"["{}\n\u00e9{}", x]"#, text, text);
+        let options = ParserOptions::new().buffer_size(64);
+        let error = StreamingJsonParser::with_options(input.as_bytes(), options).parse_single();
+
+        // Columns count characters, so the run must be counted as such.
+        let chars = input.chars().count();
+        let x = input.len() - 2;
+        let expected = ParseError::InvalidCharacter { char: 'x', position: at(x, 1, chars - 1) };
+        assert_eq!(error, Err(expected));
+
+        let input = format!(r#"["{}\n\u00e9{}"]"#, text, text);
+        let options = ParserOptions::new().buffer_size(64);
+        let value = StreamingJsonParser::with_options(input.as_bytes(), options).parse_single();
+        let expected = JsonValue::Array(vec![JsonValue::String(format!("{}\né{}", text, text))]);
+        assert_eq!(value, Ok(expected));
+    }
+
+    #[test]
+    fn test_whitespace_runs_across_reads() {
+        let indent = " ".repeat(100);
+        let input = format!("{{\r\n{0}\"a\": [\n{0}1,\n\n\t{0}x\n]}}", indent);
+        let options = ParserOptions::new().buffer_size(64);
+        let error = StreamingJsonParser::with_options(input.as_bytes(), options).parse_single();
+        let x = input.find('x').unwrap();
+        let expected = ParseError::InvalidCharacter { char: 'x', position: at(x, 5, 102) };
+        assert_eq!(error, Err(expected));
+
+        let value = parse_json_string(&input.replace('x', "2")).unwrap();
+        assert_eq!(value.to_string(), r#"{"a":[1,2]}"#);
+    }
+
+    #[test]
+    fn test_spawn_json_stream() {
+        let input = "{\"n\": 1}\n{\"n\": 2}\n{\"n\": }\n[3]".to_string();
+        let results: Vec<_> = spawn_json_stream(std::io::Cursor::new(input)).unwrap().into_iter().collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1], parse_json_string(r#"{"n": 2}"#));
+        assert!(results[2].is_err());
+
+        // A malformed document is sent once, then the channel closes.
+        let receiver = spawn_json_stream_with("{\"n\": 1}\n@\n[2]".as_bytes(), ParserOptions::new(), 1).unwrap();
+        assert!(receiver.recv().unwrap().is_ok());
+        assert!(matches!(receiver.recv().unwrap(), Err(ParseError::InvalidCharacter { char: '@', .. })));
+        assert!(receiver.recv().is_err());
+        let options = ParserOptions::new().recover_from_errors(true);
+        let results: Vec<_> = spawn_json_stream_with("{\"n\": 1}\n@\n[2]".as_bytes(), options, 1).unwrap().iter().collect();
+        assert_eq!(results.len(), 3);
+
+        // With room for one value ahead, the thread waits on the consumer.
+        let receiver = spawn_json_stream_with("[1] [2] [3]".as_bytes(), ParserOptions::new(), 1).unwrap();
+        assert_eq!(receiver.iter().take(2).count(), 2);
+        drop(receiver);
+    }
+
+    #[test]
+    fn test_parser_moves_between_threads() {
+        let input = std::io::Cursor::new(b"[1] {\"a\": [2,\n3]} \"x\"".to_vec());
+        let mut parser = parse_json_stream(input);
+        assert_eq!(parser.next(), Some(Ok(JsonValue::Array(vec![JsonValue::from(1)]))));
+
+        let mut parser = std::thread::spawn(move || {
+            assert_eq!(parser.next().unwrap().unwrap().to_string(), r#"{"a":[2,3]}"#);
+            parser
+        })
+        .join()
+        .unwrap();
+        assert_eq!(parser.next(), Some(Ok(JsonValue::String("x".to_string()))));
+        assert_eq!(parser.next(), None);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_streaming_parser() {
+        use std::future::Future;
+        use std::sync::Arc;
+        use std::task::{Context as TaskContext, Poll, Wake, Waker};
+
+        struct Noop;
+        impl Wake for Noop {
+            fn wake(self: Arc<Self>) {}
+        }
+
+        // In-memory readers never wait, so polling once finishes each read.
+        fn ready<F: Future>(future: F) -> F::Output {
+            let mut future = std::pin::pin!(future);
+            let waker = Waker::from(Arc::new(Noop));
+            match future.as_mut().poll(&mut TaskContext::from_waker(&waker)) {
+                Poll::Ready(output) => output,
+                Poll::Pending => panic!("an in-memory read never waits"),
+            }
+        }
+
+        // Small reads split tokens and characters between them.
+        let input = "{\"a\": [1, \"é\"]}\n[true, null]\n".repeat(3);
+        let options = ParserOptions::new().buffer_size(7);
+        let mut parser = AsyncStreamingJsonParser::with_options(input.as_bytes(), options);
+        for _ in 0..3 {
+            let first = ready(parser.next_value()).unwrap().unwrap();
+            assert_eq!(first.to_string(), r#"{"a":[1,"é"]}"#);
+            assert_eq!(ready(parser.next_value()), Some(parse_json_string("[true, null]")));
+        }
+        assert_eq!(ready(parser.next_value()), None);
+
+        let mut parser = AsyncStreamingJsonParser::new(r#"[1, {"b": x}]"#.as_bytes());
+        let error = ready(parser.next_value()).unwrap().unwrap_err();
+        assert!(matches!(error, ParseError::InvalidCharacter { char: 'x', .. }));
+        assert_eq!(ready(parser.next_value()), None);
+
+        let options = ParserOptions::new().raw_value_at(Path::root().index(0)).buffer_size(7);
+        let mut parser = AsyncStreamingJsonParser::with_options(r#"[{"k": [1]}, 2]"#.as_bytes(), options);
+        let value = ready(parser.next_value()).unwrap().unwrap();
+        assert_eq!(value.to_string(), "[null,2]");
+        assert_eq!(parser.take_raw_values()[0].1.as_str(), r#"{"k": [1]}"#);
+    }
+
+    #[test]
+    fn test_chunk_parser() {
+        let mut parser = ChunkParser::new();
+        assert!(parser.feed(b"{\"event\": \"tick\", \"n\": 1").next().is_none());
+        assert!(parser.needs_more_data());
+        let values: Vec<_> = parser.feed("}\n{\"n\": 2}\n[tr".as_bytes()).collect();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[1], parse_json_string(r#"{"n": 2}"#));
+        let values: Vec<_> = parser.feed(b"ue]").collect();
+        assert_eq!(values, vec![parse_json_string("[true]")]);
+        assert!(!parser.needs_more_data());
+        assert_eq!(parser.finish().count(), 0);
+
+        // Values completed before an error in the same chunk are kept.
+        let mut parser = ChunkParser::new();
+        let values: Vec<_> = parser.feed(r#"[1] {"a": [2, x]}"#).collect();
+        assert_eq!(values[0], parse_json_string("[1]"));
+        assert!(values[1].is_err());
+        assert_eq!(parser.error_path(), Some(&Path::root().key("a").index(1)));
+        assert!(parser.feed("[3]").next().is_none());
+
+        let mut parser = ChunkParser::new();
+        assert_eq!(parser.feed("[1, 2").count(), 0);
+        let end = parser.finish().next();
+        assert!(matches!(end, Some(Err(ref error)) if error.is_end_of_input()));
+
+        // Values are built as the streaming parser builds them, even where
+        // a raw value or a duplicate key is split between chunks.
+        let options = ParserOptions::new()
+            .raw_value_at(Path::root().key("payload"))
+            .collect_warnings(true);
+        let mut parser = ChunkParser::with_options(options);
+        let input = r#"{"payload": {"a" : [1, "b"]}, "k": 1, "k": 2}"#;
+        let mut values = Vec::new();
+        for piece in input.as_bytes().chunks(3) {
+            values.extend(parser.feed(piece));
+        }
+        assert_eq!(values, vec![parse_json_string(r#"{"payload": null, "k": 2}"#)]);
+        let raw = parser.take_raw_values();
+        assert_eq!(raw.len(), 1);
+        assert_eq!(raw[0].1.as_str(), r#"{"a" : [1, "b"]}"#);
+        let warnings = parser.take_warnings();
+        assert!(matches!(&warnings[..], [Warning { kind: WarningKind::DuplicateKey(key), .. }] if key == "k"));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_input() {
+        use std::io::Write;
+
+        let gzip = |text: &str| {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+            encoder.write_all(text.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+        // Two members, as `cat a.gz b.gz` makes.
+        let mut archive = gzip("{\"n\": 1}\n{\"n\": 2}\n");
+        archive.extend(gzip("{\"n\": 3}\n"));
+
+        let values: Vec<_> = stream_json_objects(archive.as_slice()).collect();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[2], parse_json_string(r#"{"n": 3}"#));
+
+        // The magic bytes are found even when the first read returns just one.
+        let split = std::io::Read::chain(&archive[..1], &archive[1..]);
+        assert_eq!(stream_json_objects(split).count(), 3);
+
+        let plain: Vec<_> = stream_json_objects("[1] [2]".as_bytes()).collect();
+        assert_eq!(plain, vec![parse_json_string("[1]"), parse_json_string("[2]")]);
+        assert_eq!(stream_json_objects("1".as_bytes()).count(), 1);
+
+        // A damaged archive fails as a read error, which ends the stream.
+        archive.truncate(archive.len() - 4);
+        let values: Vec<_> = stream_json_objects(archive.as_slice()).collect();
+        assert!(matches!(values.last(), Some(Err(ParseError::Io(_)))));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_input() {
+        let mut archive = zstd::encode_all("{\"n\": 1}\n{\"n\": 2}\n".as_bytes(), 3).unwrap();
+        archive.extend(zstd::encode_all("{\"n\": 3}\n".as_bytes(), 3).unwrap());
+
+        let values: Vec<_> = stream_json_objects(archive.as_slice()).collect();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[2], parse_json_string(r#"{"n": 3}"#));
+        let document = StreamingJsonParser::new(Decompressor::new(&archive[..archive.len() / 2]));
+        assert!(document.last().is_some_and(|value| value.is_err()));
+    }
+
+    #[test]
+    fn test_parse_json_file_and_reader() {
+        let dir = std::env::temp_dir().join(format!("json-file-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        std::fs::write(&path, "{\"port\": 8080}\n").unwrap();
+        assert_eq!(parse_json_file(&path), parse_json_string(r#"{"port": 8080}"#));
+
+        std::fs::write(&path, "{} {}").unwrap();
+        assert!(matches!(parse_json_file(&path), Err(ParseError::UnexpectedToken { .. })));
+
+        let missing = dir.join("missing.json");
+        match parse_json_file(&missing) {
+            Err(ParseError::Io(error)) => {
+                assert_eq!(error.kind, std::io::ErrorKind::NotFound);
+                assert!(error.message.contains("missing.json"));
+            }
+            other => panic!("expected a read error, got {:?}", other),
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(parse_json_reader("[1, 2]".as_bytes()), parse_json_string("[1, 2]"));
+        let options = ParserOptions::new().allow_comments(true);
+        assert!(parse_json_reader_with("[1] // ok".as_bytes(), options).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Envelope {
+            id: u32,
+            payload: JsonValue,
+        }
+
+        let text = r#"{"id":7,"payload":{"tags":["a",null,true],"big":18446744073709551615,"ratio":0.25}}"#;
+        let envelope: Envelope = serde_json::from_str(text).unwrap();
+        assert_eq!(envelope.id, 7);
+        let payload = &envelope.payload;
+        assert_eq!(payload.get("tags"), Some(&parse_json_string(r#"["a", null, true]"#).unwrap()));
+        assert_eq!(payload.get("big").and_then(JsonValue::as_u64), Some(u64::MAX));
+        assert_eq!(payload.get("ratio").and_then(JsonValue::as_f64), Some(0.25));
+
+        let back: serde_json::Value = serde_json::from_str(&serde_json::to_string(&envelope).unwrap()).unwrap();
+        assert_eq!(back, serde_json::from_str::<serde_json::Value>(text).unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_from_value() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        enum Role {
+            Admin,
+            Guest { until: u32 },
+        }
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct User<'a> {
+            name: &'a str,
+            age: u8,
+            email: Option<String>,
+            roles: Vec<Role>,
+        }
+
+        let value = parse_json_string(
+            r#"{"name": "ada", "age": 36, "email": null, "roles": ["Admin", {"Guest": {"until": 9}}]}"#,
+        )
+        .unwrap();
+        let user: User = from_value(&value).unwrap();
+        assert_eq!(
+            user,
+            User { name: "ada", age: 36, email: None, roles: vec![Role::Admin, Role::Guest { until: 9 }] }
+        );
+
+        let value = parse_json_string(r#"{"name": "ada", "age": 36, "roles": [{"Guest": {"until": "x"}}]}"#)
+            .unwrap();
+        match from_value::<User>(&value) {
+            Err(ParseError::TypeMismatch { expected, found, path }) => {
+                assert_eq!(expected, "u32");
+                assert_eq!(found, "string");
+                assert_eq!(path, "$.roles[0].Guest.until");
+            }
+            other => panic!("expected a type mismatch, got {:?}", other),
+        }
+
+        let value = parse_json_string(r#"[{"age": 1}]"#).unwrap();
+        let error = from_value::<Vec<User>>(&value).unwrap_err();
+        assert_eq!(error.path(), Some("$[0]"));
+        assert_eq!(error.to_string(), "missing field `name` at $[0]");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_value() {
+        use std::collections::BTreeMap;
+
+        #[derive(serde::Serialize)]
+        enum Shape {
+            Point,
+            Circle(f64),
+            Rect { w: u32, h: u32 },
+        }
+
+        #[derive(serde::Serialize)]
+        struct Scene {
+            name: &'static str,
+            shapes: Vec<Shape>,
+            origin: (i8, i8),
+            layers: BTreeMap<u32, bool>,
+            note: Option<String>,
+            id: u128,
+        }
+
+        let scene = Scene {
+            name: "demo",
+            shapes: vec![Shape::Point, Shape::Circle(0.5), Shape::Rect { w: 2, h: 3 }],
+            origin: (-1, 1),
+            layers: BTreeMap::from([(1, true), (2, false)]),
+            note: None,
+            id: u128::MAX,
+        };
+        let value = to_value(&scene).unwrap();
+        let expected = parse_json_string(
+            r#"{"name": "demo", "shapes": ["Point", {"Circle": 0.5}, {"Rect": {"w": 2, "h": 3}}],
+                "origin": [-1, 1], "layers": {"1": true, "2": false}, "note": null,
+                "id": 340282366920938463463374607431768211455}"#,
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+        assert!(to_string(&value).contains(r#""id":340282366920938463463374607431768211455"#));
+
+        let keyed_by_array = BTreeMap::from([(vec![1], 1)]);
+        assert!(matches!(to_value(&keyed_by_array), Err(ParseError::Custom { .. })));
+
+        let floats = to_value(&(f64::NAN, f32::INFINITY, 1.5f32)).unwrap();
+        assert_eq!(floats.to_string(), "[null,null,1.5]");
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_from_and_to_json() {
+        #[derive(Debug, PartialEq, FromJson, ToJson)]
+        #[json(rename_all = "snake_case")]
+        enum Backend {
+            InMemory,
+            Disk(String),
+            Remote { host: String, port: u16 },
+        }
+
+        #[derive(Debug, PartialEq, FromJson, ToJson)]
+        #[json(rename_all = "camelCase")]
+        struct Settings<T> {
+            max_connections: u32,
+            #[json(rename = "tls")]
+            use_tls: bool,
+            timeout_ms: Option<u64>,
+            backends: Vec<Backend>,
+            extra: HashMap<String, T>,
+        }
+
+        let text = r#"{"maxConnections": 8, "tls": true, "backends": ["in_memory", {"disk": "/var/db"},
+            {"remote": {"host": "db", "port": 5432}}], "extra": {"region": "eu"}}"#;
+        let settings = Settings::<String>::from_json(&parse_json_string(text).unwrap()).unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                max_connections: 8,
+                use_tls: true,
+                timeout_ms: None,
+                backends: vec![
+                    Backend::InMemory,
+                    Backend::Disk("/var/db".to_string()),
+                    Backend::Remote { host: "db".to_string(), port: 5432 },
+                ],
+                extra: HashMap::from([("region".to_string(), "eu".to_string())]),
+            }
+        );
+        let json = settings.to_json();
+        assert_eq!(json.get("timeoutMs"), Some(&JsonValue::Null));
+        assert_eq!(Settings::<String>::from_json(&json).unwrap(), settings);
+
+        let bad = parse_json_string(r#"{"maxConnections": 8, "tls": true, "backends": [{"remote": {"host": "db",
+            "port": 70000}}], "extra": {}}"#).unwrap();
+        match Settings::<String>::from_json(&bad) {
+            Err(ParseError::TypeMismatch { expected, found, path }) => {
+                assert_eq!(expected, "u16");
+                assert_eq!(found, "number");
+                assert_eq!(path, "$.backends[0].remote.port");
+            }
+            other => panic!("expected a type mismatch, got {:?}", other),
+        }
+
+        let missing = parse_json_string(r#"{"tls": false, "backends": [], "extra": {}}"#).unwrap();
+        let error = Settings::<String>::from_json(&missing).unwrap_err();
+        assert_eq!(error, ParseError::custom("missing field `maxConnections`"));
+        assert!(Backend::from_json(&JsonValue::from("tape")).is_err());
+        assert_eq!(vec![1.5, f64::NAN, f64::NEG_INFINITY].to_json().to_string(), "[1.5,null,null]");
+        assert_eq!(f32::INFINITY.to_json(), JsonValue::Null);
+    }
+
+    #[test]
+    fn test_try_from_json_value() {
+        let mut value =
+            parse_json_string(r#"{"name": "ada", "scores": [1, 2, 3], "limits": {"cpu": 2.5}}"#).unwrap();
+
+        let name = String::try_from(value.remove_at(["name"]).unwrap()).unwrap();
+        let scores: Vec<u64> = value.remove_at(["scores"]).unwrap().try_into().unwrap();
+        let limits: HashMap<String, f64> = value.remove_at(["limits"]).unwrap().try_into().unwrap();
+        assert_eq!(name, "ada");
+        assert_eq!(scores, [1, 2, 3]);
+        assert_eq!(limits, HashMap::from([("cpu".to_string(), 2.5)]));
+        assert_eq!(bool::try_from(JsonValue::Boolean(true)), Ok(true));
+        assert_eq!(i64::try_from(JsonValue::from(-4)), Ok(-4));
+
+        let nested = parse_json_string(r#"[["a"], ["b", 7]]"#).unwrap();
+        match Vec::<Vec<String>>::try_from(nested) {
+            Err(ParseError::TypeMismatch { expected, found, path }) => {
+                assert_eq!(expected, "string");
+                assert_eq!(found, "number");
+                assert_eq!(path, "$[1][1]");
+            }
+            other => panic!("expected a type mismatch, got {:?}", other),
+        }
+        assert!(matches!(u64::try_from(JsonValue::from(-1)), Err(ParseError::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_expect_accessors() {
+        let config = parse_json_string(r#"{"server": {"port": "8080", "hosts": ["a"], "debug": false}}"#).unwrap();
+        let server = config.expect_object(Path::root()).unwrap().get("server").unwrap();
+        let server = server.expect_object(["server"]).unwrap();
+        assert_eq!(server["hosts"].expect_array(["server", "hosts"]).unwrap().len(), 1);
+        assert_eq!(server["debug"].expect_bool(["server", "debug"]), Ok(false));
+
+        let error = server["port"].expect_u64(["server", "port"]).unwrap_err();
+        assert_eq!(
+            error,
+            PathError::TypeMismatch {
+                expected: "non-negative integer".to_string(),
+                found: "string".to_string(),
+                path: "$.server.port".to_string(),
+            }
+        );
+        assert_eq!(error.to_string(), "Expected non-negative integer at $.server.port, found string");
+        assert!(matches!(
+            JsonValue::from(1.5).expect_i64(["ratio"]),
+            Err(PathError::TypeMismatch { found, .. }) if found == "number"
+        ));
+    }
+
+    #[test]
+    fn test_infer_schema() {
+        let feed = r#"{"id": 1, "level": "info", "latency": 0.5, "tags": ["a"], "user": {"name": "x"}}
+            {"id": 2, "level": "warn", "latency": 3, "tags": [], "user": null}
+            {"id": 3, "level": "info", "latency": 1.25, "tags": ["b", 7], "retry": true}
+            {"id": 4, "level": "warn", "latency": 2, "tags": []}"#;
+        let values = parse_json_stream(feed.as_bytes()).map(Result::unwrap);
+        let schema = schema::infer(values);
+
+        let expected = parse_json_string(
+            r#"{
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "type": "object",
+                "properties": {
+                    "id": {"type": "integer"},
+                    "level": {"type": "string", "enum": ["info", "warn"]},
+                    "latency": {"type": "number"},
+                    "tags": {"type": "array", "items": {"type": ["string", "integer"]}},
+                    "user": {"type": ["object", "null"], "properties": {"name": {"type": "string"}}, "required": ["name"]},
+                    "retry": {"type": "boolean"}
+                },
+                "required": ["id", "latency", "level", "tags"]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(schema, expected);
+
+        let mut builder = SchemaBuilder::new().max_enum_values(0);
+        builder.add(&parse_json_string(r#"["a", "a", "a"]"#).unwrap());
+        assert_eq!(builder.samples(), 1);
+        assert_eq!(builder.to_schema().get("items"), Some(&parse_json_string(r#"{"type": "string"}"#).unwrap()));
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_schema_validator() {
+        let schema = parse_json_string(
+            r##"{
+                "type": "object",
+                "required": ["name", "servers"],
+                "properties": {
+                    "name": {"type": "string", "pattern": "^[a-z-]+$"},
+                    "mode": {"enum": ["dev", "prod"]},
+                    "servers": {"type": "array", "items": {"$ref": "#/definitions/server"}}
+                },
+                "definitions": {
+                    "server": {
+                        "type": "object",
+                        "required": ["host"],
+                        "properties": {
+                            "host": {"type": "string"},
+                            "port": {"type": "integer", "minimum": 1, "maximum": 65535},
+                            "backup": {"$ref": "#/definitions/server"}
+                        }
+                    }
+                }
+            }"##,
+        )
+        .unwrap();
+        let validator = Validator::compile(&schema).unwrap();
+
+        let valid = parse_json_string(r#"{"name": "api", "mode": "dev", "servers": [{"host": "a", "port": 80}]}"#);
+        assert!(validator.is_valid(&valid.unwrap()));
+
+        let config = parse_json_string(
+            r#"{"name": "Api!", "mode": "test",
+                "servers": [{"host": "a", "port": 0}, {"port": 8080.5, "backup": {"host": 1}}]}"#,
+        )
+        .unwrap();
+        let mut violations: Vec<(String, &str)> = validator
+            .validate(&config)
+            .into_iter()
+            .map(|violation| (violation.pointer.to_string(), violation.keyword))
+            .collect();
+        let mut expected = vec![
+            ("/name".to_string(), "pattern"),
+            ("/mode".to_string(), "enum"),
+            ("/servers/0/port".to_string(), "minimum"),
+            ("/servers/1".to_string(), "required"),
+            ("/servers/1/port".to_string(), "type"),
+            ("/servers/1/backup/host".to_string(), "type"),
+        ];
+        violations.sort();
+        expected.sort();
+        assert_eq!(violations, expected);
+
+        let missing = validator.validate(&parse_json_string("{}").unwrap());
+        assert_eq!(missing[0].to_string(), r#"missing required property "name""#);
+
+        let broken = parse_json_string(r##"{"properties": {"a": {"$ref": "#/definitions/nope"}}}"##).unwrap();
+        match Validator::compile(&broken) {
+            Err(ParseError::InvalidSchema { path, .. }) => assert_eq!(path, "/properties/a/$ref"),
+            other => panic!("expected an invalid schema, got {:?}", other),
+        }
+        let looping = parse_json_string(r##"{"definitions": {"a": {"$ref": "#/definitions/a"}}, "$ref": "#/definitions/a"}"##);
+        assert!(Validator::compile(&looping.unwrap()).is_err());
+    }
+
+    #[cfg(feature = "query")]
+    #[test]
+    fn test_jsonpath_query() {
+        let store = parse_json_string(
+            r#"{"store": {
+                "book": [
+                    {"category": "reference", "author": "Nigel Rees", "title": "Sayings of the Century", "price": 8.95},
+                    {"category": "fiction", "author": "Evelyn Waugh", "title": "Sword of Honour", "price": 12.99},
+                    {"category": "fiction", "author": "Herman Melville", "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99},
+                    {"category": "fiction", "author": "J. R. R. Tolkien", "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99}
+                ],
+                "bicycle": {"color": "red", "price": 399}
+            }}"#,
+        )
+        .unwrap();
+        let select = |query: &str| -> Vec<(String, String)> {
+            store
+                .query(query)
+                .unwrap()
+                .into_iter()
+                .map(|(path, value)| (path.to_string(), value.to_string()))
+                .collect()
+        };
+        let values = |query: &str| -> Vec<String> { select(query).into_iter().map(|(_, value)| value).collect() };
+
+        assert_eq!(
+            select("$.store.book[?(@.price < 10)].title"),
+            vec![
+                ("$.store.book[0].title".to_string(), r#""Sayings of the Century""#.to_string()),
+                ("$.store.book[2].title".to_string(), r#""Moby Dick""#.to_string()),
+            ]
+        );
+        assert_eq!(values("$..book[-1].author"), vec![r#""J. R. R. Tolkien""#]);
+        assert_eq!(values("$..book[:2].price"), vec!["8.95", "12.99"]);
+        assert_eq!(values("$.store.book[::-2]['title']"), vec![r#""The Lord of the Rings""#, r#""Sword of Honour""#]);
+        assert_eq!(values("$..book[?@.isbn].price"), vec!["8.99", "22.99"]);
+        assert_eq!(values("$..book[0, 0].price"), vec!["8.95", "8.95"]);
+        assert_eq!(values("$..*[?@.price > 100].color"), vec![r#""red""#]);
+        assert_eq!(
+            values("$.store.book[?@.category == 'fiction' && !(@.price >= 20) || @.author == \"Nigel Rees\"].price"),
+            vec!["8.95", "12.99", "8.99"]
+        );
+        assert_eq!(values("$.store.book[?match(@.author, 'H.*')].price"), vec!["8.99"]);
+        assert_eq!(values("$.store.book[?search(@.title, 'of')].price"), vec!["8.95", "12.99", "22.99"]);
+        assert_eq!(values("$.store.book[?length(@.title) < 10].title"), vec![r#""Moby Dick""#]);
+        assert_eq!(values("$.store[?count(@.*) == 2].color"), vec![r#""red""#]);
+        assert_eq!(values("$.store.book[?value(@..isbn) == '0-553-21311-3'].price"), vec!["8.99"]);
+        assert_eq!(values("$.store.book[?@.price == $.store.book[0].price].price"), vec!["8.95"]);
+        assert_eq!(values("$..price").len(), 5);
+        assert!(values("$.store.missing[0]").is_empty());
+
+        let names = store.query("$.store.book[?match(@.isbn, '[0-9]-[0-9]{3}-[0-9-]+')].author").unwrap();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[0].0.to_normalized_path(), "$['store']['book'][2]['author']");
+        assert_eq!(Path::root().key("it's\\\n\u{1}").index(3).to_normalized_path(), r"$['it\'s\\\n\u0001'][3]");
+        assert_eq!(values(r"$.store.book[?search(@.author, '\\p{Lu}\\. R')].price"), vec!["22.99"]);
+        assert_eq!(values("$.store.book[?search(@.isbn, '^0|3$')].price"), Vec::<String>::new());
+        assert_eq!(values("$.store.book[?@.price < 8.990].price"), vec!["8.95"]);
+
+        let path: JsonPath = "$.store.bicycle.price".parse().unwrap();
+        assert_eq!(path.query(&store)[0].1, &JsonValue::from(399));
+        for pattern in [r"\\d", "(?i)a", "a*?", "a{,2}", "[a-z-b]", "[]", r"\\p{Xx}", r"\\1", "a)"] {
+            let query = format!("$[?match(@, '{}')]", pattern);
+            assert!(JsonPath::parse(&query).is_err(), "expected {:?} to be rejected", pattern);
+        }
+        for invalid in ["store", "$.store.", "$[01]", "$[-0]", "$[?@.*.price == 1]", "$[?length(@)]", "$[?foo(@)]", "$[?true]", "$ "] {
+            match JsonPath::parse(invalid) {
+                Err(ParseError::InvalidQuery { position, .. }) => assert!(position.offset <= invalid.len()),
+                other => panic!("expected {:?} to be invalid, got {:?}", invalid, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_borrowed_parser_limits_depth() {
+        let deep = "[".repeat(1_000_000);
+        assert_eq!(
+            parse_json_str_borrowed(&deep),
+            Err(ParseError::DepthLimitExceeded {
+                limit: DEFAULT_MAX_DEPTH,
+                position: at(DEFAULT_MAX_DEPTH, 1, DEFAULT_MAX_DEPTH + 1),
+            })
+        );
+        let nested = format!("{}{}", "[".repeat(DEFAULT_MAX_DEPTH), "]".repeat(DEFAULT_MAX_DEPTH));
+        assert!(parse_json_str_borrowed(&nested).is_ok());
+        assert_eq!(parse_json_str_borrowed("[-0.5e+3, 10]").unwrap().as_array().unwrap().len(), 2);
+        assert!(parse_json_str_borrowed("[01]").is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::borrowed::BorrowedParser;
+use crate::number::JsonNumber;
+use crate::types::{Context, JsonValue, Key, Map, ParseError};
+
+/// The tree read by [`parse_json_string_lossy`]: a [`JsonValue`] in which any
+/// value may instead be the error that kept it from being read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PartialValue {
+    Null,
+    Boolean(bool),
+    Number(JsonNumber),
+    String(String),
+    /// Members in source order, duplicates included.
+    Object(Vec<(Key, PartialValue)>),
+    Array(Vec<PartialValue>),
+    Error(ParseError),
+}
+
+impl PartialValue {
+    /// The value of the first member named `key`, if this is an object.
+    pub fn get(&self, key: &str) -> Option<&PartialValue> {
+        match self {
+            PartialValue::Object(members) => {
+                members.iter().find(|(name, _)| &**name == key).map(|(_, value)| value)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, PartialValue::Error(_))
+    }
+
+    /// The complete value, or `None` if any part of it is an error. A
+    /// duplicate key keeps its last value, as when parsing normally.
+    pub fn to_value(&self) -> Option<JsonValue> {
+        Some(match self {
+            PartialValue::Null => JsonValue::Null,
+            PartialValue::Boolean(b) => JsonValue::Boolean(*b),
+            PartialValue::Number(n) => JsonValue::Number(n.clone()),
+            PartialValue::String(s) => JsonValue::String(s.clone()),
+            PartialValue::Object(members) => {
+                let mut object = Map::new();
+                for (key, value) in members {
+                    object.insert(key.clone(), value.to_value()?);
+                }
+                JsonValue::Object(object)
+            }
+            PartialValue::Array(elements) => {
+                JsonValue::Array(elements.iter().map(PartialValue::to_value).collect::<Option<_>>()?)
+            }
+            PartialValue::Error(_) => return None,
+        })
+    }
+}
+
+/// Parses as much of `input` as can be made sense of, for an editor that has
+/// to show a tree while the document is still being typed. A value that
+/// can't be read becomes a [`PartialValue::Error`] holding the problem, and
+/// parsing resumes at the next `,` or closing bracket; containers still open
+/// at the end are closed. Returns the tree and every error found, in order.
+pub fn parse_json_string_lossy(input: &str) -> (PartialValue, Vec<ParseError>) {
+    let mut lossy = Lossy {
+        parser: BorrowedParser::new(input),
+        stack: Vec::new(),
+        errors: Vec::new(),
+    };
+    let value = lossy.parse();
+    (value, lossy.errors)
+}
+
+/// An object or array whose closing bracket hasn't been read yet. An object
+/// holds the key of the member whose value is being read.
+enum Frame {
+    Object(Vec<(Key, PartialValue)>, Option<Key>),
+    Array(Vec<PartialValue>),
+}
+
+enum State {
+    Value,
+    Key,
+    // A value has been read and goes into the container being read, if any.
+    Done(PartialValue),
+    Separator,
+}
+
+struct Lossy<'a> {
+    parser: BorrowedParser<'a>,
+    stack: Vec<Frame>,
+    errors: Vec<ParseError>,
+}
+
+impl Lossy<'_> {
+    fn parse(&mut self) -> PartialValue {
+        let mut state = State::Value;
+        loop {
+            state = match state {
+                State::Value => self.value(),
+                State::Key => self.key(),
+                State::Done(value) => match self.stack.last_mut() {
+                    None => {
+                        if let Err(e) = self.parser.finish() {
+                            self.errors.push(e);
+                        }
+                        return value;
+                    }
+                    Some(Frame::Object(object, key)) => {
+                        if let Some(key) = key.take() {
+                            object.push((key, value));
+                        }
+                        State::Separator
+                    }
+                    Some(Frame::Array(array)) => {
+                        array.push(value);
+                        State::Separator
+                    }
+                },
+                State::Separator => self.separator(),
+            };
+        }
+    }
+
+    fn value(&mut self) -> State {
+        self.parser.skip_whitespace();
+        let parser = &mut self.parser;
+        let value = match parser.peek() {
+            Some(b'{') if parser.open_container(b'}') => Ok(PartialValue::Object(Vec::new())),
+            Some(b'{') => {
+                self.stack.push(Frame::Object(Vec::new(), None));
+                return State::Key;
+            }
+            Some(b'[') if parser.open_container(b']') => Ok(PartialValue::Array(Vec::new())),
+            Some(b'[') => {
+                self.stack.push(Frame::Array(Vec::new()));
+                return State::Value;
+            }
+            Some(b'"') => parser.parse_string().map(|s| PartialValue::String(s.into_owned())),
+            Some(b'-' | b'0'..=b'9') => parser.parse_number().map(PartialValue::Number),
+            Some(b't') => parser.parse_literal("true", PartialValue::Boolean(true)),
+            Some(b'f') => parser.parse_literal("false", PartialValue::Boolean(false)),
+            Some(b'n') => parser.parse_literal("null", PartialValue::Null),
+            _ => Err(parser.invalid_character()),
+        };
+        State::Done(value.unwrap_or_else(|e| {
+            self.recover();
+            self.error(e)
+        }))
+    }
+
+    fn key(&mut self) -> State {
+        self.parser.skip_whitespace();
+        if self.parser.peek() != Some(b'"') {
+            let e = self.parser.invalid_character();
+            self.errors.push(e);
+            self.recover();
+            return State::Separator;
+        }
+        let key = match self.parser.parse_string() {
+            Ok(key) => key.into_owned(),
+            Err(e) => {
+                self.errors.push(e);
+                self.recover();
+                return State::Separator;
+            }
+        };
+        if let Some(Frame::Object(_, pending)) = self.stack.last_mut() {
+            *pending = Some(Key::from(key));
+        }
+        match self.parser.expect(b':', "':'") {
+            Ok(()) => State::Value,
+            // With no value after the key either, the member's value is the error.
+            Err(e) if matches!(self.parser.peek(), None | Some(b',' | b'}')) => {
+                State::Done(self.error(e))
+            }
+            Err(e) => {
+                self.errors.push(e);
+                State::Value
+            }
+        }
+    }
+
+    /// Reads the `,` or closing bracket after a member or element.
+    fn separator(&mut self) -> State {
+        let (close, other, expected) = match self.stack.last() {
+            Some(Frame::Object(..)) => (b'}', b']', "',' or '}'"),
+            _ => (b']', b'}', "',' or ']'"),
+        };
+        let object = close == b'}';
+        self.parser.skip_whitespace();
+        match self.parser.peek() {
+            Some(b',') => {
+                self.parser.skip_char();
+                self.parser.skip_whitespace();
+                if self.parser.peek() != Some(close) {
+                    return if object { State::Key } else { State::Value };
+                }
+                self.errors.push(ParseError::TrailingComma(self.parser.position()));
+                self.parser.skip_char();
+                self.close()
+            }
+            Some(byte) if byte == close => {
+                self.parser.skip_char();
+                self.close()
+            }
+            None => {
+                // Report the end of the input once, not for every container.
+                if !matches!(self.errors.last(), Some(ParseError::UnexpectedEndOfInput { .. })) {
+                    let context = if object { Context::Object } else { Context::Array };
+                    self.errors.push(self.parser.end_of_input(context));
+                }
+                self.close()
+            }
+            Some(byte) => {
+                self.errors.push(self.parser.unexpected(expected));
+                match byte {
+                    // The wrong bracket still ends the container.
+                    _ if byte == other => {
+                        self.parser.skip_char();
+                        self.close()
+                    }
+                    // Most likely a missing comma.
+                    b'"' if object => State::Key,
+                    b'{' | b'[' | b'"' | b'-' | b'0'..=b'9' | b't' | b'f' | b'n' if !object => {
+                        State::Value
+                    }
+                    _ => {
+                        self.recover();
+                        State::Separator
+                    }
+                }
+            }
+        }
+    }
+
+    fn close(&mut self) -> State {
+        State::Done(match self.stack.pop() {
+            Some(Frame::Object(object, _)) => PartialValue::Object(object),
+            Some(Frame::Array(array)) => PartialValue::Array(array),
+            None => unreachable!("separators are only read inside containers"),
+        })
+    }
+
+    fn error(&mut self, e: ParseError) -> PartialValue {
+        self.errors.push(e.clone());
+        PartialValue::Error(e)
+    }
+
+    /// Skips to the next `,` or bracket, where reading can go on.
+    fn recover(&mut self) {
+        while let Some(byte) = self.parser.peek() {
+            if matches!(byte, b',' | b'}' | b']') {
+                return;
+            }
+            self.parser.skip_char();
+        }
+    }
+}
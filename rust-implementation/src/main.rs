@@ -1,13 +1,16 @@
 use std::env;
 use std::fs::File;
-use std::io::{self, Read, BufReader};
-use streaming_json_parser::{parse_json_string, parse_json_stream, JsonValue};
+use std::io::{self, Read};
+use streaming_json_parser::{parse_json_stream, parse_json_stream_with, to_string_pretty, JsonValue, ParseError, ParserOptions, Path};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
     
     if args.len() < 2 {
-        eprintln!("Usage: {} <file.json> [--stream] [--validate-only] [--pretty]", args[0]);
+        eprintln!(
+            "Usage: {} <file.json> [--stream] [--validate-only] [--pretty] [--error-format=json] [--progress]",
+            args[0]
+        );
         eprintln!("       echo '{{\"key\": \"value\"}}' | {} --stdin", args[0]);
         std::process::exit(1);
     }
@@ -16,6 +19,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut validate_only = false;
     let mut pretty_print = false;
     let mut use_stdin = false;
+    let mut json_errors = false;
+    let mut progress = false;
     let mut filename = None;
 
     for arg in args.iter().skip(1) {
@@ -24,6 +29,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "--validate-only" => validate_only = true,
             "--pretty" => pretty_print = true,
             "--stdin" => use_stdin = true,
+            "--error-format=json" => json_errors = true,
+            "--error-format=human" => json_errors = false,
+            "--progress" => progress = true,
             _ => {
                 if filename.is_none() && !arg.starts_with("--") {
                     filename = Some(arg.clone());
@@ -32,10 +40,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    let output = Output { validate_only, pretty_print, json_errors, progress };
     if use_stdin {
-        process_stdin(stream_mode, validate_only, pretty_print)?;
+        process_stdin(stream_mode, &output)?;
     } else if let Some(file_path) = filename {
-        process_file(&file_path, stream_mode, validate_only, pretty_print)?;
+        process_file(&file_path, stream_mode, &output)?;
     } else {
         eprintln!("Error: No input file specified");
         std::process::exit(1);
@@ -44,81 +53,101 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn process_stdin(stream_mode: bool, validate_only: bool, pretty_print: bool) -> Result<(), Box<dyn std::error::Error>> {
-    let stdin = io::stdin();
-    let reader = BufReader::new(stdin.lock());
+/// What to print for each document and error.
+struct Output {
+    validate_only: bool,
+    pretty_print: bool,
+    json_errors: bool,
+    progress: bool,
+}
 
-    if stream_mode {
-        process_stream(reader, validate_only, pretty_print)
-    } else {
-        let mut input = String::new();
-        io::stdin().read_to_string(&mut input)?;
-        process_single_json(&input, validate_only, pretty_print)
-    }
+/// Input read between progress reports in stream mode.
+const PROGRESS_INTERVAL: usize = 16 * 1024 * 1024;
+
+fn process_stdin(stream_mode: bool, output: &Output) -> Result<(), Box<dyn std::error::Error>> {
+    process_input(io::stdin().lock(), stream_mode, output)
+}
+
+fn process_file(file_path: &str, stream_mode: bool, output: &Output) -> Result<(), Box<dyn std::error::Error>> {
+    process_input(File::open(file_path)?, stream_mode, output)
 }
 
-fn process_file(file_path: &str, stream_mode: bool, validate_only: bool, pretty_print: bool) -> Result<(), Box<dyn std::error::Error>> {
+/// Compressed input is decompressed first when built with `gzip` or `zstd`.
+fn process_input<R: Read>(reader: R, stream_mode: bool, output: &Output) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    let reader = streaming_json_parser::Decompressor::new(reader);
     if stream_mode {
-        let file = File::open(file_path)?;
-        let reader = BufReader::new(file);
-        process_stream(reader, validate_only, pretty_print)
+        process_stream(reader, output)
     } else {
-        let mut file = File::open(file_path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        process_single_json(&contents, validate_only, pretty_print)
+        // Bytes rather than a `String`, so UTF-16 and UTF-32 files parse too.
+        let mut input = Vec::new();
+        let mut reader = reader;
+        reader.read_to_end(&mut input)?;
+        process_single_json(&input, output)
     }
 }
 
-fn process_single_json(input: &str, validate_only: bool, pretty_print: bool) -> Result<(), Box<dyn std::error::Error>> {
-    match parse_json_string(input) {
+fn process_single_json(input: &[u8], output: &Output) -> Result<(), Box<dyn std::error::Error>> {
+    // What `parse_json_bytes` does, keeping the parser for `error_path`.
+    let mut parser = parse_json_stream(input);
+    match parser.parse_single() {
         Ok(json_value) => {
-            if validate_only {
+            if output.validate_only {
                 println!("✓ Valid JSON");
-            } else if pretty_print {
-                print_json_pretty(&json_value, 0);
-                println!();
+            } else if output.pretty_print {
+                println!("{}", render_pretty(&json_value));
             } else {
                 println!("{}", json_value);
             }
             Ok(())
         }
         Err(e) => {
-            eprintln!("✗ Invalid JSON: {}", e);
+            if output.json_errors {
+                eprintln!("{}", e.diagnostic_at(parser.error_path()).to_json().sorted_keys());
+            } else if let Ok(text) = std::str::from_utf8(input) {
+                eprintln!("✗ Invalid JSON\n{}", e.render(text));
+            } else {
+                eprintln!("✗ Invalid JSON\nerror: {}", e);
+            }
             std::process::exit(1);
         }
     }
 }
 
-fn process_stream<R: Read>(reader: R, validate_only: bool, pretty_print: bool) -> Result<(), Box<dyn std::error::Error>> {
-    let parser = parse_json_stream(reader);
+fn process_stream<R: Read>(reader: R, output: &Output) -> Result<(), Box<dyn std::error::Error>> {
+    // Report every bad record, going on with the line after each.
+    let mut parser = parse_json_stream_with(reader, ParserOptions::new().recover_from_errors(true));
+    if output.progress {
+        parser = parser.on_progress(PROGRESS_INTERVAL, |progress| {
+            eprintln!("Read {} MiB, {} objects...", progress.bytes >> 20, progress.records);
+        });
+    }
     let mut count = 0;
     let mut errors = 0;
 
-    for result in parser {
+    while let Some(result) = parser.next() {
         count += 1;
         match result {
             Ok(json_value) => {
-                if validate_only {
+                if output.validate_only {
                     if count % 1000 == 0 {
                         eprintln!("Processed {} objects...", count);
                     }
-                } else if pretty_print {
+                } else if output.pretty_print {
                     println!("--- Object {} ---", count);
-                    print_json_pretty(&json_value, 0);
-                    println!();
+                    println!("{}", render_pretty(&json_value));
                 } else {
                     println!("{}", json_value);
                 }
             }
             Err(e) => {
                 errors += 1;
-                eprintln!("Error in object {}: {}", count, e);
+                report_stream_error(count, &e, parser.error_path(), output);
             }
         }
     }
 
-    if validate_only {
+    if output.validate_only {
         println!("✓ Processed {} JSON objects ({} errors)", count, errors);
     }
 
@@ -129,59 +158,28 @@ fn process_stream<R: Read>(reader: R, validate_only: bool, pretty_print: bool) -
     Ok(())
 }
 
-fn print_json_pretty(value: &JsonValue, indent: usize) {
-    let indent_str = "  ".repeat(indent);
-    
-    match value {
-        JsonValue::String(s) => print!("\"{}\"", escape_string(s)),
-        JsonValue::Number(n) => print!("{}", n),
-        JsonValue::Boolean(b) => print!("{}", b),
-        JsonValue::Null => print!("null"),
-        JsonValue::Object(obj) => {
-            println!("{{");
-            let mut first = true;
-            for (key, val) in obj {
-                if !first {
-                    println!(",");
-                }
-                print!("{}  \"{}\": ", indent_str, escape_string(key));
-                print_json_pretty(val, indent + 1);
-                first = false;
-            }
-            if !obj.is_empty() {
-                println!();
-            }
-            print!("{}}}", indent_str);
-        }
-        JsonValue::Array(arr) => {
-            println!("[");
-            let mut first = true;
-            for val in arr {
-                if !first {
-                    println!(",");
-                }
-                print!("{}  ", indent_str);
-                print_json_pretty(val, indent + 1);
-                first = false;
-            }
-            if !arr.is_empty() {
-                println!();
-            }
-            print!("{}]", indent_str);
-        }
+fn report_stream_error(count: usize, error: &ParseError, path: Option<&Path>, output: &Output) {
+    if output.json_errors {
+        let mut diagnostic = error.diagnostic_at(path).to_json();
+        diagnostic.entry("document").or_insert(JsonValue::from(count as u64));
+        eprintln!("{}", diagnostic.sorted_keys());
+    } else if let Some(path) = path {
+        eprintln!("Error in object {} at {}: {}", count, path, error);
+    } else {
+        eprintln!("Error in object {}: {}", count, error);
     }
 }
 
-fn escape_string(s: &str) -> String {
-    s.chars()
-        .map(|c| match c {
-            '"' => "\\\"".to_string(),
-            '\\' => "\\\\".to_string(),
-            '\n' => "\\n".to_string(),
-            '\r' => "\\r".to_string(),
-            '\t' => "\\t".to_string(),
-            c if c.is_control() => format!("\\u{:04x}", c as u32),
-            c => c.to_string(),
-        })
-        .collect()
-}
\ No newline at end of file
+/// Pretty output, colored when built with the `color` feature and stdout is a
+/// terminal (unless `NO_COLOR` is set).
+fn render_pretty(value: &JsonValue) -> String {
+    #[cfg(feature = "color")]
+    {
+        use std::io::IsTerminal;
+        if io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none() {
+            let mut colors = streaming_json_parser::ColorWriter::new();
+            return streaming_json_parser::to_string_with(value, &mut colors);
+        }
+    }
+    to_string_pretty(value)
+}
@@ -0,0 +1,111 @@
+use crate::types::{remove_member, JsonValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrayMerge {
+    /// The incoming array replaces the existing one.
+    #[default]
+    Replace,
+    /// Incoming elements are appended.
+    Concat,
+    /// Incoming elements are appended unless an equal element is already present.
+    Union,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullMerge {
+    /// An incoming `null` overwrites the existing value.
+    #[default]
+    Overwrite,
+    /// An incoming `null` leaves the existing value untouched.
+    Ignore,
+    /// An incoming `null` removes the member, as in RFC 7386 merge patches.
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergePolicy {
+    deep_objects: bool,
+    arrays: ArrayMerge,
+    nulls: NullMerge,
+}
+
+impl Default for MergePolicy {
+    fn default() -> Self {
+        Self {
+            deep_objects: true,
+            arrays: ArrayMerge::Replace,
+            nulls: NullMerge::Overwrite,
+        }
+    }
+}
+
+impl MergePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The semantics of an RFC 7386 JSON Merge Patch.
+    pub fn merge_patch() -> Self {
+        Self::default().nulls(NullMerge::Remove)
+    }
+
+    /// When disabled, an incoming object replaces the existing one wholesale.
+    pub fn deep_objects(mut self, deep: bool) -> Self {
+        self.deep_objects = deep;
+        self
+    }
+
+    pub fn arrays(mut self, arrays: ArrayMerge) -> Self {
+        self.arrays = arrays;
+        self
+    }
+
+    pub fn nulls(mut self, nulls: NullMerge) -> Self {
+        self.nulls = nulls;
+        self
+    }
+}
+
+impl JsonValue {
+    /// Merges `other` into `self`; values from `other` win on conflict.
+    pub fn merge(&mut self, other: JsonValue, policy: MergePolicy) {
+        match (self, other) {
+            (JsonValue::Object(target), JsonValue::Object(source)) if policy.deep_objects => {
+                for (key, value) in source {
+                    if value.is_null() {
+                        match policy.nulls {
+                            NullMerge::Overwrite => {
+                                target.insert(key, value);
+                            }
+                            NullMerge::Ignore => {}
+                            NullMerge::Remove => {
+                                remove_member(target, &key);
+                            }
+                        }
+                        continue;
+                    }
+
+                    match target.get_mut(&key) {
+                        Some(existing) => existing.merge(value, policy),
+                        None => {
+                            target.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (JsonValue::Array(target), JsonValue::Array(source)) => match policy.arrays {
+                ArrayMerge::Replace => *target = source,
+                ArrayMerge::Concat => target.extend(source),
+                ArrayMerge::Union => {
+                    for value in source {
+                        if !target.contains(&value) {
+                            target.push(value);
+                        }
+                    }
+                }
+            },
+            (_, JsonValue::Null) if policy.nulls == NullMerge::Ignore => {}
+            (target, source) => *target = source,
+        }
+    }
+}
@@ -0,0 +1,16 @@
+/// Counters kept while parsing, read with
+/// [`StreamingJsonParser::metrics`](crate::StreamingJsonParser::metrics) or
+/// [`EventReader::metrics`](crate::EventReader::metrics).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseMetrics {
+    /// Bytes read from the input, after any conversion to UTF-8.
+    pub bytes_read: usize,
+    pub tokens: usize,
+    /// Deepest container nesting seen in any document.
+    pub max_depth: usize,
+    /// Documents completed. A [`StreamingJsonParser`](crate::StreamingJsonParser)
+    /// counts the values it returned instead, so array elements read one at a
+    /// time count separately.
+    pub records: usize,
+    pub errors: usize,
+}
@@ -0,0 +1,149 @@
+use std::fmt::Write;
+use std::io::Cursor;
+use crate::lexer::Lexer;
+use crate::options::ParserOptions;
+use crate::ser::{write_str, STRING_WRITE};
+use crate::types::{Context, Token, TokenType, ParseError, ParseResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Value,
+    ValueOrClose,
+    Key,
+    KeyOrClose,
+    Colon,
+    CommaOrClose,
+    End,
+}
+
+/// Strips insignificant whitespace in a single pass over the tokens, without
+/// building a `JsonValue`. The input is still fully validated; numbers are
+/// copied verbatim.
+pub fn minify(input: &str) -> ParseResult<String> {
+    let options = ParserOptions::new().preserve_number_text(true);
+    let mut lexer = Lexer::with_options(Cursor::new(input), options);
+
+    let mut out = String::with_capacity(input.len());
+    let mut stack = Vec::new();
+    let mut expect = Expect::Value;
+
+    loop {
+        let end = lexer.position();
+        let Token { token_type, position } =
+            lexer.next().unwrap_or(Ok(Token::new(TokenType::Eof, end)))?;
+        let span = lexer.token_span();
+        let unexpected = |expected: &str| ParseError::UnexpectedToken {
+            expected: expected.to_string(),
+            found: format!("{:?}", token_type),
+            span,
+            hint: None,
+        };
+
+        if matches!(expect, Expect::Value | Expect::ValueOrClose) {
+            let is_value = match &token_type {
+                TokenType::LeftBrace => {
+                    stack.push('{');
+                    out.push('{');
+                    expect = Expect::KeyOrClose;
+                    continue;
+                }
+                TokenType::LeftBracket => {
+                    stack.push('[');
+                    out.push('[');
+                    expect = Expect::ValueOrClose;
+                    continue;
+                }
+                TokenType::String(s) => {
+                    write_str(&mut out, s).expect(STRING_WRITE);
+                    true
+                }
+                TokenType::Number(n) => {
+                    write!(out, "{}", n).expect(STRING_WRITE);
+                    true
+                }
+                TokenType::Boolean(b) => {
+                    write!(out, "{}", b).expect(STRING_WRITE);
+                    true
+                }
+                TokenType::Null => {
+                    out.push_str("null");
+                    true
+                }
+                // Not a value; closing brackets and errors are handled below.
+                TokenType::RightBrace
+                | TokenType::RightBracket
+                | TokenType::Comma
+                | TokenType::Colon
+                | TokenType::Eof => false,
+            };
+            if is_value {
+                expect = after_value(&stack);
+                continue;
+            }
+        }
+
+        match (&token_type, expect) {
+            (TokenType::String(key), Expect::Key | Expect::KeyOrClose) => {
+                write_str(&mut out, key).expect(STRING_WRITE);
+                expect = Expect::Colon;
+            }
+            (TokenType::Colon, Expect::Colon) => {
+                out.push(':');
+                expect = Expect::Value;
+            }
+            (TokenType::Comma, Expect::CommaOrClose) => {
+                out.push(',');
+                expect = match stack.last() {
+                    Some('{') => Expect::Key,
+                    _ => Expect::Value,
+                };
+            }
+            // `Key` only follows a comma; `Value` inside an array does too.
+            (TokenType::RightBrace, Expect::Key) => return Err(ParseError::TrailingComma(position)),
+            (TokenType::RightBracket, Expect::Value) if stack.last() == Some(&'[') => {
+                return Err(ParseError::TrailingComma(position));
+            }
+            (TokenType::RightBrace, Expect::KeyOrClose | Expect::CommaOrClose)
+                if stack.last() == Some(&'{') =>
+            {
+                stack.pop();
+                out.push('}');
+                expect = after_value(&stack);
+            }
+            (TokenType::RightBracket, Expect::ValueOrClose | Expect::CommaOrClose)
+                if stack.last() == Some(&'[') =>
+            {
+                stack.pop();
+                out.push(']');
+                expect = after_value(&stack);
+            }
+            (TokenType::Eof, Expect::End) => return Ok(out),
+            (TokenType::Eof, _) => {
+                let while_parsing = match stack.last() {
+                    Some('{') => Context::Object,
+                    Some('[') => Context::Array,
+                    _ => Context::Value,
+                };
+                return Err(ParseError::UnexpectedEndOfInput { while_parsing, position });
+            }
+            (_, Expect::Value | Expect::ValueOrClose) => return Err(unexpected("a value")),
+            (_, Expect::Key | Expect::KeyOrClose) => return Err(unexpected("an object key")),
+            (_, Expect::Colon) => return Err(unexpected("':'")),
+            (_, Expect::CommaOrClose) => {
+                return Err(unexpected(match stack.last() {
+                    Some('{') => "',' or '}'",
+                    _ => "',' or ']'",
+                }))
+            }
+            (_, Expect::End) => return Err(unexpected("end of input")),
+        }
+    }
+}
+
+fn after_value(stack: &[char]) -> Expect {
+    if stack.is_empty() {
+        Expect::End
+    } else {
+        Expect::CommaOrClose
+    }
+}
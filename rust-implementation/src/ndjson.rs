@@ -0,0 +1,76 @@
+use std::borrow::Borrow;
+use std::io::{self, Write};
+use crate::ser::to_writer;
+use crate::types::JsonValue;
+
+/// When [`NdjsonWriter`] flushes the underlying writer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushPolicy {
+    /// Only on an explicit `flush()`; the writer's own buffering decides.
+    #[default]
+    Manual,
+    /// After every record, so each line is visible to readers immediately.
+    EveryRecord,
+    /// After every `n` records.
+    Every(usize),
+}
+
+/// Writes values as newline-delimited JSON: one compact document per line,
+/// the counterpart of [`parse_json_stream`](crate::parse_json_stream).
+pub struct NdjsonWriter<W: Write> {
+    writer: W,
+    flush: FlushPolicy,
+    records: usize,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            flush: FlushPolicy::default(),
+            records: 0,
+        }
+    }
+
+    pub fn flush_policy(mut self, policy: FlushPolicy) -> Self {
+        self.flush = policy;
+        self
+    }
+
+    pub fn write(&mut self, value: &JsonValue) -> io::Result<()> {
+        to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")?;
+        self.records += 1;
+
+        match self.flush {
+            FlushPolicy::EveryRecord => self.writer.flush(),
+            FlushPolicy::Every(n) if self.records.is_multiple_of(n) => self.writer.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn write_values<I>(&mut self, values: I) -> io::Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<JsonValue>,
+    {
+        values.into_iter().try_for_each(|value| self.write(value.borrow()))
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Number of records written so far.
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
@@ -0,0 +1,347 @@
+use std::borrow::Cow;
+use std::fmt;
+use crate::ser::STRING_WRITE;
+#[cfg(feature = "bignum")]
+use bigdecimal::BigDecimal;
+
+// Integers beyond 2^53 keep their decimal text so they survive a round trip.
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// A JSON number: its `f64` value plus, optionally, the exact source text it
+/// was parsed from.
+///
+/// Equality compares the numeric value only, so `1.0` and `1` are equal even
+/// when their source text differs.
+#[derive(Debug, Clone)]
+pub struct JsonNumber {
+    value: f64,
+    raw: Option<Box<str>>,
+    #[cfg(feature = "bignum")]
+    big: Option<Box<BigDecimal>>,
+}
+
+impl JsonNumber {
+    pub fn from_f64(value: f64) -> Self {
+        Self {
+            value,
+            raw: None,
+            #[cfg(feature = "bignum")]
+            big: None,
+        }
+    }
+
+    /// Keeps `raw` as the number's source text. `raw` must be the lexeme
+    /// `value` was parsed from; it is written out verbatim.
+    pub fn with_raw(value: f64, raw: impl Into<Box<str>>) -> Self {
+        Self {
+            value,
+            raw: Some(raw.into()),
+            #[cfg(feature = "bignum")]
+            big: None,
+        }
+    }
+
+    /// Builds a number from a JSON number lexeme, switching to a big-decimal
+    /// representation when `f64` can't hold it exactly.
+    #[cfg(feature = "bignum")]
+    pub fn from_lexeme(lexeme: &str, value: f64) -> Self {
+        if !exceeds_native_range(lexeme, value) {
+            return JsonNumber::from_f64(value);
+        }
+        let big = lexeme.parse::<BigDecimal>().ok().map(Box::new);
+        Self {
+            value,
+            raw: Some(lexeme.into()),
+            big,
+        }
+    }
+
+    /// Keeps `source` as the text the number was written as, where that
+    /// isn't the lexeme it was parsed from, as for JSON5's `0xFF`.
+    pub(crate) fn with_source_text(mut self, source: impl Into<Box<str>>) -> Self {
+        self.raw = Some(source.into());
+        self
+    }
+
+    /// Converts lexeme text the lexer has already checked against the JSON
+    /// number grammar.
+    pub(crate) fn parse_lexeme(lexeme: &str) -> Option<Self> {
+        let value = lexeme.parse::<f64>().ok()?;
+        #[cfg(feature = "bignum")]
+        return Some(JsonNumber::from_lexeme(lexeme, value));
+        #[cfg(not(feature = "bignum"))]
+        Some(JsonNumber::from_f64(value))
+    }
+
+    /// The exact value of a number that didn't fit in `i64`, `u64` or `f64`.
+    #[cfg(feature = "bignum")]
+    pub fn as_big_decimal(&self) -> Option<&BigDecimal> {
+        self.big.as_deref()
+    }
+
+    /// Converts any finite number to a `BigDecimal`, exactly when the source
+    /// text is available.
+    #[cfg(feature = "bignum")]
+    pub fn to_big_decimal(&self) -> Option<BigDecimal> {
+        if let Some(big) = &self.big {
+            return Some((**big).clone());
+        }
+        if let Some(big) = self.raw.as_deref().and_then(|raw| raw.parse().ok()) {
+            return Some(big);
+        }
+        BigDecimal::try_from(self.value).ok()
+    }
+
+    #[cfg(feature = "bignum")]
+    pub fn is_big(&self) -> bool {
+        self.big.is_some()
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.value
+    }
+
+    /// False for `NaN` and the infinities, which JSON can't represent. A
+    /// number too large for `f64` that kept its source text, like `1e400`
+    /// with the `bignum` feature, is finite.
+    pub fn is_finite(&self) -> bool {
+        self.value.is_finite()
+            || self
+                .raw
+                .as_deref()
+                .is_some_and(|raw| {
+                    raw.trim_start_matches(['-', '+']).starts_with(|ch: char| ch.is_ascii_digit() || ch == '.')
+                })
+    }
+
+    /// The exact source text, when the parser was asked to preserve it.
+    pub fn as_raw_str(&self) -> Option<&str> {
+        self.raw.as_deref()
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        if let Some(n) = self.raw.as_deref().and_then(|raw| raw.parse::<i64>().ok()) {
+            return Some(n);
+        }
+        let n = self.value;
+        if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 {
+            Some(n as i64)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        if let Some(n) = self.raw.as_deref().and_then(|raw| raw.parse::<u64>().ok()) {
+            return Some(n);
+        }
+        let n = self.value;
+        if n.fract() == 0.0 && n >= 0.0 && n < u64::MAX as f64 {
+            Some(n as u64)
+        } else {
+            None
+        }
+    }
+}
+
+impl PartialEq for JsonNumber {
+    fn eq(&self, other: &Self) -> bool {
+        #[cfg(feature = "bignum")]
+        if self.big.is_some() || other.big.is_some() {
+            return match (self.to_big_decimal(), other.to_big_decimal()) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            };
+        }
+        self.value == other.value
+    }
+}
+
+/// Numbers are ordered by value, as they are compared.
+impl PartialOrd for JsonNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        #[cfg(feature = "bignum")]
+        if self.big.is_some() || other.big.is_some() {
+            return match (self.to_big_decimal(), other.to_big_decimal()) {
+                (Some(a), Some(b)) => a.partial_cmp(&b),
+                _ => None,
+            };
+        }
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+// Significant digits beyond what an f64 can distinguish.
+#[cfg(feature = "bignum")]
+const MAX_EXACT_DIGITS: usize = 17;
+
+#[cfg(feature = "bignum")]
+fn exceeds_native_range(lexeme: &str, value: f64) -> bool {
+    if !value.is_finite() {
+        return true;
+    }
+
+    let mantissa = lexeme.split(['e', 'E']).next().unwrap_or(lexeme);
+    let is_integer = mantissa.len() == lexeme.len() && !mantissa.contains('.');
+    if is_integer {
+        return lexeme.parse::<i64>().is_err() && lexeme.parse::<u64>().is_err();
+    }
+
+    let digits = mantissa
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .skip_while(|&digit| digit == b'0')
+        .count();
+    let underflowed = value == 0.0 && mantissa.bytes().any(|digit| matches!(digit, b'1'..=b'9'));
+    digits > MAX_EXACT_DIGITS || underflowed
+}
+
+/// How numbers without preserved source text are written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberFormat {
+    /// The shortest text that parses back to the same `f64`. Integers have no
+    /// fractional part, and very large or small magnitudes use an exponent.
+    #[default]
+    Shortest,
+    /// Round to this many significant digits, then write the shortest form.
+    Precision(usize),
+}
+
+impl JsonNumber {
+    pub fn format(&self, format: NumberFormat) -> String {
+        let mut out = String::new();
+        self.write_formatted(&mut out, format).expect(STRING_WRITE);
+        out
+    }
+
+    pub fn write_formatted<W: fmt::Write>(&self, out: &mut W, format: NumberFormat) -> fmt::Result {
+        let value = match (&self.raw, format) {
+            (Some(raw), NumberFormat::Shortest) => return out.write_str(raw),
+            (_, NumberFormat::Precision(digits)) => self.rounded(digits),
+            _ => self.value,
+        };
+        write_shortest(out, value)
+    }
+
+    /// This number as `format` has it, for a formatter to write as it
+    /// writes any other number.
+    pub(crate) fn formatted(&self, format: NumberFormat) -> Cow<'_, JsonNumber> {
+        match format {
+            NumberFormat::Shortest => Cow::Borrowed(self),
+            NumberFormat::Precision(digits) => Cow::Owned(JsonNumber::from_f64(self.rounded(digits))),
+        }
+    }
+
+    /// The value rounded to `digits` significant digits.
+    fn rounded(&self, digits: usize) -> f64 {
+        if !self.value.is_finite() {
+            return self.value;
+        }
+        let digits = digits.clamp(1, MAX_SIGNIFICANT_DIGITS);
+        format!("{:.*e}", digits - 1, self.value)
+            .parse::<f64>()
+            .unwrap_or(self.value)
+    }
+}
+
+const MAX_SIGNIFICANT_DIGITS: usize = 17;
+
+fn write_shortest<W: fmt::Write>(out: &mut W, value: f64) -> fmt::Result {
+    if value.is_nan() {
+        return out.write_str("NaN");
+    }
+    if value.is_infinite() {
+        return out.write_str(if value > 0.0 { "Infinity" } else { "-Infinity" });
+    }
+
+    let magnitude = value.abs();
+    if magnitude != 0.0 && !(1e-6..1e21).contains(&magnitude) {
+        write!(out, "{:e}", value)
+    } else {
+        write!(out, "{}", value)
+    }
+}
+
+/// Writes a finite `value` the way ECMAScript's `Number.prototype.toString`
+/// does, as RFC 8785 requires.
+pub(crate) fn write_ecmascript<W: fmt::Write + ?Sized>(out: &mut W, value: f64) -> fmt::Result {
+    if value == 0.0 {
+        return out.write_char('0');
+    }
+    if value < 0.0 {
+        out.write_char('-')?;
+    }
+
+    // `{:e}` gives the shortest round-trip digits, e.g. "1.25e-7".
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').expect("`{:e}` always has an exponent");
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().expect("`{:e}` exponent is an integer") + 1;
+
+    if k <= n && n <= 21 {
+        out.write_str(&digits)?;
+        (k..n).try_for_each(|_| out.write_char('0'))
+    } else if 0 < n && n <= 21 {
+        let (int, frac) = digits.split_at(n as usize);
+        write!(out, "{}.{}", int, frac)
+    } else if -6 < n && n <= 0 {
+        out.write_str("0.")?;
+        (n..0).try_for_each(|_| out.write_char('0'))?;
+        out.write_str(&digits)
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.write_str(first)?;
+        if !rest.is_empty() {
+            write!(out, ".{}", rest)?;
+        }
+        let e = n - 1;
+        write!(out, "e{}{}", if e < 0 { '-' } else { '+' }, e.abs())
+    }
+}
+
+impl fmt::Display for JsonNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_formatted(f, NumberFormat::Shortest)
+    }
+}
+
+impl From<f64> for JsonNumber {
+    fn from(value: f64) -> Self {
+        JsonNumber::from_f64(value)
+    }
+}
+
+impl From<i64> for JsonNumber {
+    fn from(value: i64) -> Self {
+        let n = value as f64;
+        if n as i64 == value && n.abs() <= MAX_SAFE_INTEGER {
+            JsonNumber::from_f64(n)
+        } else {
+            JsonNumber::with_raw(n, value.to_string())
+        }
+    }
+}
+
+impl From<i32> for JsonNumber {
+    fn from(value: i32) -> Self {
+        JsonNumber::from_f64(value.into())
+    }
+}
+
+impl From<u32> for JsonNumber {
+    fn from(value: u32) -> Self {
+        JsonNumber::from_f64(value.into())
+    }
+}
+
+impl From<u64> for JsonNumber {
+    fn from(value: u64) -> Self {
+        let n = value as f64;
+        if n <= MAX_SAFE_INTEGER {
+            JsonNumber::from_f64(n)
+        } else {
+            JsonNumber::with_raw(n, value.to_string())
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use crate::path::Path;
+
+/// Settings that change what input the parser accepts and how it reads it.
+///
+/// The defaults parse strict RFC 8259 JSON. Pass the options to
+/// [`StreamingJsonParser::with_options`](crate::StreamingJsonParser::with_options),
+/// [`parse_json_string_with`](crate::parse_json_string_with) or
+/// [`parse_json_stream_with`](crate::parse_json_stream_with).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserOptions {
+    pub(crate) dialect: Dialect,
+    pub(crate) preserve_number_text: bool,
+    pub(crate) allow_non_finite_numbers: bool,
+    pub(crate) allow_comments: bool,
+    pub(crate) allow_trailing_commas: bool,
+    pub(crate) allow_unquoted_keys: bool,
+    pub(crate) allow_control_characters: bool,
+    pub(crate) replace_unpaired_surrogates: bool,
+    pub(crate) allow_bom: bool,
+    pub(crate) replace_invalid_utf8: bool,
+    pub(crate) max_depth: usize,
+    pub(crate) buffer_size: usize,
+    pub(crate) max_string_length: Option<usize>,
+    pub(crate) max_input_bytes: Option<usize>,
+    pub(crate) max_container_elements: Option<usize>,
+    pub(crate) max_nodes: Option<usize>,
+    pub(crate) max_total_allocated: Option<usize>,
+    pub(crate) raw_paths: Vec<Path>,
+    pub(crate) framing: Framing,
+    pub(crate) recover_from_errors: bool,
+    pub(crate) collect_warnings: bool,
+}
+
+/// Nesting allowed by default: far beyond real documents, well within the
+/// stack of a default thread.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Bytes requested from the reader per read unless
+/// [`ParserOptions::buffer_size`] says otherwise.
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+pub(crate) const MIN_BUFFER_SIZE: usize = 64;
+
+/// The grammar the parser accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// RFC 8259 JSON, plus whatever the individual `allow_*` options enable.
+    #[default]
+    Json,
+    /// [JSON5](https://spec.json5.org): comments, trailing commas, unquoted
+    /// keys, single-quoted and multi-line strings, hexadecimal numbers,
+    /// leading `+`, leading or trailing decimal points, `Infinity` and `NaN`.
+    Json5,
+}
+
+/// How the documents of a stream are delimited from one another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// Documents follow one another, separated by optional whitespace.
+    #[default]
+    Concatenated,
+    /// One document per line, as in NDJSON and JSON Lines: a document may
+    /// not span lines or share one with another.
+    NewlineDelimited,
+    /// Documents separated by commas, as in `{"a":1},{"a":2}`.
+    CommaSeparated,
+    /// Each document preceded by its length in bytes as a decimal number,
+    /// as in `7 {"a":1}`. The length covers the document itself, not the
+    /// whitespace around it.
+    LengthPrefixed,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            dialect: Dialect::Json,
+            preserve_number_text: false,
+            allow_non_finite_numbers: false,
+            allow_comments: false,
+            allow_trailing_commas: false,
+            allow_unquoted_keys: false,
+            allow_control_characters: false,
+            replace_unpaired_surrogates: false,
+            allow_bom: true,
+            replace_invalid_utf8: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            max_string_length: None,
+            max_input_bytes: None,
+            max_container_elements: None,
+            max_nodes: None,
+            max_total_allocated: None,
+            raw_paths: Vec::new(),
+            framing: Framing::Concatenated,
+            recover_from_errors: false,
+            collect_warnings: false,
+        }
+    }
+}
+
+impl ParserOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Switches grammar. `Dialect::Json5` also turns on `allow_comments`,
+    /// `allow_trailing_commas`, `allow_unquoted_keys` and
+    /// `allow_non_finite_numbers`, and `Dialect::Json` turns them off again;
+    /// setting any of them afterwards overrides it.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        let json5 = dialect == Dialect::Json5;
+        self.dialect = dialect;
+        self.allow_comments = json5;
+        self.allow_trailing_commas = json5;
+        self.allow_unquoted_keys = json5;
+        self.allow_non_finite_numbers = json5;
+        self
+    }
+
+    /// Keep the exact source text of every number (see [`JsonNumber::as_raw_str`](crate::JsonNumber::as_raw_str))
+    /// so values can be written back out byte-for-byte. Under
+    /// [`Dialect::Json5`] that is the JSON5 text, such as `0xFF` or `+.5`.
+    pub fn preserve_number_text(mut self, preserve: bool) -> Self {
+        self.preserve_number_text = preserve;
+        self
+    }
+
+    /// Accept the `NaN`, `Infinity` and `-Infinity` literals emitted by Python's
+    /// `json` module and some JavaScript serializers. Rejected by default.
+    pub fn allow_non_finite_numbers(mut self, allow: bool) -> Self {
+        self.allow_non_finite_numbers = allow;
+        self
+    }
+
+    /// Skip `// line` and `/* block */` comments wherever whitespace is
+    /// allowed, as in JSONC files such as VS Code's settings.
+    pub fn allow_comments(mut self, allow: bool) -> Self {
+        self.allow_comments = allow;
+        self
+    }
+
+    /// Accept one comma after the last member or element, as in `[1, 2,]`,
+    /// instead of failing with [`ParseError::TrailingComma`](crate::ParseError::TrailingComma).
+    pub fn allow_trailing_commas(mut self, allow: bool) -> Self {
+        self.allow_trailing_commas = allow;
+        self
+    }
+
+    /// Accept bare identifiers (letters, digits, `_` and `$`, not starting
+    /// with a digit) as object keys, as in JSON5's `{port: 8080}`.
+    pub fn allow_unquoted_keys(mut self, allow: bool) -> Self {
+        self.allow_unquoted_keys = allow;
+        self
+    }
+
+    /// Accept raw control characters (U+0000 to U+001F, such as a literal tab
+    /// or newline) inside strings. RFC 8259 requires them to be escaped, so
+    /// they are rejected by default.
+    pub fn allow_control_characters(mut self, allow: bool) -> Self {
+        self.allow_control_characters = allow;
+        self
+    }
+
+    /// Decode a `\uD800`-style escape that isn't half of a surrogate pair as
+    /// U+FFFD instead of failing with
+    /// [`ParseError::UnpairedSurrogate`](crate::ParseError::UnpairedSurrogate).
+    pub fn replace_unpaired_surrogates(mut self, replace: bool) -> Self {
+        self.replace_unpaired_surrogates = replace;
+        self
+    }
+
+    /// Skip a UTF-8 byte order mark at the start of the input, as written by
+    /// many Windows tools. On by default; turn off to reject it as an
+    /// invalid character.
+    pub fn allow_bom(mut self, allow: bool) -> Self {
+        self.allow_bom = allow;
+        self
+    }
+
+    /// Decode malformed input bytes as U+FFFD instead of failing with
+    /// [`ParseError::Io`](crate::ParseError::Io), for dirty logs where one bad
+    /// byte shouldn't stop the stream. Also covers UTF-16 and UTF-32 input.
+    pub fn replace_invalid_utf8(mut self, replace: bool) -> Self {
+        self.replace_invalid_utf8 = replace;
+        self
+    }
+
+    /// Fail with [`ParseError::DepthLimitExceeded`](crate::ParseError::DepthLimitExceeded)
+    /// once objects and arrays nest more than `depth` levels deep. Parsing
+    /// doesn't recurse, but dropping, cloning, comparing and printing a
+    /// [`JsonValue`](crate::JsonValue) do, so this is what keeps hostile input
+    /// like `[[[[...` from exhausting the stack.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Bytes requested from the reader per read, 8 KiB by default. The
+    /// parser buffers its input itself, so files and sockets need no
+    /// `BufReader`; a reader that is already buffered is read through
+    /// without a second copy as long as this is at least its capacity.
+    /// Sizes under 64 bytes are rounded up.
+    pub fn buffer_size(mut self, bytes: usize) -> Self {
+        self.buffer_size = bytes;
+        self
+    }
+
+    // The size limits below are off by default. Each fails with
+    // `ParseError::LimitExceeded` naming the limit, so untrusted input can be
+    // rejected before it exhausts memory.
+
+    /// Longest string or key accepted, in bytes of decoded UTF-8.
+    pub fn max_string_length(mut self, bytes: usize) -> Self {
+        self.max_string_length = Some(bytes);
+        self
+    }
+
+    /// Most bytes read from the input. For a stream this counts every
+    /// document read so far, not each one separately.
+    pub fn max_input_bytes(mut self, bytes: usize) -> Self {
+        self.max_input_bytes = Some(bytes);
+        self
+    }
+
+    /// Most members in one object or elements in one array.
+    pub fn max_container_elements(mut self, elements: usize) -> Self {
+        self.max_container_elements = Some(elements);
+        self
+    }
+
+    /// Most values, containers and scalars alike, in one document.
+    pub fn max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = Some(nodes);
+        self
+    }
+
+    /// Most memory the values of one document may take once built, in
+    /// bytes: an approximation counting each value's `JsonValue` plus the
+    /// text of its strings and keys. A string longer than the whole budget
+    /// is stopped while it is read.
+    pub fn max_total_allocated(mut self, bytes: usize) -> Self {
+        self.max_total_allocated = Some(bytes);
+        self
+    }
+
+    /// Keep the value at `path` in each document as its exact source text
+    /// instead of decoding it. It is still validated, and left as `null` in
+    /// the tree; take the text from
+    /// [`StreamingJsonParser::take_raw_values`](crate::StreamingJsonParser::take_raw_values)
+    /// or [`parse_json_string_with_raw_values`](crate::parse_json_string_with_raw_values).
+    /// Functions returning only the value fail with
+    /// [`ParseError::UnsupportedOption`](crate::ParseError::UnsupportedOption)
+    /// rather than drop it. Can be given several times.
+    pub fn raw_value_at(mut self, path: Path) -> Self {
+        self.raw_paths.push(path);
+        self
+    }
+
+    /// How the documents of a stream are delimited; concatenated by default.
+    /// Input that breaks the framing fails with
+    /// [`ParseError::InvalidFraming`](crate::ParseError::InvalidFraming).
+    pub fn framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// After an error in a stream, skip to the line after the one where the
+    /// failed document started and go on reading there. Without it,
+    /// iterating a stream ends after its first error. Suits one record per
+    /// line, where a bad line then costs only its own record. The input of
+    /// the document being read is kept until it completes, up to
+    /// [`max_total_allocated`](ParserOptions::max_total_allocated) bytes; a
+    /// document longer than that fails with
+    /// [`ParseError::LimitExceeded`](crate::ParseError::LimitExceeded), and
+    /// reading goes on after the next line break from where it stopped.
+    pub fn recover_from_errors(mut self, recover: bool) -> Self {
+        self.recover_from_errors = recover;
+        self
+    }
+
+    /// Keep a [`Warning`](crate::Warning) for everything read that strict
+    /// JSON would reject, such as a comment, trailing comma, unquoted key,
+    /// single-quoted string or hex number, and for duplicate keys, to be
+    /// collected with
+    /// [`StreamingJsonParser::take_warnings`](crate::StreamingJsonParser::take_warnings).
+    pub fn collect_warnings(mut self, collect: bool) -> Self {
+        self.collect_warnings = collect;
+        self
+    }
+}
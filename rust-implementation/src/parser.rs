@@ -1,240 +1,729 @@
-use std::collections::HashMap;
+use std::fs::File;
 use std::io::Read;
-use crate::types::{Token, TokenType, JsonValue, ParseError, ParseResult};
-use crate::lexer::Lexer;
+#[cfg(feature = "intern_keys")]
+use std::collections::HashSet;
+use crate::types::{TokenType, IoError, JsonValue, Key, Map, ParseError, ParseResult, RawJson, Span};
+use crate::event::{Event, EventReader};
+use crate::metrics::ParseMetrics;
+use crate::options::ParserOptions;
+use crate::path::{array_index, path_not_found, Path, PathSegment};
+use crate::warning::{Warning, WarningKind};
 
-pub struct StreamingJsonParser<R: Read> {
-    lexer: Lexer<R>,
-    current_token: Option<Token>,
-    peeked_token: Option<ParseResult<Token>>,
+/// Distinct keys remembered per parser; past this, new keys are allocated
+/// individually so documents with unbounded key sets can't grow the cache.
+#[cfg(feature = "intern_keys")]
+const MAX_INTERNED_KEYS: usize = 4096;
+
+/// An object or array whose end event hasn't been read yet. An object
+/// holds the key of the member whose value is being built.
+enum Frame {
+    Object(Map, Option<Key>),
+    Array(Vec<JsonValue>),
 }
 
-impl<R: Read> StreamingJsonParser<R> {
-    pub fn new(reader: R) -> Self {
+/// A value at one of the paths given to [`ParserOptions::raw_value_at`],
+/// being read with the input recorded from byte `start`.
+struct RawValue {
+    path: Path,
+    start: usize,
+    // Where the value's first token starts, once it has been read.
+    value_start: Option<usize>,
+    depth: usize,
+}
+
+/// Builds `JsonValue`s from the events of an [`EventReader`], one document
+/// at a time, for every parser that builds them from events. Open
+/// containers are kept on an explicit stack rather than the call stack, so
+/// nesting is bounded only by `max_depth` and memory.
+pub(crate) struct Builder {
+    stack: Vec<Frame>,
+    raw: Option<RawValue>,
+    raw_values: Vec<(Path, RawJson)>,
+    error_path: Option<Path>,
+    #[cfg(feature = "intern_keys")]
+    interned_keys: HashSet<Key>,
+}
+
+impl Builder {
+    pub(crate) fn new() -> Self {
         Self {
-            lexer: Lexer::new(reader),
-            current_token: None,
-            peeked_token: None,
+            stack: Vec::new(),
+            raw: None,
+            raw_values: Vec::new(),
+            error_path: None,
+            #[cfg(feature = "intern_keys")]
+            interned_keys: HashSet::new(),
         }
     }
 
-    fn peek_token(&mut self) -> &ParseResult<Token> {
-        if self.peeked_token.is_none() {
-            self.peeked_token = Some(
-                self.lexer.next()
-                    .unwrap_or_else(|| Ok(Token::new(TokenType::Eof, 0)))
-            );
+    /// Whether no container is open, so the next event starts a value.
+    pub(crate) fn at_root(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    pub(crate) fn take_raw_values(&mut self) -> Vec<(Path, RawJson)> {
+        std::mem::take(&mut self.raw_values)
+    }
+
+    /// Drops the document being built, after an error or at the end of the
+    /// array whose elements are being built.
+    pub(crate) fn reset<R: Read>(&mut self, events: &mut EventReader<R>) {
+        self.stack.clear();
+        if self.raw.take().is_some() {
+            events.lexer.stop_recording();
         }
-        self.peeked_token.as_ref().unwrap()
     }
 
-    fn advance_token(&mut self) -> ParseResult<Token> {
-        if let Some(peeked) = self.peeked_token.take() {
-            let token = peeked?;
-            self.current_token = Some(token.clone());
-            Ok(token)
-        } else {
-            let token = self.lexer.next()
-                .unwrap_or_else(|| Ok(Token::new(TokenType::Eof, 0)))?;
-            self.current_token = Some(token.clone());
-            Ok(token)
+    /// Drops the document being built after an error, keeping the path of
+    /// the value that was being read for [`error_path`](Builder::error_path).
+    pub(crate) fn fail<R: Read>(&mut self, events: &mut EventReader<R>) {
+        self.error_path = Some(self.path());
+        self.reset(events);
+    }
+
+    /// The path of the value being read when the last error was found.
+    pub(crate) fn error_path(&self) -> Option<&Path> {
+        self.error_path.as_ref()
+    }
+
+    /// Moves the path of the last error under `parent`, for an error in an
+    /// element built on its own.
+    pub(crate) fn nest_error_path(&mut self, parent: Path) {
+        if let Some(path) = &mut self.error_path {
+            let mut nested = parent;
+            for segment in path.segments() {
+                nested.push(segment.clone());
+            }
+            *path = nested;
         }
     }
 
-    fn expect_token(&mut self, expected: TokenType) -> ParseResult<Token> {
-        let token = self.advance_token()?;
-        if std::mem::discriminant(&token.token_type) != std::mem::discriminant(&expected) {
-            return Err(ParseError::UnexpectedToken {
-                expected: format!("{:?}", expected),
-                found: format!("{:?}", token.token_type),
-                position: token.position,
-            });
+    /// The path of the value being read: its key or index in each open
+    /// container. While a key is read, or the separator after a member,
+    /// that of the object.
+    fn path(&self) -> Path {
+        let mut path = Path::root();
+        for frame in &self.stack {
+            match frame {
+                Frame::Object(_, Some(key)) => path.push(key.to_string()),
+                Frame::Object(_, None) => break,
+                Frame::Array(array) => path.push(array.len()),
+            }
         }
-        Ok(token)
+        path
     }
 
-    fn parse_value(&mut self) -> ParseResult<JsonValue> {
-        let token = match self.peek_token() {
-            Ok(token) => token.clone(),
-            Err(e) => return Err(e.clone()),
+    /// To be called before reading each event: if the value about to be
+    /// read is at a raw path, starts recording the input.
+    pub(crate) fn before_event<R: Read>(&mut self, events: &mut EventReader<R>) {
+        if self.raw.is_some() {
+            return;
+        }
+        if let Some(path) = self.raw_path(&events.options().raw_paths) {
+            let start = events.lexer.offset();
+            self.raw = Some(RawValue { path, start, value_start: None, depth: 0 });
+            events.lexer.start_recording();
+        }
+    }
+
+    /// Adds the event just read from `events`, returning the document once
+    /// it is complete. A raw value is checked as usual, but only its source
+    /// text is kept, and `null` stands in for it.
+    pub(crate) fn push<R: Read>(&mut self, event: Event, events: &mut EventReader<R>) -> Option<JsonValue> {
+        let Some(raw) = &mut self.raw else {
+            return self.event(event, events);
         };
+        match event {
+            Event::StartObject | Event::StartArray => raw.depth += 1,
+            // The container ended instead; there is no value here.
+            Event::EndObject | Event::EndArray if raw.depth == 0 => {
+                self.raw = None;
+                events.lexer.stop_recording();
+                return self.event(event, events);
+            }
+            Event::EndObject | Event::EndArray => raw.depth -= 1,
+            _ => {}
+        }
+        let Span { start: token_start, end: token_end } = events.lexer.token_span();
+        let value_start = *raw.value_start.get_or_insert(token_start.offset);
+        if raw.depth > 0 {
+            return None;
+        }
+        let recorded = events.lexer.stop_recording();
+        let text = &recorded[value_start - raw.start..token_end.offset - raw.start];
+        let text = String::from_utf8_lossy(text).into_owned();
+        let path = self.raw.take().expect("checked above").path;
+        self.raw_values.push((path, RawJson::new_unchecked(text)));
+        self.add(JsonValue::Null)
+    }
 
-        match &token.token_type {
-            TokenType::LeftBrace => self.parse_object(),
-            TokenType::LeftBracket => self.parse_array(),
-            TokenType::String(_) => {
-                let token = self.advance_token()?;
-                if let TokenType::String(s) = token.token_type {
-                    Ok(JsonValue::String(s))
-                } else {
-                    unreachable!()
-                }
+    fn event<R: Read>(&mut self, event: Event, events: &mut EventReader<R>) -> Option<JsonValue> {
+        let value = match event {
+            Event::StartObject => {
+                self.stack.push(Frame::Object(Map::new(), None));
+                return None;
             }
-            TokenType::Number(_) => {
-                let token = self.advance_token()?;
-                if let TokenType::Number(n) = token.token_type {
-                    Ok(JsonValue::Number(n))
-                } else {
-                    unreachable!()
-                }
+            Event::StartArray => {
+                self.stack.push(Frame::Array(Vec::new()));
+                return None;
             }
-            TokenType::Boolean(_) => {
-                let token = self.advance_token()?;
-                if let TokenType::Boolean(b) = token.token_type {
-                    Ok(JsonValue::Boolean(b))
-                } else {
-                    unreachable!()
+            Event::Key(key) => {
+                let key = self.make_key(key);
+                if let Some(Frame::Object(object, pending)) = self.stack.last_mut() {
+                    if events.options().collect_warnings && object.contains_key(&key) {
+                        let kind = WarningKind::DuplicateKey(key.to_string());
+                        let position = EventReader::position(events);
+                        events.lexer.warnings.push(Warning { kind, position });
+                    }
+                    *pending = Some(key);
                 }
+                return None;
             }
-            TokenType::Null => {
-                self.advance_token()?;
-                Ok(JsonValue::Null)
+            Event::EndObject | Event::EndArray => match self.stack.pop() {
+                Some(Frame::Object(object, _)) => JsonValue::Object(object),
+                Some(Frame::Array(array)) => JsonValue::Array(array),
+                None => unreachable!("the grammar balances containers"),
+            },
+            Event::String(s) => JsonValue::String(s),
+            Event::Number(n) => JsonValue::Number(n),
+            Event::Boolean(b) => JsonValue::Boolean(b),
+            Event::Null => JsonValue::Null,
+        };
+        self.add(value)
+    }
+
+    /// Puts a finished value in its container, or returns it if it is the
+    /// whole document.
+    fn add(&mut self, value: JsonValue) -> Option<JsonValue> {
+        match self.stack.last_mut() {
+            None => return Some(value),
+            Some(Frame::Object(object, key)) => {
+                object.insert(key.take().expect("a pending key"), value);
             }
-            _ => Err(ParseError::UnexpectedToken {
-                expected: "JSON value".to_string(),
-                found: format!("{:?}", token.token_type),
-                position: token.position,
-            }),
+            Some(Frame::Array(array)) => array.push(value),
         }
+        None
+    }
+
+    /// The raw path the value about to be read is at, if any.
+    fn raw_path(&self, paths: &[Path]) -> Option<Path> {
+        paths.iter().find(|path| {
+            path.len() == self.stack.len()
+                && path.segments().iter().zip(&self.stack).all(|pair| match pair {
+                    (PathSegment::Key(key), Frame::Object(_, Some(pending))) => {
+                        &**pending == key.as_str()
+                    }
+                    (segment, Frame::Array(array)) => array_index(segment) == Some(array.len()),
+                    _ => false,
+                })
+        }).cloned()
+    }
+
+    #[cfg(not(feature = "intern_keys"))]
+    fn make_key(&mut self, key: String) -> Key {
+        key
     }
 
-    fn parse_object(&mut self) -> ParseResult<JsonValue> {
-        self.expect_token(TokenType::LeftBrace)?;
-        let mut object = HashMap::new();
+    /// Returns the shared copy of `key`, so every document read by this parser
+    /// points at the same allocation for the same key.
+    #[cfg(feature = "intern_keys")]
+    fn make_key(&mut self, key: String) -> Key {
+        if let Some(interned) = self.interned_keys.get(key.as_str()) {
+            return interned.clone();
+        }
+        let key = Key::from(key);
+        if self.interned_keys.len() < MAX_INTERNED_KEYS {
+            self.interned_keys.insert(key.clone());
+        }
+        key
+    }
+}
 
-        if let Ok(token) = self.peek_token() {
-            if matches!(token.token_type, TokenType::RightBrace) {
-                self.advance_token()?;
-                return Ok(JsonValue::Object(object));
+/// How far a parse has got, as passed to the hook given to
+/// [`StreamingJsonParser::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Bytes of input consumed.
+    pub bytes: usize,
+    /// Documents, or array elements when reading them one at a time,
+    /// returned so far.
+    pub records: usize,
+}
+
+struct ProgressHook {
+    every: usize,
+    // Bytes consumed at which the hook is next called.
+    next: usize,
+    callback: Box<dyn FnMut(Progress) + Send>,
+}
+
+/// Parses a stream of documents from a reader into `JsonValue`s, one at a
+/// time.
+///
+/// The parser is `Send` whenever its reader is, so it can be handed to
+/// another thread between documents, such as into `spawn_blocking`, and
+/// carry on where it left off there.
+pub struct StreamingJsonParser<R: Read> {
+    events: EventReader<R>,
+    records: usize,
+    progress: Option<ProgressHook>,
+    builder: Builder,
+    // Set by the first error unless recovering from errors, to end iteration.
+    failed: bool,
+}
+
+// Fails to compile if a field ever stops the parsers being `Send`.
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<StreamingJsonParser<std::fs::File>>();
+    assert_send::<EventReader<std::fs::File>>();
+    assert_send::<crate::push::PushParser>();
+    assert_send::<ParseError>();
+};
+
+impl<R: Read> StreamingJsonParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParserOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParserOptions) -> Self {
+        Self {
+            events: EventReader::with_options(reader, options),
+            records: 0,
+            progress: None,
+            builder: Builder::new(),
+            failed: false,
+        }
+    }
+
+    /// Shorthand for [`ParserOptions::preserve_number_text`].
+    pub fn preserve_number_text(mut self, preserve: bool) -> Self {
+        self.events.lexer.options.preserve_number_text = preserve;
+        self
+    }
+
+    /// Shorthand for [`ParserOptions::allow_non_finite_numbers`].
+    pub fn allow_non_finite_numbers(mut self, allow: bool) -> Self {
+        self.events.lexer.options.allow_non_finite_numbers = allow;
+        self
+    }
+
+    pub fn options(&self) -> &ParserOptions {
+        self.events.options()
+    }
+
+    /// Calls `callback` each time another `every_bytes` bytes of input have
+    /// been consumed, for reporting progress through large inputs.
+    pub fn on_progress(
+        mut self,
+        every_bytes: usize,
+        callback: impl FnMut(Progress) + Send + 'static,
+    ) -> Self {
+        let every = every_bytes.max(1);
+        let next = self.events.lexer.offset() + every;
+        self.progress = Some(ProgressHook { every, next, callback: Box::new(callback) });
+        self
+    }
+
+    /// What has been read so far; see [`ParseMetrics`].
+    pub fn metrics(&self) -> ParseMetrics {
+        ParseMetrics { records: self.records, ..self.events.metrics() }
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress { bytes: self.events.lexer.offset(), records: self.records }
+    }
+
+    fn report_progress(&mut self) {
+        let progress = self.progress();
+        if let Some(hook) = &mut self.progress {
+            if progress.bytes >= hook.next {
+                hook.next = progress.bytes - progress.bytes % hook.every + hook.every;
+                (hook.callback)(progress);
             }
         }
+    }
 
-        loop {
-            let key_token = self.expect_token(TokenType::String(String::new()))?;
-            let key = match key_token.token_type {
-                TokenType::String(s) => s,
-                _ => unreachable!(),
-            };
+    /// The warnings found since the last call; see [`ParserOptions::collect_warnings`].
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.events.take_warnings()
+    }
 
-            self.expect_token(TokenType::Colon)?;
-            let value = self.parse_value()?;
-            object.insert(key, value);
+    /// The values kept as source text since the last call, each with its
+    /// path in the document it came from; see [`ParserOptions::raw_value_at`].
+    pub fn take_raw_values(&mut self) -> Vec<(Path, RawJson)> {
+        self.builder.take_raw_values()
+    }
 
-            let separator = match self.peek_token() {
-                Ok(token) => token.clone(),
-                Err(e) => return Err(e.clone()),
-            };
+    /// The path of the value that was being read when the last error was
+    /// found, as in `$.servers[3].tls.cert`, or `$` if it was between
+    /// documents. Kept from the parser's own stack as the error is raised,
+    /// so it holds for any reader and any options.
+    pub fn error_path(&self) -> Option<&Path> {
+        self.builder.error_path()
+    }
 
-            match separator.token_type {
-                TokenType::RightBrace => {
-                    self.advance_token()?;
-                    break;
-                }
-                TokenType::Comma => {
-                    self.advance_token()?;
-                    if let Ok(next_token) = self.peek_token() {
-                        if matches!(next_token.token_type, TokenType::RightBrace) {
-                            return Err(ParseError::TrailingComma(next_token.position));
-                        }
-                    }
-                }
-                _ => {
-                    return Err(ParseError::UnexpectedToken {
-                        expected: "',' or '}'".to_string(),
-                        found: format!("{:?}", separator.token_type),
-                        position: separator.position,
-                    });
+    /// Builds the next document, or returns `None` at the end of the input.
+    fn read_value(&mut self) -> ParseResult<Option<JsonValue>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "json_record",
+            record = self.records,
+            offset = self.events.lexer.offset(),
+        )
+        .entered();
+        self.build_value()
+    }
+
+    /// Builds the value that starts with the next event. Returns `None` if
+    /// there is none: at the end of the input, or of the array whose
+    /// elements are being read.
+    fn build_value(&mut self) -> ParseResult<Option<JsonValue>> {
+        loop {
+            if self.progress.is_some() {
+                self.report_progress();
+            }
+            self.builder.before_event(&mut self.events);
+            let event = match self.events.next_event() {
+                Ok(Some(Event::EndArray)) if self.builder.at_root() => None,
+                Ok(event) => event,
+                Err(error) => {
+                    self.builder.fail(&mut self.events);
+                    return Err(error);
                 }
+            };
+            let Some(event) = event else {
+                self.builder.reset(&mut self.events);
+                return Ok(None);
+            };
+            if let Some(value) = self.builder.push(event, &mut self.events) {
+                self.records += 1;
+                return Ok(Some(value));
             }
         }
+    }
 
-        Ok(JsonValue::Object(object))
+    fn parse_value(&mut self) -> ParseResult<JsonValue> {
+        match self.read_value()? {
+            Some(value) => Ok(value),
+            None => {
+                self.builder.fail(&mut self.events);
+                Err(self.events.missing_value(TokenType::Eof))
+            }
+        }
     }
 
-    fn parse_array(&mut self) -> ParseResult<JsonValue> {
-        self.expect_token(TokenType::LeftBracket)?;
-        let mut array = Vec::new();
+    /// Reads past the next value without building it or allocating for its
+    /// strings; see [`EventReader::skip_value`].
+    pub fn skip_value(&mut self) -> ParseResult<()> {
+        self.events.skip_value()
+    }
 
-        if let Ok(token) = self.peek_token() {
-            if matches!(token.token_type, TokenType::RightBracket) {
-                self.advance_token()?;
-                return Ok(JsonValue::Array(array));
-            }
+    /// Reads the next document as a top-level array, yielding each element
+    /// as soon as it is complete, so an array too large to hold in memory
+    /// can still be processed. Paths given to
+    /// [`ParserOptions::raw_value_at`] are matched within each element.
+    pub fn array_elements(&mut self) -> ArrayElements<'_, R> {
+        ArrayElements {
+            parser: self,
+            state: ElementsState::Start,
+            index: 0,
         }
+    }
 
-        loop {
-            let value = self.parse_value()?;
-            array.push(value);
+    /// Reads the start of the array at `path`, failing if the value there
+    /// is anything else.
+    fn expect_array(&mut self, path: &Path) -> ParseResult<()> {
+        let found = match self.events.next_event()? {
+            Some(Event::StartArray) => return Ok(()),
+            Some(Event::StartObject) => "object",
+            Some(Event::String(_)) => "string",
+            Some(Event::Number(_)) => "number",
+            Some(Event::Boolean(_)) => "boolean",
+            Some(Event::Null) => "null",
+            Some(Event::EndArray) => return Err(path_not_found(path, path.len() - 1).into()),
+            Some(_) => unreachable!("a value is expected here"),
+            None => return Err(self.events.missing_value(TokenType::Eof)),
+        };
+        Err(ParseError::TypeMismatch {
+            expected: "array".to_string(),
+            found: found.to_string(),
+            path: path.to_string(),
+        })
+    }
 
-            let separator = match self.peek_token() {
-                Ok(token) => token.clone(),
-                Err(e) => return Err(e.clone()),
-            };
+    /// Builds the next element of the array at `path` being read, the one
+    /// at `index`, or returns `None` at its end.
+    fn next_element(&mut self, path: &Path, index: usize) -> ParseResult<Option<JsonValue>> {
+        let result = self.build_value();
+        if result.is_err() {
+            self.builder.nest_error_path(path.clone().index(index));
+        }
+        result
+    }
 
-            match separator.token_type {
-                TokenType::RightBracket => {
-                    self.advance_token()?;
-                    break;
+    /// Reads up to the value at `path` in the next document, skipping every
+    /// member and element on the way without building it.
+    fn seek(&mut self, path: &Path) -> ParseResult<()> {
+        for (depth, segment) in path.segments().iter().enumerate() {
+            let found = match self.events.next_event()? {
+                Some(Event::StartObject) => {
+                    let PathSegment::Key(wanted) = segment else {
+                        return Err(path_not_found(path, depth).into());
+                    };
+                    loop {
+                        match self.events.next_event()? {
+                            Some(Event::Key(key)) if key == *wanted => break,
+                            Some(Event::Key(_)) => self.events.skip_value()?,
+                            _ => return Err(path_not_found(path, depth).into()),
+                        }
+                    }
+                    continue;
                 }
-                TokenType::Comma => {
-                    self.advance_token()?;
-                    if let Ok(next_token) = self.peek_token() {
-                        if matches!(next_token.token_type, TokenType::RightBracket) {
-                            return Err(ParseError::TrailingComma(next_token.position));
+                Some(Event::StartArray) => {
+                    for _ in 0..array_index(segment).ok_or_else(|| path_not_found(path, depth))? {
+                        if !self.events.skip_element()? {
+                            return Err(path_not_found(path, depth).into());
                         }
                     }
+                    continue;
                 }
-                _ => {
-                    return Err(ParseError::UnexpectedToken {
-                        expected: "',' or ']'".to_string(),
-                        found: format!("{:?}", separator.token_type),
-                        position: separator.position,
-                    });
+                Some(Event::String(_)) => "string",
+                Some(Event::Number(_)) => "number",
+                Some(Event::Boolean(_)) => "boolean",
+                Some(Event::Null) => "null",
+                // The array before this segment ended just short of its index.
+                Some(Event::EndArray) => return Err(path_not_found(path, depth - 1).into()),
+                Some(_) => unreachable!("a value is expected here"),
+                None => return Err(self.events.missing_value(TokenType::Eof)),
+            };
+            return Err(ParseError::TypeMismatch {
+                expected: "object or array".to_string(),
+                found: found.to_string(),
+                path: path.prefix(depth).to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn parse_single(&mut self) -> ParseResult<JsonValue> {
+        let value = self.parse_value()?;
+        if let Err(error) = self.events.expect_end() {
+            self.builder.fail(&mut self.events);
+            return Err(error);
+        }
+        Ok(value)
+    }
+}
+
+impl<R: Read> Iterator for StreamingJsonParser<R> {
+    type Item = ParseResult<JsonValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.read_value();
+        self.failed = result.is_err() && !self.events.options().recover_from_errors;
+        result.transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ElementsState {
+    Start,
+    InArray,
+    Done,
+}
+
+/// Iterator over the elements of a top-level array; see
+/// [`StreamingJsonParser::array_elements`]. Stops after the closing `]` or
+/// the first error. When the document isn't an array, the rest of it is
+/// skipped, leaving the parser at the next document; after any other error
+/// the parser is left where the error was found.
+pub struct ArrayElements<'p, R: Read> {
+    parser: &'p mut StreamingJsonParser<R>,
+    state: ElementsState,
+    // Elements returned so far.
+    index: usize,
+}
+
+impl<R: Read> ArrayElements<'_, R> {
+    fn next_element(&mut self) -> ParseResult<Option<JsonValue>> {
+        if self.state == ElementsState::Start {
+            if let Err(error) = self.parser.expect_array(&Path::root()) {
+                if matches!(error, ParseError::TypeMismatch { .. }) {
+                    self.parser.events.skip_document()?;
                 }
+                return Err(error);
             }
+            self.state = ElementsState::InArray;
         }
+        self.parser.next_element(&Path::root(), self.index)
+    }
+}
+
+impl<R: Read> Iterator for ArrayElements<'_, R> {
+    type Item = ParseResult<JsonValue>;
 
-        Ok(JsonValue::Array(array))
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.state == ElementsState::Done {
+            return None;
+        }
+        let result = self.next_element();
+        match result {
+            Ok(Some(_)) => self.index += 1,
+            _ => self.state = ElementsState::Done,
+        }
+        result.transpose()
     }
+}
 
-    pub fn parse_single(&mut self) -> ParseResult<JsonValue> {
-        let value = self.parse_value()?;
-        
-        let next_token = match self.peek_token() {
-            Ok(token) => token.clone(),
-            Err(e) => return Err(e.clone()),
-        };
+/// Iterator over the elements of the array at a path inside a document;
+/// see [`parse_json_items`]. Stops after the array's closing `]` or the
+/// first error, without reading the rest of the document.
+pub struct JsonItems<R: Read> {
+    parser: StreamingJsonParser<R>,
+    path: Path,
+    state: ElementsState,
+    // Elements returned so far.
+    index: usize,
+}
 
-        if !matches!(next_token.token_type, TokenType::Eof) {
-            return Err(ParseError::UnexpectedToken {
-                expected: "end of input".to_string(),
-                found: format!("{:?}", next_token.token_type),
-                position: next_token.position,
-            });
+impl<R: Read> JsonItems<R> {
+    fn next_item(&mut self) -> ParseResult<Option<JsonValue>> {
+        if self.state == ElementsState::Start {
+            self.parser.seek(&self.path)?;
+            self.parser.expect_array(&self.path)?;
+            self.state = ElementsState::InArray;
         }
+        self.parser.next_element(&self.path, self.index)
+    }
 
-        Ok(value)
+    /// See [`StreamingJsonParser::error_path`]; for an error inside an
+    /// element, the path is the element's within the whole document.
+    pub fn error_path(&self) -> Option<&Path> {
+        self.parser.error_path()
     }
 }
 
-impl<R: Read> Iterator for StreamingJsonParser<R> {
+impl<R: Read> Iterator for JsonItems<R> {
     type Item = ParseResult<JsonValue>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.peek_token() {
-            Ok(token) if matches!(token.token_type, TokenType::Eof) => None,
-            Ok(_) => Some(self.parse_value()),
-            Err(e) => Some(Err(e.clone())),
+        if self.state == ElementsState::Done {
+            return None;
+        }
+        let result = self.next_item();
+        match result {
+            Ok(Some(_)) => self.index += 1,
+            _ => self.state = ElementsState::Done,
         }
+        result.transpose()
     }
 }
 
 pub fn parse_json_string(input: &str) -> ParseResult<JsonValue> {
+    parse_json_string_with(input, ParserOptions::default())
+}
+
+pub fn parse_json_string_with(input: &str, options: ParserOptions) -> ParseResult<JsonValue> {
+    reject_raw_values(&options)?;
     let cursor = std::io::Cursor::new(input);
-    let mut parser = StreamingJsonParser::new(cursor);
+    let mut parser = StreamingJsonParser::with_options(cursor, options);
     parser.parse_single()
 }
 
+/// Parses one document like [`parse_json_string_with`], also returning the
+/// values kept as source text by [`ParserOptions::raw_value_at`], each with
+/// its path.
+pub fn parse_json_string_with_raw_values(
+    input: &str,
+    options: ParserOptions,
+) -> ParseResult<(JsonValue, Vec<(Path, RawJson)>)> {
+    let mut parser = StreamingJsonParser::with_options(std::io::Cursor::new(input), options);
+    let value = parser.parse_single()?;
+    Ok((value, parser.take_raw_values()))
+}
+
+/// Fails with [`ParseError::UnsupportedOption`] if `options` keeps values as
+/// source text, which a function returning only the value would drop.
+pub(crate) fn reject_raw_values(options: &ParserOptions) -> ParseResult<()> {
+    match options.raw_paths.first() {
+        Some(path) => Err(ParseError::UnsupportedOption(format!(
+            "raw_value_at({}) needs parse_json_string_with_raw_values or StreamingJsonParser::take_raw_values",
+            path
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Parses a document held as raw bytes, such as a network buffer, without
+/// first converting it to a `String`. The bytes may be UTF-8, UTF-16 or UTF-32.
+pub fn parse_json_bytes(input: &[u8]) -> ParseResult<JsonValue> {
+    parse_json_bytes_with(input, ParserOptions::default())
+}
+
+pub fn parse_json_bytes_with(input: &[u8], options: ParserOptions) -> ParseResult<JsonValue> {
+    reject_raw_values(&options)?;
+    StreamingJsonParser::with_options(input, options).parse_single()
+}
+
+/// Parses the one document in the file at `path`, reading it in chunks as
+/// it goes rather than loading it whole. Failing to open the file is a
+/// [`ParseError::Io`] naming the path.
+pub fn parse_json_file<P: AsRef<std::path::Path>>(path: P) -> ParseResult<JsonValue> {
+    parse_json_file_with(path, ParserOptions::default())
+}
+
+pub fn parse_json_file_with<P: AsRef<std::path::Path>>(
+    path: P,
+    options: ParserOptions,
+) -> ParseResult<JsonValue> {
+    reject_raw_values(&options)?;
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| {
+        ParseError::Io(IoError::new(e.kind(), format!("{}: {}", path.display(), e)))
+    })?;
+    parse_json_reader_with(file, options)
+}
+
+/// Parses the one document `reader` holds, failing if anything but
+/// whitespace follows it. The parser buffers its input itself, so there is
+/// no need to wrap the reader in a `BufReader`.
+pub fn parse_json_reader<R: Read>(reader: R) -> ParseResult<JsonValue> {
+    parse_json_reader_with(reader, ParserOptions::default())
+}
+
+pub fn parse_json_reader_with<R: Read>(reader: R, options: ParserOptions) -> ParseResult<JsonValue> {
+    reject_raw_values(&options)?;
+    StreamingJsonParser::with_options(reader, options).parse_single()
+}
+
 pub fn parse_json_stream<R: Read>(reader: R) -> StreamingJsonParser<R> {
     StreamingJsonParser::new(reader)
-}
\ No newline at end of file
+}
+
+pub fn parse_json_stream_with<R: Read>(reader: R, options: ParserOptions) -> StreamingJsonParser<R> {
+    StreamingJsonParser::with_options(reader, options)
+}
+
+/// Streams the elements of the array at `pointer`, such as `/results/items`,
+/// inside one huge document. Everything outside that array is skipped at
+/// the token level, so only one element is held in memory at a time.
+pub fn parse_json_items<R: Read>(reader: R, pointer: &str) -> ParseResult<JsonItems<R>> {
+    parse_json_items_with(reader, pointer, ParserOptions::default())
+}
+
+pub fn parse_json_items_with<R: Read>(
+    reader: R,
+    pointer: &str,
+    options: ParserOptions,
+) -> ParseResult<JsonItems<R>> {
+    Ok(JsonItems {
+        parser: StreamingJsonParser::with_options(reader, options),
+        path: Path::from_pointer(pointer)?,
+        state: ElementsState::Start,
+        index: 0,
+    })
+}
@@ -0,0 +1,556 @@
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+use crate::types::{remove_member, JsonValue, Key, ParseError};
+
+/// An error from looking up or changing a value by [`Path`] or JSON
+/// Pointer. Converts into the [`ParseError`] variant of the same name, so
+/// `?` works in functions returning [`ParseResult`](crate::ParseResult).
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum PathError {
+    #[error("Path {path} not found: no {segment} at {parent}")]
+    PathNotFound {
+        path: String,
+        parent: String,
+        segment: String,
+    },
+
+    /// A value of the wrong type; `found` is the type name of the value
+    /// there (`"string"`, `"number"`, ...) and `path` is where it sits.
+    #[error("Expected {expected} at {path}, found {found}")]
+    TypeMismatch {
+        expected: String,
+        found: String,
+        path: String,
+    },
+
+    #[error("Invalid JSON pointer '{0}'")]
+    InvalidPointer(String),
+}
+
+impl From<PathError> for ParseError {
+    fn from(error: PathError) -> Self {
+        match error {
+            PathError::PathNotFound { path, parent, segment } => ParseError::PathNotFound { path, parent, segment },
+            PathError::TypeMismatch { expected, found, path } => ParseError::TypeMismatch { expected, found, path },
+            PathError::InvalidPointer(pointer) => ParseError::InvalidPointer(pointer),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl From<&str> for PathSegment {
+    fn from(key: &str) -> Self {
+        PathSegment::Key(key.to_string())
+    }
+}
+
+impl From<String> for PathSegment {
+    fn from(key: String) -> Self {
+        PathSegment::Key(key)
+    }
+}
+
+impl From<usize> for PathSegment {
+    fn from(index: usize) -> Self {
+        PathSegment::Index(index)
+    }
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Key(key) if is_identifier(key) => write!(f, ".{}", key),
+            PathSegment::Key(key) => write!(f, "[{:?}]", key),
+            PathSegment::Index(index) => write!(f, "[{}]", index),
+        }
+    }
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(ch) if ch.is_alphabetic() || ch == '_' => {}
+        _ => return false,
+    }
+    chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+}
+
+/// A location inside a document, made of object keys and array indices.
+/// Displays as `$.server.ports[0]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Path {
+    segments: Vec<PathSegment>,
+}
+
+impl Path {
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.segments.push(PathSegment::Key(key.into()));
+        self
+    }
+
+    pub fn index(mut self, index: usize) -> Self {
+        self.segments.push(PathSegment::Index(index));
+        self
+    }
+
+    pub fn push(&mut self, segment: impl Into<PathSegment>) {
+        self.segments.push(segment.into());
+    }
+
+    pub fn pop(&mut self) -> Option<PathSegment> {
+        self.segments.pop()
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Parses an RFC 6901 JSON Pointer such as `/servers/0/name`.
+    pub fn from_pointer(pointer: &str) -> Result<Path, PathError> {
+        if pointer.is_empty() {
+            return Ok(Path::root());
+        }
+        if !pointer.starts_with('/') {
+            return Err(PathError::InvalidPointer(pointer.to_string()));
+        }
+
+        let mut segments = Vec::new();
+        for token in pointer[1..].split('/') {
+            let mut key = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(ch) = chars.next() {
+                if ch == '~' {
+                    match chars.next() {
+                        Some('0') => key.push('~'),
+                        Some('1') => key.push('/'),
+                        _ => return Err(PathError::InvalidPointer(pointer.to_string())),
+                    }
+                } else {
+                    key.push(ch);
+                }
+            }
+            segments.push(PathSegment::Key(key));
+        }
+
+        Ok(Path { segments })
+    }
+
+    pub fn to_pointer(&self) -> String {
+        let mut pointer = String::new();
+        for segment in &self.segments {
+            pointer.push('/');
+            match segment {
+                PathSegment::Key(key) => {
+                    pointer.push_str(&key.replace('~', "~0").replace('/', "~1"))
+                }
+                PathSegment::Index(index) => pointer.push_str(&index.to_string()),
+            }
+        }
+        pointer
+    }
+
+    pub(crate) fn prefix(&self, len: usize) -> Path {
+        Path {
+            segments: self.segments[..len].to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.segments {
+            write!(f, "{}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<PathSegment>> for Path {
+    fn from(segments: Vec<PathSegment>) -> Self {
+        Path { segments }
+    }
+}
+
+impl From<&Path> for Path {
+    fn from(path: &Path) -> Self {
+        path.clone()
+    }
+}
+
+impl<S: Into<PathSegment> + Clone> From<&[S]> for Path {
+    fn from(segments: &[S]) -> Self {
+        Path {
+            segments: segments.iter().cloned().map(Into::into).collect(),
+        }
+    }
+}
+
+impl<S: Into<PathSegment> + Clone, const N: usize> From<&[S; N]> for Path {
+    fn from(segments: &[S; N]) -> Self {
+        Path::from(&segments[..])
+    }
+}
+
+impl<S: Into<PathSegment>, const N: usize> From<[S; N]> for Path {
+    fn from(segments: [S; N]) -> Self {
+        Path {
+            segments: segments.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A [`Path`] that displays in RFC 6901 form, e.g. `/servers/0/name`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct JsonPointer(Path);
+
+impl JsonPointer {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn into_path(self) -> Path {
+        self.0
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.to_pointer())
+    }
+}
+
+impl FromStr for JsonPointer {
+    type Err = PathError;
+
+    fn from_str(pointer: &str) -> Result<Self, PathError> {
+        Path::from_pointer(pointer).map(JsonPointer)
+    }
+}
+
+impl From<Path> for JsonPointer {
+    fn from(path: Path) -> Self {
+        JsonPointer(path)
+    }
+}
+
+impl From<JsonPointer> for Path {
+    fn from(pointer: JsonPointer) -> Self {
+        pointer.0
+    }
+}
+
+impl From<&JsonPointer> for Path {
+    fn from(pointer: &JsonPointer) -> Self {
+        pointer.0.clone()
+    }
+}
+
+/// Iterator returned by [`JsonValue::iter_leaves`].
+pub struct Leaves<'a> {
+    stack: Vec<(Path, &'a JsonValue)>,
+}
+
+impl<'a> Iterator for Leaves<'a> {
+    type Item = (JsonPointer, &'a JsonValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, value)) = self.stack.pop() {
+            match value {
+                JsonValue::Object(obj) if !obj.is_empty() => {
+                    #[cfg_attr(feature = "preserve_order", allow(unused_mut))]
+                    let mut children: Vec<_> = obj.iter().collect();
+                    // A `HashMap` keeps no order, so go by key for the same
+                    // order on every run.
+                    #[cfg(not(feature = "preserve_order"))]
+                    children.sort_unstable_by(|(a, _), (b, _)| a[..].cmp(&b[..]));
+                    for (key, child) in children.into_iter().rev() {
+                        self.stack.push((path.clone().key(&key[..]), child));
+                    }
+                }
+                JsonValue::Array(arr) if !arr.is_empty() => {
+                    for (index, child) in arr.iter().enumerate().rev() {
+                        self.stack.push((path.clone().index(index), child));
+                    }
+                }
+                leaf => return Some((JsonPointer(path), leaf)),
+            }
+        }
+        None
+    }
+}
+
+impl JsonValue {
+    pub fn get(&self, segment: impl Into<PathSegment>) -> Option<&JsonValue> {
+        match (self, segment.into()) {
+            (JsonValue::Object(obj), PathSegment::Key(key)) => obj.get(key.as_str()),
+            (JsonValue::Array(arr), segment) => array_index(&segment).and_then(|index| arr.get(index)),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, segment: impl Into<PathSegment>) -> Option<&mut JsonValue> {
+        match (self, segment.into()) {
+            (JsonValue::Object(obj), PathSegment::Key(key)) => obj.get_mut(key.as_str()),
+            (JsonValue::Array(arr), segment) => array_index(&segment).and_then(|index| arr.get_mut(index)),
+            _ => None,
+        }
+    }
+
+    /// Yields every scalar, empty object and empty array with its pointer,
+    /// depth-first. Array elements come in order; object members come in
+    /// insertion order with the `preserve_order` feature, and sorted by key
+    /// without it, since a `HashMap` keeps no order of its own.
+    pub fn iter_leaves(&self) -> Leaves<'_> {
+        Leaves {
+            stack: vec![(Path::root(), self)],
+        }
+    }
+
+    pub fn get_path<P: Into<Path>>(&self, path: P) -> Result<&JsonValue, PathError> {
+        let path = path.into();
+        let mut current = self;
+
+        for (depth, segment) in path.segments().iter().enumerate() {
+            current = match current.get(segment.clone()) {
+                Some(value) => value,
+                None => return Err(path_not_found(&path, depth)),
+            };
+        }
+
+        Ok(current)
+    }
+
+    pub fn get_path_mut<P: Into<Path>>(&mut self, path: P) -> Result<&mut JsonValue, PathError> {
+        let path = path.into();
+        let mut current = self;
+
+        for (depth, segment) in path.segments().iter().enumerate() {
+            current = match current.get_mut(segment.clone()) {
+                Some(value) => value,
+                None => return Err(path_not_found(&path, depth)),
+            };
+        }
+
+        Ok(current)
+    }
+
+    /// Stores `value` at `path`, creating intermediate objects and arrays
+    /// as needed, and returns the value it replaced. An array index must
+    /// name an existing element or the end of the array, as in RFC 6901.
+    pub fn insert_path<P: Into<Path>>(
+        &mut self,
+        path: P,
+        value: JsonValue,
+    ) -> Result<Option<JsonValue>, PathError> {
+        let path = path.into();
+        insert_at(self, &path, 0, value)
+    }
+
+    /// Like [`JsonValue::insert_path`], addressed by a JSON Pointer.
+    /// The `-` index appends to an array.
+    pub fn set(&mut self, pointer: &str, value: JsonValue) -> Result<Option<JsonValue>, PathError> {
+        self.insert_path(Path::from_pointer(pointer)?, value)
+    }
+
+    pub fn remove_at<P: Into<Path>>(&mut self, path: P) -> Result<JsonValue, PathError> {
+        let path = path.into();
+        let (last, parent_path) = match path.segments().split_last() {
+            Some((last, parent)) => (last, Path::from(parent)),
+            None => return Ok(self.take()),
+        };
+
+        let parent = self.get_path_mut(&parent_path)?;
+        let removed = match (parent, last) {
+            (JsonValue::Object(obj), PathSegment::Key(key)) => remove_member(obj, key),
+            (JsonValue::Array(arr), segment) => match array_index(segment) {
+                Some(index) if index < arr.len() => Some(arr.remove(index)),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        removed.ok_or_else(|| path_not_found(&path, path.len() - 1))
+    }
+
+    /// Removes the value addressed by a JSON Pointer and returns it.
+    pub fn remove_path(&mut self, pointer: &str) -> Result<JsonValue, PathError> {
+        self.remove_at(Path::from_pointer(pointer)?)
+    }
+
+    pub fn get_str_at<P: Into<Path>>(&self, path: P) -> Result<&str, PathError> {
+        let path = path.into();
+        let value = self.get_path(&path)?;
+        value.as_str().ok_or_else(|| type_mismatch("string", value, &path))
+    }
+
+    pub fn get_f64_at<P: Into<Path>>(&self, path: P) -> Result<f64, PathError> {
+        let path = path.into();
+        let value = self.get_path(&path)?;
+        value.as_f64().ok_or_else(|| type_mismatch("number", value, &path))
+    }
+
+    pub fn get_i64_at<P: Into<Path>>(&self, path: P) -> Result<i64, PathError> {
+        let path = path.into();
+        let value = self.get_path(&path)?;
+        value.as_i64().ok_or_else(|| type_mismatch("integer", value, &path))
+    }
+
+    pub fn get_bool_at<P: Into<Path>>(&self, path: P) -> Result<bool, PathError> {
+        let path = path.into();
+        let value = self.get_path(&path)?;
+        value.as_bool().ok_or_else(|| type_mismatch("boolean", value, &path))
+    }
+}
+
+/// The array index a segment names. A key must be written as RFC 6901
+/// does: `0`, or digits without a leading zero or sign.
+pub(crate) fn array_index(segment: &PathSegment) -> Option<usize> {
+    match segment {
+        PathSegment::Index(index) => Some(*index),
+        PathSegment::Key(key) if key == "0" => Some(0),
+        PathSegment::Key(key) if key.starts_with(|ch: char| ('1'..='9').contains(&ch))
+            && key.bytes().all(|byte| byte.is_ascii_digit()) => key.parse().ok(),
+        PathSegment::Key(_) => None,
+    }
+}
+
+fn is_array_segment(segment: &PathSegment) -> bool {
+    array_index(segment).is_some() || matches!(segment, PathSegment::Key(key) if key == "-")
+}
+
+/// The element of an array of `len` elements that `segment` may write to:
+/// an existing one, or the one past the end, which `-` also names.
+fn writable_index(segment: &PathSegment, len: usize, path: &Path, depth: usize) -> Result<usize, PathError> {
+    let index = match segment {
+        PathSegment::Key(key) if key == "-" => len,
+        _ => array_index(segment).ok_or_else(|| PathError::TypeMismatch {
+            expected: "array index".to_string(),
+            found: describe(segment),
+            path: path.prefix(depth).to_string(),
+        })?,
+    };
+    if index > len {
+        return Err(path_not_found(path, depth));
+    }
+    Ok(index)
+}
+
+/// Checks that [`insert_at`] can store a value at `path` in `target`
+/// before anything is created, so a failed insert leaves the document as
+/// it was. `None` stands for a value that isn't there yet.
+fn check_insert(mut target: Option<&JsonValue>, path: &Path) -> Result<(), PathError> {
+    for (depth, segment) in path.segments().iter().enumerate() {
+        target = match target.filter(|value| !value.is_null()) {
+            // A new array starts empty.
+            None if is_array_segment(segment) => {
+                writable_index(segment, 0, path, depth)?;
+                None
+            }
+            None => None,
+            Some(JsonValue::Object(obj)) => match segment {
+                PathSegment::Key(key) => obj.get(key.as_str()),
+                PathSegment::Index(index) => obj.get(index.to_string().as_str()),
+            },
+            Some(JsonValue::Array(arr)) => arr.get(writable_index(segment, arr.len(), path, depth)?),
+            Some(other) => return Err(type_mismatch("object or array", other, &path.prefix(depth))),
+        };
+    }
+    Ok(())
+}
+
+fn insert_at(
+    target: &mut JsonValue,
+    path: &Path,
+    depth: usize,
+    value: JsonValue,
+) -> Result<Option<JsonValue>, PathError> {
+    if depth == 0 {
+        check_insert(Some(target), path)?;
+    }
+    let segment = match path.segments().get(depth) {
+        Some(segment) => segment,
+        None => return Ok(Some(target.replace(value))),
+    };
+
+    if target.is_null() {
+        *target = if is_array_segment(segment) {
+            JsonValue::Array(Vec::new())
+        } else {
+            JsonValue::Object(Default::default())
+        };
+    }
+
+    let (slot, created) = match target {
+        JsonValue::Object(obj) => {
+            let index_key;
+            let key = match segment {
+                PathSegment::Key(key) => key.as_str(),
+                PathSegment::Index(index) => {
+                    index_key = index.to_string();
+                    index_key.as_str()
+                }
+            };
+            let created = !obj.contains_key(key);
+            (obj.entry(Key::from(key)).or_insert(JsonValue::Null), created)
+        }
+        JsonValue::Array(arr) => {
+            let index = writable_index(segment, arr.len(), path, depth)?;
+            let created = index == arr.len();
+            if created {
+                arr.push(JsonValue::Null);
+            }
+            (&mut arr[index], created)
+        }
+        other => return Err(type_mismatch("object or array", other, &path.prefix(depth))),
+    };
+
+    if depth + 1 == path.len() {
+        let previous = slot.replace(value);
+        Ok(if created { None } else { Some(previous) })
+    } else {
+        insert_at(slot, path, depth + 1, value)
+    }
+}
+
+/// A segment as errors name it: `key "name"` or `index 3`.
+fn describe(segment: &PathSegment) -> String {
+    match segment {
+        PathSegment::Key(key) => format!("key {:?}", key),
+        PathSegment::Index(index) => format!("index {}", index),
+    }
+}
+
+pub(crate) fn path_not_found(path: &Path, depth: usize) -> PathError {
+    PathError::PathNotFound {
+        path: path.to_string(),
+        parent: path.prefix(depth).to_string(),
+        segment: describe(&path.segments()[depth]),
+    }
+}
+
+pub(crate) fn type_mismatch(expected: &str, found: &JsonValue, path: &Path) -> PathError {
+    PathError::TypeMismatch {
+        expected: expected.to_string(),
+        found: found.type_name().to_string(),
+        path: path.to_string(),
+    }
+}
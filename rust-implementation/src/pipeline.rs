@@ -0,0 +1,48 @@
+use std::io::{self, Read};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+use crate::options::ParserOptions;
+use crate::parser::{reject_raw_values, StreamingJsonParser};
+use crate::types::{JsonValue, ParseResult};
+
+/// Values parsed ahead of the consumer before the parsing thread waits.
+pub const DEFAULT_PIPELINE_CAPACITY: usize = 64;
+
+/// Parses the documents of `reader` on a thread of their own and sends
+/// each one, or its error, down the returned channel, so reading and
+/// parsing overlap with the work done on every record.
+///
+/// At most [`DEFAULT_PIPELINE_CAPACITY`] values are parsed ahead. The
+/// thread stops at the end of the input, after sending the first error
+/// unless [`ParserOptions::recover_from_errors`] is set, or as soon as the
+/// receiver is dropped; the channel closes when it does. Fails only if the
+/// thread can't be spawned.
+pub fn spawn_json_stream<R: Read + Send + 'static>(
+    reader: R,
+) -> io::Result<Receiver<ParseResult<JsonValue>>> {
+    spawn_json_stream_with(reader, ParserOptions::default(), DEFAULT_PIPELINE_CAPACITY)
+}
+
+/// Like [`spawn_json_stream`], with options and the number of values
+/// parsed ahead. Values can't be kept as source text here, so options
+/// with [`ParserOptions::raw_value_at`] fail with `InvalidInput`.
+pub fn spawn_json_stream_with<R: Read + Send + 'static>(
+    reader: R,
+    options: ParserOptions,
+    capacity: usize,
+) -> io::Result<Receiver<ParseResult<JsonValue>>> {
+    reject_raw_values(&options).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    let (sender, receiver) = sync_channel(capacity);
+    thread::Builder::new()
+        .name("json-stream".to_string())
+        .spawn(move || {
+            let recover = options.recover_from_errors;
+            for result in StreamingJsonParser::with_options(reader, options) {
+                let failed = result.is_err();
+                if sender.send(result).is_err() || (failed && !recover) {
+                    break;
+                }
+            }
+        })?;
+    Ok(receiver)
+}
@@ -0,0 +1,87 @@
+use crate::path::Path;
+use crate::types::JsonValue;
+
+impl JsonValue {
+    /// Recursively removes object members and array elements for which `keep`
+    /// returns `false`. Parents are tested before their children, so a dropped
+    /// subtree is never descended into.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&Path, &JsonValue) -> bool,
+    {
+        retain_at(self, &mut Path::root(), &mut keep);
+    }
+
+    /// Rebuilds the document bottom-up: every member and element is passed to
+    /// `f` after its own children, and is replaced by the result or dropped on `None`.
+    pub fn filter_map<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Path, JsonValue) -> Option<JsonValue>,
+    {
+        filter_map_at(self, &mut Path::root(), &mut f);
+    }
+}
+
+fn retain_at<F>(value: &mut JsonValue, path: &mut Path, keep: &mut F)
+where
+    F: FnMut(&Path, &JsonValue) -> bool,
+{
+    match value {
+        JsonValue::Object(obj) => obj.retain(|key, child| {
+            path.push(&key[..]);
+            let kept = keep(path, child);
+            if kept {
+                retain_at(child, path, keep);
+            }
+            path.pop();
+            kept
+        }),
+        JsonValue::Array(arr) => {
+            let mut index = 0;
+            arr.retain_mut(|child| {
+                path.push(index);
+                let kept = keep(path, child);
+                if kept {
+                    retain_at(child, path, keep);
+                }
+                path.pop();
+                index += 1;
+                kept
+            });
+        }
+        _ => {}
+    }
+}
+
+fn filter_map_at<F>(value: &mut JsonValue, path: &mut Path, f: &mut F)
+where
+    F: FnMut(&Path, JsonValue) -> Option<JsonValue>,
+{
+    match value {
+        JsonValue::Object(obj) => {
+            let members = std::mem::take(obj);
+            for (key, mut child) in members {
+                path.push(&key[..]);
+                filter_map_at(&mut child, path, f);
+                let mapped = f(path, child);
+                path.pop();
+                if let Some(mapped) = mapped {
+                    obj.insert(key, mapped);
+                }
+            }
+        }
+        JsonValue::Array(arr) => {
+            let elements = std::mem::take(arr);
+            for (index, mut child) in elements.into_iter().enumerate() {
+                path.push(index);
+                filter_map_at(&mut child, path, f);
+                let mapped = f(path, child);
+                path.pop();
+                if let Some(mapped) = mapped {
+                    arr.push(mapped);
+                }
+            }
+        }
+        _ => {}
+    }
+}
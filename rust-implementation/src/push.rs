@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::vec;
+use crate::event::{Event, EventReader};
+use crate::options::ParserOptions;
+use crate::types::ParseResult;
+use crate::warning::Warning;
+
+/// Input handed to [`PushParser::feed`] and not yet read by the lexer. When
+/// it runs out before `finish()`, reads fail with `WouldBlock`.
+pub(crate) struct Feed {
+    bytes: VecDeque<u8>,
+    closed: bool,
+    ran_dry: bool,
+}
+
+impl Read for Feed {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.bytes.is_empty() && !self.closed {
+            self.ran_dry = true;
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        self.bytes.read(buf)
+    }
+}
+
+/// The events completed by [`PushParser::feed`], and whether the input so
+/// far could end there. Iterates over the events.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pushed {
+    /// The input so far ends between documents.
+    Complete(Vec<Event>),
+    /// The input so far ends partway through a token or document, so more
+    /// is needed to finish it.
+    NeedMoreData(Vec<Event>),
+}
+
+impl Pushed {
+    pub fn events(&self) -> &[Event] {
+        match self {
+            Pushed::Complete(events) | Pushed::NeedMoreData(events) => events,
+        }
+    }
+
+    pub fn into_events(self) -> Vec<Event> {
+        match self {
+            Pushed::Complete(events) | Pushed::NeedMoreData(events) => events,
+        }
+    }
+}
+
+impl IntoIterator for Pushed {
+    type Item = Event;
+    type IntoIter = vec::IntoIter<Event>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_events().into_iter()
+    }
+}
+
+/// Parses input as it arrives, for event loops that receive data in pieces
+/// and can't hand the parser a blocking `Read`.
+///
+/// Each [`feed`](PushParser::feed) returns the events completed so far. A
+/// token cut off by the end of the data, such as `tr` or `12` (which might
+/// yet become `123`), is held back until more arrives or
+/// [`finish`](PushParser::finish) says there is no more. Like
+/// [`parse_json_stream`](crate::parse_json_stream), any number of documents
+/// may follow one another, read by the same [`EventReader`]. After an error
+/// the parser should be discarded, so
+/// [`ParserOptions::recover_from_errors`] doesn't apply.
+pub struct PushParser {
+    events: EventReader<Feed>,
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        Self::with_options(ParserOptions::default())
+    }
+
+    pub fn with_options(options: ParserOptions) -> Self {
+        let feed = Feed {
+            bytes: VecDeque::new(),
+            closed: false,
+            ran_dry: false,
+        };
+        let mut events = EventReader::with_options(feed, options);
+        events.resumable = true;
+        Self { events }
+    }
+
+    pub fn options(&self) -> &ParserOptions {
+        self.events.options()
+    }
+
+    /// The warnings found since the last call; see
+    /// [`ParserOptions::collect_warnings`].
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.events.take_warnings()
+    }
+
+    /// Adds `bytes` to the input and returns every event they complete.
+    pub fn feed(&mut self, bytes: &[u8]) -> ParseResult<Pushed> {
+        let mut events = Vec::new();
+        self.feed_into(bytes, &mut events)?;
+        Ok(match self.events.at_rest() {
+            true => Pushed::Complete(events),
+            false => Pushed::NeedMoreData(events),
+        })
+    }
+
+    /// Marks the end of the input, returning the last events. Fails if it
+    /// ends partway through a document.
+    pub fn finish(mut self) -> ParseResult<Vec<Event>> {
+        let mut events = Vec::new();
+        self.finish_into(&mut events)?;
+        Ok(events)
+    }
+
+    /// Like [`feed`](PushParser::feed), but adds the events to `events`, so
+    /// those read before an error are kept.
+    fn feed_into(&mut self, bytes: &[u8], events: &mut Vec<Event>) -> ParseResult<()> {
+        self.push_bytes(bytes);
+        self.drain(events)
+    }
+
+    fn finish_into(&mut self, events: &mut Vec<Event>) -> ParseResult<()> {
+        self.close();
+        self.drain(events)
+    }
+
+    fn drain(&mut self, events: &mut Vec<Event>) -> ParseResult<()> {
+        while let Some(event) = self.next_event()? {
+            events.push(event);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn push_bytes(&mut self, bytes: &[u8]) {
+        self.events.lexer.get_mut().bytes.extend(bytes);
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.events.lexer.get_mut().closed = true;
+    }
+
+    pub(crate) fn at_rest(&self) -> bool {
+        self.events.at_rest()
+    }
+
+    pub(crate) fn reader(&mut self) -> &mut EventReader<Feed> {
+        &mut self.events
+    }
+
+    /// Reads the next event, or returns `None` once the input so far has
+    /// been used up.
+    pub(crate) fn next_event(&mut self) -> ParseResult<Option<Event>> {
+        match self.events.read_event() {
+            // Go back to the start of the token the input ran out in.
+            Err(_) if self.events.lexer.get_mut().ran_dry => {
+                self.events.lexer.get_mut().ran_dry = false;
+                self.events.lexer.rollback();
+                Ok(None)
+            }
+            result => result,
+        }
+    }
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
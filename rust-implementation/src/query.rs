@@ -0,0 +1,1086 @@
+//! [JSONPath](https://www.rfc-editor.org/rfc/rfc9535) queries, such as
+//! `$.store.book[?@.price < 10].title`.
+
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+use regex::Regex;
+use crate::lexer::{combine_surrogates, is_high_surrogate};
+use crate::number::JsonNumber;
+use crate::path::{Path, PathSegment};
+use crate::types::{JsonValue, ParseError, ParseResult, Position};
+
+// Integers an index or slice may use: the range I-JSON numbers are exact in.
+const MAX_INTEGER: i64 = (1 << 53) - 1;
+
+/// A compiled JSONPath query (RFC 9535), checked once and then run against
+/// any number of documents.
+///
+/// All of the RFC is supported: name, wildcard, index, slice and filter
+/// selectors, descendant segments (`..`), comparisons, `&&`, `||`, `!`, and
+/// the functions `length`, `count`, `match`, `search` and `value`.
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    text: String,
+    query: Query,
+}
+
+impl JsonPath {
+    /// Compiles `query`, failing with [`ParseError::InvalidQuery`] if it
+    /// isn't well-formed and well-typed.
+    pub fn parse(query: &str) -> ParseResult<JsonPath> {
+        let mut parser = Parser { query, pos: 0 };
+        if !parser.eat("$") {
+            return Err(parser.error("a query starts with `$`"));
+        }
+        let segments = parser.segments()?;
+        if parser.pos < query.len() {
+            return Err(parser.unexpected());
+        }
+        Ok(JsonPath {
+            text: query.to_string(),
+            query: Query { absolute: true, segments },
+        })
+    }
+
+    /// The nodes of `value` the query selects, with their paths, in the
+    /// order the RFC gives them. A node selected twice, as by `$[0,0]`, is
+    /// listed twice.
+    pub fn query<'a>(&self, value: &'a JsonValue) -> Vec<(Path, &'a JsonValue)> {
+        self.query.select(value, value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = ParseError;
+
+    fn from_str(query: &str) -> ParseResult<Self> {
+        JsonPath::parse(query)
+    }
+}
+
+impl JsonValue {
+    /// Runs the JSONPath `query` against this value; shorthand for
+    /// [`JsonPath::parse`] and [`JsonPath::query`].
+    pub fn query(&self, query: &str) -> ParseResult<Vec<(Path, &JsonValue)>> {
+        Ok(JsonPath::parse(query)?.query(self))
+    }
+}
+
+impl Path {
+    /// The path as an RFC 9535 Normalized Path (section 2.7), such as
+    /// `$['store']['book'][0]`: names in single quotes with only the
+    /// escapes the RFC allows, so equal paths are equal strings.
+    pub fn to_normalized_path(&self) -> String {
+        let mut out = String::from("$");
+        for segment in self.segments() {
+            match segment {
+                PathSegment::Index(index) => write!(out, "[{}]", index).unwrap(),
+                PathSegment::Key(name) => {
+                    out.push_str("['");
+                    for c in name.chars() {
+                        match c {
+                            '\x08' => out.push_str("\\b"),
+                            '\x0c' => out.push_str("\\f"),
+                            '\n' => out.push_str("\\n"),
+                            '\r' => out.push_str("\\r"),
+                            '\t' => out.push_str("\\t"),
+                            '\'' => out.push_str("\\'"),
+                            '\\' => out.push_str("\\\\"),
+                            c if c < ' ' => write!(out, "\\u{:04x}", c as u32).unwrap(),
+                            c => out.push(c),
+                        }
+                    }
+                    out.push_str("']");
+                }
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Query {
+    /// Starts at the document (`$`) rather than the current node (`@`).
+    absolute: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Child(Vec<Selector>),
+    /// `..`: the selectors applied to a node and all of its descendants.
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice { start: Option<i64>, end: Option<i64>, step: Option<i64> },
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone)]
+enum Filter {
+    Or(Vec<Filter>),
+    And(Vec<Filter>),
+    Not(Box<Filter>),
+    Compare(Comparable, Comparison, Comparable),
+    /// A query on its own: true if it selects anything.
+    Exists(Query),
+    /// `match` or `search`.
+    Function(Function),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// What a comparison compares: something that evaluates to one value, or to
+/// nothing.
+#[derive(Debug, Clone)]
+enum Comparable {
+    Literal(JsonValue),
+    /// A query selecting at most one node.
+    Query(Query),
+    Function(Box<Function>),
+}
+
+#[derive(Debug, Clone)]
+enum Function {
+    Length(Comparable),
+    Count(Query),
+    Value(Query),
+    /// The whole string matches.
+    Match(Comparable, Pattern),
+    /// Some substring matches.
+    Search(Comparable, Pattern),
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Literal(Regex),
+    Dynamic(Comparable),
+}
+
+impl Query {
+    fn select<'a>(&self, root: &'a JsonValue, current: &'a JsonValue) -> Vec<(Path, &'a JsonValue)> {
+        let start = if self.absolute { root } else { current };
+        let mut nodes = vec![(Path::root(), start)];
+        for segment in &self.segments {
+            let mut selected = Vec::new();
+            for (path, value) in &nodes {
+                match segment {
+                    Segment::Child(selectors) => select(selectors, path, value, root, &mut selected),
+                    Segment::Descendant(selectors) => descend(selectors, path, value, root, &mut selected),
+                }
+            }
+            nodes = selected;
+        }
+        nodes
+    }
+
+    /// Made of names and indices only, so it can't select more than one node.
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| match segment {
+            Segment::Child(selectors) => {
+                matches!(selectors.as_slice(), [Selector::Name(_)] | [Selector::Index(_)])
+            }
+            Segment::Descendant(_) => false,
+        })
+    }
+}
+
+fn descend<'a>(
+    selectors: &[Selector],
+    path: &Path,
+    value: &'a JsonValue,
+    root: &'a JsonValue,
+    out: &mut Vec<(Path, &'a JsonValue)>,
+) {
+    select(selectors, path, value, root, out);
+    match value {
+        JsonValue::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                descend(selectors, &path.clone().index(index), item, root, out);
+            }
+        }
+        JsonValue::Object(map) => {
+            for (key, member) in map {
+                descend(selectors, &path.clone().key(&key[..]), member, root, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn select<'a>(
+    selectors: &[Selector],
+    path: &Path,
+    value: &'a JsonValue,
+    root: &'a JsonValue,
+    out: &mut Vec<(Path, &'a JsonValue)>,
+) {
+    for selector in selectors {
+        match (selector, value) {
+            (Selector::Name(name), JsonValue::Object(map)) => {
+                if let Some(member) = map.get(name.as_str()) {
+                    out.push((path.clone().key(name.as_str()), member));
+                }
+            }
+            (Selector::Wildcard, JsonValue::Array(items)) => {
+                out.extend(items.iter().enumerate().map(|(index, item)| (path.clone().index(index), item)));
+            }
+            (Selector::Wildcard, JsonValue::Object(map)) => {
+                out.extend(map.iter().map(|(key, member)| (path.clone().key(&key[..]), member)));
+            }
+            (Selector::Index(index), JsonValue::Array(items)) => {
+                let len = items.len() as i64;
+                let index = if *index < 0 { len + index } else { *index };
+                if (0..len).contains(&index) {
+                    out.push((path.clone().index(index as usize), &items[index as usize]));
+                }
+            }
+            (&Selector::Slice { start, end, step }, JsonValue::Array(items)) => {
+                for index in slice_indices(items.len() as i64, start, end, step.unwrap_or(1)) {
+                    out.push((path.clone().index(index), &items[index]));
+                }
+            }
+            (Selector::Filter(filter), JsonValue::Array(items)) => {
+                for (index, item) in items.iter().enumerate() {
+                    if filter.test(item, root) {
+                        out.push((path.clone().index(index), item));
+                    }
+                }
+            }
+            (Selector::Filter(filter), JsonValue::Object(map)) => {
+                for (key, member) in map {
+                    if filter.test(member, root) {
+                        out.push((path.clone().key(&key[..]), member));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The indices `start:end:step` selects from an array of `len` items, per
+/// section 2.3.4.2.2 of the RFC.
+fn slice_indices(len: i64, start: Option<i64>, end: Option<i64>, step: i64) -> Vec<usize> {
+    let normalize = |index: i64| if index < 0 { len + index } else { index };
+    let mut indices = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut index = lower;
+        while index < upper {
+            indices.push(index as usize);
+            index += step;
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+        let mut index = upper;
+        while lower < index {
+            indices.push(index as usize);
+            index += step;
+        }
+    }
+    indices
+}
+
+impl Filter {
+    fn test(&self, current: &JsonValue, root: &JsonValue) -> bool {
+        match self {
+            Filter::Or(filters) => filters.iter().any(|filter| filter.test(current, root)),
+            Filter::And(filters) => filters.iter().all(|filter| filter.test(current, root)),
+            Filter::Not(filter) => !filter.test(current, root),
+            Filter::Compare(left, comparison, right) => {
+                let left = left.evaluate(current, root);
+                let right = right.evaluate(current, root);
+                compare(left.as_deref(), *comparison, right.as_deref())
+            }
+            Filter::Exists(query) => !query.select(root, current).is_empty(),
+            Filter::Function(function) => function.test(current, root),
+        }
+    }
+}
+
+impl Comparable {
+    /// The value, or `None` for nothing, as from a query selecting no node.
+    fn evaluate<'a>(&'a self, current: &'a JsonValue, root: &'a JsonValue) -> Option<Cow<'a, JsonValue>> {
+        match self {
+            Comparable::Literal(value) => Some(Cow::Borrowed(value)),
+            Comparable::Query(query) => query.select(root, current).pop().map(|(_, value)| Cow::Borrowed(value)),
+            Comparable::Function(function) => function.evaluate(current, root),
+        }
+    }
+}
+
+impl Function {
+    fn evaluate<'a>(&'a self, current: &'a JsonValue, root: &'a JsonValue) -> Option<Cow<'a, JsonValue>> {
+        match self {
+            Function::Length(argument) => {
+                let len = match argument.evaluate(current, root)?.as_ref() {
+                    JsonValue::String(s) => s.chars().count(),
+                    JsonValue::Array(items) => items.len(),
+                    JsonValue::Object(map) => map.len(),
+                    _ => return None,
+                };
+                Some(Cow::Owned(JsonValue::from(len as u64)))
+            }
+            Function::Count(query) => {
+                Some(Cow::Owned(JsonValue::from(query.select(root, current).len() as u64)))
+            }
+            Function::Value(query) => match query.select(root, current).as_slice() {
+                [(_, value)] => Some(Cow::Borrowed(*value)),
+                _ => None,
+            },
+            Function::Match(..) | Function::Search(..) => unreachable!("logical functions aren't compared"),
+        }
+    }
+
+    fn test(&self, current: &JsonValue, root: &JsonValue) -> bool {
+        let (argument, pattern, whole) = match self {
+            Function::Match(argument, pattern) => (argument, pattern, true),
+            Function::Search(argument, pattern) => (argument, pattern, false),
+            _ => unreachable!("only logical functions are tested"),
+        };
+        let Some(value) = argument.evaluate(current, root) else {
+            return false;
+        };
+        let JsonValue::String(s) = value.as_ref() else {
+            return false;
+        };
+        match pattern {
+            Pattern::Literal(regex) => regex.is_match(s),
+            Pattern::Dynamic(pattern) => match pattern.evaluate(current, root).as_deref() {
+                // A pattern that isn't a valid I-Regexp matches nothing.
+                Some(JsonValue::String(pattern)) => {
+                    compile_pattern(pattern, whole).is_some_and(|regex| regex.is_match(s))
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+/// Compiles an I-Regexp (RFC 9485), anchored at both ends for `match`, or
+/// `None` if `pattern` isn't one.
+fn compile_pattern(pattern: &str, whole: bool) -> Option<Regex> {
+    let mut translator = IRegexp { chars: pattern.chars().peekable(), out: String::with_capacity(pattern.len()) };
+    translator.regexp()?;
+    if translator.chars.next().is_some() {
+        return None;
+    }
+    let translated = translator.out;
+    let translated = if whole { format!("^(?:{})$", translated) } else { translated };
+    Regex::new(&translated).ok()
+}
+
+/// Checks a pattern against the I-Regexp grammar while writing it out in
+/// the regex crate's syntax. Anything the grammar leaves out, such as
+/// `\d`, `(?i)`, lazy quantifiers or backreferences, is rejected; `^` and
+/// `$` are ordinary characters, and `.` matches any character but a line
+/// break, where the regex crate's only leaves out `\n`.
+struct IRegexp<'p> {
+    chars: Peekable<Chars<'p>>,
+    out: String,
+}
+
+impl IRegexp<'_> {
+    fn regexp(&mut self) -> Option<()> {
+        self.branch()?;
+        while self.chars.next_if_eq(&'|').is_some() {
+            self.out.push('|');
+            self.branch()?;
+        }
+        Some(())
+    }
+
+    fn branch(&mut self) -> Option<()> {
+        while !matches!(self.chars.peek(), None | Some('|' | ')')) {
+            self.atom()?;
+            self.quantifier()?;
+        }
+        Some(())
+    }
+
+    fn atom(&mut self) -> Option<()> {
+        match self.chars.next()? {
+            '(' => {
+                self.out.push_str("(?:");
+                self.regexp()?;
+                self.chars.next_if_eq(&')')?;
+                self.out.push(')');
+            }
+            '.' => self.out.push_str("[^\\n\\r]"),
+            '[' => self.class()?,
+            '\\' => match self.chars.next()? {
+                c @ ('p' | 'P') => self.category(c)?,
+                c => self.single_char_escape(c)?,
+            },
+            '*' | '+' | '?' | '{' | '}' | ']' => return None,
+            c => self.literal(c),
+        }
+        Some(())
+    }
+
+    fn quantifier(&mut self) -> Option<()> {
+        match self.chars.peek() {
+            Some(&c @ ('*' | '+' | '?')) => {
+                self.chars.next();
+                self.out.push(c);
+            }
+            Some('{') => {
+                self.chars.next();
+                self.out.push('{');
+                self.digits()?;
+                if self.chars.next_if_eq(&',').is_some() {
+                    self.out.push(',');
+                    if self.chars.peek() != Some(&'}') {
+                        self.digits()?;
+                    }
+                }
+                self.chars.next_if_eq(&'}')?;
+                self.out.push('}');
+            }
+            _ => {}
+        }
+        Some(())
+    }
+
+    fn digits(&mut self) -> Option<()> {
+        let start = self.out.len();
+        while let Some(digit) = self.chars.next_if(char::is_ascii_digit) {
+            self.out.push(digit);
+        }
+        (self.out.len() > start).then_some(())
+    }
+
+    /// A class, after its `[`; `-` is literal only first or last.
+    fn class(&mut self) -> Option<()> {
+        self.out.push('[');
+        if self.chars.next_if_eq(&'^').is_some() {
+            self.out.push('^');
+        }
+        if self.chars.next_if_eq(&'-').is_some() {
+            self.literal('-');
+        } else {
+            self.class_item()?;
+        }
+        loop {
+            if self.chars.next_if_eq(&']').is_some() {
+                break;
+            }
+            if self.chars.next_if_eq(&'-').is_some() {
+                self.chars.next_if_eq(&']')?;
+                self.literal('-');
+                break;
+            }
+            self.class_item()?;
+        }
+        self.out.push(']');
+        Some(())
+    }
+
+    /// A character, a range or a category escape in a class.
+    fn class_item(&mut self) -> Option<()> {
+        let mut ahead = self.chars.clone();
+        if let (Some('\\'), Some(c @ ('p' | 'P'))) = (ahead.next(), ahead.next()) {
+            self.chars = ahead;
+            return self.category(c);
+        }
+        self.class_char()?;
+        let mut ahead = self.chars.clone();
+        if ahead.next() == Some('-') && ahead.peek() != Some(&']') {
+            self.chars = ahead;
+            self.out.push('-');
+            self.class_char()?;
+        }
+        Some(())
+    }
+
+    fn class_char(&mut self) -> Option<()> {
+        match self.chars.next()? {
+            '\\' => {
+                let c = self.chars.next()?;
+                self.single_char_escape(c)
+            }
+            '-' | '[' | ']' => None,
+            c => {
+                self.literal(c);
+                Some(())
+            }
+        }
+    }
+
+    /// The character after a `\` that stands for a single character.
+    fn single_char_escape(&mut self, c: char) -> Option<()> {
+        match c {
+            'n' | 'r' | 't' => {
+                self.out.push('\\');
+                self.out.push(c);
+            }
+            '(' | ')' | '*' | '+' | '-' | '.' | '?' | '[' | '\\' | ']' | '^' | '{' | '|' | '}' => self.literal(c),
+            _ => return None,
+        }
+        Some(())
+    }
+
+    /// `\p{..}` or `\P{..}`, after the `p`, naming a general category.
+    fn category(&mut self, p: char) -> Option<()> {
+        self.chars.next_if_eq(&'{')?;
+        let mut name = String::new();
+        while let Some(c) = self.chars.next_if(|&c| c != '}') {
+            name.push(c);
+        }
+        self.chars.next_if_eq(&'}')?;
+        let mut letters = name.chars();
+        let subcategories = match letters.next()? {
+            'L' => "lmotu",
+            'M' => "cen",
+            'N' => "dlo",
+            'P' => "cdefios",
+            'Z' => "lps",
+            'S' => "ckmo",
+            'C' => "cfno",
+            _ => return None,
+        };
+        match (letters.next(), letters.next()) {
+            (None, _) => {}
+            (Some(c), None) if subcategories.contains(c) => {}
+            _ => return None,
+        }
+        write!(self.out, "\\{}{{{}}}", p, name).unwrap();
+        Some(())
+    }
+
+    fn literal(&mut self, c: char) {
+        self.out.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+    }
+}
+
+fn compare(left: Option<&JsonValue>, comparison: Comparison, right: Option<&JsonValue>) -> bool {
+    match comparison {
+        Comparison::Equal => equal(left, right),
+        Comparison::NotEqual => !equal(left, right),
+        Comparison::Less => less(left, right),
+        Comparison::LessOrEqual => less(left, right) || equal(left, right),
+        Comparison::Greater => less(right, left),
+        Comparison::GreaterOrEqual => less(right, left) || equal(left, right),
+    }
+}
+
+/// Nothing equals only nothing; numbers are equal by value, and containers
+/// when their contents are.
+fn equal(left: Option<&JsonValue>, right: Option<&JsonValue>) -> bool {
+    match (left, right) {
+        (None, None) => true,
+        (Some(left), Some(right)) => values_equal(left, right),
+        _ => false,
+    }
+}
+
+fn values_equal(left: &JsonValue, right: &JsonValue) -> bool {
+    match (left, right) {
+        (JsonValue::Null, JsonValue::Null) => true,
+        (JsonValue::Boolean(a), JsonValue::Boolean(b)) => a == b,
+        (JsonValue::Number(a), JsonValue::Number(b)) => a == b,
+        (JsonValue::String(a), JsonValue::String(b)) => a == b,
+        (JsonValue::Array(a), JsonValue::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        (JsonValue::Object(a), JsonValue::Object(b)) => {
+            a.len() == b.len()
+                && a.iter().all(|(key, a)| b.get(&key[..]).is_some_and(|b| values_equal(a, b)))
+        }
+        _ => false,
+    }
+}
+
+/// Only numbers and strings are ordered, numbers by value as they are
+/// compared for equality, and strings by code point.
+fn less(left: Option<&JsonValue>, right: Option<&JsonValue>) -> bool {
+    match (left, right) {
+        (Some(JsonValue::Number(a)), Some(JsonValue::Number(b))) => a < b,
+        (Some(JsonValue::String(a)), Some(JsonValue::String(b))) => a < b,
+        _ => false,
+    }
+}
+
+/// An operand in a filter, before its type is checked against where it is
+/// used.
+enum Operand {
+    Literal(JsonValue),
+    Query(Query),
+    Function(Function),
+}
+
+struct Parser<'q> {
+    query: &'q str,
+    /// Byte offset of the next character.
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error_at(&self, pos: usize, reason: impl Into<String>) -> ParseError {
+        ParseError::InvalidQuery { position: Position::in_text(self.query, pos), reason: reason.into() }
+    }
+
+    fn error(&self, reason: impl Into<String>) -> ParseError {
+        self.error_at(self.pos, reason)
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(c) => self.error(format!("unexpected {:?}", c)),
+            None => self.error("unexpected end of query"),
+        }
+    }
+
+    fn rest(&self) -> &str {
+        &self.query[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> ParseResult<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`", token)))
+        }
+    }
+
+    fn skip_blanks(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn segments(&mut self) -> ParseResult<Vec<Segment>> {
+        let mut segments = Vec::new();
+        loop {
+            // Blanks may separate segments, but only count as part of the
+            // query when a segment follows them.
+            let before = self.pos;
+            self.skip_blanks();
+            if self.eat("..") {
+                let selectors = match self.peek() {
+                    Some('[') => self.bracketed()?,
+                    Some('*') => {
+                        self.pos += 1;
+                        vec![Selector::Wildcard]
+                    }
+                    _ => vec![Selector::Name(self.member_name()?)],
+                };
+                segments.push(Segment::Descendant(selectors));
+            } else if self.eat(".") {
+                let selector = if self.eat("*") { Selector::Wildcard } else { Selector::Name(self.member_name()?) };
+                segments.push(Segment::Child(vec![selector]));
+            } else if self.peek() == Some('[') {
+                segments.push(Segment::Child(self.bracketed()?));
+            } else {
+                self.pos = before;
+                return Ok(segments);
+            }
+        }
+    }
+
+    /// A name after `.` or `..`, as in `$.store`.
+    fn member_name(&mut self) -> ParseResult<String> {
+        let start = self.pos;
+        match self.peek() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' || !c.is_ascii() => {}
+            _ => return Err(self.error("expected a member name, `*` or `[`")),
+        }
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_' || !c.is_ascii()) {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+        Ok(self.query[start..self.pos].to_string())
+    }
+
+    fn bracketed(&mut self) -> ParseResult<Vec<Selector>> {
+        self.expect("[")?;
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_blanks();
+            selectors.push(self.selector()?);
+            self.skip_blanks();
+            if !self.eat(",") {
+                break;
+            }
+        }
+        self.expect("]")?;
+        Ok(selectors)
+    }
+
+    fn selector(&mut self) -> ParseResult<Selector> {
+        match self.peek() {
+            Some('\'' | '"') => return Ok(Selector::Name(self.string()?)),
+            Some('*') => {
+                self.pos += 1;
+                return Ok(Selector::Wildcard);
+            }
+            Some('?') => {
+                self.pos += 1;
+                self.skip_blanks();
+                return Ok(Selector::Filter(self.logical_or()?));
+            }
+            _ => {}
+        }
+
+        let start = self.optional_integer()?;
+        self.skip_blanks();
+        if !self.eat(":") {
+            return start.map(Selector::Index).ok_or_else(|| self.error("expected a selector"));
+        }
+        self.skip_blanks();
+        let end = self.optional_integer()?;
+        self.skip_blanks();
+        let step = if self.eat(":") {
+            self.skip_blanks();
+            self.optional_integer()?
+        } else {
+            None
+        };
+        Ok(Selector::Slice { start, end, step })
+    }
+
+    fn optional_integer(&mut self) -> ParseResult<Option<i64>> {
+        match self.peek() {
+            Some('-' | '0'..='9') => self.integer().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// An index or slice bound: no leading zeros, no `-0`, and within
+    /// ±(2^53 - 1).
+    fn integer(&mut self) -> ParseResult<i64> {
+        let start = self.pos;
+        self.eat("-");
+        let digits = self.pos;
+        while let Some('0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+        let text = &self.query[start..self.pos];
+        let digits = &self.query[digits..self.pos];
+        if digits.is_empty() {
+            return Err(self.error("expected a digit"));
+        }
+        if (digits.starts_with('0') && digits.len() > 1) || text == "-0" {
+            return Err(self.error_at(start, format!("invalid integer {}", text)));
+        }
+        text.parse::<i64>()
+            .ok()
+            .filter(|n| (-MAX_INTEGER..=MAX_INTEGER).contains(n))
+            .ok_or_else(|| self.error_at(start, format!("integer {} is out of range", text)))
+    }
+
+    /// A string in single or double quotes, with JSON's escapes plus `\'`.
+    fn string(&mut self) -> ParseResult<String> {
+        let quote = self.bump().expect("a quote");
+        let mut s = String::new();
+        loop {
+            let c = match self.bump() {
+                Some(c) if c == quote => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some(c @ ('/' | '\\')) => c,
+                    Some(c) if c == quote => c,
+                    Some('u') => self.unicode_escape()?,
+                    _ => return Err(self.error_at(self.pos - 1, "invalid escape")),
+                },
+                Some(c) if c < ' ' => return Err(self.error_at(self.pos - 1, "control character in string")),
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            s.push(c);
+        }
+    }
+
+    /// The character of a `\u` escape, whose `\u` has been read; a high
+    /// surrogate must be followed by the escape of a low one.
+    fn unicode_escape(&mut self) -> ParseResult<char> {
+        let start = self.pos - 2;
+        let high = self.hex4()?;
+        let ch = if is_high_surrogate(high) && self.eat("\\u") {
+            combine_surrogates(high, self.hex4()?)
+        } else {
+            char::from_u32(high)
+        };
+        ch.ok_or_else(|| self.error_at(start, "unpaired surrogate"))
+    }
+
+    fn hex4(&mut self) -> ParseResult<u32> {
+        let hex = self.rest().get(..4).filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()));
+        let code = hex.map(|hex| u32::from_str_radix(hex, 16).expect("hex digits"));
+        let code = code.ok_or_else(|| self.error("expected four hex digits"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn logical_or(&mut self) -> ParseResult<Filter> {
+        let mut filters = vec![self.logical_and()?];
+        loop {
+            self.skip_blanks();
+            if !self.eat("||") {
+                break;
+            }
+            self.skip_blanks();
+            filters.push(self.logical_and()?);
+        }
+        Ok(if filters.len() == 1 { filters.pop().expect("one filter") } else { Filter::Or(filters) })
+    }
+
+    fn logical_and(&mut self) -> ParseResult<Filter> {
+        let mut filters = vec![self.basic()?];
+        loop {
+            self.skip_blanks();
+            if !self.eat("&&") {
+                break;
+            }
+            self.skip_blanks();
+            filters.push(self.basic()?);
+        }
+        Ok(if filters.len() == 1 { filters.pop().expect("one filter") } else { Filter::And(filters) })
+    }
+
+    /// A parenthesized expression, a comparison or a test, any but a
+    /// comparison possibly negated.
+    fn basic(&mut self) -> ParseResult<Filter> {
+        if self.eat("!") {
+            self.skip_blanks();
+            if self.peek() == Some('(') {
+                return Ok(Filter::Not(Box::new(self.parenthesized()?)));
+            }
+            let start = self.pos;
+            let operand = self.operand()?;
+            return Ok(Filter::Not(Box::new(self.test(operand, start)?)));
+        }
+        if self.peek() == Some('(') {
+            return self.parenthesized();
+        }
+
+        let start = self.pos;
+        let left = self.operand()?;
+        let before = self.pos;
+        self.skip_blanks();
+        let Some(comparison) = self.comparison() else {
+            self.pos = before;
+            return self.test(left, start);
+        };
+        let left = self.comparable(left, start)?;
+        self.skip_blanks();
+        let start = self.pos;
+        let right = self.operand()?;
+        let right = self.comparable(right, start)?;
+        Ok(Filter::Compare(left, comparison, right))
+    }
+
+    fn parenthesized(&mut self) -> ParseResult<Filter> {
+        self.expect("(")?;
+        self.skip_blanks();
+        let filter = self.logical_or()?;
+        self.skip_blanks();
+        self.expect(")")?;
+        Ok(filter)
+    }
+
+    fn comparison(&mut self) -> Option<Comparison> {
+        let operators = [
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+        operators.into_iter().find(|(token, _)| self.eat(token)).map(|(_, comparison)| comparison)
+    }
+
+    /// An operand standing alone as a test: a query, or a function
+    /// returning a logical value.
+    fn test(&self, operand: Operand, start: usize) -> ParseResult<Filter> {
+        match operand {
+            Operand::Query(query) => Ok(Filter::Exists(query)),
+            Operand::Function(function @ (Function::Match(..) | Function::Search(..))) => {
+                Ok(Filter::Function(function))
+            }
+            Operand::Function(_) => Err(self.error_at(start, "a function returning a value must be compared")),
+            Operand::Literal(_) => Err(self.error_at(start, "a literal must be compared")),
+        }
+    }
+
+    /// `operand` where a comparison, or a function parameter, takes a value.
+    fn comparable(&self, operand: Operand, start: usize) -> ParseResult<Comparable> {
+        match operand {
+            Operand::Literal(value) => Ok(Comparable::Literal(value)),
+            Operand::Query(query) if query.is_singular() => Ok(Comparable::Query(query)),
+            Operand::Query(_) => Err(self.error_at(start, "only a singular query has a value")),
+            Operand::Function(Function::Match(..) | Function::Search(..)) => {
+                Err(self.error_at(start, "a function returning a logical value has no value"))
+            }
+            Operand::Function(function) => Ok(Comparable::Function(Box::new(function))),
+        }
+    }
+
+    fn operand(&mut self) -> ParseResult<Operand> {
+        match self.peek() {
+            Some('@') | Some('$') => {
+                let absolute = self.bump() == Some('$');
+                Ok(Operand::Query(Query { absolute, segments: self.segments()? }))
+            }
+            Some('\'' | '"') => Ok(Operand::Literal(JsonValue::String(self.string()?))),
+            Some('-' | '0'..='9') => self.number().map(Operand::Literal),
+            Some('a'..='z') => {
+                let start = self.pos;
+                while let Some('a'..='z' | '0'..='9' | '_') = self.peek() {
+                    self.pos += 1;
+                }
+                match &self.query[start..self.pos] {
+                    "true" => Ok(Operand::Literal(JsonValue::Boolean(true))),
+                    "false" => Ok(Operand::Literal(JsonValue::Boolean(false))),
+                    "null" => Ok(Operand::Literal(JsonValue::Null)),
+                    name => {
+                        let name = name.to_string();
+                        self.function(&name, start).map(Operand::Function)
+                    }
+                }
+            }
+            _ => Err(self.error("expected a query, literal or function")),
+        }
+    }
+
+    /// A number literal, with JSON's grammar except that `-0` is allowed.
+    fn number(&mut self) -> ParseResult<JsonValue> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let from = parser.pos;
+            while let Some('0'..='9') = parser.peek() {
+                parser.pos += 1;
+            }
+            parser.pos > from
+        };
+        self.eat("-");
+        let int = self.pos;
+        if !digits(self) {
+            return Err(self.error("expected a digit"));
+        }
+        if self.query[int..self.pos].starts_with('0') && self.pos - int > 1 {
+            return Err(self.error_at(start, "numbers can't have leading zeros"));
+        }
+        if self.eat(".") && !digits(self) {
+            return Err(self.error("expected a digit"));
+        }
+        if self.eat("e") || self.eat("E") {
+            let _ = self.eat("+") || self.eat("-");
+            if !digits(self) {
+                return Err(self.error("expected a digit"));
+            }
+        }
+        let text = &self.query[start..self.pos];
+        let value = text.parse::<f64>().expect("a valid number");
+        Ok(JsonValue::Number(JsonNumber::from_f64(value)))
+    }
+
+    /// The arguments of the function `name`, whose name has been read, and
+    /// their types checked against its parameters.
+    fn function(&mut self, name: &str, start: usize) -> ParseResult<Function> {
+        if !self.eat("(") {
+            return Err(self.error_at(start, format!("unknown name `{}`", name)));
+        }
+        let mut arguments = Vec::new();
+        self.skip_blanks();
+        if !self.eat(")") {
+            loop {
+                self.skip_blanks();
+                let at = self.pos;
+                arguments.push((self.operand()?, at));
+                self.skip_blanks();
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+
+        let arity = match name {
+            "length" | "count" | "value" => 1,
+            "match" | "search" => 2,
+            _ => return Err(self.error_at(start, format!("unknown function `{}`", name))),
+        };
+        if arguments.len() != arity {
+            let reason = format!("`{}` takes {} argument{}", name, arity, if arity == 1 { "" } else { "s" });
+            return Err(self.error_at(start, reason));
+        }
+        let mut arguments = arguments.into_iter();
+        let (first, at) = arguments.next().expect("an argument");
+        Ok(match name {
+            "length" => Function::Length(self.comparable(first, at)?),
+            "count" | "value" => {
+                let Operand::Query(query) = first else {
+                    return Err(self.error_at(at, format!("`{}` takes a query", name)));
+                };
+                if name == "count" { Function::Count(query) } else { Function::Value(query) }
+            }
+            _ => {
+                let whole = name == "match";
+                let value = self.comparable(first, at)?;
+                let (pattern, at) = arguments.next().expect("a pattern");
+                let pattern = match pattern {
+                    Operand::Literal(JsonValue::String(pattern)) => compile_pattern(&pattern, whole)
+                        .map(Pattern::Literal)
+                        .ok_or_else(|| self.error_at(at, format!("invalid regular expression {:?}", pattern)))?,
+                    pattern => Pattern::Dynamic(self.comparable(pattern, at)?),
+                };
+                if whole { Function::Match(value, pattern) } else { Function::Search(value, pattern) }
+            }
+        })
+    }
+}
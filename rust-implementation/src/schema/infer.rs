@@ -0,0 +1,200 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use crate::types::JsonValue;
+
+/// Distinct strings a field may take and still be described as an `enum`,
+/// unless [`SchemaBuilder::max_enum_values`] says otherwise.
+pub const DEFAULT_MAX_ENUM_VALUES: usize = 10;
+
+const DRAFT_07: &str = "http://json-schema.org/draft-07/schema#";
+
+/// Infers a draft-07 JSON Schema describing every document in `values`:
+/// shorthand for feeding them all to a [`SchemaBuilder`].
+pub fn infer<I>(values: I) -> JsonValue
+where
+    I: IntoIterator,
+    I::Item: Borrow<JsonValue>,
+{
+    let mut builder = SchemaBuilder::new();
+    for value in values {
+        builder.add(value.borrow());
+    }
+    builder.to_schema()
+}
+
+/// Accumulates what sample documents look like, one at a time, so a feed
+/// of any length can be described in one pass, then writes it out as a
+/// draft-07 JSON Schema.
+///
+/// The schema lists the types seen at each place, the properties of
+/// objects (`required` when every object had them) and the items of arrays.
+/// Strings that only ever take a few distinct values, each seen more than
+/// once on average, become an `enum`.
+#[derive(Debug, Clone)]
+pub struct SchemaBuilder {
+    root: Shape,
+    max_enum_values: usize,
+}
+
+impl Default for SchemaBuilder {
+    fn default() -> Self {
+        Self { root: Shape::default(), max_enum_values: DEFAULT_MAX_ENUM_VALUES }
+    }
+}
+
+impl SchemaBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Most distinct strings described as an `enum`; 0 turns enums off.
+    pub fn max_enum_values(mut self, max: usize) -> Self {
+        self.max_enum_values = max;
+        self
+    }
+
+    pub fn add(&mut self, value: &JsonValue) {
+        self.root.observe(value, self.max_enum_values);
+    }
+
+    /// Documents added so far.
+    pub fn samples(&self) -> usize {
+        self.root.count
+    }
+
+    pub fn to_schema(&self) -> JsonValue {
+        let mut schema = self.root.to_schema();
+        if let JsonValue::Object(map) = &mut schema {
+            map.insert("$schema".into(), JsonValue::from(DRAFT_07));
+        }
+        schema
+    }
+}
+
+/// Everything seen at one place in the samples.
+#[derive(Debug, Clone, Default)]
+struct Shape {
+    count: usize,
+    nulls: usize,
+    booleans: usize,
+    integers: usize,
+    /// Numbers with a fractional part.
+    fractions: usize,
+    strings: usize,
+    /// Distinct strings seen, until there are too many to be an enum.
+    string_values: Option<BTreeSet<String>>,
+    arrays: usize,
+    items: Option<Box<Shape>>,
+    objects: usize,
+    properties: BTreeMap<String, Shape>,
+}
+
+impl Shape {
+    fn observe(&mut self, value: &JsonValue, max_enum_values: usize) {
+        self.count += 1;
+        match value {
+            JsonValue::Null => self.nulls += 1,
+            JsonValue::Boolean(_) => self.booleans += 1,
+            JsonValue::Number(n) if n.as_f64().fract() == 0.0 => self.integers += 1,
+            JsonValue::Number(_) => self.fractions += 1,
+            JsonValue::String(s) => {
+                if self.strings == 0 {
+                    self.string_values = Some(BTreeSet::new());
+                }
+                self.strings += 1;
+                if let Some(values) = &mut self.string_values {
+                    values.insert(s.clone());
+                    if values.len() > max_enum_values {
+                        self.string_values = None;
+                    }
+                }
+            }
+            JsonValue::Array(items) => {
+                self.arrays += 1;
+                let shape = self.items.get_or_insert_with(Default::default);
+                for item in items {
+                    shape.observe(item, max_enum_values);
+                }
+            }
+            JsonValue::Object(map) => {
+                self.objects += 1;
+                for (key, value) in map {
+                    self.properties.entry(key.to_string()).or_default().observe(value, max_enum_values);
+                }
+            }
+        }
+    }
+
+    fn types(&self) -> Vec<&'static str> {
+        let mut types = Vec::new();
+        if self.objects > 0 {
+            types.push("object");
+        }
+        if self.arrays > 0 {
+            types.push("array");
+        }
+        if self.strings > 0 {
+            types.push("string");
+        }
+        // Integers are numbers too, so a mix is just `number`.
+        if self.fractions > 0 {
+            types.push("number");
+        } else if self.integers > 0 {
+            types.push("integer");
+        }
+        if self.booleans > 0 {
+            types.push("boolean");
+        }
+        if self.nulls > 0 {
+            types.push("null");
+        }
+        types
+    }
+
+    fn to_schema(&self) -> JsonValue {
+        let mut schema: Vec<(&str, JsonValue)> = Vec::new();
+        let types = self.types();
+        match types.as_slice() {
+            [] => {}
+            [single] => schema.push(("type", JsonValue::from(*single))),
+            types => schema.push(("type", types.iter().map(|&t| JsonValue::from(t)).collect())),
+        }
+
+        if self.objects > 0 {
+            let properties = self
+                .properties
+                .iter()
+                .map(|(key, shape)| (key.as_str(), shape.to_schema()))
+                .collect();
+            schema.push(("properties", properties));
+            let required: Vec<JsonValue> = self
+                .properties
+                .iter()
+                .filter(|(_, shape)| shape.count == self.objects)
+                .map(|(key, _)| JsonValue::from(key.as_str()))
+                .collect();
+            if !required.is_empty() {
+                schema.push(("required", JsonValue::Array(required)));
+            }
+        }
+
+        if let Some(items) = self.items.as_ref().filter(|items| items.count > 0) {
+            schema.push(("items", items.to_schema()));
+        }
+
+        // An enum restricts every value, so it only fits strings and nulls,
+        // and only when values repeat enough to look like a closed set.
+        let only_strings = self.strings + self.nulls == self.count;
+        if let Some(values) = self.string_values.as_ref().filter(|_| only_strings) {
+            if !values.is_empty() && self.strings >= 2 * values.len() {
+                let mut values: Vec<JsonValue> = values.iter().map(|s| JsonValue::from(s.as_str())).collect();
+                if self.nulls > 0 {
+                    values.push(JsonValue::Null);
+                }
+                schema.push(("enum", JsonValue::Array(values)));
+            }
+        }
+
+        schema.into_iter().collect()
+    }
+}
@@ -0,0 +1,9 @@
+//! [JSON Schema](https://json-schema.org) (draft-07) support.
+
+pub mod infer;
+#[cfg(feature = "schema")]
+pub mod validate;
+
+pub use infer::{infer, SchemaBuilder, DEFAULT_MAX_ENUM_VALUES};
+#[cfg(feature = "schema")]
+pub use validate::{Validator, Violation};
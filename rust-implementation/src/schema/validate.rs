@@ -0,0 +1,378 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use regex::Regex;
+use crate::path::{JsonPointer, Path, PathSegment};
+use crate::types::{JsonValue, ParseError, ParseResult};
+
+/// A compiled JSON Schema, checked once and then used to validate any number
+/// of documents.
+///
+/// Supports the core of draft-07: `type`, `properties`, `required`,
+/// `items` (one schema or a tuple of them), `enum`, `minimum`, `maximum`,
+/// `exclusiveMinimum`, `exclusiveMaximum`, `pattern`, boolean schemas, and
+/// `$ref` to JSON Pointers within the schema itself (`#/definitions/port`).
+/// Other keywords are ignored.
+///
+/// `pattern` is compiled with the [`regex`] crate, whose syntax differs from
+/// the ECMA-262 regular expressions the spec calls for: lookaround and
+/// backreferences aren't supported, so a schema using them fails to compile.
+#[derive(Debug, Clone)]
+pub struct Validator {
+    nodes: Vec<Node>,
+}
+
+/// One way a document breaks its schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Where in the document, as in `/servers/0/port`; empty for the root.
+    pub pointer: JsonPointer,
+    /// The schema keyword that failed, such as `required`.
+    pub keyword: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.pointer.path().is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.pointer, self.message)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Null,
+    Boolean,
+    Object,
+    Array,
+    Number,
+    Integer,
+    String,
+}
+
+impl Type {
+    fn parse(name: &str) -> Option<Type> {
+        Some(match name {
+            "null" => Type::Null,
+            "boolean" => Type::Boolean,
+            "object" => Type::Object,
+            "array" => Type::Array,
+            "number" => Type::Number,
+            "integer" => Type::Integer,
+            "string" => Type::String,
+            _ => return None,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Null => "null",
+            Type::Boolean => "boolean",
+            Type::Object => "object",
+            Type::Array => "array",
+            Type::Number => "number",
+            Type::Integer => "integer",
+            Type::String => "string",
+        }
+    }
+
+    fn matches(self, value: &JsonValue) -> bool {
+        match (self, value) {
+            (Type::Null, JsonValue::Null)
+            | (Type::Boolean, JsonValue::Boolean(_))
+            | (Type::Object, JsonValue::Object(_))
+            | (Type::Array, JsonValue::Array(_))
+            | (Type::Number, JsonValue::Number(_))
+            | (Type::String, JsonValue::String(_)) => true,
+            (Type::Integer, JsonValue::Number(n)) => n.as_f64().fract() == 0.0,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Items {
+    Each(usize),
+    Tuple(Vec<usize>),
+}
+
+/// A compiled schema; subschemas are indices into `Validator::nodes`.
+#[derive(Debug, Clone, Default)]
+struct Node {
+    /// `false`, which nothing matches.
+    reject: bool,
+    /// Draft-07 ignores every other keyword next to `$ref`.
+    reference: Option<usize>,
+    types: Option<Vec<Type>>,
+    properties: Vec<(String, usize)>,
+    required: Vec<String>,
+    items: Option<Items>,
+    values: Option<Vec<JsonValue>>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive_minimum: Option<f64>,
+    exclusive_maximum: Option<f64>,
+    pattern: Option<Regex>,
+}
+
+fn invalid(path: &Path, reason: impl Into<String>) -> ParseError {
+    ParseError::InvalidSchema { path: path.to_pointer(), reason: reason.into() }
+}
+
+struct Compiler<'a> {
+    root: &'a JsonValue,
+    nodes: Vec<Node>,
+    /// Nodes by the JSON Pointer of their schema, so that `$ref`s to one
+    /// place share a node and recursive schemas terminate.
+    compiled: HashMap<String, usize>,
+}
+
+impl<'a> Compiler<'a> {
+    fn compile(&mut self, schema: &'a JsonValue, path: &Path) -> ParseResult<usize> {
+        let pointer = path.to_pointer();
+        if let Some(&id) = self.compiled.get(&pointer) {
+            return Ok(id);
+        }
+        let id = self.nodes.len();
+        self.nodes.push(Node::default());
+        self.compiled.insert(pointer, id);
+        self.nodes[id] = self.compile_node(schema, path)?;
+        Ok(id)
+    }
+
+    fn child(
+        &mut self,
+        schema: &'a JsonValue,
+        path: &Path,
+        segment: impl Into<PathSegment>,
+    ) -> ParseResult<usize> {
+        let mut path = path.clone();
+        path.push(segment);
+        self.compile(schema, &path)
+    }
+
+    fn compile_node(&mut self, schema: &'a JsonValue, path: &Path) -> ParseResult<Node> {
+        let object = match schema {
+            JsonValue::Boolean(accept) => return Ok(Node { reject: !accept, ..Node::default() }),
+            JsonValue::Object(object) => object,
+            other => {
+                return Err(invalid(path, format!("expected a schema, found {}", other.type_name())))
+            }
+        };
+        let mut node = Node::default();
+        let keyword = |name: &str| {
+            let mut path = path.clone();
+            path.push(name);
+            path
+        };
+        let malformed = |name: &str, reason: &str| invalid(&keyword(name), reason);
+
+        if let Some(reference) = object.get("$ref") {
+            let reference = reference.as_str().ok_or_else(|| malformed("$ref", "expected a string"))?;
+            let target = reference
+                .strip_prefix('#')
+                .and_then(|pointer| Path::from_pointer(pointer).ok())
+                .ok_or_else(|| {
+                    let reason = format!("only references within the schema are supported, not {:?}", reference);
+                    invalid(&keyword("$ref"), reason)
+                })?;
+            let schema = self
+                .root
+                .get_path(&target)
+                .map_err(|_| invalid(&keyword("$ref"), format!("{:?} points to nothing", reference)))?;
+            node.reference = Some(self.compile(schema, &target)?);
+            return Ok(node);
+        }
+
+        if let Some(types) = object.get("type") {
+            let names: Vec<&JsonValue> = match types {
+                JsonValue::Array(names) => names.iter().collect(),
+                name => vec![name],
+            };
+            let types = names
+                .into_iter()
+                .map(|name| name.as_str().and_then(Type::parse))
+                .collect::<Option<Vec<Type>>>()
+                .ok_or_else(|| malformed("type", "expected JSON type names"))?;
+            node.types = Some(types);
+        }
+
+        if let Some(properties) = object.get("properties") {
+            let properties = properties.as_object().ok_or_else(|| malformed("properties", "expected an object"))?;
+            let path = keyword("properties");
+            for (name, schema) in properties {
+                let id = self.child(schema, &path, name.to_string())?;
+                node.properties.push((name.to_string(), id));
+            }
+        }
+
+        if let Some(required) = object.get("required") {
+            node.required = required
+                .as_array()
+                .and_then(|names| names.iter().map(|name| name.as_str().map(str::to_string)).collect())
+                .ok_or_else(|| malformed("required", "expected an array of strings"))?;
+        }
+
+        if let Some(items) = object.get("items") {
+            let path = keyword("items");
+            node.items = Some(match items {
+                JsonValue::Array(schemas) => Items::Tuple(
+                    schemas
+                        .iter()
+                        .enumerate()
+                        .map(|(index, schema)| self.child(schema, &path, index))
+                        .collect::<ParseResult<_>>()?,
+                ),
+                schema => Items::Each(self.compile(schema, &path)?),
+            });
+        }
+
+        if let Some(values) = object.get("enum") {
+            let values = values.as_array().ok_or_else(|| malformed("enum", "expected an array"))?;
+            node.values = Some(values.clone());
+        }
+
+        let bound = |name: &str| -> ParseResult<Option<f64>> {
+            object
+                .get(name)
+                .map(|bound| bound.as_f64().ok_or_else(|| malformed(name, "expected a number")))
+                .transpose()
+        };
+        node.minimum = bound("minimum")?;
+        node.maximum = bound("maximum")?;
+        node.exclusive_minimum = bound("exclusiveMinimum")?;
+        node.exclusive_maximum = bound("exclusiveMaximum")?;
+
+        if let Some(pattern) = object.get("pattern") {
+            let pattern = pattern.as_str().ok_or_else(|| malformed("pattern", "expected a string"))?;
+            let regex = Regex::new(pattern).map_err(|error| malformed("pattern", &error.to_string()))?;
+            node.pattern = Some(regex);
+        }
+
+        Ok(node)
+    }
+}
+
+impl Validator {
+    /// Compiles `schema`, failing with [`ParseError::InvalidSchema`] if a
+    /// supported keyword is malformed, a `$ref` can't be resolved or
+    /// references only loop back on themselves.
+    pub fn compile(schema: &JsonValue) -> ParseResult<Validator> {
+        let mut compiler = Compiler { root: schema, nodes: Vec::new(), compiled: HashMap::new() };
+        compiler.compile(schema, &Path::root())?;
+        let validator = Validator { nodes: compiler.nodes };
+
+        for start in 0..validator.nodes.len() {
+            let mut seen = HashSet::new();
+            let mut id = start;
+            while let Some(next) = validator.nodes[id].reference {
+                if !seen.insert(id) {
+                    let pointer = compiler.compiled.iter().find(|&(_, &node)| node == start);
+                    return Err(ParseError::InvalidSchema {
+                        path: pointer.map(|(pointer, _)| pointer.clone()).unwrap_or_default(),
+                        reason: "$ref refers back to itself".to_string(),
+                    });
+                }
+                id = next;
+            }
+        }
+        Ok(validator)
+    }
+
+    /// Every way `value` breaks the schema; empty if it is valid.
+    pub fn validate(&self, value: &JsonValue) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(0, value, &mut Path::root(), &mut violations);
+        violations
+    }
+
+    pub fn is_valid(&self, value: &JsonValue) -> bool {
+        self.validate(value).is_empty()
+    }
+
+    fn check(&self, id: usize, value: &JsonValue, path: &mut Path, out: &mut Vec<Violation>) {
+        let node = &self.nodes[id];
+        let mut fail = |keyword: &'static str, message: String| {
+            out.push(Violation { pointer: JsonPointer::from(path.clone()), keyword, message });
+        };
+
+        if node.reject {
+            fail("false", "no value is allowed here".to_string());
+            return;
+        }
+        if let Some(target) = node.reference {
+            self.check(target, value, path, out);
+            return;
+        }
+
+        if let Some(types) = &node.types {
+            if !types.iter().any(|t| t.matches(value)) {
+                let expected: Vec<&str> = types.iter().map(|t| t.name()).collect();
+                fail("type", format!("expected {}, found {}", expected.join(" or "), value.type_name()));
+                // The other keywords would only restate the mismatch.
+                return;
+            }
+        }
+
+        if let Some(values) = &node.values {
+            if !values.contains(value) {
+                let allowed: Vec<String> = values.iter().map(JsonValue::to_string).collect();
+                fail("enum", format!("{} is not one of {}", value, allowed.join(", ")));
+            }
+        }
+
+        if let JsonValue::Number(n) = value {
+            let n = n.as_f64();
+            if let Some(minimum) = node.minimum.filter(|&minimum| n < minimum) {
+                fail("minimum", format!("{} is less than the minimum of {}", value, minimum));
+            }
+            if let Some(maximum) = node.maximum.filter(|&maximum| n > maximum) {
+                fail("maximum", format!("{} is greater than the maximum of {}", value, maximum));
+            }
+            if let Some(minimum) = node.exclusive_minimum.filter(|&minimum| n <= minimum) {
+                fail("exclusiveMinimum", format!("{} is not greater than {}", value, minimum));
+            }
+            if let Some(maximum) = node.exclusive_maximum.filter(|&maximum| n >= maximum) {
+                fail("exclusiveMaximum", format!("{} is not less than {}", value, maximum));
+            }
+        }
+
+        if let (JsonValue::String(s), Some(pattern)) = (value, &node.pattern) {
+            if !pattern.is_match(s) {
+                fail("pattern", format!("{:?} does not match the pattern {:?}", s, pattern.as_str()));
+            }
+        }
+
+        if let JsonValue::Object(object) = value {
+            for name in &node.required {
+                if !object.contains_key(name.as_str()) {
+                    fail("required", format!("missing required property {:?}", name));
+                }
+            }
+            for (name, id) in &node.properties {
+                if let Some(member) = object.get(name.as_str()) {
+                    path.push(name.as_str());
+                    self.check(*id, member, path, out);
+                    path.pop();
+                }
+            }
+        }
+
+        if let (JsonValue::Array(items), Some(schemas)) = (value, &node.items) {
+            for (index, item) in items.iter().enumerate() {
+                let id = match schemas {
+                    Items::Each(id) => *id,
+                    Items::Tuple(ids) => match ids.get(index) {
+                        Some(id) => *id,
+                        None => break,
+                    },
+                };
+                path.push(index);
+                self.check(id, item, path, out);
+                path.pop();
+            }
+        }
+    }
+}
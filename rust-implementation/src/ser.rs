@@ -0,0 +1,803 @@
+use std::fmt::{self, Write};
+use std::io;
+use thiserror::Error;
+use crate::number::{JsonNumber, NumberFormat};
+use crate::types::{JsonValue, Key};
+
+pub(crate) const STRING_WRITE: &str = "writing to a String cannot fail";
+
+/// A value that can't be written in the requested form.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SerializeError {
+    #[error("Non-finite number {0} has no canonical JSON form")]
+    NonFiniteNumber(String),
+}
+
+/// Serializes `value` as compact JSON.
+pub fn to_string(value: &JsonValue) -> String {
+    to_string_with(value, &mut CompactFormatter)
+}
+
+/// Serializes `value` with two-space indentation and one member or element
+/// per line.
+pub fn to_string_pretty(value: &JsonValue) -> String {
+    to_string_with(value, &mut PrettyFormatter::new())
+}
+
+pub fn to_string_with<F: Formatter + ?Sized>(value: &JsonValue, formatter: &mut F) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, formatter).expect(STRING_WRITE);
+    out
+}
+
+/// Writes compact JSON straight to `writer`. Writes are not buffered, so wrap
+/// files and sockets in a `BufWriter`.
+pub fn to_writer<W: io::Write>(writer: W, value: &JsonValue) -> io::Result<()> {
+    to_writer_with(writer, value, &mut CompactFormatter)
+}
+
+pub fn to_writer_pretty<W: io::Write>(writer: W, value: &JsonValue) -> io::Result<()> {
+    to_writer_with(writer, value, &mut PrettyFormatter::new())
+}
+
+pub fn to_writer_with<W, F>(writer: W, value: &JsonValue, formatter: &mut F) -> io::Result<()>
+where
+    W: io::Write,
+    F: Formatter + ?Sized,
+{
+    write_io(writer, |out| write_value(out, value, formatter))
+}
+
+/// Walks `value`, letting `formatter` decide how each piece is written.
+pub fn write_value<W, F>(out: &mut W, value: &JsonValue, formatter: &mut F) -> fmt::Result
+where
+    W: Write + ?Sized,
+    F: Formatter + ?Sized,
+{
+    write_node(out, value, formatter, &Settings::default(), 0)
+}
+
+/// A formatter plus the options that apply whatever the layout, for output
+/// the free functions can't describe.
+#[derive(Debug, Clone, Default)]
+pub struct Serializer<F = CompactFormatter> {
+    formatter: F,
+    settings: Settings,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Settings {
+    sort_keys: bool,
+    escape: EscapePolicy,
+    line_ending: LineEnding,
+    trailing_newline: bool,
+    max_depth: Option<usize>,
+    max_array_items: Option<usize>,
+    allow_non_finite: bool,
+    number_format: NumberFormat,
+}
+
+/// The line terminator written wherever the formatter breaks a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Serializer<PrettyFormatter> {
+    pub fn pretty() -> Self {
+        Self::with_formatter(PrettyFormatter::new())
+    }
+}
+
+impl<F: Formatter> Serializer<F> {
+    pub fn with_formatter(formatter: F) -> Self {
+        Self {
+            formatter,
+            settings: Settings::default(),
+        }
+    }
+
+    /// Emit object members in lexicographic key order, whatever order the
+    /// map iterates in.
+    pub fn sort_keys(mut self, sort: bool) -> Self {
+        self.settings.sort_keys = sort;
+        self
+    }
+
+    /// Escape every non-ASCII character as `\uXXXX`, like Python's
+    /// `ensure_ascii=True`.
+    pub fn ensure_ascii(mut self, ascii: bool) -> Self {
+        self.settings.escape = self.settings.escape.ascii_only(ascii);
+        self
+    }
+
+    pub fn escape_policy(mut self, policy: EscapePolicy) -> Self {
+        self.settings.escape = policy;
+        self
+    }
+
+    pub fn line_ending(mut self, ending: LineEnding) -> Self {
+        self.settings.line_ending = ending;
+        self
+    }
+
+    /// End the document with a line break, as most editors expect of files.
+    pub fn trailing_newline(mut self, newline: bool) -> Self {
+        self.settings.trailing_newline = newline;
+        self
+    }
+
+    /// Write `NaN`, `Infinity` and `-Infinity` as those literals, which only
+    /// lenient parsers accept. By default they are written as `null`.
+    pub fn allow_non_finite(mut self, allow: bool) -> Self {
+        self.settings.allow_non_finite = allow;
+        self
+    }
+
+    /// How numbers are written. With [`NumberFormat::Precision`], every
+    /// number is rounded, whatever source text it kept; by default numbers
+    /// are written as they were read.
+    pub fn number_format(mut self, format: NumberFormat) -> Self {
+        self.settings.number_format = format;
+        self
+    }
+
+    /// Show only the outermost `depth` levels of containers; anything deeper
+    /// is elided as `{… 3 members}` or `[… 12 items]`. The output is then no
+    /// longer JSON, so this is meant for logs and previews.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.settings.max_depth = Some(depth);
+        self
+    }
+
+    /// Write at most `items` elements of each array, followed by a
+    /// `… 97 more` marker for the rest. Like `max_depth`, for previews only.
+    pub fn max_array_items(mut self, items: usize) -> Self {
+        self.settings.max_array_items = Some(items);
+        self
+    }
+
+    pub fn write<W: Write + ?Sized>(&mut self, out: &mut W, value: &JsonValue) -> fmt::Result {
+        match self.settings.line_ending {
+            LineEnding::Lf => self.write_document(out, value),
+            LineEnding::CrLf => self.write_document(&mut CrLf(out), value),
+        }
+    }
+
+    fn write_document<W: Write + ?Sized>(&mut self, out: &mut W, value: &JsonValue) -> fmt::Result {
+        write_node(out, value, &mut self.formatter, &self.settings, 0)?;
+        if self.settings.trailing_newline {
+            out.write_char('\n')?;
+        }
+        Ok(())
+    }
+
+    pub fn to_string(&mut self, value: &JsonValue) -> String {
+        let mut out = String::new();
+        self.write(&mut out, value).expect(STRING_WRITE);
+        out
+    }
+
+    pub fn to_writer<W: io::Write>(&mut self, writer: W, value: &JsonValue) -> io::Result<()> {
+        write_io(writer, |out| self.write(out, value))
+    }
+}
+
+/// Rewrites line breaks as CRLF. Strings escape their own newlines, so every
+/// raw `\n` in serializer output is a formatter line break.
+struct CrLf<'a, W: ?Sized>(&'a mut W);
+
+impl<W: Write + ?Sized> Write for CrLf<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut lines = s.split('\n');
+        if let Some(first) = lines.next() {
+            self.0.write_str(first)?;
+        }
+        for line in lines {
+            self.0.write_str("\r\n")?;
+            self.0.write_str(line)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_io<W: io::Write>(
+    writer: W,
+    write: impl FnOnce(&mut IoAdapter<W>) -> fmt::Result,
+) -> io::Result<()> {
+    let mut adapter = IoAdapter::new(writer);
+    let result = write(&mut adapter);
+    adapter.check(result)
+}
+
+/// Lets the `fmt::Write`-based serializer target an `io::Write`, keeping the
+/// underlying I/O error that `fmt::Error` can't carry.
+pub(crate) struct IoAdapter<W> {
+    pub(crate) inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W> IoAdapter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self { inner, error: None }
+    }
+
+    /// Turns the result of a write through this adapter back into an I/O result.
+    pub(crate) fn check(&mut self, result: fmt::Result) -> io::Result<()> {
+        result.map_err(|fmt::Error| {
+            self.error
+                .take()
+                .unwrap_or_else(|| io::Error::other("formatter error"))
+        })
+    }
+}
+
+impl<W: io::Write> Write for IoAdapter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+fn write_node<W, F>(
+    out: &mut W,
+    value: &JsonValue,
+    formatter: &mut F,
+    settings: &Settings,
+    depth: usize,
+) -> fmt::Result
+where
+    W: Write + ?Sized,
+    F: Formatter + ?Sized,
+{
+    if settings.max_depth.is_some_and(|max| depth >= max) {
+        match value {
+            JsonValue::Object(obj) if !obj.is_empty() => {
+                return write!(out, "{{… {}}}", plural(obj.len(), "member"))
+            }
+            JsonValue::Array(arr) if !arr.is_empty() => {
+                return write!(out, "[… {}]", plural(arr.len(), "item"))
+            }
+            _ => {}
+        }
+    }
+
+    match value {
+        JsonValue::String(s) => formatter.write_string(out, s, settings.escape),
+        // JSON has no literal for these, so they are written as null unless asked.
+        JsonValue::Number(n) if !n.is_finite() && !settings.allow_non_finite => formatter.write_null(out),
+        JsonValue::Number(n) => formatter.write_number(out, &n.formatted(settings.number_format)),
+        JsonValue::Boolean(b) => formatter.write_bool(out, *b),
+        JsonValue::Null => formatter.write_null(out),
+        JsonValue::Object(obj) if settings.sort_keys => {
+            let mut members: Vec<_> = obj.iter().collect();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+            write_object(out, value, members, formatter, settings, depth)
+        }
+        JsonValue::Object(obj) => write_object(out, value, obj, formatter, settings, depth),
+        JsonValue::Array(arr) => {
+            let shown = settings.max_array_items.map_or(arr.len(), |max| max.min(arr.len()));
+            before_container(value, formatter, settings, depth);
+            formatter.begin_array(out)?;
+            for (i, value) in arr[..shown].iter().enumerate() {
+                formatter.begin_array_value(out, i == 0)?;
+                write_node(out, value, formatter, settings, depth + 1)?;
+                formatter.end_array_value(out)?;
+            }
+            if shown < arr.len() {
+                formatter.begin_array_value(out, shown == 0)?;
+                write!(out, "… {} more", arr.len() - shown)?;
+                formatter.end_array_value(out)?;
+            }
+            formatter.end_array(out)
+        }
+    }
+}
+
+fn write_object<'v, W, F>(
+    out: &mut W,
+    object: &JsonValue,
+    members: impl IntoIterator<Item = (&'v Key, &'v JsonValue)>,
+    formatter: &mut F,
+    settings: &Settings,
+    depth: usize,
+) -> fmt::Result
+where
+    W: Write + ?Sized,
+    F: Formatter + ?Sized,
+{
+    before_container(object, formatter, settings, depth);
+    formatter.begin_object(out)?;
+    for (i, (key, value)) in members.into_iter().enumerate() {
+        formatter.begin_object_key(out, i == 0)?;
+        formatter.write_key(out, key, settings.escape)?;
+        formatter.begin_object_value(out)?;
+        write_node(out, value, formatter, settings, depth + 1)?;
+        formatter.end_object_value(out)?;
+    }
+    formatter.end_object(out)
+}
+
+/// Tells `formatter` whether `container` fits on one line, if it asks,
+/// measuring it as it would be written with `settings`.
+fn before_container<F>(container: &JsonValue, formatter: &mut F, settings: &Settings, depth: usize)
+where
+    F: Formatter + ?Sized,
+{
+    let fits = formatter.inline_limit().is_some_and(|max| {
+        write_node(&mut LengthLimit { remaining: max }, container, &mut InlineFormatter, settings, depth).is_ok()
+    });
+    formatter.before_container(fits);
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// Controls the text emitted for each part of a document. Every method has a
+/// compact default, so implementors override only the pieces they restyle;
+/// string escaping stays with [`write_escaped`] unless `write_string` is replaced.
+pub trait Formatter {
+    fn write_null<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        out.write_str("null")
+    }
+
+    fn write_bool<W: Write + ?Sized>(&mut self, out: &mut W, value: bool) -> fmt::Result {
+        out.write_str(if value { "true" } else { "false" })
+    }
+
+    fn write_number<W: Write + ?Sized>(&mut self, out: &mut W, value: &JsonNumber) -> fmt::Result {
+        write!(out, "{}", value)
+    }
+
+    fn write_string<W: Write + ?Sized>(&mut self, out: &mut W, value: &str, escape: EscapePolicy) -> fmt::Result {
+        write_escaped(out, value, escape)
+    }
+
+    fn write_key<W: Write + ?Sized>(&mut self, out: &mut W, key: &str, escape: EscapePolicy) -> fmt::Result {
+        self.write_string(out, key, escape)
+    }
+
+    /// The most characters a container may take on one line for
+    /// [`before_container`](Formatter::before_container) to be told it
+    /// fits; `None`, the default, for layouts that don't depend on size.
+    fn inline_limit(&self) -> Option<usize> {
+        None
+    }
+
+    /// Called with each object or array just before it is opened, with
+    /// whether its single-line form, written with the serializer's
+    /// settings, fits in [`inline_limit`](Formatter::inline_limit).
+    fn before_container(&mut self, _fits_inline: bool) {}
+
+    fn begin_object<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        out.write_char('{')
+    }
+
+    fn end_object<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        out.write_char('}')
+    }
+
+    fn begin_object_key<W: Write + ?Sized>(&mut self, out: &mut W, first: bool) -> fmt::Result {
+        if first {
+            Ok(())
+        } else {
+            out.write_char(',')
+        }
+    }
+
+    /// Written between a key and its value.
+    fn begin_object_value<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        out.write_char(':')
+    }
+
+    fn end_object_value<W: Write + ?Sized>(&mut self, _out: &mut W) -> fmt::Result {
+        Ok(())
+    }
+
+    fn begin_array<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        out.write_char('[')
+    }
+
+    fn end_array<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        out.write_char(']')
+    }
+
+    fn begin_array_value<W: Write + ?Sized>(&mut self, out: &mut W, first: bool) -> fmt::Result {
+        if first {
+            Ok(())
+        } else {
+            out.write_char(',')
+        }
+    }
+
+    fn end_array_value<W: Write + ?Sized>(&mut self, _out: &mut W) -> fmt::Result {
+        Ok(())
+    }
+}
+
+/// No whitespace at all.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactFormatter;
+
+impl Formatter for CompactFormatter {}
+
+/// The unit of indentation used by [`PrettyFormatter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Spaces(usize),
+    Tabs,
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        IndentStyle::Spaces(2)
+    }
+}
+
+impl IndentStyle {
+    fn unit(self) -> String {
+        match self {
+            IndentStyle::Spaces(width) => " ".repeat(width),
+            IndentStyle::Tabs => "\t".to_string(),
+        }
+    }
+}
+
+/// One member or element per line, indented by nesting depth.
+#[derive(Debug, Clone)]
+pub struct PrettyFormatter {
+    indent: String,
+    max_inline_length: usize,
+    depth: usize,
+    has_value: bool,
+    pending_inline: bool,
+    // Depth of the outermost container currently being written on one line.
+    inline_from: Option<usize>,
+}
+
+impl Default for PrettyFormatter {
+    fn default() -> Self {
+        Self::with_indent(IndentStyle::default().unit())
+    }
+}
+
+impl PrettyFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_indent(indent: impl Into<String>) -> Self {
+        Self {
+            indent: indent.into(),
+            max_inline_length: 0,
+            depth: 0,
+            has_value: false,
+            pending_inline: false,
+            inline_from: None,
+        }
+    }
+
+    pub fn indent_style(mut self, style: IndentStyle) -> Self {
+        self.indent = style.unit();
+        self
+    }
+
+    /// Keep any object or array whose single-line form (`[1, 2]`,
+    /// `{"a": 1}`) is at most `max` characters on one line. `0`, the
+    /// default, always expands.
+    pub fn max_inline_length(mut self, max: usize) -> Self {
+        self.max_inline_length = max;
+        self
+    }
+
+    fn write_indent<W: Write + ?Sized>(&self, out: &mut W) -> fmt::Result {
+        (0..self.depth).try_for_each(|_| out.write_str(&self.indent))
+    }
+
+    fn begin<W: Write + ?Sized>(&mut self, out: &mut W, open: char) -> fmt::Result {
+        if self.pending_inline {
+            self.pending_inline = false;
+            self.inline_from = Some(self.depth);
+        }
+        self.depth += 1;
+        self.has_value = false;
+        out.write_char(open)
+    }
+
+    fn end<W: Write + ?Sized>(&mut self, out: &mut W, close: char) -> fmt::Result {
+        self.depth -= 1;
+        match self.inline_from {
+            Some(depth) if depth == self.depth => self.inline_from = None,
+            Some(_) => {}
+            None if self.has_value => {
+                out.write_char('\n')?;
+                self.write_indent(out)?;
+            }
+            None => {}
+        }
+        out.write_char(close)
+    }
+
+    fn begin_entry<W: Write + ?Sized>(&mut self, out: &mut W, first: bool) -> fmt::Result {
+        if self.inline_from.is_some() {
+            return out.write_str(if first { "" } else { ", " });
+        }
+        out.write_str(if first { "\n" } else { ",\n" })?;
+        self.write_indent(out)
+    }
+}
+
+/// The single-line layout `PrettyFormatter` uses for small containers.
+struct InlineFormatter;
+
+impl Formatter for InlineFormatter {
+    fn begin_object_key<W: Write + ?Sized>(&mut self, out: &mut W, first: bool) -> fmt::Result {
+        out.write_str(if first { "" } else { ", " })
+    }
+
+    fn begin_object_value<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        out.write_str(": ")
+    }
+
+    fn begin_array_value<W: Write + ?Sized>(&mut self, out: &mut W, first: bool) -> fmt::Result {
+        out.write_str(if first { "" } else { ", " })
+    }
+}
+
+/// Counts characters written, failing as soon as `remaining` runs out.
+struct LengthLimit {
+    remaining: usize,
+}
+
+impl Write for LengthLimit {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.remaining = self.remaining.checked_sub(s.chars().count()).ok_or(fmt::Error)?;
+        Ok(())
+    }
+}
+
+impl Formatter for PrettyFormatter {
+    fn inline_limit(&self) -> Option<usize> {
+        (self.max_inline_length > 0 && self.inline_from.is_none()).then_some(self.max_inline_length)
+    }
+
+    fn before_container(&mut self, fits_inline: bool) {
+        if self.inline_from.is_none() {
+            self.pending_inline = fits_inline;
+        }
+    }
+
+    fn begin_object<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        self.begin(out, '{')
+    }
+
+    fn end_object<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        self.end(out, '}')
+    }
+
+    fn begin_object_key<W: Write + ?Sized>(&mut self, out: &mut W, first: bool) -> fmt::Result {
+        self.begin_entry(out, first)
+    }
+
+    fn begin_object_value<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        out.write_str(": ")
+    }
+
+    fn end_object_value<W: Write + ?Sized>(&mut self, _out: &mut W) -> fmt::Result {
+        self.has_value = true;
+        Ok(())
+    }
+
+    fn begin_array<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        self.begin(out, '[')
+    }
+
+    fn end_array<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        self.end(out, ']')
+    }
+
+    fn begin_array_value<W: Write + ?Sized>(&mut self, out: &mut W, first: bool) -> fmt::Result {
+        self.begin_entry(out, first)
+    }
+
+    fn end_array_value<W: Write + ?Sized>(&mut self, _out: &mut W) -> fmt::Result {
+        self.has_value = true;
+        Ok(())
+    }
+}
+
+/// Wraps another formatter and colors its output with ANSI escape codes:
+/// keys, strings, numbers and the `true`/`false`/`null` literals each get their
+/// own color. Intended for terminals only; the codes are not valid JSON.
+#[cfg(feature = "color")]
+#[derive(Debug, Clone, Default)]
+pub struct ColorWriter<F = PrettyFormatter> {
+    inner: F,
+}
+
+#[cfg(feature = "color")]
+impl ColorWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "color")]
+impl<F: Formatter> ColorWriter<F> {
+    const KEY: &'static str = "\x1b[1;34m";
+    const STRING: &'static str = "\x1b[32m";
+    const NUMBER: &'static str = "\x1b[36m";
+    const BOOLEAN: &'static str = "\x1b[33m";
+    const NULL: &'static str = "\x1b[90m";
+    const RESET: &'static str = "\x1b[0m";
+
+    pub fn with_formatter(inner: F) -> Self {
+        Self { inner }
+    }
+
+    fn colored<W: Write + ?Sized>(
+        out: &mut W,
+        color: &str,
+        write: impl FnOnce(&mut W) -> fmt::Result,
+    ) -> fmt::Result {
+        out.write_str(color)?;
+        write(out)?;
+        out.write_str(Self::RESET)
+    }
+}
+
+#[cfg(feature = "color")]
+impl<F: Formatter> Formatter for ColorWriter<F> {
+    fn write_null<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        Self::colored(out, Self::NULL, |out| self.inner.write_null(out))
+    }
+
+    fn write_bool<W: Write + ?Sized>(&mut self, out: &mut W, value: bool) -> fmt::Result {
+        Self::colored(out, Self::BOOLEAN, |out| self.inner.write_bool(out, value))
+    }
+
+    fn write_number<W: Write + ?Sized>(&mut self, out: &mut W, value: &JsonNumber) -> fmt::Result {
+        Self::colored(out, Self::NUMBER, |out| self.inner.write_number(out, value))
+    }
+
+    fn write_string<W: Write + ?Sized>(&mut self, out: &mut W, value: &str, escape: EscapePolicy) -> fmt::Result {
+        Self::colored(out, Self::STRING, |out| self.inner.write_string(out, value, escape))
+    }
+
+    fn write_key<W: Write + ?Sized>(&mut self, out: &mut W, key: &str, escape: EscapePolicy) -> fmt::Result {
+        Self::colored(out, Self::KEY, |out| self.inner.write_key(out, key, escape))
+    }
+
+    fn inline_limit(&self) -> Option<usize> {
+        self.inner.inline_limit()
+    }
+
+    fn before_container(&mut self, fits_inline: bool) {
+        self.inner.before_container(fits_inline)
+    }
+
+    fn begin_object<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        self.inner.begin_object(out)
+    }
+
+    fn end_object<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        self.inner.end_object(out)
+    }
+
+    fn begin_object_key<W: Write + ?Sized>(&mut self, out: &mut W, first: bool) -> fmt::Result {
+        self.inner.begin_object_key(out, first)
+    }
+
+    fn begin_object_value<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        self.inner.begin_object_value(out)
+    }
+
+    fn end_object_value<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        self.inner.end_object_value(out)
+    }
+
+    fn begin_array<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        self.inner.begin_array(out)
+    }
+
+    fn end_array<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        self.inner.end_array(out)
+    }
+
+    fn begin_array_value<W: Write + ?Sized>(&mut self, out: &mut W, first: bool) -> fmt::Result {
+        self.inner.begin_array_value(out, first)
+    }
+
+    fn end_array_value<W: Write + ?Sized>(&mut self, out: &mut W) -> fmt::Result {
+        self.inner.end_array_value(out)
+    }
+}
+
+/// Which characters [`write_escaped`] writes as escape sequences, beyond the
+/// quotes, backslashes and control characters JSON always requires.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EscapePolicy {
+    ascii_only: bool,
+    slash: bool,
+    line_separators: bool,
+}
+
+impl EscapePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Escape all non-ASCII characters, astral ones as surrogate pairs.
+    pub fn ascii_only(mut self, ascii: bool) -> Self {
+        self.ascii_only = ascii;
+        self
+    }
+
+    /// Write `/` as `\/`, so output embedded in HTML can't close a `</script>`.
+    pub fn escape_slash(mut self, escape: bool) -> Self {
+        self.slash = escape;
+        self
+    }
+
+    /// Escape U+2028 and U+2029, which are line terminators in JavaScript
+    /// before ES2019 and break `eval`-style parsers.
+    pub fn escape_line_separators(mut self, escape: bool) -> Self {
+        self.line_separators = escape;
+        self
+    }
+}
+
+/// Writes `s` as a quoted JSON string, escaping quotes, backslashes and
+/// control characters.
+pub fn write_str<W: Write + ?Sized>(out: &mut W, s: &str) -> fmt::Result {
+    write_escaped(out, s, EscapePolicy::default())
+}
+
+/// Writes `s` as a quoted JSON string, escaping according to `policy`.
+pub fn write_escaped<W: Write + ?Sized>(out: &mut W, s: &str, policy: EscapePolicy) -> fmt::Result {
+    out.write_char('"')?;
+    let mut start = 0;
+    for (i, ch) in s.char_indices() {
+        let escape = match ch {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{08}' => "\\b",
+            '\u{0C}' => "\\f",
+            '\u{00}'..='\u{1F}' => "",
+            '/' if policy.slash => "\\/",
+            '\u{2028}' | '\u{2029}' if policy.line_separators => "",
+            ch if policy.ascii_only && !ch.is_ascii() => "",
+            _ => continue,
+        };
+        out.write_str(&s[start..i])?;
+        if escape.is_empty() {
+            let mut units = [0u16; 2];
+            for unit in ch.encode_utf16(&mut units) {
+                write!(out, "\\u{:04x}", unit)?;
+            }
+        } else {
+            out.write_str(escape)?;
+        }
+        start = i + ch.len_utf8();
+    }
+    out.write_str(&s[start..])?;
+    out.write_char('"')
+}
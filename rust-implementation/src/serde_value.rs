@@ -0,0 +1,676 @@
+use std::fmt;
+use std::slice;
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{
+    self, Deserialize, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde::ser::{
+    self, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+    SerializeTupleStruct, SerializeTupleVariant, Serializer,
+};
+use crate::number::JsonNumber;
+use crate::types::{Key, Map, JsonValue, ParseError, ParseResult};
+
+/// Integral numbers go out as `i64` or `u64` when they fit and everything
+/// else as `f64`, so a `BigDecimal` loses whatever precision `f64` lacks.
+impl Serialize for JsonValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JsonValue::String(s) => serializer.serialize_str(s),
+            JsonValue::Number(n) => n.serialize(serializer),
+            JsonValue::Boolean(b) => serializer.serialize_bool(*b),
+            JsonValue::Null => serializer.serialize_unit(),
+            JsonValue::Object(map) => {
+                let mut out = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    out.serialize_entry(&**key, value)?;
+                }
+                out.end()
+            }
+            JsonValue::Array(items) => {
+                let mut out = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    out.serialize_element(item)?;
+                }
+                out.end()
+            }
+        }
+    }
+}
+
+impl Serialize for JsonNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Some(n) = self.as_i64() {
+            serializer.serialize_i64(n)
+        } else if let Some(n) = self.as_u64() {
+            serializer.serialize_u64(n)
+        } else {
+            serializer.serialize_f64(self.as_f64())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Boolean(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(n))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(n))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<JsonValue, E> {
+        Ok(JsonValue::from(n))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(s.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(s))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        JsonValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(JsonValue::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<JsonValue, A::Error> {
+        let mut map = Map::default();
+        while let Some((key, value)) = access.next_entry::<String, JsonValue>()? {
+            map.insert(Key::from(key), value);
+        }
+        Ok(JsonValue::Object(map))
+    }
+}
+
+/// Builds a `T` from a parsed value, as `serde_json::from_value` does, so
+/// documents read with this crate's parsers can land in `#[derive(Deserialize)]`
+/// structs. Strings are borrowed from `value` where `T` allows it.
+///
+/// A mismatch is a [`ParseError::TypeMismatch`] and other errors, such as a
+/// missing field, a [`ParseError::Custom`], each with the path of the value.
+pub fn from_value<'de, T: Deserialize<'de>>(value: &'de JsonValue) -> ParseResult<T> {
+    T::deserialize(value)
+}
+
+impl de::Error for ParseError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ParseError::custom(msg)
+    }
+
+    fn invalid_type(unexpected: Unexpected<'_>, expected: &dyn de::Expected) -> Self {
+        // Named like `JsonValue::type_name`, as in the other mismatches.
+        let found = match unexpected {
+            Unexpected::Bool(_) => "boolean".to_string(),
+            Unexpected::Unsigned(_) | Unexpected::Signed(_) | Unexpected::Float(_) => "number".to_string(),
+            Unexpected::Char(_) | Unexpected::Str(_) => "string".to_string(),
+            Unexpected::Unit => "null".to_string(),
+            Unexpected::Seq => "array".to_string(),
+            Unexpected::Map => "object".to_string(),
+            other => other.to_string(),
+        };
+        ParseError::TypeMismatch { expected: expected.to_string(), found, path: "$".to_string() }
+    }
+}
+
+impl<'de> Deserializer<'de> for &'de JsonValue {
+    type Error = ParseError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        match self {
+            JsonValue::String(s) => visitor.visit_borrowed_str(s),
+            JsonValue::Number(n) => {
+                if let Some(n) = n.as_i64() {
+                    visitor.visit_i64(n)
+                } else if let Some(n) = n.as_u64() {
+                    visitor.visit_u64(n)
+                } else {
+                    visitor.visit_f64(n.as_f64())
+                }
+            }
+            JsonValue::Boolean(b) => visitor.visit_bool(*b),
+            JsonValue::Null => visitor.visit_unit(),
+            JsonValue::Object(map) => {
+                let mut access = MapDeserializer { entries: map.iter(), value: None };
+                let value = visitor.visit_map(&mut access)?;
+                match access.entries.count() {
+                    0 => Ok(value),
+                    left => Err(de::Error::invalid_length(map.len() - left, &"fewer members")),
+                }
+            }
+            JsonValue::Array(items) => {
+                let mut access = SeqDeserializer { items: items.iter(), index: 0 };
+                let value = visitor.visit_seq(&mut access)?;
+                match access.items.len() {
+                    0 => Ok(value),
+                    left => Err(de::Error::invalid_length(items.len() - left, &"fewer elements")),
+                }
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        match self {
+            JsonValue::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> ParseResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// A unit variant is a string, and any other variant an object with the
+    /// variant name as its one key, as serde_json writes them.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> ParseResult<V::Value> {
+        match self {
+            JsonValue::String(variant) => {
+                visitor.visit_enum(EnumDeserializer { variant, value: &JsonValue::Null })
+            }
+            JsonValue::Object(map) if map.len() == 1 => {
+                let (variant, value) = map.iter().next().expect("one member");
+                visitor.visit_enum(EnumDeserializer { variant, value })
+            }
+            other => Err(de::Error::invalid_type(unexpected(other), &"string or object with one key")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+fn unexpected(value: &JsonValue) -> Unexpected<'_> {
+    match value {
+        JsonValue::String(s) => Unexpected::Str(s),
+        JsonValue::Number(n) => Unexpected::Float(n.as_f64()),
+        JsonValue::Boolean(b) => Unexpected::Bool(*b),
+        JsonValue::Null => Unexpected::Unit,
+        JsonValue::Object(_) => Unexpected::Map,
+        JsonValue::Array(_) => Unexpected::Seq,
+    }
+}
+
+fn str_deserializer(s: &str) -> BorrowedStrDeserializer<'_, ParseError> {
+    BorrowedStrDeserializer::new(s)
+}
+
+struct SeqDeserializer<'de> {
+    items: slice::Iter<'de, JsonValue>,
+    index: usize,
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer<'de> {
+    type Error = ParseError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> ParseResult<Option<T::Value>> {
+        let Some(item) = self.items.next() else {
+            return Ok(None);
+        };
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(item).map(Some).map_err(|error| error.within(index))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct MapDeserializer<'de, I> {
+    entries: I,
+    value: Option<(&'de Key, &'de JsonValue)>,
+}
+
+impl<'de, I> MapAccess<'de> for MapDeserializer<'de, I>
+where
+    I: Iterator<Item = (&'de Key, &'de JsonValue)>,
+{
+    type Error = ParseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> ParseResult<Option<K::Value>> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some((key, value));
+        seed.deserialize(str_deserializer(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> ParseResult<V::Value> {
+        let (key, value) = self.value.take().expect("next_value_seed called before next_key_seed");
+        seed.deserialize(value).map_err(|error| error.within(&**key))
+    }
+}
+
+struct EnumDeserializer<'de> {
+    variant: &'de str,
+    value: &'de JsonValue,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer<'de> {
+    type Error = ParseError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> ParseResult<(V::Value, Self)> {
+        let variant = seed.deserialize(str_deserializer(self.variant))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> EnumDeserializer<'de> {
+    fn within(&self, error: ParseError) -> ParseError {
+        error.within(self.variant)
+    }
+}
+
+impl<'de> VariantAccess<'de> for EnumDeserializer<'de> {
+    type Error = ParseError;
+
+    fn unit_variant(self) -> ParseResult<()> {
+        match self.value {
+            JsonValue::Null => Ok(()),
+            other => Err(self.within(de::Error::invalid_type(unexpected(other), &"unit variant"))),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> ParseResult<T::Value> {
+        seed.deserialize(self.value).map_err(|error| self.within(error))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> ParseResult<V::Value> {
+        self.value.deserialize_seq(visitor).map_err(|error| self.within(error))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> ParseResult<V::Value> {
+        self.value.deserialize_map(visitor).map_err(|error| self.within(error))
+    }
+}
+
+/// Converts any `Serialize` type into a value, as `serde_json::to_value`
+/// does, to be merged, edited by path or written with this crate's
+/// serializer. Map keys must serialize as strings, numbers or booleans;
+/// anything else fails with [`ParseError::Custom`].
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> ParseResult<JsonValue> {
+    value.serialize(ValueSerializer)
+}
+
+impl ser::Error for ParseError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ParseError::custom(msg)
+    }
+}
+
+/// Wraps `value` as the one member of an object keyed by `variant`, the
+/// shape serde_json gives non-unit enum variants.
+fn variant_object(variant: &str, value: JsonValue) -> JsonValue {
+    let mut map = Map::default();
+    map.insert(Key::from(variant), value);
+    JsonValue::Object(map)
+}
+
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = JsonValue;
+    type Error = ParseError;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeObject;
+    type SerializeStruct = SerializeObject;
+    type SerializeStructVariant = SerializeObject;
+
+    fn serialize_bool(self, b: bool) -> ParseResult<JsonValue> {
+        Ok(JsonValue::Boolean(b))
+    }
+
+    fn serialize_i8(self, n: i8) -> ParseResult<JsonValue> {
+        self.serialize_i64(n.into())
+    }
+
+    fn serialize_i16(self, n: i16) -> ParseResult<JsonValue> {
+        self.serialize_i64(n.into())
+    }
+
+    fn serialize_i32(self, n: i32) -> ParseResult<JsonValue> {
+        self.serialize_i64(n.into())
+    }
+
+    fn serialize_i64(self, n: i64) -> ParseResult<JsonValue> {
+        Ok(JsonValue::from(n))
+    }
+
+    /// Beyond `i64` the decimal text is kept, so the value is written out
+    /// exactly.
+    fn serialize_i128(self, n: i128) -> ParseResult<JsonValue> {
+        match i64::try_from(n) {
+            Ok(n) => self.serialize_i64(n),
+            Err(_) => Ok(JsonValue::Number(JsonNumber::with_raw(n as f64, n.to_string()))),
+        }
+    }
+
+    fn serialize_u8(self, n: u8) -> ParseResult<JsonValue> {
+        self.serialize_u64(n.into())
+    }
+
+    fn serialize_u16(self, n: u16) -> ParseResult<JsonValue> {
+        self.serialize_u64(n.into())
+    }
+
+    fn serialize_u32(self, n: u32) -> ParseResult<JsonValue> {
+        self.serialize_u64(n.into())
+    }
+
+    fn serialize_u64(self, n: u64) -> ParseResult<JsonValue> {
+        Ok(JsonValue::from(n))
+    }
+
+    fn serialize_u128(self, n: u128) -> ParseResult<JsonValue> {
+        match u64::try_from(n) {
+            Ok(n) => self.serialize_u64(n),
+            Err(_) => Ok(JsonValue::Number(JsonNumber::with_raw(n as f64, n.to_string()))),
+        }
+    }
+
+    fn serialize_f32(self, n: f32) -> ParseResult<JsonValue> {
+        self.serialize_f64(n.into())
+    }
+
+    // JSON has no NaN or infinity; like serde_json, they become null.
+    fn serialize_f64(self, n: f64) -> ParseResult<JsonValue> {
+        Ok(if n.is_finite() { JsonValue::from(n) } else { JsonValue::Null })
+    }
+
+    fn serialize_char(self, c: char) -> ParseResult<JsonValue> {
+        Ok(JsonValue::String(c.to_string()))
+    }
+
+    fn serialize_str(self, s: &str) -> ParseResult<JsonValue> {
+        Ok(JsonValue::from(s))
+    }
+
+    /// Bytes become an array of numbers, as in serde_json.
+    fn serialize_bytes(self, bytes: &[u8]) -> ParseResult<JsonValue> {
+        Ok(bytes.iter().map(|&b| JsonValue::from(u32::from(b))).collect())
+    }
+
+    fn serialize_none(self) -> ParseResult<JsonValue> {
+        Ok(JsonValue::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> ParseResult<JsonValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> ParseResult<JsonValue> {
+        Ok(JsonValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> ParseResult<JsonValue> {
+        Ok(JsonValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> ParseResult<JsonValue> {
+        Ok(JsonValue::from(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> ParseResult<JsonValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> ParseResult<JsonValue> {
+        Ok(variant_object(variant, to_value(value)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> ParseResult<SerializeArray> {
+        Ok(SerializeArray { variant: None, items: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> ParseResult<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> ParseResult<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> ParseResult<SerializeArray> {
+        Ok(SerializeArray { variant: Some(variant), items: Vec::with_capacity(len) })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> ParseResult<SerializeObject> {
+        Ok(SerializeObject { variant: None, map: Map::default(), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> ParseResult<SerializeObject> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> ParseResult<SerializeObject> {
+        Ok(SerializeObject { variant: Some(variant), map: Map::default(), key: None })
+    }
+}
+
+struct SerializeArray {
+    /// The variant of a tuple variant, which the array is wrapped in.
+    variant: Option<&'static str>,
+    items: Vec<JsonValue>,
+}
+
+impl SerializeArray {
+    fn finish(self) -> JsonValue {
+        let array = JsonValue::Array(self.items);
+        match self.variant {
+            Some(variant) => variant_object(variant, array),
+            None => array,
+        }
+    }
+}
+
+impl SerializeSeq for SerializeArray {
+    type Ok = JsonValue;
+    type Error = ParseError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> ParseResult<()> {
+        self.items.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> ParseResult<JsonValue> {
+        Ok(self.finish())
+    }
+}
+
+impl SerializeTuple for SerializeArray {
+    type Ok = JsonValue;
+    type Error = ParseError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> ParseResult<()> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> ParseResult<JsonValue> {
+        Ok(self.finish())
+    }
+}
+
+impl SerializeTupleStruct for SerializeArray {
+    type Ok = JsonValue;
+    type Error = ParseError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> ParseResult<()> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> ParseResult<JsonValue> {
+        Ok(self.finish())
+    }
+}
+
+impl SerializeTupleVariant for SerializeArray {
+    type Ok = JsonValue;
+    type Error = ParseError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> ParseResult<()> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> ParseResult<JsonValue> {
+        Ok(self.finish())
+    }
+}
+
+struct SerializeObject {
+    /// The variant of a struct variant, which the object is wrapped in.
+    variant: Option<&'static str>,
+    map: Map,
+    /// The key of the member whose value comes next.
+    key: Option<Key>,
+}
+
+impl SerializeObject {
+    fn finish(self) -> JsonValue {
+        let object = JsonValue::Object(self.map);
+        match self.variant {
+            Some(variant) => variant_object(variant, object),
+            None => object,
+        }
+    }
+}
+
+impl SerializeMap for SerializeObject {
+    type Ok = JsonValue;
+    type Error = ParseError;
+
+    /// Numbers and booleans are keyed by their JSON text, as in serde_json.
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> ParseResult<()> {
+        let key = match to_value(key)? {
+            JsonValue::String(key) => key,
+            key @ (JsonValue::Number(_) | JsonValue::Boolean(_)) => key.to_string(),
+            other => {
+                return Err(ParseError::custom(format_args!(
+                    "object key must be a string, found {}",
+                    other.type_name()
+                )))
+            }
+        };
+        self.key = Some(Key::from(key));
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> ParseResult<()> {
+        let key = self.key.take().expect("serialize_value called before serialize_key");
+        self.map.insert(key, to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> ParseResult<JsonValue> {
+        Ok(self.finish())
+    }
+}
+
+impl SerializeStruct for SerializeObject {
+    type Ok = JsonValue;
+    type Error = ParseError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> ParseResult<()> {
+        self.map.insert(Key::from(key), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> ParseResult<JsonValue> {
+        Ok(self.finish())
+    }
+}
+
+impl SerializeStructVariant for SerializeObject {
+    type Ok = JsonValue;
+    type Error = ParseError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> ParseResult<()> {
+        SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> ParseResult<JsonValue> {
+        Ok(self.finish())
+    }
+}
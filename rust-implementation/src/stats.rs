@@ -0,0 +1,104 @@
+use std::mem::{size_of, size_of_val};
+use crate::number::JsonNumber;
+use crate::types::{JsonValue, Key, Map};
+use crate::visit::{Visitor, Walk};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentStats {
+    /// Deepest container nesting; a scalar document has depth 0.
+    pub max_depth: usize,
+    pub objects: usize,
+    pub arrays: usize,
+    pub strings: usize,
+    pub numbers: usize,
+    pub booleans: usize,
+    pub nulls: usize,
+    pub object_members: usize,
+    /// UTF-8 bytes held in string values, excluding object keys.
+    pub string_bytes: usize,
+    pub key_bytes: usize,
+    /// Rough estimate of the heap memory owned by the parsed tree.
+    pub approx_heap_bytes: usize,
+}
+
+impl DocumentStats {
+    pub fn node_count(&self) -> usize {
+        self.objects + self.arrays + self.strings + self.numbers + self.booleans + self.nulls
+    }
+}
+
+#[derive(Default)]
+struct StatsCollector {
+    stats: DocumentStats,
+    depth: usize,
+}
+
+impl StatsCollector {
+    fn enter(&mut self) {
+        self.depth += 1;
+        self.stats.max_depth = self.stats.max_depth.max(self.depth);
+    }
+}
+
+impl Visitor for StatsCollector {
+    fn visit_object(&mut self, object: &Map) -> Walk {
+        self.enter();
+        self.stats.objects += 1;
+        self.stats.object_members += object.len();
+        self.stats.approx_heap_bytes += object.len() * size_of::<(Key, JsonValue)>();
+        Walk::Continue
+    }
+
+    fn visit_key(&mut self, key: &str) -> Walk {
+        self.stats.key_bytes += key.len();
+        self.stats.approx_heap_bytes += key.len();
+        Walk::Continue
+    }
+
+    fn end_object(&mut self) -> Walk {
+        self.depth -= 1;
+        Walk::Continue
+    }
+
+    fn visit_array(&mut self, array: &[JsonValue]) -> Walk {
+        self.enter();
+        self.stats.arrays += 1;
+        self.stats.approx_heap_bytes += size_of_val(array);
+        Walk::Continue
+    }
+
+    fn end_array(&mut self) -> Walk {
+        self.depth -= 1;
+        Walk::Continue
+    }
+
+    fn visit_string(&mut self, value: &str) -> Walk {
+        self.stats.strings += 1;
+        self.stats.string_bytes += value.len();
+        self.stats.approx_heap_bytes += value.len();
+        Walk::Continue
+    }
+
+    fn visit_number(&mut self, _value: &JsonNumber) -> Walk {
+        self.stats.numbers += 1;
+        Walk::Continue
+    }
+
+    fn visit_boolean(&mut self, _value: bool) -> Walk {
+        self.stats.booleans += 1;
+        Walk::Continue
+    }
+
+    fn visit_null(&mut self) -> Walk {
+        self.stats.nulls += 1;
+        Walk::Continue
+    }
+}
+
+impl JsonValue {
+    pub fn stats(&self) -> DocumentStats {
+        let mut collector = StatsCollector::default();
+        self.walk(&mut collector);
+        collector.stats
+    }
+}
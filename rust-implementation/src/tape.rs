@@ -0,0 +1,240 @@
+use std::borrow::Cow;
+use crate::borrowed::{parse_json_str_borrowed, BorrowedParser};
+use crate::types::{Context, JsonValue, ParseResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeKind {
+    Object,
+    Array,
+    Key,
+    String,
+    Number,
+    Boolean,
+    Null,
+}
+
+/// One value or object key in a [`Tape`], located by byte offsets into the
+/// input. Strings and keys include their quotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapeEntry {
+    pub kind: TapeKind,
+    pub start: usize,
+    pub end: usize,
+    /// Index of the entry after this one's last descendant, so a whole
+    /// container can be stepped over at once.
+    pub next: usize,
+}
+
+/// A structural index of a document: every value and key in document order,
+/// built in one validating pass. Subdocuments can then be found and sliced
+/// out any number of times without parsing the input again.
+#[derive(Debug, Clone)]
+pub struct Tape<'a> {
+    input: &'a str,
+    entries: Vec<TapeEntry>,
+}
+
+impl<'a> Tape<'a> {
+    /// Indexes `input`, whose containers may nest at most
+    /// [`DEFAULT_MAX_DEPTH`](crate::DEFAULT_MAX_DEPTH) deep, so any node can
+    /// be decoded with [`TapeNode::to_value`].
+    pub fn build(input: &'a str) -> ParseResult<Tape<'a>> {
+        let mut parser = BorrowedParser::new(input);
+        let mut entries: Vec<TapeEntry> = Vec::new();
+        // Entries of the containers not yet closed.
+        let mut open: Vec<usize> = Vec::new();
+
+        let close = |entries: &mut Vec<TapeEntry>, index: usize, end: usize| {
+            let next = entries.len();
+            let entry = &mut entries[index];
+            entry.end = end;
+            entry.next = next;
+        };
+
+        loop {
+            parser.skip_whitespace();
+            let start = parser.offset();
+            let index = entries.len();
+            let kind = match parser.peek() {
+                Some(b'{') => TapeKind::Object,
+                Some(b'[') => TapeKind::Array,
+                Some(b'"') => {
+                    parser.parse_string()?;
+                    TapeKind::String
+                }
+                Some(b'-' | b'0'..=b'9') => {
+                    parser.parse_number()?;
+                    TapeKind::Number
+                }
+                Some(b't') => parser.parse_literal("true", TapeKind::Boolean)?,
+                Some(b'f') => parser.parse_literal("false", TapeKind::Boolean)?,
+                Some(b'n') => parser.parse_literal("null", TapeKind::Null)?,
+                Some(_) => return Err(parser.invalid_character()),
+                None => return Err(parser.end_of_input(Context::Value)),
+            };
+            entries.push(TapeEntry { kind, start, end: parser.offset(), next: index + 1 });
+            if matches!(kind, TapeKind::Object | TapeKind::Array) {
+                parser.enter()?;
+            }
+
+            let needs_key = match kind {
+                TapeKind::Object if !parser.open_container(b'}') => {
+                    open.push(index);
+                    true
+                }
+                TapeKind::Array if !parser.open_container(b']') => {
+                    open.push(index);
+                    false
+                }
+                _ => {
+                    if matches!(kind, TapeKind::Object | TapeKind::Array) {
+                        parser.leave();
+                        close(&mut entries, index, parser.offset());
+                    }
+                    // Close every container this value completes, until one
+                    // needs another member or element.
+                    loop {
+                        let Some(&container) = open.last() else {
+                            parser.finish()?;
+                            return Ok(Tape { input, entries });
+                        };
+                        let object = entries[container].kind == TapeKind::Object;
+                        let closed = if object {
+                            parser.parse_separator(b'}', "',' or '}'")?
+                        } else {
+                            parser.parse_separator(b']', "',' or ']'")?
+                        };
+                        if !closed {
+                            break object;
+                        }
+                        open.pop();
+                        parser.leave();
+                        close(&mut entries, container, parser.offset());
+                    }
+                }
+            };
+
+            if needs_key {
+                parser.skip_whitespace();
+                let start = parser.offset();
+                if parser.peek() != Some(b'"') {
+                    return Err(parser.invalid_character());
+                }
+                parser.parse_string()?;
+                let end = parser.offset();
+                entries.push(TapeEntry { kind: TapeKind::Key, start, end, next: entries.len() + 1 });
+                parser.expect(b':', "':'")?;
+            }
+        }
+    }
+
+    pub fn entries(&self) -> &[TapeEntry] {
+        &self.entries
+    }
+
+    pub fn root(&self) -> TapeNode<'_, 'a> {
+        TapeNode { tape: self, index: 0 }
+    }
+
+    /// The value or key at `index` in [`entries`](Tape::entries).
+    pub fn node(&self, index: usize) -> Option<TapeNode<'_, 'a>> {
+        (index < self.entries.len()).then_some(TapeNode { tape: self, index })
+    }
+}
+
+/// A value in a [`Tape`].
+#[derive(Debug, Clone, Copy)]
+pub struct TapeNode<'t, 'a> {
+    tape: &'t Tape<'a>,
+    index: usize,
+}
+
+impl<'t, 'a> TapeNode<'t, 'a> {
+    fn entry(&self) -> &'t TapeEntry {
+        &self.tape.entries[self.index]
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn kind(&self) -> TapeKind {
+        self.entry().kind
+    }
+
+    /// The value's source text.
+    pub fn raw_json(&self) -> &'a str {
+        let entry = self.entry();
+        &self.tape.input[entry.start..entry.end]
+    }
+
+    /// Decodes the value, or just a key.
+    pub fn to_value(&self) -> ParseResult<JsonValue> {
+        parse_json_str_borrowed(self.raw_json()).map(|value| value.into_owned())
+    }
+
+    /// The text of a string or key, borrowed from the input unless it has
+    /// escape sequences.
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
+        match self.kind() {
+            TapeKind::String | TapeKind::Key => {
+                let entry = self.entry();
+                BorrowedParser::at(self.tape.input, entry.start).parse_string().ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// The values directly inside an array, or the keys and values of an
+    /// object, alternating.
+    pub fn children(&self) -> TapeChildren<'t, 'a> {
+        let entry = self.entry();
+        let is_container = matches!(entry.kind, TapeKind::Object | TapeKind::Array);
+        TapeChildren {
+            tape: self.tape,
+            next: self.index + 1,
+            end: if is_container { entry.next } else { self.index + 1 },
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<TapeNode<'t, 'a>> {
+        if self.kind() != TapeKind::Object {
+            return None;
+        }
+        let mut children = self.children();
+        while let (Some(name), Some(value)) = (children.next(), children.next()) {
+            if name.as_str().as_deref() == Some(key) {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    pub fn at(&self, index: usize) -> Option<TapeNode<'t, 'a>> {
+        match self.kind() {
+            TapeKind::Array => self.children().nth(index),
+            _ => None,
+        }
+    }
+}
+
+/// Iterator over the children of a [`TapeNode`], stepping over each one's
+/// descendants without visiting them.
+pub struct TapeChildren<'t, 'a> {
+    tape: &'t Tape<'a>,
+    next: usize,
+    end: usize,
+}
+
+impl<'t, 'a> Iterator for TapeChildren<'t, 'a> {
+    type Item = TapeNode<'t, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+        let node = TapeNode { tape: self.tape, index: self.next };
+        self.next = self.tape.entries[self.next].next;
+        Some(node)
+    }
+}
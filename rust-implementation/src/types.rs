@@ -1,6 +1,12 @@
 use std::collections::HashMap;
+#[cfg(not(feature = "preserve_order"))]
+use std::collections::hash_map;
 use std::fmt;
+use std::io;
+use std::ops::Range;
 use thiserror::Error;
+use crate::number::JsonNumber;
+use crate::path::{type_mismatch, Path, PathError, PathSegment};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
@@ -11,20 +17,72 @@ pub enum TokenType {
     Comma,
     Colon,
     String(String),
-    Number(f64),
+    Number(JsonNumber),
     Boolean(bool),
     Null,
     Eof,
 }
 
+/// A point in the input: a byte offset into the UTF-8 text, and the line and
+/// column there, both counted from 1 with columns in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub const START: Position = Position { offset: 0, line: 1, column: 1 };
+
+    /// The position of the byte `offset` of `text`, which is worked out
+    /// only when an error needs it.
+    pub(crate) fn in_text(text: &str, offset: usize) -> Position {
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Position {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::START
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// Where a token starts and ends in the input: `end` is the position just
+/// past it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// The bytes of the token, for slicing the input.
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
-    pub position: usize,
+    pub position: Position,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, position: usize) -> Self {
+    pub fn new(token_type: TokenType, position: Position) -> Self {
         Self {
             token_type,
             position,
@@ -32,83 +90,757 @@ impl Token {
     }
 }
 
+/// A token with its extent in the input. Both ends of the span are full
+/// [`Position`]s, so the byte offsets slice the text (see [`Span::range`])
+/// and the lines and columns are for showing to people.
 #[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token_type: TokenType,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum JsonValue {
     String(String),
-    Number(f64),
+    Number(JsonNumber),
     Boolean(bool),
+    #[default]
     Null,
-    Object(HashMap<String, JsonValue>),
+    Object(Map),
     Array(Vec<JsonValue>),
 }
 
+/// The exact source text of a valid JSON value, kept without decoding it,
+/// as by [`ParserOptions::raw_value_at`](crate::ParserOptions::raw_value_at).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawJson(String);
+
+impl RawJson {
+    /// Checks that `text` is one JSON value and wraps it as is.
+    pub fn from_string(text: String) -> ParseResult<Self> {
+        crate::borrowed::parse_json_str_borrowed(&text)?;
+        Ok(RawJson(text))
+    }
+
+    /// Wraps text the parser has already validated.
+    pub(crate) fn new_unchecked(text: String) -> Self {
+        RawJson(text)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Decodes the text into a value. Fails if it was captured with options
+    /// that accept more than strict JSON and uses those extensions.
+    pub fn parse(&self) -> ParseResult<JsonValue> {
+        crate::borrowed::parse_json_str_borrowed(&self.0).map(|value| value.into_owned())
+    }
+}
+
+impl fmt::Display for RawJson {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// An object key: a `String`, or a shared `Arc<str>` with the `intern_keys`
+/// feature so that parsed documents with a common schema reuse one allocation
+/// per distinct key.
+#[cfg(not(feature = "intern_keys"))]
+pub type Key = String;
+#[cfg(feature = "intern_keys")]
+pub type Key = std::sync::Arc<str>;
+
+/// The map behind `JsonValue::Object`: a `HashMap`, or an insertion-ordered
+/// `IndexMap` with the `preserve_order` feature.
+#[cfg(not(feature = "preserve_order"))]
+pub type Map = HashMap<Key, JsonValue>;
+#[cfg(feature = "preserve_order")]
+pub type Map = indexmap::IndexMap<Key, JsonValue>;
+
+#[cfg(not(feature = "preserve_order"))]
+pub type Entry<'a> = hash_map::Entry<'a, Key, JsonValue>;
+#[cfg(feature = "preserve_order")]
+pub type Entry<'a> = indexmap::map::Entry<'a, Key, JsonValue>;
+
+/// Removes a member, keeping the order of the remaining members when
+/// `preserve_order` is enabled.
+pub(crate) fn remove_member(map: &mut Map, key: &str) -> Option<JsonValue> {
+    #[cfg(feature = "preserve_order")]
+    return map.shift_remove(key);
+    #[cfg(not(feature = "preserve_order"))]
+    map.remove(key)
+}
+
+impl JsonValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            JsonValue::String(_) => "string",
+            JsonValue::Number(_) => "number",
+            JsonValue::Boolean(_) => "boolean",
+            JsonValue::Null => "null",
+            JsonValue::Object(_) => "object",
+            JsonValue::Array(_) => "array",
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&JsonNumber> {
+        match self {
+            JsonValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(JsonNumber::as_f64)
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(JsonNumber::as_i64)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(JsonNumber::as_u64)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Map> {
+        match self {
+            JsonValue::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut Map> {
+        match self {
+            JsonValue::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// Moves the value out, leaving `Null` in its place.
+    pub fn take(&mut self) -> JsonValue {
+        std::mem::take(self)
+    }
+
+    pub fn replace(&mut self, value: JsonValue) -> JsonValue {
+        std::mem::replace(self, value)
+    }
+
+    /// Returns the entry for `key`, turning `Null` into an empty object first.
+    ///
+    /// Panics if the value is neither an object nor `Null`.
+    pub fn entry(&mut self, key: impl Into<Key>) -> Entry<'_> {
+        if self.is_null() {
+            *self = JsonValue::Object(Map::new());
+        }
+        match self {
+            JsonValue::Object(obj) => obj.entry(key.into()),
+            other => panic!("cannot take an entry of a JSON {}", other.type_name()),
+        }
+    }
+
+    pub fn as_str_or<'a>(&'a self, default: &'a str) -> &'a str {
+        self.as_str().unwrap_or(default)
+    }
+
+    pub fn as_f64_or(&self, default: f64) -> f64 {
+        self.as_f64().unwrap_or(default)
+    }
+
+    pub fn as_i64_or(&self, default: i64) -> i64 {
+        self.as_i64().unwrap_or(default)
+    }
+
+    pub fn as_u64_or(&self, default: u64) -> u64 {
+        self.as_u64().unwrap_or(default)
+    }
+
+    pub fn as_u16_or(&self, default: u16) -> u16 {
+        self.as_u64()
+            .and_then(|n| u16::try_from(n).ok())
+            .unwrap_or(default)
+    }
+
+    pub fn as_bool_or(&self, default: bool) -> bool {
+        self.as_bool().unwrap_or(default)
+    }
+
+    // The `expect_*` accessors fail with `PathError::TypeMismatch` at
+    // `path`, the place this value was taken from, such as
+    // `["server", "port"]`.
+
+    fn expect<T>(&self, value: Option<T>, expected: &str, path: impl Into<Path>) -> Result<T, PathError> {
+        value.ok_or_else(|| type_mismatch(expected, self, &path.into()))
+    }
+
+    pub fn expect_object(&self, path: impl Into<Path>) -> Result<&Map, PathError> {
+        self.expect(self.as_object(), "object", path)
+    }
+
+    pub fn expect_array(&self, path: impl Into<Path>) -> Result<&Vec<JsonValue>, PathError> {
+        self.expect(self.as_array(), "array", path)
+    }
+
+    pub fn expect_str(&self, path: impl Into<Path>) -> Result<&str, PathError> {
+        self.expect(self.as_str(), "string", path)
+    }
+
+    pub fn expect_f64(&self, path: impl Into<Path>) -> Result<f64, PathError> {
+        self.expect(self.as_f64(), "number", path)
+    }
+
+    pub fn expect_i64(&self, path: impl Into<Path>) -> Result<i64, PathError> {
+        self.expect(self.as_i64(), "integer", path)
+    }
+
+    pub fn expect_u64(&self, path: impl Into<Path>) -> Result<u64, PathError> {
+        self.expect(self.as_u64(), "non-negative integer", path)
+    }
+
+    pub fn expect_bool(&self, path: impl Into<Path>) -> Result<bool, PathError> {
+        self.expect(self.as_bool(), "boolean", path)
+    }
+
+    pub fn expect_null(&self, path: impl Into<Path>) -> Result<(), PathError> {
+        self.expect(self.is_null().then_some(()), "null", path)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(s: &str) -> Self {
+        JsonValue::String(s.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(s: String) -> Self {
+        JsonValue::String(s)
+    }
+}
+
+impl From<JsonNumber> for JsonValue {
+    fn from(n: JsonNumber) -> Self {
+        JsonValue::Number(n)
+    }
+}
+
+impl From<f64> for JsonValue {
+    fn from(n: f64) -> Self {
+        JsonValue::Number(n.into())
+    }
+}
+
+impl From<i64> for JsonValue {
+    fn from(n: i64) -> Self {
+        JsonValue::Number(n.into())
+    }
+}
+
+impl From<i32> for JsonValue {
+    fn from(n: i32) -> Self {
+        JsonValue::Number(n.into())
+    }
+}
+
+impl From<u32> for JsonValue {
+    fn from(n: u32) -> Self {
+        JsonValue::Number(n.into())
+    }
+}
+
+impl From<u64> for JsonValue {
+    fn from(n: u64) -> Self {
+        JsonValue::Number(n.into())
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(b: bool) -> Self {
+        JsonValue::Boolean(b)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(JsonValue::Null, Into::into)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Vec<T>> for JsonValue {
+    fn from(values: Vec<T>) -> Self {
+        JsonValue::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<Key>, T: Into<JsonValue>> From<HashMap<K, T>> for JsonValue {
+    fn from(map: HashMap<K, T>) -> Self {
+        JsonValue::Object(map.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl<K: Into<Key>, T: Into<JsonValue>> From<indexmap::IndexMap<K, T>> for JsonValue {
+    fn from(map: indexmap::IndexMap<K, T>) -> Self {
+        JsonValue::Object(map.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+impl FromIterator<JsonValue> for JsonValue {
+    fn from_iter<I: IntoIterator<Item = JsonValue>>(iter: I) -> Self {
+        JsonValue::Array(iter.into_iter().collect())
+    }
+}
+
+impl<K: Into<Key>> FromIterator<(K, JsonValue)> for JsonValue {
+    fn from_iter<I: IntoIterator<Item = (K, JsonValue)>>(iter: I) -> Self {
+        JsonValue::Object(iter.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+}
+
+/// Appends to an array, turning `Null` into an empty array first.
+///
+/// Panics if the value is neither an array nor `Null`.
+impl Extend<JsonValue> for JsonValue {
+    fn extend<I: IntoIterator<Item = JsonValue>>(&mut self, iter: I) {
+        if self.is_null() {
+            *self = JsonValue::Array(Vec::new());
+        }
+        match self {
+            JsonValue::Array(arr) => arr.extend(iter),
+            other => panic!("cannot extend a JSON {} with elements", other.type_name()),
+        }
+    }
+}
+
+/// Inserts members into an object, turning `Null` into an empty object first.
+///
+/// Panics if the value is neither an object nor `Null`.
+impl<K: Into<Key>> Extend<(K, JsonValue)> for JsonValue {
+    fn extend<I: IntoIterator<Item = (K, JsonValue)>>(&mut self, iter: I) {
+        if self.is_null() {
+            *self = JsonValue::Object(Map::new());
+        }
+        match self {
+            JsonValue::Object(obj) => obj.extend(iter.into_iter().map(|(k, v)| (k.into(), v))),
+            other => panic!("cannot extend a JSON {} with members", other.type_name()),
+        }
+    }
+}
+
+/// Compact JSON; the alternate flag (`{:#}`) pretty-prints instead.
 impl fmt::Display for JsonValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            crate::ser::write_value(f, self, &mut crate::ser::PrettyFormatter::new())
+        } else {
+            crate::ser::write_value(f, self, &mut crate::ser::CompactFormatter)
+        }
+    }
+}
+
+/// A view of a value whose objects display with their keys in sorted order.
+///
+/// Without `preserve_order`, objects are backed by a `HashMap`, so ordering has
+/// to be applied when the document is written out rather than stored in the
+/// value itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortedKeys<'a>(pub &'a JsonValue);
+
+impl JsonValue {
+    pub fn sorted_keys(&self) -> SortedKeys<'_> {
+        SortedKeys(self)
+    }
+
+    /// Recursively reorders every object's members by key. Only
+    /// `preserve_order` objects keep an order; in every build,
+    /// [`sorted_keys`](JsonValue::sorted_keys) writes keys in sorted order.
+    #[cfg(feature = "preserve_order")]
+    pub fn sort_keys(&mut self) {
         match self {
-            JsonValue::String(s) => write!(f, "\"{}\"", s),
-            JsonValue::Number(n) => write!(f, "{}", n),
-            JsonValue::Boolean(b) => write!(f, "{}", b),
-            JsonValue::Null => write!(f, "null"),
             JsonValue::Object(obj) => {
-                write!(f, "{{")?;
-                let mut first = true;
-                for (key, value) in obj {
-                    if !first {
-                        write!(f, ",")?;
-                    }
-                    write!(f, "\"{}\":{}", key, value)?;
-                    first = false;
-                }
-                write!(f, "}}")
-            }
-            JsonValue::Array(arr) => {
-                write!(f, "[")?;
-                let mut first = true;
-                for value in arr {
-                    if !first {
-                        write!(f, ",")?;
-                    }
-                    write!(f, "{}", value)?;
-                    first = false;
-                }
-                write!(f, "]")
+                obj.sort_keys();
+                obj.values_mut().for_each(JsonValue::sort_keys);
             }
+            JsonValue::Array(arr) => arr.iter_mut().for_each(JsonValue::sort_keys),
+            _ => {}
         }
     }
 }
 
-#[derive(Error, Debug, Clone)]
+impl fmt::Display for SortedKeys<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            crate::ser::Serializer::pretty().sort_keys(true).write(f, self.0)
+        } else {
+            crate::ser::Serializer::new().sort_keys(true).write(f, self.0)
+        }
+    }
+}
+
+/// The resource a [`ParseError::LimitExceeded`] refers to; see the matching
+/// [`ParserOptions`](crate::ParserOptions) setters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceLimit {
+    StringLength,
+    InputBytes,
+    ContainerElements,
+    Nodes,
+    TotalAllocated,
+}
+
+impl fmt::Display for ResourceLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ResourceLimit::StringLength => "String length",
+            ResourceLimit::InputBytes => "Input size",
+            ResourceLimit::ContainerElements => "Container size",
+            ResourceLimit::Nodes => "Value count",
+            ResourceLimit::TotalAllocated => "Memory budget",
+        })
+    }
+}
+
+/// The error of a failed read, as a [`ParseError::Io`]. Keeps the
+/// [`io::ErrorKind`] so callers can tell `WouldBlock` from a closed
+/// connection, but only the message of the original error, so it stays
+/// `Clone` and comparable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoError {
+    pub kind: io::ErrorKind,
+    pub message: String,
+    /// How far parsing had got when the read failed, if it was parsing.
+    pub position: Option<Position>,
+}
+
+impl IoError {
+    pub fn new(kind: io::ErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into(), position: None }
+    }
+
+    pub(crate) fn at(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<io::Error> for IoError {
+    fn from(error: io::Error) -> Self {
+        Self::new(error.kind(), error.to_string())
+    }
+}
+
+impl From<IoError> for io::Error {
+    fn from(error: IoError) -> Self {
+        io::Error::new(error.kind, error.message)
+    }
+}
+
+/// What was being read when the input ran out; see
+/// [`ParseError::UnexpectedEndOfInput`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    /// A document, or a value whose kind isn't known yet.
+    Value,
+    Object,
+    Array,
+    Number,
+    /// `true`, `false` or `null`.
+    Literal,
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Context::Value => "a value",
+            Context::Object => "an object",
+            Context::Array => "an array",
+            Context::Number => "a number",
+            Context::Literal => "a literal",
+        })
+    }
+}
+
+/// Errors found in a document carry the [`Position`] where they were
+/// found, and so does a read that failed while one was being parsed. Errors
+/// about values carry the path of the value instead, errors in a JSON
+/// Pointer, schema or JSONPath query say where in that text they are, and
+/// none of these has a place in the input.
+#[derive(Error, Debug, Clone, PartialEq)]
 pub enum ParseError {
-    #[error("Unexpected end of input at position {0}")]
-    UnexpectedEof(usize),
+    /// The input ended partway through a document, which more input might
+    /// complete. `position` is where it ended.
+    #[error("Unexpected end of input while parsing {while_parsing} at {position}")]
+    UnexpectedEndOfInput { while_parsing: Context, position: Position },
     
-    #[error("Invalid character '{char}' at position {position}")]
-    InvalidCharacter { char: char, position: usize },
+    #[error("Invalid character '{char}' at {position}")]
+    InvalidCharacter { char: char, position: Position },
     
-    #[error("Invalid number format at position {0}")]
-    InvalidNumber(usize),
+    #[error("Invalid number format at {0}")]
+    InvalidNumber(Position),
     
-    #[error("Unterminated string at position {0}")]
-    UnterminatedString(usize),
+    #[error("Unterminated string at {0}")]
+    UnterminatedString(Position),
     
-    #[error("Invalid escape sequence at position {0}")]
-    InvalidEscape(usize),
+    #[error("Invalid escape sequence at {0}")]
+    InvalidEscape(Position),
+
+    /// `\uD800` and the like; the span is the escape of the unpaired half.
+    #[error("Unpaired UTF-16 surrogate in \\u escape at {}", .0.start)]
+    UnpairedSurrogate(Span),
+
+    #[error("Unterminated comment at {0}")]
+    UnterminatedComment(Position),
     
-    #[error("Expected {expected}, found {found} at position {position}")]
+    #[error("Expected {expected}, found {found} at {}{}", .span.start, hint_suffix(.hint))]
     UnexpectedToken {
         expected: String,
         found: String,
-        position: usize,
+        /// The whole unexpected token.
+        span: Span,
+        /// A guess at the mistake, such as a missing comma.
+        hint: Option<String>,
     },
     
-    #[error("Trailing comma not allowed at position {0}")]
-    TrailingComma(usize),
+    #[error("Trailing comma not allowed at {0}")]
+    TrailingComma(Position),
     
-    #[error("Invalid JSON structure at position {0}")]
-    InvalidStructure(usize),
+    #[error("Invalid JSON structure at {0}")]
+    InvalidStructure(Position),
+
+    #[error("Nesting deeper than {limit} levels at {position}")]
+    DepthLimitExceeded { limit: usize, position: Position },
+
+    #[error("{limit} exceeds the limit of {max} at {position}")]
+    LimitExceeded {
+        limit: ResourceLimit,
+        max: usize,
+        position: Position,
+    },
     
     #[error("IO error: {0}")]
-    Io(String),
+    Io(IoError),
+
+    #[error("Path {path} not found: no {segment} at {parent}")]
+    PathNotFound {
+        path: String,
+        parent: String,
+        segment: String,
+    },
+
+    #[error("Invalid JSON pointer '{0}'")]
+    InvalidPointer(String),
+
+    /// A value of the wrong type; `found` is the type name of the value
+    /// there (`"string"`, `"number"`, ...) and `path` is where it sits.
+    #[error("Expected {expected} at {path}, found {found}")]
+    TypeMismatch {
+        expected: String,
+        found: String,
+        path: String,
+    },
+
+    #[error("Invalid record framing at {position}: {reason}")]
+    InvalidFraming { reason: String, position: Position },
+
+    /// A JSON Schema that can't be compiled; `path` is the JSON Pointer of
+    /// the offending keyword within the schema.
+    #[error("Invalid schema at '{path}': {reason}")]
+    InvalidSchema { path: String, reason: String },
+
+    /// A JSONPath query that isn't well-formed or well-typed; `position` is
+    /// where in the query the problem was found.
+    #[error("Invalid JSONPath query at {position}: {reason}")]
+    InvalidQuery { position: Position, reason: String },
+
+    /// An option the function called can't honour, such as
+    /// [`ParserOptions::raw_value_at`](crate::ParserOptions::raw_value_at)
+    /// given to one that returns only the value.
+    #[error("Unsupported option: {0}")]
+    UnsupportedOption(String),
+
+    /// A message from a serde `Deserialize` or `Serialize` implementation,
+    /// such as a missing field, with the path of the value it is about.
+    #[error("{message} at {path}")]
+    Custom { message: String, path: String },
+}
+
+impl ParseError {
+    /// A [`Custom`](ParseError::Custom) error about the root value.
+    pub fn custom(message: impl fmt::Display) -> ParseError {
+        ParseError::Custom { message: message.to_string(), path: "$".to_string() }
+    }
+
+    /// The path of the value an error from reading a `JsonValue` is about,
+    /// as in `$.servers[3].port`: that of a
+    /// [`TypeMismatch`](ParseError::TypeMismatch),
+    /// [`PathNotFound`](ParseError::PathNotFound) or
+    /// [`Custom`](ParseError::Custom) error. For an error found in the
+    /// input, see
+    /// [`StreamingJsonParser::error_path`](crate::StreamingJsonParser::error_path).
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            ParseError::TypeMismatch { path, .. }
+            | ParseError::PathNotFound { path, .. }
+            | ParseError::Custom { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Moves an error found in the child at `segment` of a value under that
+    /// child's path, for conversions that descend into containers. Only
+    /// [`TypeMismatch`](ParseError::TypeMismatch) and
+    /// [`Custom`](ParseError::Custom) errors have a path to move.
+    pub fn within(self, segment: impl Into<PathSegment>) -> ParseError {
+        let segment = segment.into();
+        let nest = |path: &str| format!("${}{}", segment, path.strip_prefix('$').unwrap_or(path));
+        match self {
+            ParseError::TypeMismatch { expected, found, path } => {
+                ParseError::TypeMismatch { expected, found, path: nest(&path) }
+            }
+            ParseError::Custom { message, path } => ParseError::Custom { message, path: nest(&path) },
+            error => error,
+        }
+    }
+
+    /// Whether the input ended too soon: inside a document, string or
+    /// comment. A network reader can wait for more data and try again.
+    pub fn is_end_of_input(&self) -> bool {
+        matches!(
+            self,
+            ParseError::UnexpectedEndOfInput { .. }
+                | ParseError::UnterminatedString(_)
+                | ParseError::UnterminatedComment(_)
+        )
+    }
+
+    /// Where in the input the error was found: known for every error found
+    /// in a document, and for a failed read while one was being parsed.
+    /// `None` for errors about values, pointers, schemas and queries.
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            ParseError::InvalidNumber(position)
+            | ParseError::UnterminatedString(position)
+            | ParseError::InvalidEscape(position)
+            | ParseError::UnterminatedComment(position)
+            | ParseError::TrailingComma(position)
+            | ParseError::InvalidStructure(position)
+            | ParseError::UnexpectedEndOfInput { position, .. }
+            | ParseError::InvalidCharacter { position, .. }
+            | ParseError::DepthLimitExceeded { position, .. }
+            | ParseError::LimitExceeded { position, .. }
+            | ParseError::InvalidFraming { position, .. } => Some(*position),
+            ParseError::UnpairedSurrogate(span) | ParseError::UnexpectedToken { span, .. } => Some(span.start),
+            ParseError::Io(error) => error.position,
+            ParseError::PathNotFound { .. }
+            | ParseError::InvalidPointer(_)
+            | ParseError::TypeMismatch { .. }
+            | ParseError::InvalidSchema { .. }
+            | ParseError::InvalidQuery { .. }
+            | ParseError::UnsupportedOption(_)
+            | ParseError::Custom { .. } => None,
+        }
+    }
+
+    /// The bytes of the input the error is about, for slicing out of the
+    /// original buffer. Empty where only the start is known, as for an
+    /// unterminated string or comment or the end of the input.
+    pub fn span(&self) -> Option<Range<usize>> {
+        let start = self.position()?.offset;
+        let len = match self {
+            ParseError::UnpairedSurrogate(span) | ParseError::UnexpectedToken { span, .. } => {
+                return Some(span.range())
+            }
+            ParseError::InvalidCharacter { char, .. } => char.len_utf8(),
+            // The comma or bracket.
+            ParseError::TrailingComma(_) | ParseError::DepthLimitExceeded { .. } => 1,
+            _ => 0,
+        };
+        Some(start..start + len)
+    }
+
+    /// The message followed by a rustc-style snippet of the line of `source`
+    /// where the error was found, with carets under the offending bytes.
+    /// Just the message if the error has no place in `source`.
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("error: {}", self);
+        let (Some(position), Some(span)) = (self.position(), self.span()) else {
+            return out;
+        };
+        let start = span.start;
+        if start > source.len() || !source.is_char_boundary(start) {
+            return out;
+        }
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..].find('\n').map_or(source.len(), |i| start + i);
+        let line = source[line_start..line_end].trim_end_matches('\r');
+
+        // Keep tabs so the caret lines up with the text above it.
+        let indent: String = source[line_start..start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let end = span.end.clamp(start, line_end);
+        let width = source.get(start..end).map_or(0, |text| text.chars().count()).max(1);
+
+        let number = position.line.to_string();
+        let gutter = " ".repeat(number.len());
+        out.push_str(&format!("\n{} |\n{} | {}\n", gutter, number, line));
+        out.push_str(&format!("{} | {}{}", gutter, indent, "^".repeat(width)));
+        out
+    }
+}
+
+fn hint_suffix(hint: &Option<String>) -> String {
+    hint.as_ref().map_or(String::new(), |hint| format!(" — {}", hint))
+}
+
+impl From<io::Error> for ParseError {
+    fn from(error: io::Error) -> Self {
+        ParseError::Io(error.into())
+    }
 }
 
 pub type ParseResult<T> = Result<T, ParseError>;
\ No newline at end of file
@@ -0,0 +1,96 @@
+use crate::number::JsonNumber;
+use crate::types::{JsonValue, Map};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    Continue,
+    /// Don't descend into the current object or array, but keep walking its siblings.
+    SkipChildren,
+    Stop,
+}
+
+/// Callbacks for [`JsonValue::walk`]. Every method defaults to `Walk::Continue`,
+/// so implementors only override the nodes they care about.
+pub trait Visitor {
+    fn visit_object(&mut self, _object: &Map) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called before the value of each object member is visited.
+    fn visit_key(&mut self, _key: &str) -> Walk {
+        Walk::Continue
+    }
+
+    fn end_object(&mut self) -> Walk {
+        Walk::Continue
+    }
+
+    fn visit_array(&mut self, _array: &[JsonValue]) -> Walk {
+        Walk::Continue
+    }
+
+    fn end_array(&mut self) -> Walk {
+        Walk::Continue
+    }
+
+    fn visit_string(&mut self, _value: &str) -> Walk {
+        Walk::Continue
+    }
+
+    fn visit_number(&mut self, _value: &JsonNumber) -> Walk {
+        Walk::Continue
+    }
+
+    fn visit_boolean(&mut self, _value: bool) -> Walk {
+        Walk::Continue
+    }
+
+    fn visit_null(&mut self) -> Walk {
+        Walk::Continue
+    }
+}
+
+impl JsonValue {
+    /// Drives `visitor` over the tree depth-first. Returns `Walk::Stop` if the
+    /// visitor ended the walk early, `Walk::Continue` otherwise.
+    pub fn walk<V: Visitor + ?Sized>(&self, visitor: &mut V) -> Walk {
+        let control = match self {
+            JsonValue::String(s) => visitor.visit_string(s),
+            JsonValue::Number(n) => visitor.visit_number(n),
+            JsonValue::Boolean(b) => visitor.visit_boolean(*b),
+            JsonValue::Null => visitor.visit_null(),
+            JsonValue::Object(obj) => match visitor.visit_object(obj) {
+                Walk::Continue => {
+                    for (key, value) in obj {
+                        match visitor.visit_key(key) {
+                            Walk::Stop => return Walk::Stop,
+                            Walk::SkipChildren => continue,
+                            Walk::Continue => {}
+                        }
+                        if value.walk(visitor) == Walk::Stop {
+                            return Walk::Stop;
+                        }
+                    }
+                    visitor.end_object()
+                }
+                control => control,
+            },
+            JsonValue::Array(arr) => match visitor.visit_array(arr) {
+                Walk::Continue => {
+                    for value in arr {
+                        if value.walk(visitor) == Walk::Stop {
+                            return Walk::Stop;
+                        }
+                    }
+                    visitor.end_array()
+                }
+                control => control,
+            },
+        };
+
+        match control {
+            Walk::Stop => Walk::Stop,
+            _ => Walk::Continue,
+        }
+    }
+}
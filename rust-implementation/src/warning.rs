@@ -0,0 +1,61 @@
+use std::fmt;
+use std::io::Cursor;
+use crate::options::ParserOptions;
+use crate::parser::StreamingJsonParser;
+use crate::types::{JsonValue, ParseResult, Position};
+
+/// Input that parsed only because of a lenient option, or that strict
+/// parsers may read differently. Collected with
+/// [`ParserOptions::collect_warnings`](crate::ParserOptions::collect_warnings).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarningKind {
+    Comment,
+    /// A comma before a closing bracket; the position is the comma's.
+    TrailingComma,
+    UnquotedKey,
+    SingleQuotedString,
+    /// A number strict JSON lacks, such as `0xFF`, `+1`, `.5` or `NaN`.
+    NonStandardNumber,
+    /// An escape strict JSON lacks, such as `\x41`, `\'` or a line
+    /// continuation.
+    NonStandardEscape,
+    /// A raw control character in a string; the position is the first one's.
+    ControlCharacter,
+    /// A key already used in the same object. The last value is kept.
+    DuplicateKey(String),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            WarningKind::Comment => write!(f, "Comment")?,
+            WarningKind::TrailingComma => write!(f, "Trailing comma")?,
+            WarningKind::UnquotedKey => write!(f, "Unquoted key")?,
+            WarningKind::SingleQuotedString => write!(f, "Single-quoted string")?,
+            WarningKind::NonStandardNumber => write!(f, "Non-standard number")?,
+            WarningKind::NonStandardEscape => write!(f, "Non-standard escape")?,
+            WarningKind::ControlCharacter => write!(f, "Unescaped control character")?,
+            WarningKind::DuplicateKey(key) => write!(f, "Duplicate key {:?}", key)?,
+        }
+        write!(f, " at {}", self.position)
+    }
+}
+
+/// Parses one document like [`parse_json_string_with`](crate::parse_json_string_with),
+/// also returning a warning for each place it relied on `options` being
+/// lenient, and for duplicate keys. Lets CI accept JSONC but still flag it.
+pub fn parse_json_string_with_warnings(
+    input: &str,
+    options: ParserOptions,
+) -> ParseResult<(JsonValue, Vec<Warning>)> {
+    let options = options.collect_warnings(true);
+    let mut parser = StreamingJsonParser::with_options(Cursor::new(input), options);
+    let value = parser.parse_single()?;
+    Ok((value, parser.take_warnings()))
+}